@@ -0,0 +1,176 @@
+#![allow(unused_doc_comments)]
+//! Load test of the Meals Service
+//!
+//! Measures how quickly cheap requests are served while a few other requests are stuck on slow queries.
+//! The slow queries are produced by locking the `dishes` table for the whole run, so every `GET /dishes`
+//! waits on the lock, while `GET /meals` only reads the `meals` table and could be answered right away.
+//! As long as the database calls block the async workers, the stuck requests take the workers down with them
+//! and the cheap requests pile up behind them.
+//!
+//! ```sh
+//! DATABASE_URL=postgres://postgres@localhost/dishes cargo run --release --example load_test -- \
+//!     --url http://localhost:8001 --duration 10 --concurrency 32 --slow 4
+//! ```
+//!
+//! Unless the Service runs with `AUTH_MODE=dev`, pass a key with the `reader` role with `--api-key`
+
+/// Diesel imports
+use diesel::{Connection, PgConnection};
+use diesel::connection::SimpleConnection;
+
+/// Misc imports
+use futures::future::join_all;
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Settings of a run, see [Options::parse]
+struct Options {
+    url: String,
+    database_url: String,
+    duration: Duration,
+    concurrency: usize,
+    slow: usize,
+    api_key: Option<String>,
+}
+
+impl Options {
+
+    /// Parses `--url`, `--database-url`, `--duration` (seconds), `--concurrency`, `--slow` and `--api-key` from the command line
+    /// The database URL defaults to `DATABASE_URL`
+    fn parse() -> Result<Options, String> {
+        dotenv::dotenv().ok();
+        let mut options = Options {
+            url: "http://localhost:8001".to_string(),
+            database_url: std::env::var("DATABASE_URL").unwrap_or_default(),
+            duration: Duration::from_secs(10),
+            concurrency: 32,
+            slow: 4,
+            api_key: None,
+        };
+
+        let mut args = std::env::args().skip(1);
+        while let Some(flag) = args.next() {
+            let value = args.next().ok_or_else(|| format!("{} expects a value", flag))?;
+            let number = || value.parse::<u64>().map_err(|_| format!("{} expects a number, got `{}`", flag, value));
+            match flag.as_str() {
+                "--url" => options.url = value.trim_end_matches('/').to_string(),
+                "--database-url" => options.database_url = value.clone(),
+                "--duration" => options.duration = Duration::from_secs(number()?),
+                "--concurrency" => options.concurrency = number()? as usize,
+                "--slow" => options.slow = number()? as usize,
+                "--api-key" => options.api_key = Some(value.clone()),
+                _ => return Err(format!("Unknown argument `{}`", flag)),
+            }
+        }
+
+        if options.database_url.is_empty() {
+            return Err("The database URL must be set with --database-url or DATABASE_URL".to_string());
+        }
+        Ok(options)
+    }
+}
+
+/// # Locks the `dishes` table until `release` receives a message or is dropped
+/// ## Returns
+/// * Once the lock is held, or an error if the database is not reachable
+fn lock_dishes(database_url: &str, release: mpsc::Receiver<()>) -> Result<thread::JoinHandle<()>, String> {
+    let mut conn = PgConnection::establish(database_url).map_err(|e| e.to_string())?;
+    conn.batch_execute("BEGIN; LOCK TABLE dishes IN ACCESS EXCLUSIVE MODE;").map_err(|e| e.to_string())?;
+    Ok(thread::spawn(move || {
+        let _ = release.recv();
+        let _ = conn.batch_execute("ROLLBACK;");
+    }))
+}
+
+/// # Sends requests to `url` one after another until `deadline`
+/// A request still running a second after the deadline is counted as failed
+/// ## Returns
+/// * The latency of every successful request and the number of failed requests
+async fn hammer(client: reqwest::Client, url: String, deadline: Instant) -> (Vec<Duration>, usize) {
+    let mut latencies = Vec::new();
+    let mut errors = 0;
+    while Instant::now() < deadline {
+        let started = Instant::now();
+        let timeout = deadline.saturating_duration_since(started) + Duration::from_secs(1);
+        match client.get(&url).timeout(timeout).send().await {
+            Ok(response) if response.status().is_success() => latencies.push(started.elapsed()),
+            _ => errors += 1,
+        }
+    }
+    (latencies, errors)
+}
+
+/// Returns the `q` quantile of sorted latencies
+fn quantile(sorted: &[Duration], q: f64) -> Duration {
+    if sorted.is_empty() {
+        return Duration::ZERO
+    }
+    sorted[((sorted.len() - 1) as f64 * q).round() as usize]
+}
+
+#[actix_web::main]
+async fn main() {
+    let options = match Options::parse() {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(2);
+        }
+    };
+    let mut headers = reqwest::header::HeaderMap::new();
+    if let Some(api_key) = &options.api_key {
+        match api_key.parse() {
+            Ok(api_key) => headers.insert(meals_api::auth::API_KEY_HEADER, api_key),
+            Err(_) => {
+                eprintln!("--api-key is not a valid header value");
+                std::process::exit(2);
+            }
+        };
+    }
+    let client = reqwest::Client::builder().default_headers(headers).build().expect("Failed to create the HTTP client");
+
+    /// Hold the lock for the whole run, so every slow request stays stuck until the end
+    let (release, released) = mpsc::channel();
+    let lock = match lock_dishes(&options.database_url, released) {
+        Ok(lock) => lock,
+        Err(e) => {
+            eprintln!("Could not lock the dishes table: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let started = Instant::now();
+    let deadline = started + options.duration;
+    let slow = (0..options.slow)
+        .map(|_| actix_web::rt::spawn(hammer(client.clone(), format!("{}/dishes", options.url), deadline)))
+        .collect::<Vec<_>>();
+    /// Give the slow requests a head start, so they reach the database before the measurement starts
+    actix_web::rt::time::sleep(Duration::from_millis(200)).await;
+    let fast = (0..options.concurrency)
+        .map(|_| actix_web::rt::spawn(hammer(client.clone(), format!("{}/meals", options.url), deadline)))
+        .collect::<Vec<_>>();
+
+    /// Release the lock at the deadline, so the slow requests can finish
+    actix_web::rt::spawn(async move {
+        actix_web::rt::time::sleep(deadline.saturating_duration_since(Instant::now())).await;
+        let _ = release.send(());
+    });
+
+    let fast = join_all(fast).await;
+    let elapsed = started.elapsed();
+    let slow = join_all(slow).await;
+    let _ = lock.join();
+
+    let mut latencies: Vec<Duration> = fast.iter().flatten().flat_map(|(latencies, _)| latencies.clone()).collect();
+    latencies.sort();
+    let errors: usize = fast.iter().flatten().map(|(_, errors)| errors).sum();
+    let slow_done: usize = slow.iter().flatten().map(|(latencies, _)| latencies.len()).sum();
+
+    println!("GET /meals with {} clients while {} clients wait on GET /dishes for {:?}", options.concurrency, options.slow, options.duration);
+    println!("  requests:   {} ok, {} failed", latencies.len(), errors);
+    println!("  throughput: {:.1} req/s", latencies.len() as f64 / elapsed.as_secs_f64());
+    println!("  latency:    p50 {:?}, p90 {:?}, p99 {:?}, max {:?}",
+        quantile(&latencies, 0.5), quantile(&latencies, 0.9), quantile(&latencies, 0.99), quantile(&latencies, 1.0));
+    println!("  slow GET /dishes completed after the lock was released: {}", slow_done);
+}
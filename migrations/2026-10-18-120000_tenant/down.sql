@@ -0,0 +1,14 @@
+-- Names are unique across all tenants again, so only the data of the default tenant is kept
+DELETE FROM meals WHERE tenant <> 'default';
+DELETE FROM dishes WHERE tenant <> 'default';
+DELETE FROM diets WHERE tenant <> 'default';
+
+DROP INDEX diets_tenant_name_idx;
+ALTER TABLE meals DROP CONSTRAINT meals_tenant_name_key;
+ALTER TABLE meals ADD CONSTRAINT meals_name_key UNIQUE ( name );
+ALTER TABLE dishes DROP CONSTRAINT dishes_tenant_name_key;
+ALTER TABLE dishes ADD CONSTRAINT dishes_name_key UNIQUE ( name );
+
+ALTER TABLE diets DROP COLUMN tenant;
+ALTER TABLE meals DROP COLUMN tenant;
+ALTER TABLE dishes DROP COLUMN tenant;
@@ -0,0 +1,14 @@
+-- Every dish, meal and diet belongs to a tenant, the rows stored before belong to the default tenant
+ALTER TABLE dishes ADD COLUMN tenant VARCHAR ( 64 ) NOT NULL DEFAULT 'default';
+ALTER TABLE meals ADD COLUMN tenant VARCHAR ( 64 ) NOT NULL DEFAULT 'default';
+ALTER TABLE diets ADD COLUMN tenant VARCHAR ( 64 ) NOT NULL DEFAULT 'default';
+ALTER TABLE dishes ALTER COLUMN tenant DROP DEFAULT;
+ALTER TABLE meals ALTER COLUMN tenant DROP DEFAULT;
+ALTER TABLE diets ALTER COLUMN tenant DROP DEFAULT;
+
+-- Names are only unique within a tenant
+ALTER TABLE dishes DROP CONSTRAINT dishes_name_key;
+ALTER TABLE dishes ADD CONSTRAINT dishes_tenant_name_key UNIQUE ( tenant, name );
+ALTER TABLE meals DROP CONSTRAINT meals_name_key;
+ALTER TABLE meals ADD CONSTRAINT meals_tenant_name_key UNIQUE ( tenant, name );
+CREATE INDEX diets_tenant_name_idx ON diets ( tenant, name );
@@ -0,0 +1,20 @@
+-- Users are the authenticated subjects of a tenant that registered a profile
+create table users (
+    id serial PRIMARY KEY,
+    tenant VARCHAR ( 64 ) NOT NULL,
+    subject VARCHAR ( 255 ) NOT NULL,
+    age INTEGER NOT NULL,
+    sex VARCHAR ( 16 ) NOT NULL,
+    weight_kg FLOAT NOT NULL,
+    height_cm FLOAT NOT NULL,
+    activity_level VARCHAR ( 16 ) NOT NULL,
+    UNIQUE ( tenant, subject )
+);
+
+-- The diets assigned to a user, by name since the diets are stored by the Diets Service
+create table user_diets (
+    user_id INTEGER NOT NULL REFERENCES users ( id ) ON DELETE CASCADE,
+    diet VARCHAR ( 255 ) NOT NULL,
+    active BOOLEAN NOT NULL DEFAULT TRUE,
+    PRIMARY KEY ( user_id, diet )
+);
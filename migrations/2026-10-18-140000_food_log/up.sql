@@ -0,0 +1,16 @@
+-- What the users ate. The nutrients are copied from the meal or dish when the entry is logged and scaled by the portion,
+-- so the diary stays the same when the meal or dish is changed or deleted
+create table food_log (
+    id serial PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users ( id ) ON DELETE CASCADE,
+    meal_id INTEGER REFERENCES meals ( id ) ON DELETE SET NULL,
+    dish_id INTEGER REFERENCES dishes ( id ) ON DELETE SET NULL,
+    name VARCHAR ( 255 ) NOT NULL,
+    portion FLOAT NOT NULL,
+    eaten_at TIMESTAMP NOT NULL,
+    cal FLOAT NOT NULL,
+    sodium FLOAT NOT NULL,
+    sugar FLOAT NOT NULL
+);
+
+create index food_log_user_eaten_at_idx on food_log ( user_id, eaten_at );
@@ -0,0 +1,18 @@
+-- Meal plans of a tenant, a calendar of consecutive days starting at start_date
+create table plans (
+    id serial PRIMARY KEY,
+    tenant VARCHAR ( 64 ) NOT NULL,
+    name VARCHAR ( 255 ) NOT NULL,
+    start_date DATE NOT NULL,
+    days INTEGER NOT NULL,
+    UNIQUE ( tenant, name )
+);
+
+-- The meal planned for a slot of a day, a meal leaves the plans when it is deleted
+create table plan_entries (
+    plan_id INTEGER NOT NULL REFERENCES plans ( id ) ON DELETE CASCADE,
+    day DATE NOT NULL,
+    slot VARCHAR ( 16 ) NOT NULL,
+    meal_id INTEGER NOT NULL REFERENCES meals ( id ) ON DELETE CASCADE,
+    PRIMARY KEY ( plan_id, day, slot )
+);
@@ -0,0 +1,3 @@
+DROP TRIGGER update_meals_of_dish_trigger ON dishes;
+DROP FUNCTION update_meals_of_dish();
+DROP TABLE dish_ingredients;
@@ -0,0 +1,29 @@
+-- The ingredient lines of a recipe dish, each with the nutrition of its quantity as resolved by the nutrition API.
+-- The nutrition of the dish is the sum of its lines
+create table dish_ingredients (
+    dish_id INTEGER NOT NULL REFERENCES dishes ( id ) ON DELETE CASCADE,
+    line INTEGER NOT NULL,
+    ingredient VARCHAR ( 255 ) NOT NULL,
+    quantity FLOAT NOT NULL,
+    unit VARCHAR ( 16 ) NOT NULL,
+    cal FLOAT NOT NULL,
+    sodium FLOAT NOT NULL,
+    sugar FLOAT NOT NULL,
+    size FLOAT NOT NULL,
+    PRIMARY KEY ( dish_id, line )
+);
+
+-- Function to recompute the meals containing a dish whose nutrition changed,
+-- the no-op update fires update_meal_nutrition_trigger on each of them
+CREATE OR REPLACE FUNCTION update_meals_of_dish() RETURNS TRIGGER AS $$
+BEGIN
+    UPDATE meals SET appetizer = appetizer
+    WHERE appetizer = NEW.id OR main = NEW.id OR dessert = NEW.id;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER update_meals_of_dish_trigger
+    AFTER UPDATE OF cal, sodium, sugar ON dishes
+    FOR EACH ROW
+EXECUTE FUNCTION update_meals_of_dish();
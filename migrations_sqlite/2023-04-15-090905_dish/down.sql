@@ -0,0 +1,2 @@
+DROP TABLE meals;
+DROP TABLE dishes;
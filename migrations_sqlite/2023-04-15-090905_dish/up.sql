@@ -0,0 +1,57 @@
+-- SQLite version of migrations/2023-04-15-090905_dish
+-- AUTOINCREMENT keeps IDs of deleted rows from being reused, like the serial columns of Postgres
+create table dishes (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name VARCHAR ( 50 ) UNIQUE NOT NULL,
+    cal DOUBLE NOT NULL,
+    sodium DOUBLE NOT NULL,
+    sugar DOUBLE NOT NULL,
+    size DOUBLE NOT NULL
+);
+create table meals (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name VARCHAR ( 50 ) UNIQUE NOT NULL,
+    appetizer INTEGER REFERENCES dishes ( id ) ON DELETE SET NULL,
+    main INTEGER REFERENCES dishes ( id ) ON DELETE SET NULL,
+    dessert INTEGER REFERENCES dishes ( id ) ON DELETE SET NULL,
+    cal DOUBLE,
+    sodium DOUBLE,
+    sugar DOUBLE
+);
+
+-- SQLite triggers can not change the NEW row, so the nutrition of a meal is written by a second UPDATE
+-- after every insert and update, including the updates done by ON DELETE SET NULL.
+-- The UPDATE of the trigger does not fire it again, since recursive triggers are off by default
+CREATE TRIGGER update_meal_nutrition_after_insert
+    AFTER INSERT ON meals
+    FOR EACH ROW
+BEGIN
+    UPDATE meals SET
+        cal = COALESCE((SELECT cal FROM dishes WHERE id = NEW.appetizer), 0)
+            + COALESCE((SELECT cal FROM dishes WHERE id = NEW.main), 0)
+            + COALESCE((SELECT cal FROM dishes WHERE id = NEW.dessert), 0),
+        sodium = COALESCE((SELECT sodium FROM dishes WHERE id = NEW.appetizer), 0)
+            + COALESCE((SELECT sodium FROM dishes WHERE id = NEW.main), 0)
+            + COALESCE((SELECT sodium FROM dishes WHERE id = NEW.dessert), 0),
+        sugar = COALESCE((SELECT sugar FROM dishes WHERE id = NEW.appetizer), 0)
+            + COALESCE((SELECT sugar FROM dishes WHERE id = NEW.main), 0)
+            + COALESCE((SELECT sugar FROM dishes WHERE id = NEW.dessert), 0)
+    WHERE id = NEW.id;
+END;
+
+CREATE TRIGGER update_meal_nutrition_after_update
+    AFTER UPDATE ON meals
+    FOR EACH ROW
+BEGIN
+    UPDATE meals SET
+        cal = COALESCE((SELECT cal FROM dishes WHERE id = NEW.appetizer), 0)
+            + COALESCE((SELECT cal FROM dishes WHERE id = NEW.main), 0)
+            + COALESCE((SELECT cal FROM dishes WHERE id = NEW.dessert), 0),
+        sodium = COALESCE((SELECT sodium FROM dishes WHERE id = NEW.appetizer), 0)
+            + COALESCE((SELECT sodium FROM dishes WHERE id = NEW.main), 0)
+            + COALESCE((SELECT sodium FROM dishes WHERE id = NEW.dessert), 0),
+        sugar = COALESCE((SELECT sugar FROM dishes WHERE id = NEW.appetizer), 0)
+            + COALESCE((SELECT sugar FROM dishes WHERE id = NEW.main), 0)
+            + COALESCE((SELECT sugar FROM dishes WHERE id = NEW.dessert), 0)
+    WHERE id = NEW.id;
+END;
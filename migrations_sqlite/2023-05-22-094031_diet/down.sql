@@ -0,0 +1 @@
+DROP TABLE diets;
\ No newline at end of file
@@ -0,0 +1,8 @@
+-- SQLite version of migrations/2023-05-22-094031_diet
+create table diets (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name varchar(255) NOT NULL,
+    cal DOUBLE NOT NULL,
+    sodium DOUBLE NOT NULL,
+    sugar DOUBLE NOT NULL
+);
@@ -0,0 +1,70 @@
+-- Names are unique across all tenants again, so only the data of the default tenant is kept
+create table dishes_old (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name VARCHAR ( 50 ) UNIQUE NOT NULL,
+    cal DOUBLE NOT NULL,
+    sodium DOUBLE NOT NULL,
+    sugar DOUBLE NOT NULL,
+    size DOUBLE NOT NULL
+);
+create table meals_old (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name VARCHAR ( 50 ) UNIQUE NOT NULL,
+    appetizer INTEGER REFERENCES dishes_old ( id ) ON DELETE SET NULL,
+    main INTEGER REFERENCES dishes_old ( id ) ON DELETE SET NULL,
+    dessert INTEGER REFERENCES dishes_old ( id ) ON DELETE SET NULL,
+    cal DOUBLE,
+    sodium DOUBLE,
+    sugar DOUBLE
+);
+
+INSERT INTO dishes_old (id, name, cal, sodium, sugar, size)
+    SELECT id, name, cal, sodium, sugar, size FROM dishes WHERE tenant = 'default';
+INSERT INTO meals_old (id, name, appetizer, main, dessert, cal, sodium, sugar)
+    SELECT id, name, appetizer, main, dessert, cal, sodium, sugar FROM meals WHERE tenant = 'default';
+DELETE FROM sqlite_sequence WHERE name IN ('dishes_old', 'meals_old');
+INSERT INTO sqlite_sequence (name, seq) SELECT 'dishes_old', seq FROM sqlite_sequence WHERE name = 'dishes';
+INSERT INTO sqlite_sequence (name, seq) SELECT 'meals_old', seq FROM sqlite_sequence WHERE name = 'meals';
+
+DROP TABLE meals;
+DROP TABLE dishes;
+ALTER TABLE dishes_old RENAME TO dishes;
+ALTER TABLE meals_old RENAME TO meals;
+
+DELETE FROM diets WHERE tenant <> 'default';
+DROP INDEX diets_tenant_name_idx;
+ALTER TABLE diets DROP COLUMN tenant;
+
+CREATE TRIGGER update_meal_nutrition_after_insert
+    AFTER INSERT ON meals
+    FOR EACH ROW
+BEGIN
+    UPDATE meals SET
+        cal = COALESCE((SELECT cal FROM dishes WHERE id = NEW.appetizer), 0)
+            + COALESCE((SELECT cal FROM dishes WHERE id = NEW.main), 0)
+            + COALESCE((SELECT cal FROM dishes WHERE id = NEW.dessert), 0),
+        sodium = COALESCE((SELECT sodium FROM dishes WHERE id = NEW.appetizer), 0)
+            + COALESCE((SELECT sodium FROM dishes WHERE id = NEW.main), 0)
+            + COALESCE((SELECT sodium FROM dishes WHERE id = NEW.dessert), 0),
+        sugar = COALESCE((SELECT sugar FROM dishes WHERE id = NEW.appetizer), 0)
+            + COALESCE((SELECT sugar FROM dishes WHERE id = NEW.main), 0)
+            + COALESCE((SELECT sugar FROM dishes WHERE id = NEW.dessert), 0)
+    WHERE id = NEW.id;
+END;
+
+CREATE TRIGGER update_meal_nutrition_after_update
+    AFTER UPDATE ON meals
+    FOR EACH ROW
+BEGIN
+    UPDATE meals SET
+        cal = COALESCE((SELECT cal FROM dishes WHERE id = NEW.appetizer), 0)
+            + COALESCE((SELECT cal FROM dishes WHERE id = NEW.main), 0)
+            + COALESCE((SELECT cal FROM dishes WHERE id = NEW.dessert), 0),
+        sodium = COALESCE((SELECT sodium FROM dishes WHERE id = NEW.appetizer), 0)
+            + COALESCE((SELECT sodium FROM dishes WHERE id = NEW.main), 0)
+            + COALESCE((SELECT sodium FROM dishes WHERE id = NEW.dessert), 0),
+        sugar = COALESCE((SELECT sugar FROM dishes WHERE id = NEW.appetizer), 0)
+            + COALESCE((SELECT sugar FROM dishes WHERE id = NEW.main), 0)
+            + COALESCE((SELECT sugar FROM dishes WHERE id = NEW.dessert), 0)
+    WHERE id = NEW.id;
+END;
@@ -0,0 +1,78 @@
+-- SQLite version of migrations/2026-10-18-120000_tenant
+-- SQLite can not change the UNIQUE constraints of a table, so dishes and meals are rebuilt with the tenant column
+-- and copied over. The rows stored before belong to the default tenant.
+-- The sequences are carried over so the IDs of deleted rows are still not reused
+create table dishes_new (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    tenant VARCHAR ( 64 ) NOT NULL,
+    name VARCHAR ( 50 ) NOT NULL,
+    cal DOUBLE NOT NULL,
+    sodium DOUBLE NOT NULL,
+    sugar DOUBLE NOT NULL,
+    size DOUBLE NOT NULL,
+    UNIQUE ( tenant, name )
+);
+create table meals_new (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    tenant VARCHAR ( 64 ) NOT NULL,
+    name VARCHAR ( 50 ) NOT NULL,
+    appetizer INTEGER REFERENCES dishes_new ( id ) ON DELETE SET NULL,
+    main INTEGER REFERENCES dishes_new ( id ) ON DELETE SET NULL,
+    dessert INTEGER REFERENCES dishes_new ( id ) ON DELETE SET NULL,
+    cal DOUBLE,
+    sodium DOUBLE,
+    sugar DOUBLE,
+    UNIQUE ( tenant, name )
+);
+
+INSERT INTO dishes_new (id, tenant, name, cal, sodium, sugar, size)
+    SELECT id, 'default', name, cal, sodium, sugar, size FROM dishes;
+INSERT INTO meals_new (id, tenant, name, appetizer, main, dessert, cal, sodium, sugar)
+    SELECT id, 'default', name, appetizer, main, dessert, cal, sodium, sugar FROM meals;
+DELETE FROM sqlite_sequence WHERE name IN ('dishes_new', 'meals_new');
+INSERT INTO sqlite_sequence (name, seq) SELECT 'dishes_new', seq FROM sqlite_sequence WHERE name = 'dishes';
+INSERT INTO sqlite_sequence (name, seq) SELECT 'meals_new', seq FROM sqlite_sequence WHERE name = 'meals';
+
+-- Renaming dishes_new also renames the references of meals_new to it
+DROP TABLE meals;
+DROP TABLE dishes;
+ALTER TABLE dishes_new RENAME TO dishes;
+ALTER TABLE meals_new RENAME TO meals;
+
+ALTER TABLE diets ADD COLUMN tenant VARCHAR ( 64 ) NOT NULL DEFAULT 'default';
+CREATE INDEX diets_tenant_name_idx ON diets ( tenant, name );
+
+-- The triggers of migrations_sqlite/2023-04-15-090905_dish were dropped with the old meals table
+CREATE TRIGGER update_meal_nutrition_after_insert
+    AFTER INSERT ON meals
+    FOR EACH ROW
+BEGIN
+    UPDATE meals SET
+        cal = COALESCE((SELECT cal FROM dishes WHERE id = NEW.appetizer), 0)
+            + COALESCE((SELECT cal FROM dishes WHERE id = NEW.main), 0)
+            + COALESCE((SELECT cal FROM dishes WHERE id = NEW.dessert), 0),
+        sodium = COALESCE((SELECT sodium FROM dishes WHERE id = NEW.appetizer), 0)
+            + COALESCE((SELECT sodium FROM dishes WHERE id = NEW.main), 0)
+            + COALESCE((SELECT sodium FROM dishes WHERE id = NEW.dessert), 0),
+        sugar = COALESCE((SELECT sugar FROM dishes WHERE id = NEW.appetizer), 0)
+            + COALESCE((SELECT sugar FROM dishes WHERE id = NEW.main), 0)
+            + COALESCE((SELECT sugar FROM dishes WHERE id = NEW.dessert), 0)
+    WHERE id = NEW.id;
+END;
+
+CREATE TRIGGER update_meal_nutrition_after_update
+    AFTER UPDATE ON meals
+    FOR EACH ROW
+BEGIN
+    UPDATE meals SET
+        cal = COALESCE((SELECT cal FROM dishes WHERE id = NEW.appetizer), 0)
+            + COALESCE((SELECT cal FROM dishes WHERE id = NEW.main), 0)
+            + COALESCE((SELECT cal FROM dishes WHERE id = NEW.dessert), 0),
+        sodium = COALESCE((SELECT sodium FROM dishes WHERE id = NEW.appetizer), 0)
+            + COALESCE((SELECT sodium FROM dishes WHERE id = NEW.main), 0)
+            + COALESCE((SELECT sodium FROM dishes WHERE id = NEW.dessert), 0),
+        sugar = COALESCE((SELECT sugar FROM dishes WHERE id = NEW.appetizer), 0)
+            + COALESCE((SELECT sugar FROM dishes WHERE id = NEW.main), 0)
+            + COALESCE((SELECT sugar FROM dishes WHERE id = NEW.dessert), 0)
+    WHERE id = NEW.id;
+END;
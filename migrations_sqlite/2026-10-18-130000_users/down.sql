@@ -0,0 +1,2 @@
+DROP TABLE user_diets;
+DROP TABLE users;
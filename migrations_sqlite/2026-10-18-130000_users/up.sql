@@ -0,0 +1,19 @@
+-- SQLite version of migrations/2026-10-18-130000_users
+create table users (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    tenant VARCHAR ( 64 ) NOT NULL,
+    subject VARCHAR ( 255 ) NOT NULL,
+    age INTEGER NOT NULL,
+    sex VARCHAR ( 16 ) NOT NULL,
+    weight_kg DOUBLE NOT NULL,
+    height_cm DOUBLE NOT NULL,
+    activity_level VARCHAR ( 16 ) NOT NULL,
+    UNIQUE ( tenant, subject )
+);
+
+create table user_diets (
+    user_id INTEGER NOT NULL REFERENCES users ( id ) ON DELETE CASCADE,
+    diet VARCHAR ( 255 ) NOT NULL,
+    active BOOLEAN NOT NULL DEFAULT TRUE,
+    PRIMARY KEY ( user_id, diet )
+);
@@ -0,0 +1,15 @@
+-- SQLite version of migrations/2026-10-18-140000_food_log
+create table food_log (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id INTEGER NOT NULL REFERENCES users ( id ) ON DELETE CASCADE,
+    meal_id INTEGER REFERENCES meals ( id ) ON DELETE SET NULL,
+    dish_id INTEGER REFERENCES dishes ( id ) ON DELETE SET NULL,
+    name VARCHAR ( 255 ) NOT NULL,
+    portion DOUBLE NOT NULL,
+    eaten_at TIMESTAMP NOT NULL,
+    cal DOUBLE NOT NULL,
+    sodium DOUBLE NOT NULL,
+    sugar DOUBLE NOT NULL
+);
+
+create index food_log_user_eaten_at_idx on food_log ( user_id, eaten_at );
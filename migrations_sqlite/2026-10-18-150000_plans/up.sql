@@ -0,0 +1,17 @@
+-- SQLite version of migrations/2026-10-18-150000_plans
+create table plans (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    tenant VARCHAR ( 64 ) NOT NULL,
+    name VARCHAR ( 255 ) NOT NULL,
+    start_date DATE NOT NULL,
+    days INTEGER NOT NULL,
+    UNIQUE ( tenant, name )
+);
+
+create table plan_entries (
+    plan_id INTEGER NOT NULL REFERENCES plans ( id ) ON DELETE CASCADE,
+    day DATE NOT NULL,
+    slot VARCHAR ( 16 ) NOT NULL,
+    meal_id INTEGER NOT NULL REFERENCES meals ( id ) ON DELETE CASCADE,
+    PRIMARY KEY ( plan_id, day, slot )
+);
@@ -0,0 +1,2 @@
+DROP TRIGGER update_meals_of_dish;
+DROP TABLE dish_ingredients;
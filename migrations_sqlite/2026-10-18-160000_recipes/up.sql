@@ -0,0 +1,23 @@
+-- SQLite version of migrations/2026-10-18-160000_recipes
+create table dish_ingredients (
+    dish_id INTEGER NOT NULL REFERENCES dishes ( id ) ON DELETE CASCADE,
+    line INTEGER NOT NULL,
+    ingredient VARCHAR ( 255 ) NOT NULL,
+    quantity DOUBLE NOT NULL,
+    unit VARCHAR ( 16 ) NOT NULL,
+    cal DOUBLE NOT NULL,
+    sodium DOUBLE NOT NULL,
+    sugar DOUBLE NOT NULL,
+    size DOUBLE NOT NULL,
+    PRIMARY KEY ( dish_id, line )
+);
+
+-- The no-op update fires update_meal_nutrition_after_update on the meals containing the dish,
+-- which recomputes their nutrition
+CREATE TRIGGER update_meals_of_dish
+    AFTER UPDATE OF cal, sodium, sugar ON dishes
+    FOR EACH ROW
+BEGIN
+    UPDATE meals SET appetizer = appetizer
+    WHERE appetizer = NEW.id OR main = NEW.id OR dessert = NEW.id;
+END;
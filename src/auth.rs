@@ -0,0 +1,563 @@
+#![allow(unused_doc_comments)]
+//! This file contains the authentication and authorization shared by both Services
+//!
+//! * [authenticate] is wrapped around the `App` of each Service. It reads the credentials of a request,
+//!   either a static API key in the `X-Api-Key` header or a JWT in the `Authorization: Bearer` header,
+//!   and stores the authenticated [Principal] in the request. Invalid credentials are rejected with
+//!   401 and error code -9, requests without credentials are passed on without a [Principal]
+//! * Every protected route handler declares the [Role] it requires by extracting [Require],
+//!   e.g. `_auth: Require<Editor>`. Requests without a [Principal] are rejected with 401 and error code -9,
+//!   requests of a [Principal] with a lower role with 403 and error code -10
+//!
+//! The roles are ordered, an `admin` may do everything an `editor` may do, an `editor` everything a `reader` may do.
+//! JWTs are verified with local keys only, an HS256 secret and/or an RS256 public key, and must carry the
+//! `sub`, `exp` and `role` claims.
+//!
+//! Every [Principal] acts for one tenant, whose dishes, meals and diets are isolated from those of the other tenants.
+//! API keys (`subject@tenant:role:key`) and JWTs (`tenant` claim) can be bound to a tenant. Principals without a bound
+//! tenant act for the tenant in the `X-Tenant-Id` header, or [DEFAULT_TENANT] without it. A header naming another tenant
+//! than the bound one is rejected with 403 and error code -10, an invalid tenant ID with 400.
+//!
+//! In [AuthMode::Dev] no credentials are checked and every request is handled as an `admin`,
+//! which is how the Services behaved before authentication was added. It has to be chosen explicitly, see [crate::config]
+
+/// Actix imports
+use actix_web::body::MessageBody;
+use actix_web::dev::{Payload, ServiceRequest, ServiceResponse};
+use actix_web::http::header::{HeaderMap, AUTHORIZATION, WWW_AUTHENTICATE};
+use actix_web::http::StatusCode;
+use actix_web::middleware::Next;
+use actix_web::web::Data;
+use actix_web::{Error, FromRequest, HttpMessage, HttpRequest, HttpResponse, ResponseError};
+
+/// Misc imports
+use jsonwebtoken::{Algorithm, DecodingKey, Validation};
+use serde::Deserialize;
+use std::fmt;
+use std::future::{ready, Ready};
+use std::marker::PhantomData;
+use std::str::FromStr;
+use tracing::{error, warn};
+
+/// Header carrying a static API key
+pub const API_KEY_HEADER: &str = "x-api-key";
+
+/// Header choosing the tenant of principals without a bound tenant
+pub const TENANT_HEADER: &str = "x-tenant-id";
+
+/// Tenant of the requests that do not name one, and of all data stored before tenants were introduced
+pub const DEFAULT_TENANT: &str = "default";
+
+/// Longest tenant ID, as long as the `tenant` columns
+const MAX_TENANT_LEN: usize = 64;
+
+/// Error codes of failed authentication and authorization, following the codes of the Assignment
+const UNAUTHENTICATED: &str = "-9";
+const FORBIDDEN: &str = "-10";
+const INTERNAL_SERVER_ERROR: &str = "-8";
+
+/// Subject of the [Principal] of every request in [AuthMode::Dev]
+const DEV_SUBJECT: &str = "dev";
+
+/// Role of a [Principal], ordered from the least to the most privileged
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    /// May read dishes, meals and diets
+    Reader,
+    /// May also create, update and delete dishes and meals
+    Editor,
+    /// May also create diets
+    Admin,
+}
+
+impl FromStr for Role {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Role, String> {
+        match s.trim() {
+            "reader" => Ok(Role::Reader),
+            "editor" => Ok(Role::Editor),
+            "admin" => Ok(Role::Admin),
+            other => Err(format!("unknown role `{}`, expected reader, editor or admin", other)),
+        }
+    }
+}
+
+impl fmt::Display for Role {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Role::Reader => f.write_str("reader"),
+            Role::Editor => f.write_str("editor"),
+            Role::Admin => f.write_str("admin"),
+        }
+    }
+}
+
+/// The authenticated caller of a request
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Principal {
+    /// Name of the API key or `sub` claim of the JWT
+    pub subject: String,
+    pub role: Role,
+    /// The tenant whose data the request reads and writes
+    pub tenant: String,
+}
+
+/// Whether a tenant ID is 1 to 64 ASCII letters, digits, `-` or `_`
+pub fn is_valid_tenant(tenant: &str) -> bool {
+    !tenant.is_empty() && tenant.len() <= MAX_TENANT_LEN && tenant.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_'))
+}
+
+/// Whether credentials are checked
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AuthMode {
+    /// Every request is handled as an `admin`, for local development only
+    Dev,
+    /// Protected routes require valid credentials
+    Enforced,
+}
+
+impl FromStr for AuthMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<AuthMode, String> {
+        match s.trim() {
+            "dev" => Ok(AuthMode::Dev),
+            "enforced" => Ok(AuthMode::Enforced),
+            other => Err(format!("unknown mode `{}`, expected dev or enforced", other)),
+        }
+    }
+}
+
+/// A static API key and the [Principal] it authenticates
+#[derive(Clone)]
+pub struct ApiKey {
+    pub subject: String,
+    pub role: Role,
+    /// The tenant the key is bound to, if any
+    pub tenant: Option<String>,
+    pub key: String,
+}
+
+impl FromStr for ApiKey {
+    type Err = String;
+
+    /// Parses `subject:role:key` or `subject@tenant:role:key`, e.g. `importer@team-a:editor:3f6c0b`.
+    /// The key itself may contain colons
+    fn from_str(s: &str) -> Result<ApiKey, String> {
+        let mut parts = s.trim().splitn(3, ':');
+        let (subject, role, key) = match (parts.next(), parts.next(), parts.next()) {
+            (Some(subject), Some(role), Some(key)) if !subject.is_empty() && !key.is_empty() => (subject, role, key),
+            _ => return Err("expected `subject:role:key`".to_string()),
+        };
+        let (subject, tenant) = match subject.split_once('@') {
+            Some((_, tenant)) if !is_valid_tenant(tenant) => return Err(format!("invalid tenant `{}` of {}", tenant, subject)),
+            Some((subject, tenant)) => (subject, Some(tenant.to_string())),
+            None => (subject, None),
+        };
+        Ok(ApiKey { subject: subject.to_string(), role: role.parse()?, tenant, key: key.to_string() })
+    }
+}
+
+/// A local key JWTs are verified with
+#[derive(Clone)]
+pub struct JwtKey {
+    pub algorithm: Algorithm,
+    pub key: DecodingKey,
+}
+
+/// Configuration of the authentication, see [crate::config]
+#[derive(Clone)]
+pub struct AuthConfig {
+    pub mode: AuthMode,
+    pub api_keys: Vec<ApiKey>,
+    pub jwt_keys: Vec<JwtKey>,
+}
+
+/// Lists the subjects and algorithms only, so the keys do not end up in the logs
+impl fmt::Debug for AuthConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let subjects: Vec<&str> = self.api_keys.iter().map(|api_key| api_key.subject.as_str()).collect();
+        let algorithms: Vec<Algorithm> = self.jwt_keys.iter().map(|jwt_key| jwt_key.algorithm).collect();
+        f.debug_struct("AuthConfig")
+            .field("mode", &self.mode)
+            .field("api_keys", &subjects)
+            .field("jwt_keys", &algorithms)
+            .finish()
+    }
+}
+
+/// Claims a JWT has to carry, the `tenant` claim is optional
+#[derive(Deserialize)]
+struct Claims {
+    sub: String,
+    role: Role,
+    tenant: Option<String>,
+}
+
+impl AuthConfig {
+
+    /// The open behavior without any credentials, see [AuthMode::Dev]
+    pub fn dev() -> AuthConfig {
+        AuthConfig { mode: AuthMode::Dev, api_keys: Vec::new(), jwt_keys: Vec::new() }
+    }
+
+    /// # Authenticates the credentials in the headers of a request
+    /// ## Arguments
+    /// * `headers` - The headers of the request
+    /// ## Returns
+    /// * The [Principal] of the credentials, [None] if there are none, or an [AuthError]
+    ///   if they are invalid or name the wrong tenant. In [AuthMode::Dev] always a [Principal] with the `admin` role
+    pub fn authenticate(&self, headers: &HeaderMap) -> Result<Option<Principal>, AuthError> {
+        let (subject, role, bound_tenant) = match self.credentials(headers)? {
+            Some(credentials) => credentials,
+            None => return Ok(None),
+        };
+
+        /// The tenant of the header, checked against the bound tenant
+        let tenant = match headers.get(TENANT_HEADER) {
+            Some(header) => {
+                let tenant = header.to_str().ok().filter(|tenant| is_valid_tenant(tenant))
+                    .ok_or_else(|| AuthError::InvalidTenant(String::from_utf8_lossy(header.as_bytes()).into_owned()))?;
+                match bound_tenant {
+                    Some(bound_tenant) if bound_tenant != tenant => {
+                        return Err(AuthError::WrongTenant { subject, tenant: tenant.to_string() })
+                    }
+                    _ => tenant.to_string(),
+                }
+            }
+            None => bound_tenant.unwrap_or_else(|| DEFAULT_TENANT.to_string()),
+        };
+        Ok(Some(Principal { subject, role, tenant }))
+    }
+
+    /// Checks the credentials of a request and returns their subject, role and bound tenant, if there are any
+    fn credentials(&self, headers: &HeaderMap) -> Result<Option<(String, Role, Option<String>)>, AuthError> {
+        if self.mode == AuthMode::Dev {
+            return Ok(Some((DEV_SUBJECT.to_string(), Role::Admin, None)))
+        }
+
+        if let Some(key) = headers.get(API_KEY_HEADER) {
+            let key = key.to_str().map_err(|_| AuthError::Unauthenticated("API key is not ASCII".to_string()))?;
+            /// Every key is compared, so the time taken does not tell which key almost matched
+            let found = self.api_keys.iter().fold(None, |found, api_key| {
+                if constant_time_eq(api_key.key.as_bytes(), key.as_bytes()) { Some(api_key) } else { found }
+            });
+            return match found {
+                Some(api_key) => Ok(Some((api_key.subject.clone(), api_key.role, api_key.tenant.clone()))),
+                None => Err(AuthError::Unauthenticated("unknown API key".to_string())),
+            }
+        }
+
+        match headers.get(AUTHORIZATION) {
+            Some(authorization) => {
+                let token = authorization.to_str().ok()
+                    .and_then(|authorization| authorization.strip_prefix("Bearer "))
+                    .ok_or_else(|| AuthError::Unauthenticated("Authorization header is not a bearer token".to_string()))?;
+                let claims = self.verify_jwt(token.trim())?;
+                match claims.tenant {
+                    Some(tenant) if !is_valid_tenant(&tenant) => Err(AuthError::Unauthenticated(format!("invalid tenant claim `{}`", tenant))),
+                    tenant => Ok(Some((claims.sub, claims.role, tenant))),
+                }
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Verifies the signature, expiry and claims of a JWT with the key of the algorithm named in its header
+    fn verify_jwt(&self, token: &str) -> Result<Claims, AuthError> {
+        let header = jsonwebtoken::decode_header(token).map_err(|e| AuthError::Unauthenticated(format!("invalid JWT: {}", e)))?;
+        let jwt_key = self.jwt_keys.iter().find(|jwt_key| jwt_key.algorithm == header.alg)
+            .ok_or_else(|| AuthError::Unauthenticated(format!("no key configured for JWTs signed with {:?}", header.alg)))?;
+
+        let mut validation = Validation::new(jwt_key.algorithm);
+        validation.set_required_spec_claims(&["exp", "sub"]);
+        let claims = jsonwebtoken::decode::<Claims>(token, &jwt_key.key, &validation)
+            .map_err(|e| AuthError::Unauthenticated(format!("invalid JWT: {}", e)))?
+            .claims;
+        Ok(claims)
+    }
+}
+
+/// Compares two byte strings in a time that only depends on their lengths
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+/// Error of a request that is not authenticated or not authorized
+#[derive(Debug)]
+pub enum AuthError {
+    /// The credentials are missing or invalid, with the reason that is logged but not returned
+    Unauthenticated(String),
+    /// The [Principal] does not have the required [Role]
+    Forbidden { subject: String, role: Role, required: Role },
+    /// The `X-Tenant-Id` header names another tenant than the one the credentials are bound to
+    WrongTenant { subject: String, tenant: String },
+    /// The `X-Tenant-Id` header is not a valid tenant ID
+    InvalidTenant(String),
+}
+
+impl fmt::Display for AuthError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AuthError::Unauthenticated(reason) => write!(f, "Not authenticated: {}", reason),
+            AuthError::Forbidden { subject, role, required } => write!(f, "{} has the role {} but needs {}", subject, role, required),
+            AuthError::WrongTenant { subject, tenant } => write!(f, "{} is not allowed to act for tenant {}", subject, tenant),
+            AuthError::InvalidTenant(tenant) => write!(f, "Invalid tenant ID `{}`", tenant),
+        }
+    }
+}
+
+impl ResponseError for AuthError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            AuthError::Unauthenticated(_) => StatusCode::UNAUTHORIZED,
+            AuthError::Forbidden { .. } | AuthError::WrongTenant { .. } => StatusCode::FORBIDDEN,
+            AuthError::InvalidTenant(_) => StatusCode::BAD_REQUEST,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        warn!(error = %self, "Request rejected");
+        match self {
+            AuthError::Unauthenticated(_) => HttpResponse::Unauthorized()
+                .insert_header((WWW_AUTHENTICATE, "Bearer"))
+                .body(UNAUTHENTICATED),
+            AuthError::Forbidden { .. } | AuthError::WrongTenant { .. } => HttpResponse::Forbidden().body(FORBIDDEN),
+            AuthError::InvalidTenant(_) => HttpResponse::BadRequest().body("Invalid tenant ID"),
+        }
+    }
+}
+
+/// # Middleware authenticating every request
+/// Stores the [Principal] of valid credentials in the request, see [Require].
+/// Needs the [AuthConfig] as app data and fails every request with 500 if it is missing, so a Service
+/// that was not set up for authentication is never open by accident
+///
+/// `App::new().app_data(Data::new(auth_config)).wrap(actix_web::middleware::from_fn(authenticate))`
+pub async fn authenticate(req: ServiceRequest, next: Next<impl MessageBody + 'static>) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let auth_config = match req.app_data::<Data<AuthConfig>>() {
+        Some(auth_config) => auth_config,
+        None => {
+            error!("The AuthConfig is not registered as app data");
+            let response = HttpResponse::InternalServerError().body(INTERNAL_SERVER_ERROR);
+            return Ok(req.into_response(response).map_into_right_body())
+        }
+    };
+
+    match auth_config.authenticate(req.headers()) {
+        Ok(principal) => {
+            if let Some(principal) = principal {
+                req.extensions_mut().insert(principal);
+            }
+            next.call(req).await.map(ServiceResponse::map_into_left_body)
+        }
+        Err(e) => Ok(req.error_response(e).map_into_right_body()),
+    }
+}
+
+/// A [Role] a route can require, see [Require]
+pub trait RequiredRole {
+    const ROLE: Role;
+}
+
+/// Marker of routes open to every [Role]
+pub struct Reader;
+/// Marker of routes open to editors and admins
+pub struct Editor;
+/// Marker of routes open to admins only
+pub struct Admin;
+
+impl RequiredRole for Reader {
+    const ROLE: Role = Role::Reader;
+}
+
+impl RequiredRole for Editor {
+    const ROLE: Role = Role::Editor;
+}
+
+impl RequiredRole for Admin {
+    const ROLE: Role = Role::Admin;
+}
+
+/// Declares the [Role] a route handler requires, e.g. `_auth: Require<Editor>`, and gives access to the [Principal]
+///
+/// Has to be the first argument of the handler, so the credentials are checked before the body is read
+pub struct Require<R: RequiredRole> {
+    pub principal: Principal,
+    role: PhantomData<R>,
+}
+
+/// Extracts the [Principal] stored by [authenticate] if it has at least the required [Role]
+impl<R: RequiredRole> FromRequest for Require<R> {
+    type Error = Error;
+    type Future = Ready<Result<Require<R>, Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let result = match req.extensions().get::<Principal>() {
+            None => Err(AuthError::Unauthenticated("no credentials".to_string()).into()),
+            Some(principal) if principal.role < R::ROLE => Err(AuthError::Forbidden {
+                subject: principal.subject.clone(),
+                role: principal.role,
+                required: R::ROLE,
+            }.into()),
+            Some(principal) => Ok(Require { principal: principal.clone(), role: PhantomData }),
+        };
+        ready(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::middleware::from_fn;
+    use actix_web::test::{self, TestRequest};
+    use actix_web::{get, post, App, Responder};
+    use jsonwebtoken::{EncodingKey, Header};
+    use serde_json::json;
+
+    #[get("/open")]
+    async fn open() -> impl Responder {
+        "open"
+    }
+
+    #[get("/read")]
+    async fn read(auth: Require<Reader>) -> impl Responder {
+        auth.principal.subject
+    }
+
+    #[post("/write")]
+    async fn write(_auth: Require<Editor>) -> impl Responder {
+        "written"
+    }
+
+    const SECRET: &[u8] = b"test secret";
+
+    fn enforced() -> AuthConfig {
+        AuthConfig {
+            mode: AuthMode::Enforced,
+            api_keys: vec!["importer:editor:key:with:colons".parse().unwrap(), "viewer:reader:viewer-key".parse().unwrap(), "acme@acme:reader:acme-key".parse().unwrap()],
+            jwt_keys: vec![
+                JwtKey { algorithm: Algorithm::HS256, key: DecodingKey::from_secret(SECRET) },
+                JwtKey {
+                    algorithm: Algorithm::RS256,
+                    key: DecodingKey::from_rsa_pem(include_bytes!("../fixtures/auth/rs256_public.pem")).unwrap(),
+                },
+            ],
+        }
+    }
+
+    /// Signs a JWT for `sub` with `role` that expires in `expires_in` seconds
+    fn jwt(algorithm: Algorithm, sub: &str, role: &str, expires_in: i64) -> String {
+        let exp = jsonwebtoken::get_current_timestamp() as i64 + expires_in;
+        let key = match algorithm {
+            Algorithm::RS256 => EncodingKey::from_rsa_pem(include_bytes!("../fixtures/auth/rs256_private.pem")).unwrap(),
+            _ => EncodingKey::from_secret(SECRET),
+        };
+        jsonwebtoken::encode(&Header::new(algorithm), &json!({"sub": sub, "role": role, "exp": exp}), &key).unwrap()
+    }
+
+    #[actix_web::test]
+    async fn roles_are_enforced() {
+        let app = test::init_service(App::new()
+            .app_data(Data::new(enforced()))
+            .wrap(from_fn(authenticate))
+            .service(open)
+            .service(read)
+            .service(write)).await;
+
+        /// Routes without a required role stay open, the others need credentials
+        assert_eq!(test::call_service(&app, TestRequest::get().uri("/open").to_request()).await.status(), 200);
+        let res = test::call_service(&app, TestRequest::get().uri("/read").to_request()).await;
+        assert_eq!(res.status(), 401);
+        assert_eq!(res.headers().get(WWW_AUTHENTICATE).unwrap(), "Bearer");
+        assert_eq!(test::read_body(res).await, UNAUTHENTICATED);
+
+        /// API keys
+        let req = TestRequest::get().uri("/read").insert_header((API_KEY_HEADER, "viewer-key"));
+        assert_eq!(test::call_and_read_body(&app, req.to_request()).await, "viewer");
+        let req = TestRequest::post().uri("/write").insert_header((API_KEY_HEADER, "viewer-key"));
+        let res = test::call_service(&app, req.to_request()).await;
+        assert_eq!(res.status(), 403);
+        assert_eq!(test::read_body(res).await, FORBIDDEN);
+        let req = TestRequest::post().uri("/write").insert_header((API_KEY_HEADER, "key:with:colons"));
+        assert_eq!(test::call_service(&app, req.to_request()).await.status(), 200);
+
+        /// Invalid credentials are rejected even on open routes
+        let req = TestRequest::get().uri("/open").insert_header((API_KEY_HEADER, "guess"));
+        assert_eq!(test::call_service(&app, req.to_request()).await.status(), 401);
+
+        /// JWTs signed with both algorithms
+        let bearer = |token: String| (AUTHORIZATION, format!("Bearer {}", token));
+        let req = TestRequest::post().uri("/write").insert_header(bearer(jwt(Algorithm::HS256, "alice", "editor", 60)));
+        assert_eq!(test::call_service(&app, req.to_request()).await.status(), 200);
+        let req = TestRequest::get().uri("/read").insert_header(bearer(jwt(Algorithm::RS256, "bob", "admin", 60)));
+        assert_eq!(test::call_and_read_body(&app, req.to_request()).await, "bob");
+        let req = TestRequest::post().uri("/write").insert_header(bearer(jwt(Algorithm::RS256, "carol", "reader", 60)));
+        assert_eq!(test::call_service(&app, req.to_request()).await.status(), 403);
+    }
+
+    #[actix_web::test]
+    async fn invalid_jwts_are_rejected() {
+        let auth_config = enforced();
+        let headers = |value: String| {
+            let mut headers = HeaderMap::new();
+            headers.insert(AUTHORIZATION, value.parse().unwrap());
+            headers
+        };
+        let rejected = |value: String| matches!(auth_config.authenticate(&headers(value)), Err(AuthError::Unauthenticated(_)));
+
+        /// Expired beyond the leeway, an unknown role, a wrong signature, an algorithm without key and no bearer scheme
+        assert!(rejected(format!("Bearer {}", jwt(Algorithm::HS256, "alice", "editor", -600))));
+        assert!(rejected(format!("Bearer {}", jwt(Algorithm::HS256, "alice", "owner", 60))));
+        let forged = jsonwebtoken::encode(&Header::new(Algorithm::HS256), &json!({"sub": "eve", "role": "admin", "exp": u32::MAX}), &EncodingKey::from_secret(b"guess")).unwrap();
+        assert!(rejected(format!("Bearer {}", forged)));
+        assert!(rejected(format!("Bearer {}", jwt(Algorithm::HS384, "alice", "editor", 60))));
+        assert!(rejected(jwt(Algorithm::HS256, "alice", "editor", 60)));
+
+        let principal = auth_config.authenticate(&headers(format!("Bearer {}", jwt(Algorithm::HS256, "alice", "admin", 60)))).unwrap();
+        assert_eq!(principal, Some(Principal { subject: "alice".to_string(), role: Role::Admin, tenant: DEFAULT_TENANT.to_string() }));
+    }
+
+    #[actix_web::test]
+    async fn tenants_are_resolved_from_credentials_and_header() {
+        let auth_config = enforced();
+        let tenant = |headers: &[(&'static str, String)]| {
+            let mut map = HeaderMap::new();
+            for (name, value) in headers {
+                map.insert(name.parse().unwrap(), value.parse().unwrap());
+            }
+            auth_config.authenticate(&map).map(|principal| principal.unwrap().tenant)
+        };
+
+        /// Unbound credentials act for the header or the default tenant
+        assert_eq!(tenant(&[(API_KEY_HEADER, "viewer-key".to_string())]).unwrap(), DEFAULT_TENANT);
+        assert_eq!(tenant(&[(API_KEY_HEADER, "viewer-key".to_string()), (TENANT_HEADER, "team-a".to_string())]).unwrap(), "team-a");
+        assert!(matches!(tenant(&[(API_KEY_HEADER, "viewer-key".to_string()), (TENANT_HEADER, "a b".to_string())]), Err(AuthError::InvalidTenant(_))));
+
+        /// Bound credentials act for their tenant and may only repeat it in the header
+        assert_eq!(tenant(&[(API_KEY_HEADER, "acme-key".to_string())]).unwrap(), "acme");
+        assert_eq!(tenant(&[(API_KEY_HEADER, "acme-key".to_string()), (TENANT_HEADER, "acme".to_string())]).unwrap(), "acme");
+        assert!(matches!(tenant(&[(API_KEY_HEADER, "acme-key".to_string()), (TENANT_HEADER, "team-a".to_string())]), Err(AuthError::WrongTenant { .. })));
+
+        let exp = jsonwebtoken::get_current_timestamp() + 60;
+        let token = jsonwebtoken::encode(&Header::new(Algorithm::HS256), &json!({"sub": "alice", "role": "reader", "tenant": "team-b", "exp": exp}), &EncodingKey::from_secret(SECRET)).unwrap();
+        assert_eq!(tenant(&[(AUTHORIZATION.as_str(), format!("Bearer {}", token))]).unwrap(), "team-b");
+
+        assert!("x@:reader:key".parse::<ApiKey>().is_err());
+        assert!("x@a/b:reader:key".parse::<ApiKey>().is_err());
+    }
+
+    #[actix_web::test]
+    async fn dev_mode_is_open_and_a_missing_config_is_not() {
+        let app = test::init_service(App::new()
+            .app_data(Data::new(AuthConfig::dev()))
+            .wrap(from_fn(authenticate))
+            .service(write)).await;
+        assert_eq!(test::call_service(&app, TestRequest::post().uri("/write").to_request()).await.status(), 200);
+
+        let app = test::init_service(App::new().wrap(from_fn(authenticate)).service(write)).await;
+        assert_eq!(test::call_service(&app, TestRequest::post().uri("/write").to_request()).await.status(), 500);
+    }
+}
@@ -0,0 +1,91 @@
+#![allow(unused_doc_comments)]
+
+/// Misc imports
+use reqwest::Method;
+
+/// Module imports
+use super::error::ClientError;
+use super::request::{read_json, read_text, Endpoint};
+
+/// Crate imports
+use crate::diets::{GeneratedDiet, NewDiet, ReqGeneratedDiet};
+
+/// Typed client for the Diets Service
+///
+/// Diets are served as [NewDiet], i.e. without their ID
+#[derive(Clone)]
+pub struct DietsClient {
+    endpoint: Endpoint,
+}
+
+impl DietsClient {
+
+    /// Creates a client for the Diets Service at `base_url`, e.g. `http://diets_api:8002`
+    pub fn new(base_url: &str) -> Result<DietsClient, ClientError> {
+        DietsClient::with_http_client(reqwest::Client::new(), base_url)
+    }
+
+    /// Creates a client that sends its requests with the given [reqwest::Client]
+    pub fn with_http_client(http: reqwest::Client, base_url: &str) -> Result<DietsClient, ClientError> {
+        Ok(DietsClient { endpoint: Endpoint::new(http, base_url)? })
+    }
+
+    /// Returns a copy of the client that sends `request_id` as `X-Request-Id` header with every request,
+    /// so the request can be followed across the Services in the logs
+    pub fn with_request_id(&self, request_id: &str) -> DietsClient {
+        let mut client = self.clone();
+        client.endpoint.request_id = Some(request_id.to_string());
+        client
+    }
+
+    /// Returns a copy of the client that authenticates with the static API key `api_key`
+    pub fn with_api_key(&self, api_key: &str) -> DietsClient {
+        let mut client = self.clone();
+        client.endpoint.api_key = Some(api_key.to_string());
+        client
+    }
+
+    /// Returns a copy of the client that authenticates with the JWT `token`
+    pub fn with_bearer_token(&self, token: &str) -> DietsClient {
+        let mut client = self.clone();
+        client.endpoint.bearer_token = Some(token.to_string());
+        client
+    }
+
+    /// Returns a copy of the client that acts for `tenant`, unless its credentials are bound to another tenant
+    pub fn with_tenant(&self, tenant: &str) -> DietsClient {
+        let mut client = self.clone();
+        client.endpoint.tenant = Some(tenant.to_string());
+        client
+    }
+
+    /// `GET /diets` - All diets
+    pub async fn get_diets(&self) -> Result<Vec<NewDiet>, ClientError> {
+        let response = self.endpoint.request(Method::GET, self.endpoint.url(&["diets"])).send().await?;
+        read_json(response).await
+    }
+
+    /// `POST /diets` - Creates a diet, returns the confirmation message of the Service
+    pub async fn create_diet(&self, diet: &NewDiet) -> Result<String, ClientError> {
+        let response = self.endpoint.request(Method::POST, self.endpoint.url(&["diets"])).json(diet).send().await?;
+        read_text(response).await
+    }
+
+    /// `POST /diets/generate` - Generates a diet from body metrics and a goal, returns it with the energy it is based on
+    pub async fn generate_diet(&self, diet: &ReqGeneratedDiet) -> Result<GeneratedDiet, ClientError> {
+        let response = self.endpoint.request(Method::POST, self.endpoint.url(&["diets", "generate"])).json(diet).send().await?;
+        read_json(response).await
+    }
+
+    /// `GET /diets/{id}`
+    pub async fn get_diet(&self, id: i32) -> Result<NewDiet, ClientError> {
+        let response = self.endpoint.request(Method::GET, self.endpoint.url(&["diets", &id.to_string()])).send().await?;
+        read_json(response).await
+    }
+
+    /// `GET /diets/{name}`
+    pub async fn get_diet_by_name(&self, name: &str) -> Result<NewDiet, ClientError> {
+        let response = self.endpoint.request(Method::GET, self.endpoint.url(&["diets", name])).send().await?;
+        read_json(response).await
+    }
+}
@@ -0,0 +1,140 @@
+#![allow(unused_doc_comments)]
+
+/// Misc imports
+use reqwest::StatusCode;
+use std::fmt;
+
+/// Numeric error codes the Services send in the body of failed requests, as defined in the Assignment
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ErrorCode {
+    /// 0 - The body is not in a supported content type or can not be parsed
+    NotJson,
+    /// -1 - A required parameter is missing
+    ParamNotFound,
+    /// -2 - A dish or meal with the same name already exists
+    AlreadyExists,
+    /// -3 - The nutrition API does not recognize the dish
+    DishNotRecognized,
+    /// -4 - The nutrition API is not reachable
+    NinjasUnavailable,
+    /// -5 - The dish or meal does not exist
+    NotFound,
+    /// -6 - A dish referenced by a meal does not exist
+    DishIdNotFound,
+    /// -7 - The diet does not exist
+    DietNotFound,
+    /// -8 - An unexpected error occurred in the Service
+    InternalServerError,
+    /// -9 - The credentials are missing or invalid
+    Unauthenticated,
+    /// -10 - The credentials do not have the role the route requires
+    Forbidden,
+    /// -11 - A value of the profile of a user is implausible
+    InvalidProfile,
+}
+
+impl ErrorCode {
+
+    /// Parses the body of a failed response into an [ErrorCode]
+    /// Returns [None] if the body is not one of the known codes
+    pub fn from_body(body: &str) -> Option<ErrorCode> {
+        match body.trim().trim_matches('"') {
+            "0" => Some(ErrorCode::NotJson),
+            "-1" => Some(ErrorCode::ParamNotFound),
+            "-2" => Some(ErrorCode::AlreadyExists),
+            "-3" => Some(ErrorCode::DishNotRecognized),
+            "-4" => Some(ErrorCode::NinjasUnavailable),
+            "-5" => Some(ErrorCode::NotFound),
+            "-6" => Some(ErrorCode::DishIdNotFound),
+            "-7" => Some(ErrorCode::DietNotFound),
+            "-8" => Some(ErrorCode::InternalServerError),
+            "-9" => Some(ErrorCode::Unauthenticated),
+            "-10" => Some(ErrorCode::Forbidden),
+            "-11" => Some(ErrorCode::InvalidProfile),
+            _ => None,
+        }
+    }
+
+    /// The numeric value of the code
+    pub fn code(&self) -> i32 {
+        match self {
+            ErrorCode::NotJson => 0,
+            ErrorCode::ParamNotFound => -1,
+            ErrorCode::AlreadyExists => -2,
+            ErrorCode::DishNotRecognized => -3,
+            ErrorCode::NinjasUnavailable => -4,
+            ErrorCode::NotFound => -5,
+            ErrorCode::DishIdNotFound => -6,
+            ErrorCode::DietNotFound => -7,
+            ErrorCode::InternalServerError => -8,
+            ErrorCode::Unauthenticated => -9,
+            ErrorCode::Forbidden => -10,
+            ErrorCode::InvalidProfile => -11,
+        }
+    }
+}
+
+/// Error returned by the API clients
+#[derive(Debug)]
+pub enum ClientError {
+    /// The request could not be sent or the response could not be read
+    Http(reqwest::Error),
+    /// The Service answered with an unsuccessful status
+    Api {
+        status: StatusCode,
+        /// The error code of the body, if the body is one of the known [ErrorCode]s
+        code: Option<ErrorCode>,
+        body: String,
+    },
+    /// The Service answered successfully, but the body does not have the expected shape
+    Decode(String),
+}
+
+impl ClientError {
+
+    /// The [ErrorCode] sent by the Service, if any
+    pub fn code(&self) -> Option<ErrorCode> {
+        match self {
+            ClientError::Api { code, .. } => *code,
+            _ => None,
+        }
+    }
+
+    /// The HTTP status sent by the Service, if a response was received
+    pub fn status(&self) -> Option<StatusCode> {
+        match self {
+            ClientError::Api { status, .. } => Some(*status),
+            _ => None,
+        }
+    }
+
+    /// Whether the Service answered with 404 Not Found
+    pub fn is_not_found(&self) -> bool {
+        self.status() == Some(StatusCode::NOT_FOUND)
+    }
+}
+
+impl fmt::Display for ClientError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClientError::Http(e) => write!(f, "Request failed: {}", e),
+            ClientError::Api { status, body, .. } => write!(f, "Service answered with {}: {}", status, body),
+            ClientError::Decode(e) => write!(f, "Unexpected response body: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for ClientError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ClientError::Http(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for ClientError {
+    fn from(e: reqwest::Error) -> Self {
+        ClientError::Http(e)
+    }
+}
@@ -0,0 +1,320 @@
+#![allow(unused_doc_comments)]
+
+/// Misc imports
+use std::collections::BTreeMap;
+
+/// Misc imports
+use chrono::NaiveDate;
+use reqwest::Method;
+
+/// Module imports
+use super::error::ClientError;
+use super::request::{read_id, read_json, read_text, Endpoint};
+
+/// Crate imports
+use crate::food_log::{DayLog, DayQuery, LogEntry, LogSummary, RangeQuery, ReqLogEntry};
+use crate::meals::{Dish, Ingredient, Meal, NewMeal, ReqDish, ReqIngredient, ReqSuggest, ReqSuggestion, Suggestion};
+use crate::plans::{Plan, PlanEntry, PlanValidation, PlanView, ReqPlan, ReqPlanDiet, ReqPlanGeneration, ReqPlanSlot, Slot};
+use crate::users::{ReqProfile, ReqUserDiet, User, UserDiet};
+
+/// Typed client for the Meals Service
+///
+/// Every method corresponds to one route of the Service. Failed requests are returned as
+/// [ClientError::Api] carrying the numeric [super::ErrorCode] sent by the Service
+#[derive(Clone)]
+pub struct MealsClient {
+    endpoint: Endpoint,
+}
+
+impl MealsClient {
+
+    /// Creates a client for the Meals Service at `base_url`, e.g. `http://meals_api:8001`
+    pub fn new(base_url: &str) -> Result<MealsClient, ClientError> {
+        MealsClient::with_http_client(reqwest::Client::new(), base_url)
+    }
+
+    /// Creates a client that sends its requests with the given [reqwest::Client]
+    pub fn with_http_client(http: reqwest::Client, base_url: &str) -> Result<MealsClient, ClientError> {
+        Ok(MealsClient { endpoint: Endpoint::new(http, base_url)? })
+    }
+
+    /// Returns a copy of the client that sends `request_id` as `X-Request-Id` header with every request,
+    /// so the request can be followed across the Services in the logs
+    pub fn with_request_id(&self, request_id: &str) -> MealsClient {
+        let mut client = self.clone();
+        client.endpoint.request_id = Some(request_id.to_string());
+        client
+    }
+
+    /// Returns a copy of the client that authenticates with the static API key `api_key`
+    pub fn with_api_key(&self, api_key: &str) -> MealsClient {
+        let mut client = self.clone();
+        client.endpoint.api_key = Some(api_key.to_string());
+        client
+    }
+
+    /// Returns a copy of the client that authenticates with the JWT `token`
+    pub fn with_bearer_token(&self, token: &str) -> MealsClient {
+        let mut client = self.clone();
+        client.endpoint.bearer_token = Some(token.to_string());
+        client
+    }
+
+    /// Returns a copy of the client that acts for `tenant`, unless its credentials are bound to another tenant
+    pub fn with_tenant(&self, tenant: &str) -> MealsClient {
+        let mut client = self.clone();
+        client.endpoint.tenant = Some(tenant.to_string());
+        client
+    }
+
+    /// `GET /dishes` - All dishes indexed by their ID
+    pub async fn get_dishes(&self) -> Result<BTreeMap<i32, Dish>, ClientError> {
+        let response = self.endpoint.request(Method::GET, self.endpoint.url(&["dishes"])).send().await?;
+        read_json(response).await
+    }
+
+    /// `POST /dishes` - Creates a dish from the nutrition information of `name`, returns its ID
+    pub async fn create_dish(&self, name: &str) -> Result<i32, ClientError> {
+        let body = ReqDish { name: Some(name.to_string()), ingredients: None };
+        let response = self.endpoint.request(Method::POST, self.endpoint.url(&["dishes"])).json(&body).send().await?;
+        read_id(response).await
+    }
+
+    /// `GET /dishes/{id}`
+    pub async fn get_dish(&self, id: i32) -> Result<Dish, ClientError> {
+        let response = self.endpoint.request(Method::GET, self.endpoint.url(&["dishes", &id.to_string()])).send().await?;
+        read_json(response).await
+    }
+
+    /// `GET /dishes/{name}`
+    pub async fn get_dish_by_name(&self, name: &str) -> Result<Dish, ClientError> {
+        let response = self.endpoint.request(Method::GET, self.endpoint.url(&["dishes", name])).send().await?;
+        read_json(response).await
+    }
+
+    /// `DELETE /dishes/{id}` - Returns the ID of the deleted dish
+    pub async fn delete_dish(&self, id: i32) -> Result<i32, ClientError> {
+        let response = self.endpoint.request(Method::DELETE, self.endpoint.url(&["dishes", &id.to_string()])).send().await?;
+        read_id(response).await
+    }
+
+    /// `DELETE /dishes/{name}` - Returns the ID of the deleted dish
+    pub async fn delete_dish_by_name(&self, name: &str) -> Result<i32, ClientError> {
+        let response = self.endpoint.request(Method::DELETE, self.endpoint.url(&["dishes", name])).send().await?;
+        read_id(response).await
+    }
+
+    /// `GET /meals` - All meals indexed by their ID
+    pub async fn get_meals(&self) -> Result<BTreeMap<i32, Meal>, ClientError> {
+        let response = self.endpoint.request(Method::GET, self.endpoint.url(&["meals"])).send().await?;
+        read_json(response).await
+    }
+
+    /// `GET /meals?diet={diet}` - The meals within the limits of the diet
+    pub async fn get_meals_for_diet(&self, diet: &str) -> Result<Vec<Meal>, ClientError> {
+        let response = self.endpoint.request(Method::GET, self.endpoint.url(&["meals"])).query(&[("diet", diet)]).send().await?;
+        read_json(response).await
+    }
+
+    /// `POST /meals` - Creates a meal, returns its ID
+    pub async fn create_meal(&self, meal: &NewMeal) -> Result<i32, ClientError> {
+        let response = self.endpoint.request(Method::POST, self.endpoint.url(&["meals"])).json(meal).send().await?;
+        read_id(response).await
+    }
+
+    /// `GET /meals/{id}`
+    pub async fn get_meal(&self, id: i32) -> Result<Meal, ClientError> {
+        let response = self.endpoint.request(Method::GET, self.endpoint.url(&["meals", &id.to_string()])).send().await?;
+        read_json(response).await
+    }
+
+    /// `GET /meals/{name}`
+    pub async fn get_meal_by_name(&self, name: &str) -> Result<Meal, ClientError> {
+        let response = self.endpoint.request(Method::GET, self.endpoint.url(&["meals", name])).send().await?;
+        read_json(response).await
+    }
+
+    /// `PUT /meals/{id}` - Replaces the meal, returns its ID
+    pub async fn update_meal(&self, id: i32, meal: &NewMeal) -> Result<i32, ClientError> {
+        let response = self.endpoint.request(Method::PUT, self.endpoint.url(&["meals", &id.to_string()])).json(meal).send().await?;
+        read_id(response).await
+    }
+
+    /// `DELETE /meals/{id}` - Returns the ID of the deleted meal
+    pub async fn delete_meal(&self, id: i32) -> Result<i32, ClientError> {
+        let response = self.endpoint.request(Method::DELETE, self.endpoint.url(&["meals", &id.to_string()])).send().await?;
+        read_id(response).await
+    }
+
+    /// `DELETE /meals/{name}` - Returns the ID of the deleted meal
+    pub async fn delete_meal_by_name(&self, name: &str) -> Result<i32, ClientError> {
+        let response = self.endpoint.request(Method::DELETE, self.endpoint.url(&["meals", name])).send().await?;
+        read_id(response).await
+    }
+
+    /// `GET /dishes/{id}/ingredients` - The ingredient lines of a recipe dish
+    pub async fn get_dish_ingredients(&self, id: i32) -> Result<Vec<Ingredient>, ClientError> {
+        let response = self.endpoint.request(Method::GET, self.endpoint.url(&["dishes", &id.to_string(), "ingredients"])).send().await?;
+        read_json(response).await
+    }
+
+    /// `PUT /dishes/{id}/ingredients` - Replaces the ingredient lines, returns the dish with its new nutrition
+    pub async fn set_dish_ingredients(&self, id: i32, ingredients: &[ReqIngredient]) -> Result<Dish, ClientError> {
+        let url = self.endpoint.url(&["dishes", &id.to_string(), "ingredients"]);
+        let response = self.endpoint.request(Method::PUT, url).json(ingredients).send().await?;
+        read_json(response).await
+    }
+
+    /// `PUT /dishes/{id}/ingredients/{line}` - Replaces or appends one line, starting at 1, returns the dish with its new nutrition
+    pub async fn set_dish_ingredient(&self, id: i32, line: usize, ingredient: &ReqIngredient) -> Result<Dish, ClientError> {
+        let url = self.endpoint.url(&["dishes", &id.to_string(), "ingredients", &line.to_string()]);
+        let response = self.endpoint.request(Method::PUT, url).json(ingredient).send().await?;
+        read_json(response).await
+    }
+
+    /// `DELETE /dishes/{id}/ingredients/{line}` - Removes one line, returns the dish with its new nutrition
+    pub async fn remove_dish_ingredient(&self, id: i32, line: usize) -> Result<Dish, ClientError> {
+        let url = self.endpoint.url(&["dishes", &id.to_string(), "ingredients", &line.to_string()]);
+        let response = self.endpoint.request(Method::DELETE, url).send().await?;
+        read_json(response).await
+    }
+
+    /// `GET /meals/suggest?diet={diet}&limit={limit}` - Combinations of dishes within the limits of the diet, the closest first
+    pub async fn get_meal_suggestions(&self, diet: &str, limit: Option<usize>) -> Result<Vec<Suggestion>, ClientError> {
+        let query = ReqSuggest { diet: Some(diet.to_string()), limit };
+        let response = self.endpoint.request(Method::GET, self.endpoint.url(&["meals", "suggest"])).query(&query).send().await?;
+        read_json(response).await
+    }
+
+    /// `POST /meals/suggest` - Stores a suggestion as a meal, returns its ID
+    pub async fn save_meal_suggestion(&self, suggestion: &ReqSuggestion) -> Result<i32, ClientError> {
+        let response = self.endpoint.request(Method::POST, self.endpoint.url(&["meals", "suggest"])).json(suggestion).send().await?;
+        read_id(response).await
+    }
+
+    /// `POST /users` - Registers the caller with its profile, returns the ID of the user
+    pub async fn register_user(&self, profile: &ReqProfile) -> Result<i32, ClientError> {
+        let response = self.endpoint.request(Method::POST, self.endpoint.url(&["users"])).json(profile).send().await?;
+        read_id(response).await
+    }
+
+    /// `GET /users/me` - The caller and its profile
+    pub async fn get_me(&self) -> Result<User, ClientError> {
+        let response = self.endpoint.request(Method::GET, self.endpoint.url(&["users", "me"])).send().await?;
+        read_json(response).await
+    }
+
+    /// `PUT /users/me` - Updates the given values of the profile, returns the updated user
+    pub async fn update_me(&self, profile: &ReqProfile) -> Result<User, ClientError> {
+        let response = self.endpoint.request(Method::PUT, self.endpoint.url(&["users", "me"])).json(profile).send().await?;
+        read_json(response).await
+    }
+
+    /// `GET /users/me/diets` - The diets of the caller ordered by name
+    pub async fn get_my_diets(&self) -> Result<Vec<UserDiet>, ClientError> {
+        let response = self.endpoint.request(Method::GET, self.endpoint.url(&["users", "me", "diets"])).send().await?;
+        read_json(response).await
+    }
+
+    /// `POST /users/me/diets` - Assigns a diet to the caller
+    pub async fn assign_diet(&self, diet: &ReqUserDiet) -> Result<UserDiet, ClientError> {
+        let response = self.endpoint.request(Method::POST, self.endpoint.url(&["users", "me", "diets"])).json(diet).send().await?;
+        read_json(response).await
+    }
+
+    /// `PUT /users/me/diets/{diet}` - Activates or deactivates a diet of the caller
+    pub async fn update_my_diet(&self, name: &str, diet: &ReqUserDiet) -> Result<UserDiet, ClientError> {
+        let response = self.endpoint.request(Method::PUT, self.endpoint.url(&["users", "me", "diets", name])).json(diet).send().await?;
+        read_json(response).await
+    }
+
+    /// `DELETE /users/me/diets/{diet}` - Returns the name of the removed diet
+    pub async fn remove_my_diet(&self, name: &str) -> Result<String, ClientError> {
+        let response = self.endpoint.request(Method::DELETE, self.endpoint.url(&["users", "me", "diets", name])).send().await?;
+        read_text(response).await
+    }
+
+    /// `POST /log` - Logs a meal or dish the caller ate
+    pub async fn log_entry(&self, entry: &ReqLogEntry) -> Result<LogEntry, ClientError> {
+        let response = self.endpoint.request(Method::POST, self.endpoint.url(&["log"])).json(entry).send().await?;
+        read_json(response).await
+    }
+
+    /// `GET /log?date={date}` - The entries of a day, today if no date is given
+    pub async fn get_day_log(&self, date: Option<NaiveDate>) -> Result<DayLog, ClientError> {
+        let query = DayQuery { date };
+        let response = self.endpoint.request(Method::GET, self.endpoint.url(&["log"])).query(&query).send().await?;
+        read_json(response).await
+    }
+
+    /// `GET /log/summary?from={from}&to={to}` - The daily and weekly totals of the date range
+    pub async fn get_log_summary(&self, from: NaiveDate, to: NaiveDate) -> Result<LogSummary, ClientError> {
+        let query = RangeQuery { from: Some(from), to: Some(to) };
+        let response = self.endpoint.request(Method::GET, self.endpoint.url(&["log", "summary"])).query(&query).send().await?;
+        read_json(response).await
+    }
+
+    /// `DELETE /log/{id}` - Returns the ID of the deleted entry
+    pub async fn delete_log_entry(&self, id: i32) -> Result<i32, ClientError> {
+        let response = self.endpoint.request(Method::DELETE, self.endpoint.url(&["log", &id.to_string()])).send().await?;
+        read_id(response).await
+    }
+
+    /// `GET /plans` - All plans indexed by their ID
+    pub async fn get_plans(&self) -> Result<BTreeMap<i32, Plan>, ClientError> {
+        let response = self.endpoint.request(Method::GET, self.endpoint.url(&["plans"])).send().await?;
+        read_json(response).await
+    }
+
+    /// `POST /plans` - Creates a plan, returns its ID
+    pub async fn create_plan(&self, plan: &ReqPlan) -> Result<i32, ClientError> {
+        let response = self.endpoint.request(Method::POST, self.endpoint.url(&["plans"])).json(plan).send().await?;
+        read_id(response).await
+    }
+
+    /// `POST /plans/generate` - Generates and stores a plan within the limits of a diet
+    pub async fn generate_plan(&self, generation: &ReqPlanGeneration) -> Result<PlanView, ClientError> {
+        let response = self.endpoint.request(Method::POST, self.endpoint.url(&["plans", "generate"])).json(generation).send().await?;
+        read_json(response).await
+    }
+
+    /// `GET /plans/{id}` - The plan with its daily and weekly totals
+    pub async fn get_plan(&self, id: i32) -> Result<PlanView, ClientError> {
+        let response = self.endpoint.request(Method::GET, self.endpoint.url(&["plans", &id.to_string()])).send().await?;
+        read_json(response).await
+    }
+
+    /// `PUT /plans/{id}` - Replaces the plan, returns its ID
+    pub async fn update_plan(&self, id: i32, plan: &ReqPlan) -> Result<i32, ClientError> {
+        let response = self.endpoint.request(Method::PUT, self.endpoint.url(&["plans", &id.to_string()])).json(plan).send().await?;
+        read_id(response).await
+    }
+
+    /// `DELETE /plans/{id}` - Returns the ID of the deleted plan
+    pub async fn delete_plan(&self, id: i32) -> Result<i32, ClientError> {
+        let response = self.endpoint.request(Method::DELETE, self.endpoint.url(&["plans", &id.to_string()])).send().await?;
+        read_id(response).await
+    }
+
+    /// `PUT /plans/{id}/days/{date}/{slot}` - Plans a meal for a slot
+    pub async fn set_plan_slot(&self, id: i32, date: NaiveDate, slot: Slot, meal: i32) -> Result<PlanEntry, ClientError> {
+        let url = self.endpoint.url(&["plans", &id.to_string(), "days", &date.to_string(), slot.as_str()]);
+        let response = self.endpoint.request(Method::PUT, url).json(&ReqPlanSlot { meal: Some(meal) }).send().await?;
+        read_json(response).await
+    }
+
+    /// `DELETE /plans/{id}/days/{date}/{slot}` - Empties a slot, returns the ID of the plan
+    pub async fn remove_plan_slot(&self, id: i32, date: NaiveDate, slot: Slot) -> Result<i32, ClientError> {
+        let url = self.endpoint.url(&["plans", &id.to_string(), "days", &date.to_string(), slot.as_str()]);
+        let response = self.endpoint.request(Method::DELETE, url).send().await?;
+        read_id(response).await
+    }
+
+    /// `GET /plans/{id}/validation?diet={diet}` - The totals of the plan compared against the diet
+    pub async fn validate_plan(&self, id: i32, diet: &str) -> Result<PlanValidation, ClientError> {
+        let query = ReqPlanDiet { diet: Some(diet.to_string()) };
+        let url = self.endpoint.url(&["plans", &id.to_string(), "validation"]);
+        let response = self.endpoint.request(Method::GET, url).query(&query).send().await?;
+        read_json(response).await
+    }
+}
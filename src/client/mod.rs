@@ -0,0 +1,9 @@
+// Typed API client module
+mod diets_client;
+mod error;
+mod meals_client;
+mod request;
+
+pub use diets_client::DietsClient;
+pub use error::{ClientError, ErrorCode};
+pub use meals_client::MealsClient;
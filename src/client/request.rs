@@ -0,0 +1,112 @@
+#![allow(unused_doc_comments)]
+
+/// Misc imports
+use reqwest::{Method, RequestBuilder, Response, Url};
+use serde::de::DeserializeOwned;
+
+/// Module imports
+use super::error::{ClientError, ErrorCode};
+
+/// Base URL of a Service together with the HTTP client used to reach it
+#[derive(Clone)]
+pub struct Endpoint {
+    pub http: reqwest::Client,
+    pub base_url: Url,
+    /// Sent as `X-Request-Id` header with every request, see [crate::telemetry]
+    pub request_id: Option<String>,
+    /// Sent as `X-Api-Key` header with every request, see [crate::auth]
+    pub api_key: Option<String>,
+    /// Sent as `Authorization: Bearer` header with every request, see [crate::auth]
+    pub bearer_token: Option<String>,
+    /// Sent as `X-Tenant-Id` header with every request, see [crate::auth]
+    pub tenant: Option<String>,
+}
+
+impl Endpoint {
+
+    /// Creates an [Endpoint], the base URL may or may not end with a slash
+    pub fn new(http: reqwest::Client, base_url: &str) -> Result<Endpoint, ClientError> {
+        let base_url = Url::parse(base_url).map_err(|e| ClientError::Decode(format!("Invalid base URL {}: {}", base_url, e)))?;
+        Ok(Endpoint { http, base_url, request_id: None, api_key: None, bearer_token: None, tenant: None })
+    }
+
+    /// Starts a request, adding the `X-Request-Id` header if a request ID is set and the credentials and tenant if any are set
+    pub fn request(&self, method: Method, url: Url) -> RequestBuilder {
+        let mut request = self.http.request(method, url);
+        if let Some(request_id) = &self.request_id {
+            request = request.header(crate::telemetry::REQUEST_ID_HEADER, request_id);
+        }
+        if let Some(api_key) = &self.api_key {
+            request = request.header(crate::auth::API_KEY_HEADER, api_key);
+        }
+        if let Some(bearer_token) = &self.bearer_token {
+            request = request.bearer_auth(bearer_token);
+        }
+        if let Some(tenant) = &self.tenant {
+            request = request.header(crate::auth::TENANT_HEADER, tenant);
+        }
+        request
+    }
+
+    /// Builds the URL of a resource, percent encoding every path segment
+    /// e.g. `["dishes", "apple pie"]` becomes `{base_url}/dishes/apple%20pie`
+    pub fn url(&self, segments: &[&str]) -> Url {
+        let mut url = self.base_url.clone();
+        if let Ok(mut path) = url.path_segments_mut() {
+            path.pop_if_empty().extend(segments);
+        }
+        url
+    }
+}
+
+/// Returns the response if its status is successful, otherwise turns it into a [ClientError::Api]
+pub async fn check_status(response: Response) -> Result<Response, ClientError> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response)
+    }
+    let body = response.text().await.unwrap_or_default();
+    Err(ClientError::Api { status, code: ErrorCode::from_body(&body), body })
+}
+
+/// Reads a successful JSON response into `T`
+pub async fn read_json<T: DeserializeOwned>(response: Response) -> Result<T, ClientError> {
+    let response = check_status(response).await?;
+    let body = response.bytes().await?;
+    serde_json::from_slice(&body).map_err(|e| ClientError::Decode(e.to_string()))
+}
+
+/// Reads a successful response whose body is a plain ID, as returned by the creation and deletion routes
+pub async fn read_id(response: Response) -> Result<i32, ClientError> {
+    let response = check_status(response).await?;
+    let body = response.text().await?;
+    body.trim().parse().map_err(|_| ClientError::Decode(format!("Expected an ID, got '{}'", body)))
+}
+
+/// Reads a successful response with a plain text body
+pub async fn read_text(response: Response) -> Result<String, ClientError> {
+    let response = check_status(response).await?;
+    Ok(response.text().await?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn url_encodes_segments_and_ignores_trailing_slash() {
+        for base_url in ["http://diets_api:8002", "http://diets_api:8002/"] {
+            let endpoint = Endpoint::new(reqwest::Client::new(), base_url).unwrap();
+            assert_eq!(endpoint.url(&["diets", "low sugar/fat"]).as_str(), "http://diets_api:8002/diets/low%20sugar%2Ffat");
+        }
+    }
+
+    #[test]
+    fn error_codes_are_parsed_from_the_body() {
+        assert_eq!(ErrorCode::from_body("-5"), Some(ErrorCode::NotFound));
+        assert_eq!(ErrorCode::from_body("\"-3\"\n"), Some(ErrorCode::DishNotRecognized));
+        assert_eq!(ErrorCode::from_body("Diet low not found"), None);
+        assert_eq!(ErrorCode::from_body("-11"), Some(ErrorCode::InvalidProfile));
+        assert_eq!(ErrorCode::NinjasUnavailable.code(), -4);
+    }
+}
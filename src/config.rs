@@ -0,0 +1,593 @@
+#![allow(unused_doc_comments)]
+//! This file contains the configuration shared by all binaries
+//!
+//! The [Config] is loaded once at startup by [Config::load] from, in increasing order of precedence:
+//! * the built-in defaults
+//! * an optional TOML file, given by `--config FILE` or the `CONFIG_FILE` environment variable
+//! * the environment and the `.env` file
+//! * the command line flags
+//!
+//! Every setting is validated before the Service starts and all problems are reported at once, see [ConfigError].
+//! The Services pass the [Config] to their route handlers with `app_data`.
+//!
+//! | TOML key | Environment variable | Flag | Used by |
+//! |---|---|---|---|
+//! | `host` | `HOST` | `--host` | Meals, Diets |
+//! | `port` | `PORT` | `--port` | Meals, Diets |
+//! | `database_url` | `DATABASE_URL` | `--database-url` | all |
+//! | `database_pool.max_size` | `DATABASE_POOL_SIZE` | `--database-pool-size` | all |
+//! | `database_pool.timeout_ms` | `DATABASE_POOL_TIMEOUT_MS` | `--database-pool-timeout-ms` | all |
+//! | `diets_service.url` | `DIET_SERVICE_URL` | `--diet-service-url` | Meals |
+//! | `diets_service.timeout_ms` | `DIET_CLIENT_TIMEOUT_MS` | `--diet-client-timeout-ms` | Meals |
+//! | `diets_service.retries` | `DIET_CLIENT_RETRIES` | `--diet-client-retries` | Meals |
+//! | `diets_service.retry_backoff_ms` | `DIET_CLIENT_RETRY_BACKOFF_MS` | `--diet-client-retry-backoff-ms` | Meals |
+//! | `diets_service.cache_ttl_secs` | `DIET_CACHE_TTL_SECS` | `--diet-cache-ttl-secs` | Meals |
+//! | `diets_service.api_key` | `DIET_SERVICE_API_KEY` | `--diet-service-api-key` | Meals |
+//! | `ninjas.base_url` | `NINJAS_API_BASE_URL` | `--ninjas-api-base-url` | Meals |
+//! | `ninjas.api_key` | `NINJAS_API_KEY` | `--ninjas-api-key` | Meals |
+//! | `auth.mode` | `AUTH_MODE` | `--auth-mode` | Meals, Diets |
+//! | `auth.api_keys` | `AUTH_API_KEYS` | `--auth-api-keys` | Meals, Diets |
+//! | `auth.jwt_hs256_secret` | `AUTH_JWT_HS256_SECRET` | `--auth-jwt-hs256-secret` | Meals, Diets |
+//! | `auth.jwt_rs256_public_key_file` | `AUTH_JWT_RS256_PUBLIC_KEY_FILE` | `--auth-jwt-rs256-public-key-file` | Meals, Diets |
+//!
+//! The authentication is enforced unless `auth.mode` is `dev`, see [crate::auth]
+//!
+//! `diets_service.url` is optional. Without it the Meals Service still starts, but answers the requests that look up a diet with 503
+
+/// Misc imports
+use dotenv::dotenv;
+use jsonwebtoken::{Algorithm, DecodingKey};
+use reqwest::Url;
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+/// Crate imports
+use crate::auth::{ApiKey, AuthConfig, AuthMode, JwtKey};
+use crate::db::PoolConfig;
+use crate::meals::{DietClientConfig, NinjasConfig};
+
+/// Environment variable naming the optional TOML file
+const CONFIG_FILE_ENV: &str = "CONFIG_FILE";
+/// Flag naming the optional TOML file
+const CONFIG_FILE_FLAG: &str = "--config";
+/// Shortest HS256 secret accepted, as long as the output of SHA-256
+const MIN_HS256_SECRET_LEN: usize = 32;
+
+/// The binary a [Config] is loaded for, which decides the required settings and the default port
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Service {
+    Meals,
+    Diets,
+    DataTransfer,
+}
+
+impl Service {
+
+    /// The port the Service listens on if none is configured
+    fn default_port(&self) -> u16 {
+        match self {
+            Service::Meals => 8001,
+            Service::Diets => 8002,
+            Service::DataTransfer => 0,
+        }
+    }
+}
+
+/// A single setting and the names it has in the TOML file, the environment and on the command line
+struct Setting {
+    key: &'static str,
+    env: &'static str,
+    flag: &'static str,
+}
+
+/// All known settings, see the table in the module documentation
+const SETTINGS: [Setting; 17] = [
+    Setting { key: "host", env: "HOST", flag: "--host" },
+    Setting { key: "port", env: "PORT", flag: "--port" },
+    Setting { key: "database_url", env: "DATABASE_URL", flag: "--database-url" },
+    Setting { key: "database_pool.max_size", env: "DATABASE_POOL_SIZE", flag: "--database-pool-size" },
+    Setting { key: "database_pool.timeout_ms", env: "DATABASE_POOL_TIMEOUT_MS", flag: "--database-pool-timeout-ms" },
+    Setting { key: "diets_service.url", env: "DIET_SERVICE_URL", flag: "--diet-service-url" },
+    Setting { key: "diets_service.timeout_ms", env: "DIET_CLIENT_TIMEOUT_MS", flag: "--diet-client-timeout-ms" },
+    Setting { key: "diets_service.retries", env: "DIET_CLIENT_RETRIES", flag: "--diet-client-retries" },
+    Setting { key: "diets_service.retry_backoff_ms", env: "DIET_CLIENT_RETRY_BACKOFF_MS", flag: "--diet-client-retry-backoff-ms" },
+    Setting { key: "diets_service.cache_ttl_secs", env: "DIET_CACHE_TTL_SECS", flag: "--diet-cache-ttl-secs" },
+    Setting { key: "diets_service.api_key", env: "DIET_SERVICE_API_KEY", flag: "--diet-service-api-key" },
+    Setting { key: "ninjas.base_url", env: "NINJAS_API_BASE_URL", flag: "--ninjas-api-base-url" },
+    Setting { key: "ninjas.api_key", env: "NINJAS_API_KEY", flag: "--ninjas-api-key" },
+    Setting { key: "auth.mode", env: "AUTH_MODE", flag: "--auth-mode" },
+    Setting { key: "auth.api_keys", env: "AUTH_API_KEYS", flag: "--auth-api-keys" },
+    Setting { key: "auth.jwt_hs256_secret", env: "AUTH_JWT_HS256_SECRET", flag: "--auth-jwt-hs256-secret" },
+    Setting { key: "auth.jwt_rs256_public_key_file", env: "AUTH_JWT_RS256_PUBLIC_KEY_FILE", flag: "--auth-jwt-rs256-public-key-file" },
+];
+
+/// Configuration of a binary
+#[derive(Clone, Debug)]
+pub struct Config {
+    /// Address the HTTP server binds to
+    pub host: String,
+    /// Port the HTTP server binds to
+    pub port: u16,
+    /// Connection URL of the Postgres database, or the path of the database file with the `sqlite` feature
+    pub database_url: String,
+    /// Size of the connection pool and how long requests wait for a connection
+    pub database_pool: PoolConfig,
+    /// Client configuration of the Diets Service, set for [Service::Meals] if `diets_service.url` is configured
+    pub diets_service: Option<DietClientConfig>,
+    /// Configuration of the Ninjas nutrition API, always set for [Service::Meals]
+    pub ninjas: Option<NinjasConfig>,
+    /// Authentication of the requests, always set for [Service::Meals] and [Service::Diets]
+    pub auth: Option<AuthConfig>,
+}
+
+/// All problems found while loading a [Config]
+#[derive(Debug)]
+pub struct ConfigError {
+    pub errors: Vec<String>,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid configuration:")?;
+        for error in &self.errors {
+            write!(f, "\n  - {}", error)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// A raw setting value and where it came from, used in error messages
+struct RawValue {
+    value: String,
+    source: String,
+}
+
+/// Collects the raw values of all layers and the problems found on the way
+#[derive(Default)]
+struct Loader {
+    values: HashMap<&'static str, RawValue>,
+    errors: Vec<String>,
+}
+
+impl Loader {
+
+    /// Sets a value, overriding the value of a lower layer
+    fn set(&mut self, key: &'static str, value: String, source: String) {
+        self.values.insert(key, RawValue { value, source });
+    }
+
+    /// Reads the TOML file and adds its values
+    fn load_file(&mut self, path: &str) {
+        let contents = match std::fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) => return self.errors.push(format!("Could not read the config file {}: {}", path, e)),
+        };
+        let table: toml::Table = match contents.parse() {
+            Ok(table) => table,
+            Err(e) => return self.errors.push(format!("Could not parse the config file {}: {}", path, e)),
+        };
+
+        /// Flatten the sections into dotted keys, e.g. `[ninjas] api_key` into `ninjas.api_key`
+        let mut entries = Vec::new();
+        for (key, value) in table {
+            match value {
+                toml::Value::Table(section) => {
+                    entries.extend(section.into_iter().map(|(field, value)| (format!("{}.{}", key, field), value)));
+                }
+                value => entries.push((key, value)),
+            }
+        }
+
+        for (key, value) in entries {
+            let setting = match SETTINGS.iter().find(|setting| setting.key == key) {
+                Some(setting) => setting,
+                None => {
+                    self.errors.push(format!("Unknown key `{}` in the config file {}", key, path));
+                    continue;
+                }
+            };
+            let value = match value {
+                toml::Value::String(value) => value,
+                toml::Value::Integer(value) => value.to_string(),
+                other => {
+                    self.errors.push(format!("`{}` in the config file {} must be a string or an integer, not {}", key, path, other.type_str()));
+                    continue;
+                }
+            };
+            self.set(setting.key, value, format!("`{}` in {}", key, path));
+        }
+    }
+
+    /// Adds the values of the environment
+    fn load_env(&mut self, env: &impl Fn(&str) -> Option<String>) {
+        for setting in &SETTINGS {
+            if let Some(value) = env(setting.env) {
+                self.set(setting.key, value, format!("environment variable {}", setting.env));
+            }
+        }
+    }
+
+    /// Adds the values of the parsed command line flags
+    fn load_flags(&mut self, flags: Vec<(&'static Setting, String)>) {
+        for (setting, value) in flags {
+            self.set(setting.key, value, format!("flag {}", setting.flag));
+        }
+    }
+
+    /// Returns a string setting, recording an error if it is required but missing
+    fn string(&mut self, key: &str, required: bool) -> Option<String> {
+        match self.values.get(key) {
+            Some(raw) if raw.value.trim().is_empty() => {
+                let error = format!("{} must not be empty", raw.source);
+                self.errors.push(error);
+                None
+            }
+            Some(raw) => Some(raw.value.clone()),
+            None => {
+                if required {
+                    let setting = SETTINGS.iter().find(|setting| setting.key == key).expect("Unknown setting");
+                    self.errors.push(format!("{} must be set (environment variable {}, flag {} or `{}` in the config file)", setting.key, setting.env, setting.flag, setting.key));
+                }
+                None
+            }
+        }
+    }
+
+    /// Returns a setting parsed into `T`, recording an error if it can not be parsed
+    fn parsed<T: FromStr>(&mut self, key: &str, expected: &str) -> Option<T> {
+        let raw = self.values.get(key)?;
+        match raw.value.trim().parse() {
+            Ok(value) => Some(value),
+            Err(_) => {
+                let error = format!("{} must be {}, got `{}`", raw.source, expected, raw.value);
+                self.errors.push(error);
+                None
+            }
+        }
+    }
+
+    /// Returns a URL setting, recording an error if it is not a valid URL or if it is missing and required
+    fn url(&mut self, key: &str, required: bool) -> Option<String> {
+        let value = self.string(key, required)?;
+        match Url::parse(&value) {
+            Ok(url) if url.scheme() == "http" || url.scheme() == "https" => Some(value),
+            Ok(_) => {
+                let error = format!("{} must be an http or https URL, got `{}`", self.values[key].source, value);
+                self.errors.push(error);
+                None
+            }
+            Err(e) => {
+                let error = format!("{} must be a valid URL, got `{}`: {}", self.values[key].source, value, e);
+                self.errors.push(error);
+                None
+            }
+        }
+    }
+
+    /// Returns the authentication settings, recording an error for every invalid key
+    /// and if no credentials are configured while the authentication is enforced
+    fn auth(&mut self) -> AuthConfig {
+        let mode = self.string("auth.mode", false).map(|mode| match mode.parse() {
+            Ok(mode) => mode,
+            Err(e) => {
+                let error = format!("{}: {}", self.values["auth.mode"].source, e);
+                self.errors.push(error);
+                AuthMode::Enforced
+            }
+        }).unwrap_or(AuthMode::Enforced);
+
+        let mut api_keys: Vec<ApiKey> = Vec::new();
+        if let Some(value) = self.string("auth.api_keys", false) {
+            for entry in value.split(',').filter(|entry| !entry.trim().is_empty()) {
+                match entry.parse::<ApiKey>() {
+                    Ok(api_key) if api_keys.iter().any(|other| other.key == api_key.key) => {
+                        let error = format!("{} gives {} a key that is already used", self.values["auth.api_keys"].source, api_key.subject);
+                        self.errors.push(error);
+                    }
+                    Ok(api_key) => api_keys.push(api_key),
+                    Err(e) => {
+                        /// The entry is not repeated, it may contain a key
+                        let error = format!("{} has an invalid entry: {}", self.values["auth.api_keys"].source, e);
+                        self.errors.push(error);
+                    }
+                }
+            }
+        }
+
+        let mut jwt_keys = Vec::new();
+        if let Some(secret) = self.string("auth.jwt_hs256_secret", false) {
+            if secret.len() < MIN_HS256_SECRET_LEN {
+                let error = format!("{} must be at least {} bytes long", self.values["auth.jwt_hs256_secret"].source, MIN_HS256_SECRET_LEN);
+                self.errors.push(error);
+            }
+            jwt_keys.push(JwtKey { algorithm: Algorithm::HS256, key: DecodingKey::from_secret(secret.as_bytes()) });
+        }
+        if let Some(path) = self.string("auth.jwt_rs256_public_key_file", false) {
+            let key = std::fs::read(&path)
+                .map_err(|e| format!("Could not read the RS256 public key {}: {}", path, e))
+                .and_then(|pem| DecodingKey::from_rsa_pem(&pem).map_err(|e| format!("Invalid RS256 public key in {}: {}", path, e)));
+            match key {
+                Ok(key) => jwt_keys.push(JwtKey { algorithm: Algorithm::RS256, key }),
+                Err(e) => self.errors.push(e),
+            }
+        }
+
+        if mode == AuthMode::Enforced && api_keys.is_empty() && jwt_keys.is_empty() {
+            self.errors.push("auth.mode is enforced, so auth.api_keys, auth.jwt_hs256_secret or auth.jwt_rs256_public_key_file must be set. \
+                Set AUTH_MODE=dev to run without authentication".to_string());
+        }
+        AuthConfig { mode, api_keys, jwt_keys }
+    }
+}
+
+/// Splits the command line into the config file path and the known setting flags
+/// Both `--flag value` and `--flag=value` are accepted
+fn parse_flags(args: &[String], errors: &mut Vec<String>) -> (Option<String>, Vec<(&'static Setting, String)>) {
+    let mut config_file = None;
+    let mut flags = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let (flag, inline_value) = match arg.split_once('=') {
+            Some((flag, value)) if flag.starts_with("--") => (flag, Some(value.to_string())),
+            _ => (arg.as_str(), None),
+        };
+        let setting = SETTINGS.iter().find(|setting| setting.flag == flag);
+        if setting.is_none() && flag != CONFIG_FILE_FLAG {
+            errors.push(format!("Unknown argument `{}`", arg));
+            continue;
+        }
+        let value = match inline_value.or_else(|| args.next().cloned()) {
+            Some(value) => value,
+            None => {
+                errors.push(format!("Flag {} expects a value", flag));
+                continue;
+            }
+        };
+        match setting {
+            Some(setting) => flags.push((setting, value)),
+            None => config_file = Some(value),
+        }
+    }
+    (config_file, flags)
+}
+
+impl Config {
+
+    /// # Loads the configuration of a binary
+    /// Reads the `.env` file, the environment and the optional TOML file
+    /// ## Arguments
+    /// * `service` - The [Service] the configuration is loaded for
+    /// * `args` - The command line arguments without the program name
+    /// ## Returns
+    /// * The validated [Config] or a [ConfigError] listing every problem
+    pub fn load(service: Service, args: &[String]) -> Result<Config, ConfigError> {
+        dotenv().ok();
+        Config::from_sources(service, args, |key| std::env::var(key).ok())
+    }
+
+    /// # Loads the configuration from the given sources
+    /// ## Arguments
+    /// * `service` - The [Service] the configuration is loaded for
+    /// * `args` - The command line arguments without the program name
+    /// * `env` - Looks up an environment variable
+    /// ## Returns
+    /// * The validated [Config] or a [ConfigError] listing every problem
+    pub fn from_sources(service: Service, args: &[String], env: impl Fn(&str) -> Option<String>) -> Result<Config, ConfigError> {
+        let mut loader = Loader::default();
+
+        /// Parse the flags first, they can name the config file
+        let (config_file, flags) = parse_flags(args, &mut loader.errors);
+        if let Some(path) = config_file.or_else(|| env(CONFIG_FILE_ENV)) {
+            loader.load_file(&path);
+        }
+        loader.load_env(&env);
+        loader.load_flags(flags);
+
+        /// Validate the settings of all layers together
+        let host = loader.string("host", false).unwrap_or_else(|| "0.0.0.0".to_string());
+        let port = loader.parsed("port", "a port number between 0 and 65535").unwrap_or(service.default_port());
+        let database_url = loader.string("database_url", true);
+
+        let mut database_pool = PoolConfig::default();
+        if let Some(max_size) = loader.parsed("database_pool.max_size", "a positive integer") {
+            if max_size == 0 {
+                let error = format!("{} must be a positive integer, got `0`", loader.values["database_pool.max_size"].source);
+                loader.errors.push(error);
+            }
+            database_pool.max_size = max_size;
+        }
+        if let Some(timeout) = loader.parsed("database_pool.timeout_ms", "a non negative integer") {
+            database_pool.connection_timeout = Duration::from_millis(timeout);
+        }
+
+        let (diets_service, ninjas) = if service == Service::Meals {
+            /// Every setting is parsed even if the URL is missing or invalid, so all their errors are reported together
+            let url = loader.url("diets_service.url", false);
+            let number = "a non negative integer";
+            let timeout = loader.parsed("diets_service.timeout_ms", number);
+            let retries = loader.parsed("diets_service.retries", number);
+            let backoff = loader.parsed("diets_service.retry_backoff_ms", number);
+            let ttl = loader.parsed("diets_service.cache_ttl_secs", number);
+            let api_key = loader.string("diets_service.api_key", false);
+            let diets_service = url.map(|url| {
+                let mut diets_service = DietClientConfig::new(&url);
+                if let Some(timeout) = timeout {
+                    diets_service.timeout = Duration::from_millis(timeout);
+                }
+                if let Some(retries) = retries {
+                    diets_service.retries = retries;
+                }
+                if let Some(backoff) = backoff {
+                    diets_service.retry_backoff = Duration::from_millis(backoff);
+                }
+                if let Some(ttl) = ttl {
+                    diets_service.cache_ttl = Duration::from_secs(ttl);
+                }
+                diets_service.api_key = api_key;
+                diets_service
+            });
+            let base_url = loader.url("ninjas.base_url", true);
+            let api_key = loader.string("ninjas.api_key", true);
+            let ninjas = match (base_url, api_key) {
+                (Some(base_url), Some(api_key)) => Some(NinjasConfig { base_url, api_key }),
+                _ => None,
+            };
+            (diets_service, ninjas)
+        } else {
+            (None, None)
+        };
+        let auth = match service {
+            Service::Meals | Service::Diets => Some(loader.auth()),
+            Service::DataTransfer => None,
+        };
+
+        if !loader.errors.is_empty() {
+            return Err(ConfigError { errors: loader.errors });
+        }
+        Ok(Config {
+            host,
+            port,
+            database_url: database_url.expect("Checked above"),
+            database_pool,
+            diets_service,
+            ninjas,
+            auth,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    /// Returns an environment lookup backed by the given pairs
+    fn env(vars: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+        let vars: HashMap<String, String> = vars.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        move |key| vars.get(key).cloned()
+    }
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    const MEALS_ENV: [(&str, &str); 5] = [
+        ("DATABASE_URL", "postgres://postgres@localhost/dishes"),
+        ("AUTH_MODE", "dev"),
+        ("DIET_SERVICE_URL", "http://diets_api:8002"),
+        ("NINJAS_API_BASE_URL", "https://api.api-ninjas.com/v1/nutrition?query="),
+        ("NINJAS_API_KEY", "secret"),
+    ];
+
+    #[test]
+    fn defaults_apply_per_service() {
+        let config = Config::from_sources(Service::Meals, &[], env(&MEALS_ENV)).unwrap();
+        assert_eq!((config.host.as_str(), config.port), ("0.0.0.0", 8001));
+        assert_eq!(config.database_pool.max_size, 10);
+        assert_eq!(config.diets_service.unwrap().retries, 2);
+        assert_eq!(config.ninjas.unwrap().api_key, "secret");
+
+        /// The Meals Service starts without the Diets Service
+        let vars: Vec<_> = MEALS_ENV.iter().filter(|(key, _)| *key != "DIET_SERVICE_URL").copied().collect();
+        assert!(Config::from_sources(Service::Meals, &[], env(&vars)).unwrap().diets_service.is_none());
+
+        let config = Config::from_sources(Service::Diets, &[], env(&MEALS_ENV[..2])).unwrap();
+        assert_eq!(config.port, 8002);
+        assert!(config.diets_service.is_none() && config.ninjas.is_none());
+    }
+
+    #[test]
+    fn flags_override_env_which_overrides_the_file() {
+        let mut file = tempfile();
+        writeln!(file.1, "port = 9000\nhost = \"127.0.0.1\"\n[diets_service]\nretries = 5\ncache_ttl_secs = 1").unwrap();
+
+        let mut vars = MEALS_ENV.to_vec();
+        vars.push(("PORT", "9001"));
+        vars.push(("DATABASE_POOL_TIMEOUT_MS", "250"));
+        vars.push(("CONFIG_FILE", &file.0));
+        let config = Config::from_sources(Service::Meals, &args(&["--diet-client-retries=7"]), env(&vars)).unwrap();
+
+        assert_eq!(config.host, "127.0.0.1");
+        assert_eq!(config.port, 9001);
+        assert_eq!(config.database_pool.connection_timeout, Duration::from_millis(250));
+        let diets_service = config.diets_service.unwrap();
+        assert_eq!(diets_service.retries, 7);
+        assert_eq!(diets_service.cache_ttl, Duration::from_secs(1));
+        std::fs::remove_file(&file.0).unwrap();
+    }
+
+    #[test]
+    fn all_errors_are_reported_at_once() {
+        let vars = [("PORT", "eighty"), ("DIET_SERVICE_URL", "diets_api:8002"), ("DIET_CLIENT_RETRIES", "many"), ("NINJAS_API_KEY", " ")];
+        let errors = Config::from_sources(Service::Meals, &args(&["--verbose", "--database-pool-size=0", "--host"]), env(&vars)).unwrap_err().errors;
+
+        assert_eq!(errors.len(), 10, "{:#?}", errors);
+        assert!(errors.iter().any(|e| e.contains("--database-pool-size must be a positive integer")));
+        assert!(errors.iter().any(|e| e.contains("Unknown argument `--verbose`")));
+        assert!(errors.iter().any(|e| e.contains("--host expects a value")));
+        assert!(errors.iter().any(|e| e.contains("PORT must be a port number")));
+        assert!(errors.iter().any(|e| e.contains("database_url must be set")));
+        assert!(errors.iter().any(|e| e.contains("DIET_SERVICE_URL must be")));
+        assert!(errors.iter().any(|e| e.contains("DIET_CLIENT_RETRIES must be a non negative integer")));
+        assert!(errors.iter().any(|e| e.contains("ninjas.base_url must be set")));
+        assert!(errors.iter().any(|e| e.contains("NINJAS_API_KEY must not be empty")));
+        assert!(errors.iter().any(|e| e.contains("Set AUTH_MODE=dev to run without authentication")));
+    }
+
+    #[test]
+    fn auth_settings_are_validated() {
+        let public_key = concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/auth/rs256_public.pem");
+        let mut vars = MEALS_ENV[..1].to_vec();
+        vars.push(("AUTH_API_KEYS", "importer:editor:k1, viewer@team-a:reader:k2"));
+        vars.push(("AUTH_JWT_RS256_PUBLIC_KEY_FILE", public_key));
+        let auth = Config::from_sources(Service::Diets, &[], env(&vars)).unwrap().auth.unwrap();
+        assert_eq!(auth.mode, AuthMode::Enforced);
+        assert_eq!(auth.api_keys.iter().map(|api_key| (api_key.subject.as_str(), api_key.key.as_str())).collect::<Vec<_>>(), [("importer", "k1"), ("viewer", "k2")]);
+        assert_eq!(auth.api_keys[1].tenant.as_deref(), Some("team-a"));
+        assert_eq!(auth.jwt_keys[0].algorithm, Algorithm::RS256);
+
+        let vars = [
+            ("AUTH_MODE", "open"),
+            ("AUTH_API_KEYS", "importer:owner:k1,viewer:reader:k2,other:admin:k2,k3,team@a.b:reader:k4"),
+            ("AUTH_JWT_HS256_SECRET", "short"),
+            ("AUTH_JWT_RS256_PUBLIC_KEY_FILE", "missing.pem"),
+        ];
+        let errors = Config::from_sources(Service::Diets, &args(&["--database-url=db"]), env(&vars)).unwrap_err().errors;
+        assert_eq!(errors.len(), 7, "{:#?}", errors);
+        assert!(errors.iter().any(|e| e.contains("unknown mode `open`")));
+        assert!(errors.iter().any(|e| e.contains("unknown role `owner`")));
+        assert!(errors.iter().any(|e| e.contains("gives other a key that is already used")));
+        assert!(errors.iter().any(|e| e.contains("expected `subject:role:key`")));
+        assert!(errors.iter().any(|e| e.contains("invalid tenant `a.b` of team")));
+        assert!(errors.iter().any(|e| e.contains("AUTH_JWT_HS256_SECRET must be at least 32 bytes")));
+        assert!(errors.iter().any(|e| e.contains("Could not read the RS256 public key missing.pem")));
+        assert!(!errors.iter().any(|e| e.contains("k2")), "Keys must not be logged: {:#?}", errors);
+
+        /// The data transfer does not serve requests
+        assert!(Config::from_sources(Service::DataTransfer, &[], env(&MEALS_ENV[..1])).unwrap().auth.is_none());
+    }
+
+    #[test]
+    fn unknown_keys_in_the_file_are_errors() {
+        let mut file = tempfile();
+        writeln!(file.1, "prot = 9000").unwrap();
+
+        let errors = Config::from_sources(Service::Diets, &args(&["--config", &file.0]), env(&MEALS_ENV)).unwrap_err().errors;
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].contains("Unknown key `prot`"));
+        std::fs::remove_file(&file.0).unwrap();
+    }
+
+    /// Creates an empty file in the temp directory, returns its path and handle
+    fn tempfile() -> (String, std::fs::File) {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+        let path = std::env::temp_dir().join(format!("meals_api_config_{}_{}.toml", std::process::id(), COUNTER.fetch_add(1, Ordering::SeqCst)));
+        let file = std::fs::File::create(&path).unwrap();
+        (path.to_string_lossy().into_owned(), file)
+    }
+}
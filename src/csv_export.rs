@@ -0,0 +1,130 @@
+#![allow(unused_doc_comments)]
+//! This file contains the helpers used by both Services to serve collections as CSV
+//!
+//! A collection route answers with CSV instead of JSON if the request either has the `?format=csv` query parameter
+//! or prefers `text/csv` in its `Accept` header, see [wants_csv]
+
+/// Actix imports
+use actix_web::{HttpRequest, HttpResponse};
+use actix_web::error::ErrorInternalServerError;
+use actix_web::http::header::{Accept, ContentDisposition, DispositionParam, DispositionType, Header};
+use actix_web::web::Bytes;
+
+/// Misc imports
+use futures::stream;
+use serde::{Deserialize, Serialize};
+use utoipa::IntoParams;
+
+/// Query parameter selecting the response format of a collection route
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct FormatQuery {
+    /// Set to `csv` to get the collection as CSV
+    pub format: Option<String>,
+}
+
+/// A row of a CSV export
+/// The fields of the implementing struct are serialized in declaration order and must match [CsvRecord::HEADER]
+pub trait CsvRecord: Serialize {
+    /// Column names written as the first row of the export
+    const HEADER: &'static [&'static str];
+}
+
+/// # Checks if the client asked for a CSV response
+/// ## Arguments
+/// * `req` - The [HttpRequest], whose `Accept` header is checked
+/// * `format` - The value of the `format` query parameter, which takes precedence over the header
+/// ## Returns
+/// * `true` if `format` is `csv` or the most preferred media type in the `Accept` header is `text/csv`
+pub fn wants_csv(req: &HttpRequest, format: Option<&str>) -> bool {
+    if let Some(format) = format {
+        return format.eq_ignore_ascii_case("csv")
+    }
+
+    match Accept::parse(req) {
+        Ok(accept) => accept.preference().essence_str() == "text/csv",
+        Err(_) => false,
+    }
+}
+
+/// Serializes a single record without header into a CSV line
+fn encode_row<R: Serialize>(record: &R) -> Result<Bytes, csv::Error> {
+    let mut writer = csv::WriterBuilder::new().has_headers(false).from_writer(vec![]);
+    writer.serialize(record)?;
+    let row = writer.into_inner().map_err(|e| e.into_error())?;
+    Ok(Bytes::from(row))
+}
+
+/// # Creates a streaming CSV response
+/// Writes the header row followed by one row per record
+/// ## Arguments
+/// * `filename` - The file name suggested to the client in the `Content-Disposition` header
+/// * `records` - The rows of the export
+/// ## Returns
+/// * [HttpResponse::Ok] with a `text/csv` body
+pub fn csv_response<R: CsvRecord + 'static>(filename: &str, records: Vec<R>) -> HttpResponse {
+
+    /// Encode the header row, which can not fail for plain strings
+    let mut header = csv::Writer::from_writer(vec![]);
+    header.write_record(R::HEADER).expect("Writing to a Vec can not fail");
+    let header = Bytes::from(header.into_inner().expect("Writing to a Vec can not fail"));
+
+    /// Encode every record lazily while the body is streamed to the client
+    let rows = records.into_iter().map(|record| encode_row(&record).map_err(ErrorInternalServerError));
+    let body = stream::iter(std::iter::once(Ok(header)).chain(rows));
+
+    HttpResponse::Ok()
+        .content_type("text/csv; charset=utf-8")
+        .insert_header(ContentDisposition {
+            disposition: DispositionType::Attachment,
+            parameters: vec![DispositionParam::Filename(filename.to_string())],
+        })
+        .streaming(body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::body::to_bytes;
+    use actix_web::test::TestRequest;
+
+    #[derive(Serialize)]
+    struct Row {
+        id: i32,
+        name: String,
+    }
+
+    impl CsvRecord for Row {
+        const HEADER: &'static [&'static str] = &["id", "name"];
+    }
+
+    #[test]
+    fn csv_is_asked_for_with_the_query_or_the_accept_header() {
+        let plain = TestRequest::default().to_http_request();
+        let accepts_csv = TestRequest::default().insert_header(("Accept", "text/csv")).to_http_request();
+        let prefers_json = TestRequest::default().insert_header(("Accept", "application/json, text/csv;q=0.5")).to_http_request();
+        let prefers_csv = TestRequest::default().insert_header(("Accept", "application/json;q=0.5, text/csv")).to_http_request();
+
+        assert!(!wants_csv(&plain, None));
+        assert!(wants_csv(&accepts_csv, None));
+        assert!(!wants_csv(&prefers_json, None));
+        assert!(wants_csv(&prefers_csv, None));
+
+        /// The query parameter takes precedence over the header
+        assert!(wants_csv(&plain, Some("csv")) && wants_csv(&plain, Some("CSV")));
+        assert!(!wants_csv(&accepts_csv, Some("json")));
+    }
+
+    #[actix_web::test]
+    async fn the_header_row_comes_first() {
+        let res = csv_response("rows.csv", vec![Row { id: 1, name: "rice, beans".to_string() }, Row { id: 2, name: "soup".to_string() }]);
+        assert_eq!(res.headers().get("Content-Type").unwrap(), "text/csv; charset=utf-8");
+        assert_eq!(res.headers().get("Content-Disposition").unwrap(), "attachment; filename=\"rows.csv\"");
+        let body = to_bytes(res.into_body()).await.unwrap();
+        assert_eq!(body, "id,name\n1,\"rice, beans\"\n2,soup\n");
+
+        /// An empty collection still has its header
+        let body = to_bytes(csv_response::<Row>("rows.csv", vec![]).into_body()).await.unwrap();
+        assert_eq!(body, "id,name\n");
+    }
+}
@@ -0,0 +1,167 @@
+#![allow(unused_doc_comments)]
+
+//! This file contains the Data Transfer tool, which exports the dishes with their ingredient lines, meals, diets and plans of the database
+//! into a versioned JSON document and imports such a document back into a database
+//!
+//! Usage:
+//! * `data_transfer export [FILE]` writes the document to FILE or stdout
+//! * `data_transfer import [--mode merge|replace] [FILE]` reads the document from FILE or stdin
+
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::process::exit;
+use meals_api::config::{Config, Service};
+use meals_api::db::{create_pool, DbPool, run_migrations};
+use meals_api::transfer::{export_dataset, import_dataset, Dataset, ImportMode};
+
+const USAGE: &str = "Usage:
+    data_transfer export [FILE]
+    data_transfer import [--mode merge|replace] [FILE]
+
+If FILE is omitted, export writes to stdout and import reads from stdin.
+The import mode defaults to merge.";
+
+/// Parsed command line of the data transfer tool
+enum Command {
+    Export { output: Option<String> },
+    Import { input: Option<String>, mode: ImportMode },
+}
+
+/// Parses the command line arguments into a [Command]
+fn parse_args(args: &[String]) -> Result<Command, String> {
+    let (command, rest) = match args.split_first() {
+        Some((command, rest)) => (command.as_str(), rest),
+        None => return Err("Missing command".to_string()),
+    };
+
+    match command {
+        "export" => match rest {
+            [] => Ok(Command::Export { output: None }),
+            [file] => Ok(Command::Export { output: Some(file.clone()) }),
+            _ => Err("export takes at most one FILE argument".to_string()),
+        },
+        "import" => {
+            let mut mode = ImportMode::Merge;
+            let mut input = None;
+            let mut rest = rest.iter();
+            while let Some(arg) = rest.next() {
+                if arg == "--mode" {
+                    mode = rest.next().ok_or("--mode expects a value")?.parse()?;
+                } else if let Some(value) = arg.strip_prefix("--mode=") {
+                    mode = value.parse()?;
+                } else if input.is_none() {
+                    input = Some(arg.clone());
+                } else {
+                    return Err("import takes at most one FILE argument".to_string());
+                }
+            }
+            Ok(Command::Import { input, mode })
+        }
+        other => Err(format!("Unknown command '{}'", other)),
+    }
+}
+
+/// Main function
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let command = match parse_args(&args) {
+        Ok(command) => command,
+        Err(e) => {
+            eprintln!("Error: {}\n\n{}", e, USAGE);
+            exit(2);
+        }
+    };
+
+    /// Load the database URL from the environment or the config file named by CONFIG_FILE
+    let config = match Config::load(Service::DataTransfer, &[]) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{}", e);
+            exit(2);
+        }
+    };
+
+    /// Create a connection pool to the database and make sure the schema is up to date
+    let pool: DbPool = create_pool(&config.database_url, &config.database_pool).expect("Failed to create pool");
+    if let Err(e) = run_migrations(pool.clone()) {
+        eprintln!("Error running migrations: {}", e);
+        exit(1);
+    }
+    let conn = &mut pool.get().expect("Failed to get a connection from the pool");
+
+    match command {
+        Command::Export { output } => {
+            let dataset = match export_dataset(conn) {
+                Ok(dataset) => dataset,
+                Err(e) => {
+                    eprintln!("Error exporting the database: {}", e);
+                    exit(1);
+                }
+            };
+
+            let writer: Box<dyn Write> = match &output {
+                Some(path) => Box::new(File::create(path).unwrap_or_else(|e| {
+                    eprintln!("Error creating {}: {}", path, e);
+                    exit(1);
+                })),
+                None => Box::new(io::stdout()),
+            };
+            let mut writer = BufWriter::new(writer);
+            if let Err(e) = serde_json::to_writer_pretty(&mut writer, &dataset).map_err(io::Error::from).and_then(|_| writer.flush()) {
+                eprintln!("Error writing the export: {}", e);
+                exit(1);
+            }
+
+            eprintln!("Exported {} dishes with {} ingredient lines, {} meals, {} diets and {} plans", dataset.dishes.len(), dataset.ingredients.len(), dataset.meals.len(), dataset.diets.len(), dataset.plans.len());
+        }
+        Command::Import { input, mode } => {
+            let reader: Box<dyn Read> = match &input {
+                Some(path) => Box::new(File::open(path).unwrap_or_else(|e| {
+                    eprintln!("Error opening {}: {}", path, e);
+                    exit(1);
+                })),
+                None => Box::new(io::stdin()),
+            };
+            let dataset: Dataset = match serde_json::from_reader(BufReader::new(reader)) {
+                Ok(dataset) => dataset,
+                Err(e) => {
+                    eprintln!("Error parsing the export: {}", e);
+                    exit(1);
+                }
+            };
+
+            match import_dataset(conn, &dataset, mode) {
+                Ok(report) => println!("{}", serde_json::to_string_pretty(&report).unwrap()),
+                Err(e) => {
+                    eprintln!("Error importing the export: {}", e);
+                    exit(1);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn commands_are_parsed() {
+        assert!(matches!(parse_args(&args(&["export"])), Ok(Command::Export { output: None })));
+        assert!(matches!(parse_args(&args(&["export", "out.json"])), Ok(Command::Export { output: Some(file) }) if file == "out.json"));
+        assert!(matches!(parse_args(&args(&["import"])), Ok(Command::Import { input: None, mode: ImportMode::Merge })));
+        assert!(matches!(parse_args(&args(&["import", "--mode", "replace", "in.json"])), Ok(Command::Import { input: Some(_), mode: ImportMode::Replace })));
+        assert!(matches!(parse_args(&args(&["import", "--mode=merge"])), Ok(Command::Import { mode: ImportMode::Merge, .. })));
+    }
+
+    #[test]
+    fn wrong_commands_are_errors() {
+        for wrong in [&[][..], &["dump"], &["export", "a", "b"], &["import", "--mode"], &["import", "--mode", "overwrite"], &["import", "a", "b"]] {
+            assert!(parse_args(&args(wrong)).is_err(), "{:?}", wrong);
+        }
+    }
+}
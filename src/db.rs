@@ -1,26 +1,59 @@
 #![allow(unused_doc_comments)]
 #![allow(dead_code)]
+#![allow(clippy::empty_line_after_doc_comments)]
 /// This file contains the database connection and pool used by both Services
 ///
 /// The database connection is established using the [establish_connection] function
 /// Alternatively, a connection pool can be created using the [create_pool] function
 ///
 /// The database schema is defined in [schema.rs](../schema.rs.html)
+///
+/// The database is Postgres, or a single SQLite file if the crate is built with the `sqlite` feature.
+/// The rest of the crate only uses [BackendConnection] and does not depend on the backend
+///
+/// Every query runs inside a `db.query` tracing span, see [QueryTracing]
+///
+/// Route handlers do not query the database themselves, they go through the repositories of their Service,
+/// e.g. [crate::meals::DishRepository]. The Postgres repositories take their connection with [get_connection],
+/// which waits at most the configured pool timeout, and report every failure as a [DbError].
+/// Handlers call the repositories with [run_blocking], so a slow query never stalls the async workers,
+/// and turn the errors into responses with [DbError::response] instead of panicking
 
-use diesel::pg::PgConnection;
-use diesel::r2d2::{ConnectionManager, Pool, PoolError};
+use actix_web::{web, HttpResponse};
+use diesel::connection::{set_default_instrumentation, Instrumentation, InstrumentationEvent};
+use diesel::r2d2::{ConnectionManager, Pool, PoolError, PooledConnection};
+use diesel::result::DatabaseErrorKind;
 
-use dotenv::dotenv;
-use std::env;
+use serde_json::json;
 use std::error::Error;
+use std::fmt;
+use std::time::{Duration, Instant};
 use diesel::{Connection};
 use diesel_migrations::{embed_migrations, EmbeddedMigrations, MigrationHarness};
+use tracing::{debug, debug_span, error, warn, Span};
 
+#[cfg(not(any(feature = "postgres", feature = "sqlite")))]
+compile_error!("Enable the `postgres` or the `sqlite` feature to pick the database");
+
+/// The connection type of the database backend
+#[cfg(not(feature = "sqlite"))]
+pub type BackendConnection = diesel::pg::PgConnection;
+/// The connection type of the database backend
+#[cfg(feature = "sqlite")]
+pub type BackendConnection = diesel::sqlite::SqliteConnection;
+
+/// The migrations of the backend, the SQLite ones create the same tables as the Postgres ones
+#[cfg(not(feature = "sqlite"))]
 pub const MIGRATIONS: EmbeddedMigrations = embed_migrations!();
+#[cfg(feature = "sqlite")]
+pub const MIGRATIONS: EmbeddedMigrations = embed_migrations!("migrations_sqlite");
+
+/// Error code of a failed database access, as defined in the Assignment
+const INTERNAL_SERVER_ERROR: &str = "-8";
 
 pub fn run_migrations(pool: DbPool) -> Result<(), Box<dyn Error + Send + Sync + 'static>> {
 
-    let connection = &mut pool.get().unwrap();
+    let connection = &mut pool.get()?;
 
     connection.run_pending_migrations(MIGRATIONS)?;
 
@@ -32,24 +65,261 @@ pub fn run_migrations(pool: DbPool) -> Result<(), Box<dyn Error + Send + Sync +
 ///
 /// Establishes a connection to the database
 ///
-/// Returns a [PgConnection]
-pub fn establish_connection() -> PgConnection {
-    /// Load the .env file
-    dotenv().ok();
-    /// Get the database URL from the environment
-    let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+/// Returns a [BackendConnection]
+pub fn establish_connection(database_url: &str) -> BackendConnection {
     /// Establish a connection to the database
-    PgConnection::establish(&database_url)
+    BackendConnection::establish(database_url)
         .unwrap_or_else(|_| panic!("Error connecting to {}", database_url))
 }
 
-pub type DbPool = Pool<ConnectionManager<PgConnection>>;
+pub type DbPool = Pool<ConnectionManager<BackendConnection>>;
+
+/// A connection taken from the [DbPool]
+pub type DbConnection = PooledConnection<ConnectionManager<BackendConnection>>;
+
+/// Size and timeout of the [DbPool], see [crate::config::Config]
+#[derive(Clone, Debug)]
+pub struct PoolConfig {
+    /// Maximum number of connections
+    pub max_size: u32,
+    /// How long a request waits for a free connection before it fails with [DbError::Unavailable]
+    pub connection_timeout: Duration,
+}
+
+impl Default for PoolConfig {
+    fn default() -> Self {
+        PoolConfig { max_size: 10, connection_timeout: Duration::from_secs(2) }
+    }
+}
+
+/// Creates a connection pool to the database at `database_url`
+/// The connections of the pool trace their queries with [QueryTracing]
+/// With SQLite, `database_url` is the path of the database file, which is created if it does not exist
+pub fn create_pool(database_url: &str, config: &PoolConfig) -> Result<DbPool, PoolError> {
+    if let Err(e) = set_default_instrumentation(QueryTracing::boxed) {
+        warn!(error = %e, "Could not install the query tracing");
+    }
+    let manager = ConnectionManager::<BackendConnection>::new(database_url);
+    let builder = Pool::builder()
+        .max_size(config.max_size)
+        .connection_timeout(config.connection_timeout);
+    #[cfg(feature = "sqlite")]
+    let builder = builder.connection_customizer(Box::new(SqlitePragmas { busy_timeout: config.connection_timeout }));
+    builder.build(manager)
+}
+
+/// Settings applied to every new SQLite connection of the pool
+///
+/// SQLite only enforces foreign keys, and with them the `ON DELETE SET NULL` of the meals, if they are enabled
+/// on the connection. Writers wait up to `busy_timeout` for each other instead of failing right away,
+/// and WAL mode lets readers continue while a request writes
+#[cfg(feature = "sqlite")]
+#[derive(Debug)]
+struct SqlitePragmas {
+    busy_timeout: Duration,
+}
+
+#[cfg(feature = "sqlite")]
+impl diesel::r2d2::CustomizeConnection<BackendConnection, diesel::r2d2::Error> for SqlitePragmas {
+    fn on_acquire(&self, conn: &mut BackendConnection) -> Result<(), diesel::r2d2::Error> {
+        use diesel::connection::SimpleConnection;
+        conn.batch_execute(&format!(
+            "PRAGMA foreign_keys = ON; PRAGMA busy_timeout = {}; PRAGMA journal_mode = WAL;",
+            self.busy_timeout.as_millis(),
+        )).map_err(diesel::r2d2::Error::QueryError)
+    }
+}
+
+/// # Takes a connection from the pool
+/// Waits at most the connection timeout of the pool
+/// ## Returns
+/// * The [DbConnection], or [DbError::Unavailable] if the pool is exhausted or the database is not reachable
+pub fn get_connection(pool: &DbPool) -> Result<DbConnection, DbError> {
+    pool.get().map_err(DbError::from)
+}
+
+/// # Runs blocking storage work off the async executor
+/// Runs `f`, usually a call of a repository, on the blocking thread pool of actix,
+/// inside the tracing span of the caller, so the queries are still logged with the request ID
+/// ## Arguments
+/// * `f` - The work to run
+/// ## Returns
+/// * The result of `f`, or [DbError::Unavailable] if the blocking thread pool is gone
+pub async fn run_blocking<T, F>(f: F) -> Result<T, DbError>
+where
+    T: Send + 'static,
+    F: FnOnce() -> Result<T, DbError> + Send + 'static,
+{
+    let span = Span::current();
+    web::block(move || {
+        let _entered = span.enter();
+        f()
+    })
+    .await
+    .map_err(|e| DbError::Unavailable(e.to_string()))?
+}
+
+/// Error of a database access, returned by the repositories of both Services
+/// The in-memory repositories report the same errors as the Postgres ones
+#[derive(Debug)]
+pub enum DbError {
+    /// No connection could be taken from the pool in time or the connection was lost
+    Unavailable(String),
+    /// The requested row does not exist
+    NotFound,
+    /// A row with the same unique value, e.g. the same name, already exists
+    AlreadyExists,
+    /// The row references a row that does not exist, e.g. a meal a dish
+    MissingReference,
+    /// The query failed for any other reason
+    Query(diesel::result::Error),
+}
+
+impl DbError {
+
+    /// # Creates the response of a failed database access
+    /// The JSON body contains an error message and the error code -8.
+    /// Handlers answer [DbError::NotFound], [DbError::AlreadyExists] and [DbError::MissingReference]
+    /// with their own error codes, only the other errors are meant to end up here
+    /// ## Returns
+    /// * [HttpResponse::ServiceUnavailable] if the database is not available, so clients can retry later
+    /// * [HttpResponse::InternalServerError] otherwise
+    pub fn response(&self) -> HttpResponse {
+        error!(error = %self, "Database access failed");
+        match self {
+            DbError::Unavailable(e) => HttpResponse::ServiceUnavailable().json(json!({
+                "message": "Database unavailable",
+                "error_code": INTERNAL_SERVER_ERROR,
+                "error": e,
+            })),
+            e => HttpResponse::InternalServerError().json(json!({
+                "message": "Internal Server Error",
+                "error_code": INTERNAL_SERVER_ERROR,
+                "error": e.to_string(),
+            })),
+        }
+    }
+}
+
+impl fmt::Display for DbError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DbError::Unavailable(e) => write!(f, "Database unavailable: {}", e),
+            DbError::NotFound => write!(f, "Record not found"),
+            DbError::AlreadyExists => write!(f, "Record already exists"),
+            DbError::MissingReference => write!(f, "Referenced record does not exist"),
+            DbError::Query(e) => write!(f, "Query failed: {}", e),
+        }
+    }
+}
 
-pub fn create_pool() -> Result<DbPool, PoolError> {
-    dotenv().ok();
-    let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
-    let manager = ConnectionManager::<PgConnection>::new(database_url);
-    Pool::builder().build(manager)
+impl Error for DbError {}
+
+impl From<PoolError> for DbError {
+    fn from(e: PoolError) -> Self {
+        DbError::Unavailable(e.to_string())
+    }
+}
+
+impl From<diesel::result::Error> for DbError {
+    fn from(e: diesel::result::Error) -> Self {
+        match e {
+            /// The connection broke while the query ran, e.g. because the database restarted
+            diesel::result::Error::DatabaseError(DatabaseErrorKind::ClosedConnection, info) => DbError::Unavailable(info.message().to_string()),
+            diesel::result::Error::BrokenTransactionManager => DbError::Unavailable(e.to_string()),
+            diesel::result::Error::NotFound => DbError::NotFound,
+            diesel::result::Error::DatabaseError(DatabaseErrorKind::UniqueViolation, _) => DbError::AlreadyExists,
+            diesel::result::Error::DatabaseError(DatabaseErrorKind::ForeignKeyViolation, _) => DbError::MissingReference,
+            e => DbError::Query(e),
+        }
+    }
+}
+
+/// Diesel instrumentation running every query of a connection inside a `db.query` span
+///
+/// The span is a child of the span that is current when the query starts, usually the `request` span,
+/// so the queries of a request can be found by its request ID.
+/// The span is entered on [InstrumentationEvent::StartQuery] and left on [InstrumentationEvent::FinishQuery],
+/// which diesel always emits in pairs on the same thread
+#[derive(Default)]
+pub struct QueryTracing {
+    query: Option<(Span, Instant)>,
 }
 
+impl QueryTracing {
 
+    /// Creates the instrumentation, used with [set_default_instrumentation]
+    fn boxed() -> Option<Box<dyn Instrumentation>> {
+        Some(Box::new(QueryTracing::default()))
+    }
+}
+
+impl Instrumentation for QueryTracing {
+    fn on_connection_event(&mut self, event: InstrumentationEvent<'_>) {
+        match event {
+            InstrumentationEvent::StartQuery { query, .. } => {
+                let span = debug_span!("db.query", statement = %query);
+                span.with_subscriber(|(id, dispatch)| dispatch.enter(id));
+                self.query = Some((span, Instant::now()));
+            }
+            InstrumentationEvent::FinishQuery { error, .. } => {
+                if let Some((span, started)) = self.query.take() {
+                    let elapsed_ms = started.elapsed().as_millis() as u64;
+                    match error {
+                        Some(e) => warn!(error = %e, elapsed_ms, "Query failed"),
+                        None => debug!(elapsed_ms, "Query finished"),
+                    }
+                    span.with_subscriber(|(id, dispatch)| dispatch.exit(id));
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Creates a pool for a database nobody listens on, as if the database went away
+/// Used by the route tests to check that database failures end in error responses
+#[cfg(test)]
+pub fn unreachable_pool() -> DbPool {
+    #[cfg(not(feature = "sqlite"))]
+    let database_url = {
+        let port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        format!("postgres://postgres@127.0.0.1:{}/dishes", port)
+    };
+    /// SQLite can not create a file in a directory that does not exist
+    #[cfg(feature = "sqlite")]
+    let database_url = std::env::temp_dir().join(uuid::Uuid::new_v4().to_string()).join("dishes.db").display().to_string();
+    let manager = ConnectionManager::<BackendConnection>::new(database_url);
+    Pool::builder().connection_timeout(Duration::from_millis(200)).build_unchecked(manager)
+}
+
+/// Creates a pool for a new, migrated SQLite file in the temporary directory
+/// Used by the tests of the repositories, which need no database server with SQLite
+#[cfg(all(test, feature = "sqlite"))]
+pub fn sqlite_test_pool() -> DbPool {
+    let path = std::env::temp_dir().join(format!("meals-api-{}.db", uuid::Uuid::new_v4()));
+    let pool = create_pool(&path.display().to_string(), &PoolConfig::default()).unwrap();
+    run_migrations(pool.clone()).unwrap();
+    pool
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unreachable_database_is_unavailable() {
+        let started = Instant::now();
+        let e = get_connection(&unreachable_pool()).err().unwrap();
+        assert!(matches!(e, DbError::Unavailable(_)));
+        assert!(started.elapsed() < Duration::from_secs(2), "The pool timeout was not applied");
+        assert_eq!(e.response().status(), 503);
+    }
+
+    #[test]
+    fn query_errors_map_to_responses() {
+        assert!(matches!(DbError::from(diesel::result::Error::NotFound), DbError::NotFound));
+        assert_eq!(DbError::from(diesel::result::Error::RollbackTransaction).response().status(), 500);
+        assert_eq!(DbError::from(diesel::result::Error::BrokenTransactionManager).response().status(), 503);
+    }
+}
@@ -2,30 +2,39 @@
 
 /// Actix imports
 use actix_web::{get, post, delete, HttpResponse, Responder, HttpRequest, web};
-use actix_web::web::{Data};
-
-/// Diesel imports
-use diesel;
-use diesel::prelude::*;
-use diesel::{insert_into, QueryDsl, RunQueryDsl};
-use diesel::r2d2::{ConnectionManager, PooledConnection};
-use diesel::PgConnection;
+use actix_web::web::{Data, Query};
 
 /// Misc imports
+use tracing::{error};
 
 /// Module imports
-use super::models::{Diet, NewDiet};
+use super::generation::{generate, Goal};
+use super::models::{GeneratedDiet, NewDiet, ReqGeneratedDiet};
+use super::repository::DietRepository;
 
 /// Crate imports
-use crate::db::DbPool;
+use crate::auth::{Admin, Reader, Require};
+use crate::csv_export::{csv_response, wants_csv, FormatQuery};
+use crate::db::{run_blocking, DbError};
+use crate::negotiation::{decode_body, negotiate, request_format};
 use crate::diets::models::ReqDiet;
-use crate::schema::diets::dsl::*;
 
 
 /// Disallow DELETE requests to the /diets route
 /// Returns a [HttpResponse::MethodNotAllowed] with a JSON body containing an error message and the error code -7
+/// ## Arguments
+/// * `_auth` - Requires the [Admin] role
+#[utoipa::path(
+    delete,
+    path = "/diets",
+    tag = "diets",
+    security(("api_key" = ["admin"]), ("bearer" = ["admin"])),
+    responses(
+        (status = 405, description = "The diets collection can not be deleted", body = String),
+    ),
+)]
 #[delete("/diets")]
-pub async fn diets_collection_deletion() -> impl Responder {
+pub async fn diets_collection_deletion(_auth: Require<Admin>) -> impl Responder {
     /// Return a [HttpResponse::MethodNotAllowed] with a JSON body containing an error message and the error code -7
     HttpResponse::MethodNotAllowed().body("Method not allowed")
 }
@@ -35,17 +44,34 @@ pub async fn diets_collection_deletion() -> impl Responder {
  */
 /// # Creates the route for getting all diets in "/diets"
 /// ## Arguments
-/// * `db_pool` - A [web::Data<DbPool>] containing the connection pool to the database
+/// * `auth` - Requires the [Reader] role, only the data of its tenant is used
+/// * `diets` - A [web::Data<dyn DietRepository>] containing the stored diets
+/// * `req` - A [HttpRequest] containing the request, used to check if CSV is requested
+/// * `query` - A [web::Query<FormatQuery>] containing the optional `format` parameter
 /// ## Returns
-/// * [HttpResponse] with a status of 200 and a JSON body containing all diets
+/// * [HttpResponse] with a status of 200 and a JSON body containing all diets, or a CSV body if requested
+#[utoipa::path(
+    get,
+    path = "/diets",
+    tag = "diets",
+    security(("api_key" = ["reader"]), ("bearer" = ["reader"])),
+    params(FormatQuery),
+    responses(
+        (status = 200, description = "All diets", content(
+            (Vec<NewDiet> = "application/json"),
+            ("text/csv"),
+        )),
+        (status = 503, description = "Database unavailable, error code -8"),
+    ),
+)]
 #[get("/diets")]
-pub async fn get_all_diets(db_pool: Data<DbPool>) -> impl Responder {
-    /// Establish a connection to the database
-    let conn: &mut PooledConnection<ConnectionManager<PgConnection>> = &mut db_pool.get().unwrap();
-    /// Get all meals from the database
-    let results = diets.load::<Diet>(conn);
+pub async fn get_all_diets(auth: Require<Reader>, diets: Data<dyn DietRepository>, req: HttpRequest, query: Query<FormatQuery>) -> impl Responder {
+    let tenant = auth.principal.tenant;
+
+    /// Get all diets from the repository
+    let results = run_blocking(move || diets.all(&tenant)).await;
 
-    return match results {
+    match results {
         Ok(results) => {
             /// Convert Vec<Diet> to Vec<ReqDiet>
             let results: Vec<NewDiet> = results.into_iter().map(|diet| NewDiet {
@@ -55,13 +81,16 @@ pub async fn get_all_diets(db_pool: Data<DbPool>) -> impl Responder {
                 sugar: diet.sugar,
             }).collect();
 
+            /// If CSV was requested, return one row per diet
+            if wants_csv(&req, query.format.as_deref()) {
+                return csv_response("diets.csv", results)
+            }
+
             /// Return a 200 response with the diets in the body
-            HttpResponse::Ok().json(results)
+            negotiate(&req, HttpResponse::Ok(), &results)
         },
-        Err(e) => {
-            /// Return a 500 response with a JSON body containing an error message and the error code -6
-            HttpResponse::InternalServerError().body(format!("Error querying the database: {}", e))
-        }
+        /// Return a 500 or 503 response with a JSON body containing an error message and the error code -8
+        Err(e) => e.response(),
     }
 }
 
@@ -71,27 +100,48 @@ pub async fn get_all_diets(db_pool: Data<DbPool>) -> impl Responder {
 /// # Creates the route for creating a diet in "/diets"
 /// Creates a new diet in the database, based on the JSON body of the request
 /// ## Arguments
-/// * `db_pool` - A [web::Data<DbPool>] containing the connection pool to the database
+/// * `auth` - Requires the [Admin] role, only the data of its tenant is used
+/// * `diets` - A [web::Data<dyn DietRepository>] containing the stored diets
 /// * `req` - A [HttpRequest] containing the request
-/// * `new_diet` - A [web::Json<NewDiet>] containing the JSON body of the request
+/// * `body` - A [web::Bytes] containing the body of the request as JSON, MessagePack or CBOR
 /// ## Returns
 /// * [HttpResponse] with a status of 201 and a JSON body containing the new meal
+#[utoipa::path(
+    post,
+    path = "/diets",
+    tag = "diets",
+    security(("api_key" = ["admin"]), ("bearer" = ["admin"])),
+    request_body = ReqDiet,
+    responses(
+        (status = 201, description = "The diet was created", body = String),
+        (status = 400, description = "Body not parsable", body = String),
+        (status = 415, description = "Content type not supported", body = String),
+        (status = 422, description = "Field missing or diet already exists", body = String),
+        (status = 503, description = "Database unavailable, error code -8"),
+    ),
+)]
 #[post("/diets")]
-pub async fn create_diet(db_pool: web::Data<DbPool>, req: HttpRequest, req_diet: web::Json<ReqDiet>) -> impl Responder {
+#[allow(clippy::clone_on_copy, clippy::explicit_auto_deref)]
+pub async fn create_diet(auth: Require<Admin>, diets: web::Data<dyn DietRepository>, req: HttpRequest, body: web::Bytes) -> impl Responder {
+    let tenant = auth.principal.tenant;
 
-    /// Check if the Content-Type is application/json
+    /// Check if the Content-Type is JSON, MessagePack or CBOR
     ///
     /// If it is not, return a [HttpResponse::UnsupportedMediaType]
-    match req.headers().get("Content-Type") {
-        Some(content_type) => {
-            if content_type != "application/json" {
-                return HttpResponse::UnsupportedMediaType().body("POST expects content type to be application/json")
-            }
-        },
-        None => {
-            return HttpResponse::UnsupportedMediaType().body("POST expects content type to be application/json")
+    let body_format = match request_format(&req) {
+        Some(body_format) => body_format,
+        None => return HttpResponse::UnsupportedMediaType().body("POST expects content type to be application/json, application/msgpack or application/cbor")
+    };
+
+    /// Deserialize the body
+    /// If the body is not valid, return a [HttpResponse::BadRequest]
+    let req_diet: ReqDiet = match decode_body(body_format, &body) {
+        Ok(req_diet) => req_diet,
+        Err(e) => {
+            error!("{}", e);
+            return HttpResponse::BadRequest().body("Incorrect POST format")
         }
-    }
+    };
 
     /// Check if req_diet is has all the required fields
     /// If it does not, return a [HttpResponse::UnprocessableEntity] with a Error Code -1
@@ -107,33 +157,18 @@ pub async fn create_diet(db_pool: web::Data<DbPool>, req: HttpRequest, req_diet:
         sugar: req_diet.sugar.clone().unwrap(),
     };
 
-    /// Create a connection to the database
-    let conn: &mut PooledConnection<ConnectionManager<PgConnection>> = &mut db_pool.get().unwrap();
+    /// Insert [NewDiet] into the database, unless a diet with the same name already exists
+    let new_diet = run_blocking(move || diets.insert(&tenant, new_diet)).await;
 
-    ///Check if the diet with the same name already exists
-    /// If it does, return a [HttpResponse::UnprocessableEntity] with a Error Code -2
-    let diet_exists = diets.filter(name.eq(&*new_diet.name)).select(name).first::<String>(conn);
-    match diet_exists {
-        Ok(e) => {
-            return HttpResponse::UnprocessableEntity().body("Diet with name {} already exists".replace("{}", &*e))
-        }
-        Err(e) => {
-            eprintln!("Error: {}", e);
-            // Continue
-        }
-    };
-
-    /// Insert [NewDiet] into the database
-    let new_diet = insert_into(diets).values(new_diet).get_result::<Diet>(conn);
     /// Check if the insertion was successful
     ///
-    /// If it was not, return a [HttpResponse::UnprocessableEntity] with a Error Code -6
+    /// If a diet with the same name exists, return a [HttpResponse::UnprocessableEntity]
     let new_diet = match new_diet {
         Ok(new_diet) => new_diet,
-        Err(e) => {
-            eprintln!("Error: {}", e);
-            return HttpResponse::InternalServerError().body("Failed to insert new diet")
+        Err(DbError::AlreadyExists) => {
+            return HttpResponse::UnprocessableEntity().body("Diet with name {} already exists".replace("{}", &req_diet.name.unwrap_or_default()))
         }
+        Err(e) => return e.response(),
     };
 
     /// Return a [HttpResponse::Created] with a JSON body containing the ID of the new diet
@@ -141,26 +176,110 @@ pub async fn create_diet(db_pool: web::Data<DbPool>, req: HttpRequest, req_diet:
 
 }
 
+/*
+=============================== POST /diets/generate ===============================
+ */
+/// # Creates the route for generating a diet from body metrics in "/diets/generate"
+/// Computes the daily limits of the diet from the metrics and the goal, see [generate], and stores it like [create_diet]
+/// ## Arguments
+/// * `auth` - Requires the [Admin] role, only the data of its tenant is used
+/// * `diets` - A [web::Data<dyn DietRepository>] containing the stored diets
+/// * `req` - A [HttpRequest] containing the request
+/// * `body` - A [web::Bytes] containing the name, the metrics and the goal as JSON, MessagePack or CBOR
+/// ## Returns
+/// * [HttpResponse] with a status of 201 and a JSON body containing the new diet with its BMR and TDEE
+#[utoipa::path(
+    post,
+    path = "/diets/generate",
+    tag = "diets",
+    security(("api_key" = ["admin"]), ("bearer" = ["admin"])),
+    request_body = ReqGeneratedDiet,
+    responses(
+        (status = 201, description = "The generated diet", body = GeneratedDiet),
+        (status = 415, description = "Content type not supported", body = String),
+        (status = 422, description = "Body not parsable, field missing, implausible metrics or diet already exists", body = String),
+        (status = 503, description = "Database unavailable, error code -8"),
+    ),
+)]
+#[post("/diets/generate")]
+pub async fn generate_diet(auth: Require<Admin>, diets: web::Data<dyn DietRepository>, req: HttpRequest, body: web::Bytes) -> impl Responder {
+    let tenant = auth.principal.tenant;
+
+    /// Check if the Content-Type is JSON, MessagePack or CBOR
+    ///
+    /// If it is not, return a [HttpResponse::UnsupportedMediaType]
+    let body_format = match request_format(&req) {
+        Some(body_format) => body_format,
+        None => return HttpResponse::UnsupportedMediaType().body("POST expects content type to be application/json, application/msgpack or application/cbor")
+    };
+
+    /// Deserialize the body
+    /// If the body is not valid, return a [HttpResponse::UnprocessableEntity]
+    let req_diet: ReqGeneratedDiet = match decode_body(body_format, &body) {
+        Ok(req_diet) => req_diet,
+        Err(e) => {
+            error!("{}", e);
+            return HttpResponse::UnprocessableEntity().body("Incorrect POST format")
+        }
+    };
+
+    /// Check that the name and all metrics are present and plausible, the goal defaults to keeping the weight
+    let (name, profile) = match (req_diet.name, req_diet.profile.into_profile()) {
+        (Some(name), Some(profile)) if !name.is_empty() => (name, profile),
+        _ => return HttpResponse::UnprocessableEntity().body("Incorrect POST format"),
+    };
+    if let Err(e) = profile.validate() {
+        return HttpResponse::UnprocessableEntity().body(e)
+    }
+    let (new_diet, energy) = generate(name, &profile, req_diet.goal.unwrap_or(Goal::Maintain));
+
+    /// Insert the diet, unless a diet with the same name already exists
+    let stored = new_diet.clone();
+    let result = run_blocking(move || diets.insert(&tenant, stored)).await;
+    match result {
+        Ok(_) => negotiate(&req, HttpResponse::Created(), &GeneratedDiet { diet: new_diet, energy }),
+        Err(DbError::AlreadyExists) => {
+            HttpResponse::UnprocessableEntity().body("Diet with name {} already exists".replace("{}", &new_diet.name))
+        }
+        Err(e) => e.response(),
+    }
+}
+
 /*
 =============================== GET /diets/{id} ===============================
  */
 /// # Creates the route for getting a diet by ID in "/diets/{id}"
 /// Gets a diet from the database, based on the ID in the URL
 /// ## Arguments
-/// * `db_pool` - A [web::Data<DbPool>] containing the connection pool to the database
+/// * `auth` - Requires the [Reader] role, only the data of its tenant is used
+/// * `diets` - A [web::Data<dyn DietRepository>] containing the stored diets
+/// * `req` - A [HttpRequest] containing the request, used to pick the response format
 /// * `req_id` - A [web::Path<i32>] containing the ID of the diet
 /// ## Returns
 /// * [HttpResponse] with a status of 200 and a JSON body containing the diet
+#[utoipa::path(
+    get,
+    path = "/diets/{id}",
+    tag = "diets",
+    security(("api_key" = ["reader"]), ("bearer" = ["reader"])),
+    params(("id" = i32, Path, description = "ID of the diet")),
+    responses(
+        (status = 200, description = "The diet", body = NewDiet),
+        (status = 404, description = "Diet not found", body = String),
+        (status = 503, description = "Database unavailable, error code -8"),
+    ),
+)]
 #[get("/diets/{id:\\d+}")]
-pub async fn get_diet_by_id(db_pool: web::Data<DbPool>, req_id: web::Path<i32>) -> impl Responder {
-    /// Create a connection to the database
-    let conn: &mut PooledConnection<ConnectionManager<PgConnection>> = &mut db_pool.get().unwrap();
-    /// Get the diet from the database
-    let diet = diets.find(&*req_id).first::<Diet>(conn);
+pub async fn get_diet_by_id(auth: Require<Reader>, diets: web::Data<dyn DietRepository>, req: HttpRequest, req_id: web::Path<i32>) -> impl Responder {
+    let tenant = auth.principal.tenant;
+
+    /// Get the diet from the repository
+    let diet_id = *req_id;
+    let diet = run_blocking(move || diets.find(&tenant, diet_id)).await;
     /// Check if the diet was found
     ///
     /// If it was not, return a [HttpResponse::NotFound] with a Error Code -5
-    return match diet {
+    match diet {
         Ok(diet) => {
             /// Only return name, cal, sodium, and sugar
             let diet = NewDiet {
@@ -169,13 +288,11 @@ pub async fn get_diet_by_id(db_pool: web::Data<DbPool>, req_id: web::Path<i32>)
                 sodium: diet.sodium,
                 sugar: diet.sugar,
             };
-            HttpResponse::Ok().json(diet)
-        }
-        Err(e) => {
-            eprintln!("Error: {}", e);
-            HttpResponse::NotFound().body("Diet {} not found".replace("{}", &*req_id.to_string()))
+            negotiate(&req, HttpResponse::Ok(), &diet)
         }
-    };
+        Err(DbError::NotFound) => HttpResponse::NotFound().body("Diet {} not found".replace("{}", &req_id.to_string())),
+        Err(e) => e.response(),
+    }
 }
 
 /*
@@ -184,22 +301,37 @@ pub async fn get_diet_by_id(db_pool: web::Data<DbPool>, req_id: web::Path<i32>)
 /// # Creates the route for getting a diet by name in "/diets/{name}"
 /// Gets a diet from the database, based on the name in the URL
 /// ## Arguments
-/// * `db_pool` - A [web::Data<DbPool>] containing the connection pool to the database
+/// * `auth` - Requires the [Reader] role, only the data of its tenant is used
+/// * `diets` - A [web::Data<dyn DietRepository>] containing the stored diets
+/// * `req` - A [HttpRequest] containing the request, used to pick the response format
 /// * `name` - A [web::Path<String>] containing the name of the diet
 /// ## Returns
 /// * [HttpResponse] with a status of 200 and a JSON body containing the diet
+#[utoipa::path(
+    get,
+    path = "/diets/{name}",
+    tag = "diets",
+    security(("api_key" = ["reader"]), ("bearer" = ["reader"])),
+    params(("name" = String, Path, description = "Name of the diet, names made of digits only are taken as IDs")),
+    responses(
+        (status = 200, description = "The diet", body = NewDiet),
+        (status = 404, description = "Diet not found", body = String),
+        (status = 503, description = "Database unavailable, error code -8"),
+    ),
+)]
 #[get("/diets/{name:.*}")]
-pub async fn get_diet_by_name(db_pool: web::Data<DbPool>, diet_name: web::Path<String>) -> impl Responder {
-    /// Create a connection to the database
-    let conn: &mut PooledConnection<ConnectionManager<PgConnection>> = &mut db_pool.get().unwrap();
-    /// Get the diet from the database
-    let result = diets.filter(name.eq(&*diet_name)).first::<Diet>(conn);
+pub async fn get_diet_by_name(auth: Require<Reader>, diets: web::Data<dyn DietRepository>, req: HttpRequest, diet_name: web::Path<String>) -> impl Responder {
+    let tenant = auth.principal.tenant;
+
+    /// Get the diet from the repository
+    let filter_name = diet_name.clone();
+    let result = run_blocking(move || diets.find_by_name(&tenant, &filter_name)).await;
 
     /// Check if the diet was found
     ///
     /// If it was, return a [HttpResponse::Ok] with a JSON body containing the diet
     /// If it was not, return a [HttpResponse::NotFound] with a Error Code -5
-    return match result {
+    match result {
         Ok(result) => {
             /// Only return name, cal, sodium, and sugar
             let diet = NewDiet {
@@ -208,12 +340,97 @@ pub async fn get_diet_by_name(db_pool: web::Data<DbPool>, diet_name: web::Path<S
                 sodium: result.sodium,
                 sugar: result.sugar,
             };
-            HttpResponse::Ok().json(diet)
+            negotiate(&req, HttpResponse::Ok(), &diet)
         }
-        Err(e) => {
-            eprintln!("Error: {}", e);
-            HttpResponse::NotFound().body("Diet {} not found".replace("{}", &*diet_name))
+        Err(DbError::NotFound) => HttpResponse::NotFound().body("Diet {} not found".replace("{}", &diet_name)),
+        Err(e) => e.response(),
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{test, App};
+    use actix_web::middleware::from_fn;
+    use crate::auth::{authenticate, AuthConfig};
+    use std::sync::Arc;
+    use crate::db::unreachable_pool;
+    use crate::diets::{InMemoryDietRepository, DieselDietRepository};
+
+    #[actix_web::test]
+    async fn database_outage_returns_503() {
+        let app = test::init_service(App::new()
+            .app_data(Data::new(AuthConfig::dev()))
+            .wrap(from_fn(authenticate))
+            .app_data(Data::from(Arc::new(DieselDietRepository::new(unreachable_pool())) as Arc<dyn DietRepository>))
+            .service(get_all_diets)
+            .service(create_diet)
+            .service(get_diet_by_name)).await;
+
+        let diet = r#"{"name": "low sodium", "cal": 2000, "sodium": 1500, "sugar": 30}"#;
+        for req in [
+            test::TestRequest::get().uri("/diets"),
+            test::TestRequest::post().uri("/diets").insert_header(("Content-Type", "application/json")).set_payload(diet),
+            test::TestRequest::get().uri("/diets/vegan"),
+        ] {
+            let res = test::call_service(&app, req.to_request()).await;
+            assert_eq!(res.status(), 503);
         }
-    };
+    }
+
+    #[actix_web::test]
+    async fn diets_are_stored_in_the_repository() {
+        let app = test::init_service(App::new()
+            .app_data(Data::new(AuthConfig::dev()))
+            .wrap(from_fn(authenticate))
+            .app_data(Data::from(Arc::new(InMemoryDietRepository::new()) as Arc<dyn DietRepository>))
+            .service(get_all_diets)
+            .service(create_diet)
+            .service(get_diet_by_id)
+            .service(get_diet_by_name)).await;
 
+        let diet = r#"{"name": "low sodium", "cal": 2000, "sodium": 1500, "sugar": 30}"#;
+        let create = || test::TestRequest::post().uri("/diets").insert_header(("Content-Type", "application/json")).set_payload(diet).to_request();
+        assert_eq!(test::call_service(&app, create()).await.status(), 201);
+        assert_eq!(test::call_service(&app, create()).await.status(), 422);
+
+        let by_id: NewDiet = test::call_and_read_body_json(&app, test::TestRequest::get().uri("/diets/1").to_request()).await;
+        let by_name: NewDiet = test::call_and_read_body_json(&app, test::TestRequest::get().uri("/diets/low%20sodium").to_request()).await;
+        assert_eq!((by_id.name.as_str(), by_id.cal, by_id.sodium, by_id.sugar), ("low sodium", 2000.0, 1500.0, 30.0));
+        assert_eq!(by_name.name, by_id.name);
+
+        let all: Vec<NewDiet> = test::call_and_read_body_json(&app, test::TestRequest::get().uri("/diets").to_request()).await;
+        assert_eq!(all.len(), 1);
+        let res = test::call_service(&app, test::TestRequest::get().uri("/diets/2").to_request()).await;
+        assert_eq!(res.status(), 404);
+    }
+
+    #[actix_web::test]
+    async fn diets_are_generated_from_body_metrics() {
+        let app = test::init_service(App::new()
+            .app_data(Data::new(AuthConfig::dev()))
+            .wrap(from_fn(authenticate))
+            .app_data(Data::from(Arc::new(InMemoryDietRepository::new()) as Arc<dyn DietRepository>))
+            .service(generate_diet)
+            .service(get_diet_by_name)).await;
+
+        let generate = |body: &'static str| test::TestRequest::post().uri("/diets/generate").insert_header(("Content-Type", "application/json")).set_payload(body).to_request();
+        let body = r#"{"name": "cut", "age": 30, "sex": "male", "weight_kg": 70, "height_cm": 175, "activity_level": "moderate", "goal": "lose"}"#;
+        let res = test::call_service(&app, generate(body)).await;
+        assert_eq!(res.status(), 201);
+        let generated: serde_json::Value = test::read_body_json(res).await;
+        assert_eq!(generated, serde_json::json!({"name": "cut", "cal": 2056.0, "sodium": 2300.0, "sugar": 51.0, "bmr": 1649.0, "tdee": 2556.0}));
+
+        /// The diet is stored like any other
+        let stored: NewDiet = test::call_and_read_body_json(&app, test::TestRequest::get().uri("/diets/cut").to_request()).await;
+        assert_eq!((stored.cal, stored.sugar), (2056.0, 51.0));
+        assert_eq!(test::call_service(&app, generate(body)).await.status(), 422);
+
+        /// Missing and implausible metrics are rejected
+        let res = test::call_service(&app, generate(r#"{"name": "bulk", "age": 30, "sex": "male", "weight_kg": 70, "height_cm": 175}"#)).await;
+        assert_eq!(res.status(), 422);
+        let res = test::call_service(&app, generate(r#"{"name": "bulk", "age": 300, "sex": "male", "weight_kg": 70, "height_cm": 175, "activity_level": "light"}"#)).await;
+        assert_eq!(test::read_body(res).await, "age must be between 1 and 130, not 300");
+    }
 }
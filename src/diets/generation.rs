@@ -0,0 +1,132 @@
+#![allow(unused_doc_comments)]
+//! This file contains the computation of a diet from the body metrics and the goal of a person
+//!
+//! The basal metabolic rate (BMR) is estimated with the Mifflin-St Jeor equation and multiplied with the usual
+//! activity factors to get the total daily energy expenditure (TDEE). The goal shifts the calories from there.
+//! Sodium and sugar are capped at the defaults of the dietary guidelines
+
+/// Misc imports
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Module imports
+use super::models::NewDiet;
+
+/// Crate imports
+use crate::users::{ActivityLevel, Profile, Sex};
+
+/// Calories added to or removed from the TDEE to gain or lose about 0.5 kg a week
+const GOAL_ADJUSTMENT_KCAL: f64 = 500.0;
+
+/// Calories below which a diet is not generated without medical supervision
+const MIN_KCAL_FEMALE: f64 = 1200.0;
+const MIN_KCAL_MALE: f64 = 1500.0;
+
+/// Daily sodium limit in mg of the Dietary Guidelines for Americans
+const SODIUM_CAP_MG: f64 = 2300.0;
+
+/// Share of the calories that may come from sugar according to the WHO, and the energy of a gram of sugar
+const SUGAR_SHARE: f64 = 0.10;
+const KCAL_PER_G_SUGAR: f64 = 4.0;
+
+/// What a person wants to achieve with a diet
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Goal {
+    Lose,
+    Maintain,
+    Gain,
+}
+
+/// The values a generated diet is based on, returned together with it
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct Energy {
+    /// Basal metabolic rate in kcal per day
+    pub bmr: f64,
+    /// Total daily energy expenditure in kcal per day
+    pub tdee: f64,
+}
+
+/// Basal metabolic rate in kcal per day, after Mifflin-St Jeor
+pub fn bmr(profile: &Profile) -> f64 {
+    let base = 10.0 * profile.weight_kg + 6.25 * profile.height_cm - 5.0 * profile.age as f64;
+    match profile.sex {
+        Sex::Female => base - 161.0,
+        Sex::Male => base + 5.0,
+    }
+}
+
+/// Factor the BMR is multiplied with to get the TDEE
+pub fn activity_factor(level: ActivityLevel) -> f64 {
+    match level {
+        ActivityLevel::Sedentary => 1.2,
+        ActivityLevel::Light => 1.375,
+        ActivityLevel::Moderate => 1.55,
+        ActivityLevel::Active => 1.725,
+        ActivityLevel::VeryActive => 1.9,
+    }
+}
+
+/// # Computes a diet for a person
+/// ## Arguments
+/// * `name` - The name of the new diet
+/// * `profile` - The body metrics and the activity level, see [Profile::validate]
+/// * `goal` - Whether the person wants to lose, keep or gain weight
+/// ## Returns
+/// * The daily limits as a [NewDiet], rounded to whole kcal, mg and g, and the [Energy] they are based on
+pub fn generate(name: String, profile: &Profile, goal: Goal) -> (NewDiet, Energy) {
+    let bmr = bmr(profile);
+    let tdee = bmr * activity_factor(profile.activity_level);
+
+    /// Shift the calories by the goal, but never below the minimum for the sex
+    let min_kcal = match profile.sex {
+        Sex::Female => MIN_KCAL_FEMALE,
+        Sex::Male => MIN_KCAL_MALE,
+    };
+    let cal = match goal {
+        Goal::Lose => (tdee - GOAL_ADJUSTMENT_KCAL).max(min_kcal),
+        Goal::Maintain => tdee,
+        Goal::Gain => tdee + GOAL_ADJUSTMENT_KCAL,
+    }.round();
+
+    let diet = NewDiet {
+        name,
+        cal,
+        sodium: SODIUM_CAP_MG,
+        sugar: (cal * SUGAR_SHARE / KCAL_PER_G_SUGAR).round(),
+    };
+    (diet, Energy { bmr: bmr.round(), tdee: tdee.round() })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn profile(sex: Sex, activity_level: ActivityLevel) -> Profile {
+        Profile { age: 30, sex, weight_kg: 70.0, height_cm: 175.0, activity_level }
+    }
+
+    #[test]
+    fn bmr_follows_mifflin_st_jeor() {
+        /// 10 * 70 + 6.25 * 175 - 5 * 30 = 1643.75
+        assert_eq!(bmr(&profile(Sex::Male, ActivityLevel::Sedentary)), 1648.75);
+        assert_eq!(bmr(&profile(Sex::Female, ActivityLevel::Sedentary)), 1482.75);
+    }
+
+    #[test]
+    fn diets_follow_the_goal_and_the_guidelines() {
+        let (diet, energy) = generate("maintain".to_string(), &profile(Sex::Male, ActivityLevel::Moderate), Goal::Maintain);
+        assert_eq!(energy, Energy { bmr: 1649.0, tdee: 2556.0 });
+        assert_eq!((diet.name.as_str(), diet.cal, diet.sodium, diet.sugar), ("maintain", 2556.0, 2300.0, 64.0));
+
+        let (diet, _) = generate("gain".to_string(), &profile(Sex::Male, ActivityLevel::Moderate), Goal::Gain);
+        assert_eq!(diet.cal, 3056.0);
+
+        /// Losing weight does not go below the minimum: 1482.75 * 1.2 - 500 = 1279.3, but a light woman stays at 1200
+        let (diet, _) = generate("lose".to_string(), &profile(Sex::Female, ActivityLevel::Sedentary), Goal::Lose);
+        assert_eq!(diet.cal, 1279.0);
+        let light = Profile { weight_kg: 45.0, height_cm: 150.0, ..profile(Sex::Female, ActivityLevel::Sedentary) };
+        let (diet, _) = generate("lose".to_string(), &light, Goal::Lose);
+        assert_eq!((diet.cal, diet.sugar), (1200.0, 30.0));
+    }
+}
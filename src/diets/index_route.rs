@@ -0,0 +1,60 @@
+#![allow(unused_doc_comments)]
+
+/// Actix imports
+use actix_web::{get, web, HttpResponse, Responder};
+
+/// Misc imports
+use serde_json::json;
+use std::collections::BTreeMap;
+
+/// Crate imports
+use crate::db::DbPool;
+use crate::health::{check_database, readiness_response, Readiness};
+
+///
+/// # Creates the default route for the API in "/"
+///
+/// ## Returns
+/// * [HttpResponse::Ok] with a JSON body
+#[utoipa::path(
+    get,
+    path = "/",
+    responses(
+        (status = 200, description = "Welcome message"),
+    ),
+)]
+#[get("/")]
+pub async fn index() -> impl Responder {
+    /// Return a JSON response with a message
+    HttpResponse::Ok().json(json!({
+        "message": "Welcome to the DIETS API"
+    }))
+}
+
+/*
+=============================== GET /readyz ===============================
+ */
+///
+/// # Creates the readiness route for the Diets Service in "/readyz"
+/// Checks the database and the migrations
+/// ## Arguments
+/// * `db_pool` - A [web::Data<DbPool>] containing the connection pool to the database
+/// ## Returns
+/// * [HttpResponse::Ok] if every dependency is up, [HttpResponse::ServiceUnavailable] otherwise, with the status of each dependency
+#[utoipa::path(
+    get,
+    path = "/readyz",
+    tag = "health",
+    responses(
+        (status = 200, description = "Every dependency is up", body = Readiness),
+        (status = 503, description = "At least one dependency is down", body = Readiness),
+    ),
+)]
+#[get("/readyz")]
+pub async fn readyz(db_pool: web::Data<DbPool>) -> impl Responder {
+    let (database, migrations) = check_database(db_pool.get_ref().clone()).await;
+    readiness_response(BTreeMap::from([
+        ("database", database),
+        ("migrations", migrations),
+    ]))
+}
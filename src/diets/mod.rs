@@ -1,7 +1,38 @@
 // Diets API module
+use actix_web::web::ServiceConfig;
+use utoipa::OpenApi;
+
+use crate::health::healthz;
+use crate::metrics::metrics_route;
+use crate::openapi::swagger_ui;
 
 mod models;
 mod diets_routes;
+mod generation;
+mod index_route;
+mod openapi;
+mod repository;
 
 pub use diets_routes::*;
-pub use models::Diet;
\ No newline at end of file
+pub use generation::{generate, Energy, Goal};
+pub use index_route::{index, readyz};
+pub use models::{GeneratedDiet, NewDiet, ReqDiet, ReqGeneratedDiet};
+pub use openapi::DietsApiDoc;
+pub use repository::{DietRepository, InMemoryDietRepository, DieselDietRepository};
+
+/// Registers every route of the Diets Service, used by the `diets_api` binary and the integration tests
+/// The app needs the [crate::db::DbPool], a [DietRepository], the [crate::config::Config] and the [crate::auth::AuthConfig] as app data,
+/// and [crate::auth::authenticate] wrapped around it
+pub fn configure(cfg: &mut ServiceConfig) {
+    cfg.service(index)
+        .service(healthz)
+        .service(readyz)
+        .service(metrics_route)
+        .service(swagger_ui(DietsApiDoc::openapi()))
+        .service(diets_collection_deletion)
+        .service(get_all_diets)
+        .service(create_diet)
+        .service(generate_diet)
+        .service(get_diet_by_id)
+        .service(get_diet_by_name);
+}
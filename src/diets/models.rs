@@ -1,11 +1,17 @@
 #![allow(unused_doc_comments)]
 
+use super::generation::{Energy, Goal};
+use crate::csv_export::CsvRecord;
 use crate::schema::diets;
+use crate::users::ReqProfile;
 use diesel::prelude::*;
 use serde::{Serialize, Deserialize};
+use utoipa::ToSchema;
 
 /// Diet struct to represent a diet in the database
-#[derive(Queryable, Serialize, Deserialize)]
+/// The tenant of the diet is not part of it, the repository filters by it
+#[derive(Clone, Queryable, Selectable, Serialize, Deserialize)]
+#[diesel(table_name = diets)]
 pub struct Diet {
     pub id: i32,
     pub name: String,
@@ -17,7 +23,7 @@ pub struct Diet {
 /// Struct to represent a diet reqested by the user, either for creation or deletion
 /// The Values are optionals since a user might not include all of them and we need to be able to handle that
 /// Translates to a NewDiet struct if all values are present
-#[derive(Deserialize)]
+#[derive(Deserialize, ToSchema)]
 pub struct ReqDiet {
     pub name: Option<String>,
     pub cal: Option<f64>,
@@ -26,8 +32,10 @@ pub struct ReqDiet {
 }
 
 /// Struct that represents a new diet to be inserted into the database or served to the user
-/// Translates to a Diet struct on insertion
-#[derive(Serialize, Deserialize, Insertable)]
+/// Translates to a Diet struct on insertion, together with the tenant of the request
+/// Documented as the Diet schema, since this is the shape in which diets are served
+#[derive(Clone, Serialize, Deserialize, Insertable, ToSchema)]
+#[schema(as = Diet)]
 #[diesel(table_name = diets)]
 pub struct NewDiet {
     pub name: String,
@@ -35,3 +43,26 @@ pub struct NewDiet {
     pub sodium: f64,
     pub sugar: f64,
 }
+
+impl CsvRecord for NewDiet {
+    const HEADER: &'static [&'static str] = &["name", "cal", "sodium", "sugar"];
+}
+
+/// Struct that represents the body metrics and the goal a diet is generated from
+/// The metrics are the same as the profile of a user, see [crate::users::Profile]
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct ReqGeneratedDiet {
+    pub name: Option<String>,
+    #[serde(flatten)]
+    pub profile: ReqProfile,
+    pub goal: Option<Goal>,
+}
+
+/// A generated diet as it was stored, with the energy expenditure it is based on
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct GeneratedDiet {
+    #[serde(flatten)]
+    pub diet: NewDiet,
+    #[serde(flatten)]
+    pub energy: Energy,
+}
@@ -0,0 +1,40 @@
+#![allow(unused_doc_comments)]
+
+/// Misc imports
+use utoipa::OpenApi;
+
+/// Crate imports
+use crate::health::{Check, Readiness};
+
+/// Module imports
+use super::diets_routes::*;
+use super::generation::{Energy, Goal};
+use super::models::{GeneratedDiet, NewDiet, ReqDiet, ReqGeneratedDiet};
+use crate::users::{ActivityLevel, Sex};
+
+/// OpenAPI document of the Diets Service
+/// Every route handler of the Service has to be listed in `paths`, this is checked by the tests below
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "Diets API", description = "Diets limiting the calories, sodium and sugar of a meal"),
+    paths(super::index_route::index, super::index_route::readyz, crate::health::healthz, crate::metrics::metrics_route, diets_collection_deletion, get_all_diets, create_diet, generate_diet, get_diet_by_id, get_diet_by_name),
+    components(schemas(NewDiet, ReqDiet, ReqGeneratedDiet, GeneratedDiet, Energy, Goal, Sex, ActivityLevel, Readiness, Check)),
+    tags(
+        (name = "diets", description = "Diets limiting the calories, sodium and sugar of a meal"),
+        (name = "health", description = "Liveness and readiness of the Service and its dependencies"),
+    ),
+)]
+pub struct DietsApiDoc;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::openapi::{route_sources, undocumented_routes};
+
+    #[test]
+    fn every_route_is_documented() {
+        let sources = route_sources(&["diets"]);
+        let missing = undocumented_routes(&sources, &DietsApiDoc::openapi());
+        assert!(missing.is_empty(), "Routes without an OpenAPI description: {:?}", missing);
+    }
+}
@@ -0,0 +1,161 @@
+#![allow(unused_doc_comments)]
+//! This file contains the storage of the diets of the Diets Service
+//!
+//! The route handlers only know the [DietRepository] trait.
+//! [DieselDietRepository] stores the diets in the database, [InMemoryDietRepository] keeps them in memory,
+//! so the handlers can be tested without a database.
+//!
+//! The methods block, handlers call them with [crate::db::run_blocking]
+//!
+//! Every method works on the diets of one tenant, see [crate::auth::Principal], so diet names are only unique within a tenant
+
+/// Diesel imports
+use diesel::prelude::*;
+use diesel::insert_into;
+
+/// Misc imports
+use std::sync::Mutex;
+
+/// Module imports
+use super::models::{Diet, NewDiet};
+
+/// Crate imports
+use crate::db::{get_connection, DbError, DbPool};
+use crate::schema::diets;
+
+/// Storage of the diets
+pub trait DietRepository: Send + Sync {
+
+    /// Returns all diets ordered by their ID
+    fn all(&self, tenant: &str) -> Result<Vec<Diet>, DbError>;
+
+    /// Returns the diet with the ID, or [DbError::NotFound]
+    fn find(&self, tenant: &str, id: i32) -> Result<Diet, DbError>;
+
+    /// Returns the diet with the name, or [DbError::NotFound]
+    fn find_by_name(&self, tenant: &str, name: &str) -> Result<Diet, DbError>;
+
+    /// Stores a new diet, or returns [DbError::AlreadyExists] if its name is taken
+    fn insert(&self, tenant: &str, diet: NewDiet) -> Result<Diet, DbError>;
+}
+
+/*
+=============================== Database ===============================
+ */
+
+/// Diets stored in Postgres or SQLite, see [crate::db::BackendConnection]
+pub struct DieselDietRepository {
+    pool: DbPool,
+}
+
+impl DieselDietRepository {
+    pub fn new(pool: DbPool) -> DieselDietRepository {
+        DieselDietRepository { pool }
+    }
+}
+
+impl DietRepository for DieselDietRepository {
+
+    fn all(&self, tenant: &str) -> Result<Vec<Diet>, DbError> {
+        let conn = &mut get_connection(&self.pool)?;
+        let results = diets::table
+            .filter(diets::tenant.eq(tenant))
+            .order(diets::id)
+            .select(Diet::as_select())
+            .load(conn)?;
+        Ok(results)
+    }
+
+    fn find(&self, tenant: &str, id: i32) -> Result<Diet, DbError> {
+        let conn = &mut get_connection(&self.pool)?;
+        Ok(diets::table.find(id).filter(diets::tenant.eq(tenant)).select(Diet::as_select()).first(conn)?)
+    }
+
+    fn find_by_name(&self, tenant: &str, name: &str) -> Result<Diet, DbError> {
+        let conn = &mut get_connection(&self.pool)?;
+        let diet = diets::table
+            .filter(diets::tenant.eq(tenant))
+            .filter(diets::name.eq(name))
+            .select(Diet::as_select())
+            .first(conn)?;
+        Ok(diet)
+    }
+
+    fn insert(&self, tenant: &str, diet: NewDiet) -> Result<Diet, DbError> {
+        let conn = &mut get_connection(&self.pool)?;
+
+        /// The names of the diets are not unique in the schema, so they are checked before inserting
+        let diet_exists = diets::table
+            .filter(diets::tenant.eq(tenant))
+            .filter(diets::name.eq(&diet.name))
+            .select(diets::id)
+            .first::<i32>(conn)
+            .optional()?;
+        if diet_exists.is_some() {
+            return Err(DbError::AlreadyExists)
+        }
+        let diet = insert_into(diets::table)
+            .values((&diet, diets::tenant.eq(tenant)))
+            .returning(Diet::as_returning())
+            .get_result(conn)?;
+        Ok(diet)
+    }
+}
+
+/*
+=============================== In memory ===============================
+ */
+
+/// Diets kept in memory with their tenant, with the same behavior as [DieselDietRepository]
+#[derive(Default)]
+pub struct InMemoryDietRepository {
+    diets: Mutex<Vec<(String, Diet)>>,
+}
+
+impl InMemoryDietRepository {
+
+    pub fn new() -> InMemoryDietRepository {
+        InMemoryDietRepository::default()
+    }
+
+    /// Locks the diets, a panic of another thread holding the lock leaves them usable
+    fn diets(&self) -> std::sync::MutexGuard<'_, Vec<(String, Diet)>> {
+        self.diets.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// The diets of a tenant, ordered by their ID
+    fn diets_of(&self, tenant: &str) -> Vec<Diet> {
+        self.diets().iter().filter(|(owner, _)| owner == tenant).map(|(_, diet)| diet.clone()).collect()
+    }
+}
+
+impl DietRepository for InMemoryDietRepository {
+
+    fn all(&self, tenant: &str) -> Result<Vec<Diet>, DbError> {
+        Ok(self.diets_of(tenant))
+    }
+
+    fn find(&self, tenant: &str, id: i32) -> Result<Diet, DbError> {
+        self.diets_of(tenant).into_iter().find(|diet| diet.id == id).ok_or(DbError::NotFound)
+    }
+
+    fn find_by_name(&self, tenant: &str, name: &str) -> Result<Diet, DbError> {
+        self.diets_of(tenant).into_iter().find(|diet| diet.name == name).ok_or(DbError::NotFound)
+    }
+
+    fn insert(&self, tenant: &str, diet: NewDiet) -> Result<Diet, DbError> {
+        let mut diets = self.diets();
+        if diets.iter().any(|(owner, other)| owner == tenant && other.name == diet.name) {
+            return Err(DbError::AlreadyExists)
+        }
+        let diet = Diet {
+            id: diets.last().map_or(1, |(_, last)| last.id + 1),
+            name: diet.name,
+            cal: diet.cal,
+            sodium: diet.sodium,
+            sugar: diet.sugar,
+        };
+        diets.push((tenant.to_string(), diet.clone()));
+        Ok(diet)
+    }
+}
@@ -1,67 +1,73 @@
 #![allow(unused_doc_comments)]
 
-/// This file contains the starts the Meals Service which contains the Meals and Dishes APIs
-/// The main function initializes the logger, creates a connection pool to the database,
-/// and starts the Actix web server on the defined port and host
+//! This file contains the starts the Meals Service which contains the Meals and Dishes APIs
+//! The main function initializes the logger, loads the [Config], creates a connection pool to the database,
+//! and starts the Actix web server on the configured port and host
 
-mod db;
-mod diets;
-mod schema;
-
-use actix_web::{App, HttpResponse, HttpServer, Responder};
+use actix_web::{App, HttpServer};
+use actix_web::middleware::from_fn;
 use actix_web::web::Data;
-use serde_json::json;
-use env_logger;
-use db::{create_pool, DbPool, run_migrations};
-use diets::*;
-
-const HOST: &str = "0.0.0.0";
-const PORT: u16 = 8002;
-
-///
-/// # Creates the default route for the API in "/"
-///
-/// ## Returns
-/// * [HttpResponse::Ok] with a JSON body
-#[actix_web::get("/")]
-pub async fn index() -> impl Responder {
-    /// Return a JSON response with a message
-    HttpResponse::Ok().json(json!({
-        "message": "Welcome to the DIETS API"
-    }))
-}
+use std::sync::Arc;
+use meals_api::auth::{authenticate, AuthMode};
+use meals_api::config::{Config, Service};
+use meals_api::db::{create_pool, DbPool, run_migrations};
+use meals_api::diets::*;
+use meals_api::metrics::track_requests;
+use meals_api::telemetry::{init_logging, trace_requests};
+use tracing::{error, info, warn};
 
 /// Main function
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
 
-    ///Initialize the logger
-    std::env::set_var("RUST_LOG", "debug");
-    env_logger::init();
+    /// Load and validate the configuration, reporting every problem before exiting
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let config = match Config::load(Service::Diets, &args) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(2);
+        }
+    };
+
+    /// Initialize the JSON logger, the level is taken from RUST_LOG and defaults to info
+    init_logging();
 
     /// Create a connection pool to the database
-    let pool: DbPool = create_pool().expect("Failed to create pool");
+    let pool: DbPool = create_pool(&config.database_url, &config.database_pool).expect("Failed to create pool");
 
     /// Run the migrations
     match run_migrations(pool.clone()) {
-        Ok(_) => println!("Migrations run successfully"),
-        Err(e) => println!("Error running migrations: {}", e),
+        Ok(_) => info!("Migrations run successfully"),
+        Err(e) => error!(error = %e, "Error running migrations"),
+    }
+
+    /// The handlers only see the repository of the diets
+    let diets = Data::from(Arc::new(DieselDietRepository::new(pool.clone())) as Arc<dyn DietRepository>);
+
+    /// Authenticate every request, the routes check the role they require
+    let auth_config = config.auth.clone().expect("Validated by Config::load");
+    if auth_config.mode == AuthMode::Dev {
+        warn!("Authentication is disabled, every request is handled as an admin");
     }
+    let auth_config = Data::new(auth_config);
 
-    /// Start the Actix web server and bind it to port 8080
+    /// Start the Actix web server and bind it to the configured host and port
     ///
-    /// The server is configured to use the routes defined in the routes module
+    /// The server is configured to use the routes registered by [configure]
+    let app_config = Data::new(config.clone());
     HttpServer::new(move || {
         App::new()
+            .wrap(from_fn(authenticate))
+            .wrap(from_fn(track_requests))
+            .wrap(from_fn(trace_requests))
             .app_data(Data::new(pool.clone()))
-            .service(index)
-            .service(diets_collection_deletion)
-            .service(get_all_diets)
-            .service(create_diet)
-            .service(get_diet_by_id)
-            .service(get_diet_by_name)
+            .app_data(diets.clone())
+            .app_data(app_config.clone())
+            .app_data(auth_config.clone())
+            .configure(configure)
     })
-        .bind((HOST, PORT))?
+        .bind((config.host.as_str(), config.port))?
         .run()
         .await
 
@@ -0,0 +1,395 @@
+#![allow(unused_doc_comments)]
+
+/// Actix Imports
+use actix_web::{get, post, delete, HttpResponse, Responder, HttpRequest, web};
+use actix_web::web::{Data, Query};
+
+/// Misc Imports
+use chrono::{Days, NaiveDate, Utc};
+use serde_json::json;
+use tracing::warn;
+
+/// Module Imports
+use super::models::{DayLog, DayQuery, LogEntry, LogSummary, NewLogEntry, RangeQuery, ReqLogEntry};
+use super::repository::FoodLogRepository;
+use super::summary::{day_log, summarize};
+
+/// Crate Imports
+use crate::auth::{Principal, Reader, Require};
+use crate::db::{run_blocking, DbError};
+use crate::meals::{DietClient, DishRepository, LimitsError, MealRepository, Nutrients};
+use crate::negotiation::{decode_body, negotiate, request_format};
+use crate::telemetry::RequestId;
+use crate::users::UserRepository;
+
+/// Error codes as defined in the Assigment
+const NOT_JSON: &str = "0";
+const PARAM_NOT_FOUND: &str = "-1";
+const INVALID_ENTRY: &str = "-3";
+const NOT_FOUND: &str = "-5";
+const MEAL_OR_DISH_NOT_FOUND: &str = "-6";
+
+/// Longest range of a summary in days
+const MAX_SUMMARY_DAYS: u64 = 366;
+
+/// Why the user or the limits of a view could not be looked up
+enum LookupError {
+    /// The caller is not registered
+    NotRegistered,
+    Db(DbError),
+    Diet(LimitsError),
+}
+
+impl LookupError {
+
+    /// Returns a [HttpResponse::NotFound] with the error code -5 if the caller is not registered,
+    /// otherwise the response of the database or Diets Service error
+    fn response(&self) -> HttpResponse {
+        match self {
+            LookupError::NotRegistered => HttpResponse::NotFound().body(NOT_FOUND),
+            LookupError::Db(e) => e.response(),
+            LookupError::Diet(e) => e.response(),
+        }
+    }
+}
+
+/// Returns the ID of the caller as a registered user
+async fn user_id(users: &Data<dyn UserRepository>, principal: &Principal) -> Result<i32, LookupError> {
+    let (users, tenant, subject) = (users.clone(), principal.tenant.clone(), principal.subject.clone());
+    match run_blocking(move || users.find(&tenant, &subject)).await {
+        Ok(user) => Ok(user.ID),
+        Err(DbError::NotFound) => Err(LookupError::NotRegistered),
+        Err(e) => Err(LookupError::Db(e)),
+    }
+}
+
+/// Returns the tightest daily limits of the active diets of the caller, or [None] if no diet is active
+async fn active_limits(users: &Data<dyn UserRepository>, diet_client: &DietClient, principal: &Principal, request_id: &RequestId) -> Result<Option<Nutrients>, LookupError> {
+    let (users, tenant, subject) = (users.clone(), principal.tenant.clone(), principal.subject.clone());
+    let diets = run_blocking(move || users.diets(&tenant, &subject)).await.map_err(LookupError::Db)?;
+    let active: Vec<String> = diets.into_iter().filter(|diet| diet.active).map(|diet| diet.diet).collect();
+    if active.is_empty() {
+        return Ok(None)
+    }
+    diet_client.get_limits(&principal.tenant, &active, request_id).await.map(Some).map_err(LookupError::Diet)
+}
+
+/// Returns the entries of the user from the first day until the end of the last day
+async fn entries(food_log: Data<dyn FoodLogRepository>, user_id: i32, from: NaiveDate, to: NaiveDate) -> Result<Vec<LogEntry>, DbError> {
+    let start = from.and_hms_opt(0, 0, 0).unwrap_or_default();
+    let end = to.checked_add_days(Days::new(1)).unwrap_or(to).and_hms_opt(0, 0, 0).unwrap_or_default();
+    run_blocking(move || food_log.between(user_id, start, end)).await
+}
+
+/*
+=============================== POST /log ===============================
+ */
+/// # Creates the route for logging a meal or dish eaten by the caller in "/log"
+/// The nutrients of the meal or dish are copied into the entry and scaled by the portion
+/// ## Arguments
+/// * `auth` - Requires the [Reader] role, only the data of its tenant is used
+/// * `users` - The [UserRepository], used to look up the caller
+/// * `food_log` - The [FoodLogRepository] storing the entries
+/// * `meals` - The [MealRepository], used to look up the nutrients of a meal
+/// * `dishes` - The [DishRepository], used to look up the nutrients of a dish
+/// * `req` - A [HttpRequest] containing the request
+/// * `body` - A [web::Bytes] containing the entry as JSON, MessagePack or CBOR
+/// ## Returns
+/// * [HttpResponse] with a status of 201 and a JSON body containing the new entry
+#[utoipa::path(
+    post,
+    path = "/log",
+    tag = "log",
+    security(("api_key" = ["reader"]), ("bearer" = ["reader"])),
+    request_body = ReqLogEntry,
+    responses(
+        (status = 201, description = "The new entry", body = LogEntry),
+        (status = 404, description = "Caller not registered, error code -5", body = String),
+        (status = 415, description = "Content type not supported, error code 0", body = String),
+        (status = 422, description = "Body not parsable (0), meal or dish missing (-1), both given or portion not positive (-3), or meal or dish not found (-6)", body = String),
+        (status = 503, description = "Database unavailable, error code -8"),
+    ),
+)]
+#[post("/log")]
+pub async fn log_entry(auth: Require<Reader>, users: Data<dyn UserRepository>, food_log: Data<dyn FoodLogRepository>, meals: Data<dyn MealRepository>, dishes: Data<dyn DishRepository>, req: HttpRequest, body: web::Bytes) -> impl Responder {
+    let principal = auth.principal;
+
+    /// Check if the Content-Type is JSON, MessagePack or CBOR
+    ///
+    /// If it is not, return a [HttpResponse::UnsupportedMediaType] with a Error Code 0
+    let body_format = match request_format(&req) {
+        Some(body_format) => body_format,
+        None => return HttpResponse::UnsupportedMediaType().body(NOT_JSON)
+    };
+
+    /// Deserialize the body
+    /// If the body is not valid, return a [HttpResponse::UnprocessableEntity] with a Error Code 0
+    let req_entry: ReqLogEntry = match decode_body(body_format, &body) {
+        Ok(req_entry) => req_entry,
+        Err(e) => {
+            warn!("{}", e);
+            return HttpResponse::UnprocessableEntity().body(NOT_JSON);
+        }
+    };
+
+    /// Exactly one of meal and dish has to be given, and the portion has to be positive
+    let invalid = |message: &str| HttpResponse::UnprocessableEntity().json(json!({
+        "message": message,
+        "error_code": INVALID_ENTRY,
+    }));
+    let portion = req_entry.portion.unwrap_or(1.0);
+    if !(portion > 0.0 && portion.is_finite()) {
+        return invalid("portion must be positive")
+    }
+    let eaten_at = req_entry.eaten_at.unwrap_or_else(Utc::now).naive_utc();
+
+    let user_id = match user_id(&users, &principal).await {
+        Ok(user_id) => user_id,
+        Err(e) => return e.response(),
+    };
+
+    /// Copy the name and the nutrients of the meal or dish of the tenant
+    let tenant = principal.tenant;
+    let source = match (req_entry.meal, req_entry.dish) {
+        (Some(meal_id), None) => run_blocking(move || meals.find(&tenant, meal_id))
+            .await
+            .map(|meal| (Some(meal.ID), None, Nutrients::from(&meal), meal.name)),
+        (None, Some(dish_id)) => run_blocking(move || dishes.find(&tenant, dish_id))
+            .await
+            .map(|dish| (None, Some(dish.ID), Nutrients::from(&dish), dish.name)),
+        (None, None) => return HttpResponse::UnprocessableEntity().body(PARAM_NOT_FOUND),
+        (Some(_), Some(_)) => return invalid("either meal or dish can be logged, not both"),
+    };
+    let (meal_id, dish_id, nutrients, name) = match source {
+        Ok(source) => source,
+        Err(DbError::NotFound) => return HttpResponse::UnprocessableEntity().body(MEAL_OR_DISH_NOT_FOUND),
+        Err(e) => return e.response(),
+    };
+
+    let nutrients = nutrients * portion;
+    let new_entry = NewLogEntry {
+        user_id,
+        meal_id,
+        dish_id,
+        name,
+        portion,
+        eaten_at,
+        cal: nutrients.cal,
+        sodium: nutrients.sodium,
+        sugar: nutrients.sugar,
+    };
+    match run_blocking(move || food_log.insert(new_entry)).await {
+        Ok(entry) => negotiate(&req, HttpResponse::Created(), &entry),
+        Err(e) => e.response(),
+    }
+}
+
+/*
+=============================== GET /log ===============================
+ */
+/// # Creates the route for getting what the caller ate on a day in "/log"
+/// ## Arguments
+/// * `auth` - Requires the [Reader] role, only the data of its tenant is used
+/// * `users` - The [UserRepository], used to look up the caller and their active diets
+/// * `food_log` - The [FoodLogRepository] storing the entries
+/// * `diet_client` - A [web::Data<DietClient>] used to look up the active diets in the Diets Microservice
+/// * `request_id` - The [RequestId] of the request, forwarded to the Diets Microservice
+/// * `req` - A [HttpRequest] containing the request, used to pick the response format
+/// * `query` - A [web::Query<DayQuery>] containing the optional day
+/// ## Returns
+/// * [HttpResponse::Ok] with a JSON body containing the entries of the day, their totals and the exceeded limits
+#[utoipa::path(
+    get,
+    path = "/log",
+    tag = "log",
+    security(("api_key" = ["reader"]), ("bearer" = ["reader"])),
+    params(DayQuery),
+    responses(
+        (status = 200, description = "The entries of the day and their totals compared against the active diets", body = DayLog),
+        (status = 404, description = "Caller not registered (-5) or an active diet not found", body = String),
+        (status = 503, description = "The Diets Service or the database is unavailable"),
+        (status = 504, description = "The Diets Service did not answer in time"),
+    ),
+)]
+#[get("/log")]
+pub async fn get_day_log(auth: Require<Reader>, users: Data<dyn UserRepository>, food_log: Data<dyn FoodLogRepository>, diet_client: Data<DietClient>, request_id: RequestId, req: HttpRequest, query: Query<DayQuery>) -> impl Responder {
+    let principal = auth.principal;
+    let date = query.date.unwrap_or_else(|| Utc::now().date_naive());
+
+    let user_id = match user_id(&users, &principal).await {
+        Ok(user_id) => user_id,
+        Err(e) => return e.response(),
+    };
+    let limits = match active_limits(&users, &diet_client, &principal, &request_id).await {
+        Ok(limits) => limits,
+        Err(e) => return e.response(),
+    };
+
+    match entries(food_log, user_id, date, date).await {
+        Ok(entries) => negotiate(&req, HttpResponse::Ok(), &day_log(date, entries, limits)),
+        Err(e) => e.response(),
+    }
+}
+
+/*
+=============================== GET /log/summary ===============================
+ */
+/// # Creates the route for summing up what the caller ate in a date range in "/log/summary"
+/// ## Arguments
+/// * `auth` - Requires the [Reader] role, only the data of its tenant is used
+/// * `users` - The [UserRepository], used to look up the caller and their active diets
+/// * `food_log` - The [FoodLogRepository] storing the entries
+/// * `diet_client` - A [web::Data<DietClient>] used to look up the active diets in the Diets Microservice
+/// * `request_id` - The [RequestId] of the request, forwarded to the Diets Microservice
+/// * `req` - A [HttpRequest] containing the request, used to pick the response format
+/// * `query` - A [web::Query<RangeQuery>] containing the first and the last day
+/// ## Returns
+/// * [HttpResponse::Ok] with a JSON body containing the totals of every day and ISO week of the range
+#[utoipa::path(
+    get,
+    path = "/log/summary",
+    tag = "log",
+    security(("api_key" = ["reader"]), ("bearer" = ["reader"])),
+    params(RangeQuery),
+    responses(
+        (status = 200, description = "The daily and weekly totals compared against the active diets", body = LogSummary),
+        (status = 400, description = "Range missing, reversed or longer than a year", body = String),
+        (status = 404, description = "Caller not registered (-5) or an active diet not found", body = String),
+        (status = 503, description = "The Diets Service or the database is unavailable"),
+        (status = 504, description = "The Diets Service did not answer in time"),
+    ),
+)]
+#[get("/log/summary")]
+pub async fn get_log_summary(auth: Require<Reader>, users: Data<dyn UserRepository>, food_log: Data<dyn FoodLogRepository>, diet_client: Data<DietClient>, request_id: RequestId, req: HttpRequest, query: Query<RangeQuery>) -> impl Responder {
+    let principal = auth.principal;
+
+    /// Check the range
+    let (from, to) = match (query.from, query.to) {
+        (Some(from), Some(to)) if from <= to => (from, to),
+        _ => return HttpResponse::BadRequest().body("from and to are required and from must not be after to"),
+    };
+    if (to - from).num_days() as u64 >= MAX_SUMMARY_DAYS {
+        return HttpResponse::BadRequest().body("A summary can cover at most {} days".replace("{}", &MAX_SUMMARY_DAYS.to_string()))
+    }
+
+    let user_id = match user_id(&users, &principal).await {
+        Ok(user_id) => user_id,
+        Err(e) => return e.response(),
+    };
+    let limits = match active_limits(&users, &diet_client, &principal, &request_id).await {
+        Ok(limits) => limits,
+        Err(e) => return e.response(),
+    };
+
+    match entries(food_log, user_id, from, to).await {
+        Ok(entries) => negotiate(&req, HttpResponse::Ok(), &summarize(from, to, &entries, limits)),
+        Err(e) => e.response(),
+    }
+}
+
+/*
+=============================== DELETE /log/{id} ===============================
+ */
+/// # Creates the route for deleting an entry of the caller in "/log/{id}"
+/// ## Arguments
+/// * `auth` - Requires the [Reader] role, only the data of its tenant is used
+/// * `users` - The [UserRepository], used to look up the caller
+/// * `food_log` - The [FoodLogRepository] storing the entries
+/// * `req_id` - A [web::Path<i32>] containing the ID of the entry
+/// ## Returns
+/// * [HttpResponse::Ok] with a body containing the ID of the deleted entry
+#[utoipa::path(
+    delete,
+    path = "/log/{id}",
+    tag = "log",
+    security(("api_key" = ["reader"]), ("bearer" = ["reader"])),
+    params(("id" = i32, Path, description = "ID of the entry")),
+    responses(
+        (status = 200, description = "ID of the deleted entry", body = i32),
+        (status = 404, description = "Caller not registered or entry not found, error code -5", body = String),
+        (status = 503, description = "Database unavailable, error code -8"),
+    ),
+)]
+#[delete("/log/{id:\\d+}")]
+pub async fn delete_log_entry(auth: Require<Reader>, users: Data<dyn UserRepository>, food_log: Data<dyn FoodLogRepository>, req_id: web::Path<i32>) -> impl Responder {
+    let principal = auth.principal;
+
+    let user_id = match user_id(&users, &principal).await {
+        Ok(user_id) => user_id,
+        Err(e) => return e.response(),
+    };
+
+    /// Only entries of the caller can be deleted
+    let entry_id = *req_id;
+    match run_blocking(move || food_log.delete(user_id, entry_id)).await {
+        Ok(()) => HttpResponse::Ok().body(entry_id.to_string()),
+        Err(DbError::NotFound) => HttpResponse::NotFound().body(NOT_FOUND),
+        Err(e) => e.response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{test, App};
+    use actix_web::middleware::from_fn;
+    use crate::auth::{authenticate, AuthConfig, DEFAULT_TENANT, TENANT_HEADER};
+    use crate::food_log::InMemoryFoodLogRepository;
+    use crate::meals::{DietClientConfig, InMemoryMealsRepository, NewDish, NewMeal};
+    use crate::users::{ActivityLevel, InMemoryUserRepository, Profile, Sex};
+    use std::sync::Arc;
+
+    #[actix_web::test]
+    async fn entries_are_logged_and_summed_up() {
+        let meals = Arc::new(InMemoryMealsRepository::new());
+        for (name, cal) in [("soup", 100.0), ("steak", 500.0), ("cake", 300.0)] {
+            DishRepository::insert(&*meals, DEFAULT_TENANT, NewDish { name: name.to_string(), cal, sodium: 10.0, sugar: 5.0, size: 100.0 }).unwrap();
+        }
+        let dinner = NewMeal { name: "dinner".to_string(), appetizer: 1, main: 2, dessert: 3 };
+        MealRepository::insert(&*meals, DEFAULT_TENANT, dinner).unwrap();
+        let users = Arc::new(InMemoryUserRepository::new());
+        let profile = Profile { age: 30, sex: Sex::Female, weight_kg: 60.0, height_cm: 165.0, activity_level: ActivityLevel::Light };
+        users.insert(DEFAULT_TENANT, "dev", profile).unwrap();
+
+        /// No diet is active, so the Diets Service is never asked
+        let app = test::init_service(App::new()
+            .app_data(Data::new(AuthConfig::dev()))
+            .wrap(from_fn(authenticate))
+            .app_data(Data::from(meals.clone() as Arc<dyn MealRepository>))
+            .app_data(Data::from(meals as Arc<dyn DishRepository>))
+            .app_data(Data::from(users as Arc<dyn UserRepository>))
+            .app_data(Data::from(Arc::new(InMemoryFoodLogRepository::new()) as Arc<dyn FoodLogRepository>))
+            .app_data(Data::new(DietClient::new(DietClientConfig::new("http://127.0.0.1:9")).unwrap()))
+            .service(log_entry)
+            .service(get_day_log)
+            .service(get_log_summary)
+            .service(delete_log_entry)).await;
+
+        let post = |body: &'static str| test::TestRequest::post().uri("/log").insert_header(("Content-Type", "application/json")).set_payload(body).to_request();
+        let entry: LogEntry = test::call_and_read_body_json(&app, post(r#"{"meal": 1, "eaten_at": "2026-10-18T20:30:00+02:00"}"#)).await;
+        assert_eq!((entry.name.as_str(), entry.cal, entry.eaten_at.to_string().as_str()), ("dinner", 900.0, "2026-10-18 18:30:00"));
+        let entry: LogEntry = test::call_and_read_body_json(&app, post(r#"{"dish": 3, "portion": 0.5, "eaten_at": "2026-10-19T10:00:00Z"}"#)).await;
+        assert_eq!((entry.dish, entry.cal, entry.sugar), (Some(3), 150.0, 2.5));
+
+        assert_eq!(test::call_and_read_body(&app, post(r#"{"portion": 1}"#)).await, PARAM_NOT_FOUND);
+        assert_eq!(test::call_and_read_body(&app, post(r#"{"meal": 9}"#)).await, MEAL_OR_DISH_NOT_FOUND);
+        let res = test::call_service(&app, post(r#"{"meal": 1, "dish": 1}"#)).await;
+        assert_eq!(res.status(), 422);
+        let res = test::call_service(&app, post(r#"{"dish": 1, "portion": 0}"#)).await;
+        assert_eq!(res.status(), 422);
+
+        let day: DayLog = test::call_and_read_body_json(&app, test::TestRequest::get().uri("/log?date=2026-10-18").to_request()).await;
+        assert_eq!((day.entries.len(), day.totals.cal, day.limits), (1, 900.0, None));
+        let summary: LogSummary = test::call_and_read_body_json(&app, test::TestRequest::get().uri("/log/summary?from=2026-10-18&to=2026-10-19").to_request()).await;
+        assert_eq!((summary.weeks.len(), summary.totals.cal), (2, 1050.0));
+        let res = test::call_service(&app, test::TestRequest::get().uri("/log/summary?from=2026-10-19&to=2026-10-18").to_request()).await;
+        assert_eq!(res.status(), 400);
+
+        assert_eq!(test::call_and_read_body(&app, test::TestRequest::delete().uri("/log/2").to_request()).await, "2");
+        assert_eq!(test::call_and_read_body(&app, test::TestRequest::delete().uri("/log/2").to_request()).await, NOT_FOUND);
+
+        /// The caller is not registered in another tenant
+        let res = test::call_service(&app, test::TestRequest::get().uri("/log").insert_header((TENANT_HEADER, "team-b")).to_request()).await;
+        assert_eq!(res.status(), 404);
+    }
+}
@@ -0,0 +1,10 @@
+// Food log module, served by the Meals Service
+mod log_routes;
+mod models;
+mod repository;
+mod summary;
+
+pub use log_routes::*;
+pub use models::{DayLog, DayQuery, DaySummary, LogEntry, LogSummary, NewLogEntry, RangeQuery, ReqLogEntry, WeekSummary};
+pub use repository::{DieselFoodLogRepository, FoodLogRepository, InMemoryFoodLogRepository};
+pub use summary::{day_log, summarize, summarize_days};
@@ -0,0 +1,129 @@
+#![allow(unused_doc_comments)]
+#![allow(non_snake_case)]
+
+use crate::meals::Nutrients;
+use crate::schema::food_log;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use diesel::prelude::*;
+use serde::{Serialize, Deserialize};
+use utoipa::{IntoParams, ToSchema};
+
+/// LogEntry struct to represent a meal or dish eaten by a user
+/// The nutrients were copied from the meal or dish when the entry was logged and are already scaled by the portion.
+/// `meal` and `dish` are unset if the meal or dish was deleted afterwards
+#[derive(Clone, Debug, PartialEq, Queryable, Selectable, Serialize, Deserialize, ToSchema)]
+#[diesel(table_name = food_log)]
+pub struct LogEntry {
+    #[diesel(column_name = id)]
+    pub ID: i32,
+    #[diesel(column_name = meal_id)]
+    pub meal: Option<i32>,
+    #[diesel(column_name = dish_id)]
+    pub dish: Option<i32>,
+    /// Name of the meal or dish when it was logged
+    pub name: String,
+    pub portion: f64,
+    /// When it was eaten, in UTC
+    pub eaten_at: NaiveDateTime,
+    pub cal: f64,
+    pub sodium: f64,
+    pub sugar: f64,
+}
+
+impl LogEntry {
+
+    /// The nutrients of the entry
+    pub fn nutrients(&self) -> Nutrients {
+        Nutrients { cal: self.cal, sodium: self.sodium, sugar: self.sugar }
+    }
+}
+
+/// Struct to represent a new entry to be inserted into the database
+#[derive(Insertable)]
+#[diesel(table_name = food_log)]
+pub struct NewLogEntry {
+    pub user_id: i32,
+    pub meal_id: Option<i32>,
+    pub dish_id: Option<i32>,
+    pub name: String,
+    pub portion: f64,
+    pub eaten_at: NaiveDateTime,
+    pub cal: f64,
+    pub sodium: f64,
+    pub sugar: f64,
+}
+
+/// Struct that represents an entry logged by the user
+/// Either `meal` or `dish` has to be given. The portion defaults to 1, i.e. the whole meal or one serving of the dish,
+/// and `eaten_at` to now
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct ReqLogEntry {
+    pub meal: Option<i32>,
+    pub dish: Option<i32>,
+    pub portion: Option<f64>,
+    /// RFC 3339 timestamp, e.g. `2026-10-18T12:30:00+02:00`
+    pub eaten_at: Option<DateTime<Utc>>,
+}
+
+/// Query parameters of a day view
+#[derive(Serialize, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct DayQuery {
+    /// The day in UTC, defaults to today
+    pub date: Option<NaiveDate>,
+}
+
+/// Query parameters of a summary
+#[derive(Serialize, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct RangeQuery {
+    /// First day of the summary
+    pub from: Option<NaiveDate>,
+    /// Last day of the summary, at most a year after `from`
+    pub to: Option<NaiveDate>,
+}
+
+/// Everything a user ate on one day, compared against the limits of their active diets
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct DayLog {
+    pub date: NaiveDate,
+    pub entries: Vec<LogEntry>,
+    pub totals: Nutrients,
+    /// The tightest limits of the active diets, missing if the user has no active diet
+    pub limits: Option<Nutrients>,
+    /// The nutrients above their limit
+    pub exceeded: Vec<String>,
+}
+
+/// The totals of one day of a [LogSummary]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct DaySummary {
+    pub date: NaiveDate,
+    pub totals: Nutrients,
+    pub exceeded: Vec<String>,
+}
+
+/// The totals of one ISO week of a [LogSummary], limited to the days within the summary
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct WeekSummary {
+    /// The ISO week, e.g. `2026-W42`
+    pub week: String,
+    /// Number of days of the week within the summary
+    pub days: u32,
+    pub totals: Nutrients,
+    /// The daily limits times the number of days
+    pub limits: Option<Nutrients>,
+    pub exceeded: Vec<String>,
+}
+
+/// The totals of every day and week of a date range, compared against the limits of the active diets
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct LogSummary {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    /// The daily limits, missing if the user has no active diet
+    pub limits: Option<Nutrients>,
+    pub days: Vec<DaySummary>,
+    pub weeks: Vec<WeekSummary>,
+    pub totals: Nutrients,
+}
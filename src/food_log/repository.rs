@@ -0,0 +1,193 @@
+#![allow(unused_doc_comments)]
+//! This file contains the storage of the food log
+//!
+//! The route handlers only know the [FoodLogRepository] trait.
+//! [DieselFoodLogRepository] stores the entries in the database, [InMemoryFoodLogRepository] keeps them in memory,
+//! so the handlers can be tested without a database.
+//!
+//! The methods block, handlers call them with [crate::db::run_blocking].
+//! The entries are stored by the ID of their user, which the handlers look up within the tenant of the request first,
+//! see [crate::users::UserRepository]
+
+/// Diesel imports
+use diesel::prelude::*;
+use diesel::{delete, insert_into};
+
+/// Misc imports
+use chrono::NaiveDateTime;
+use std::sync::Mutex;
+
+/// Module imports
+use super::models::{LogEntry, NewLogEntry};
+
+/// Crate imports
+use crate::db::{get_connection, DbError, DbPool};
+use crate::schema::food_log;
+
+/// Storage of the food log
+pub trait FoodLogRepository: Send + Sync {
+
+    /// Stores a new entry
+    fn insert(&self, entry: NewLogEntry) -> Result<LogEntry, DbError>;
+
+    /// Returns the entries of the user eaten from `from` until before `to`, ordered by when they were eaten
+    fn between(&self, user_id: i32, from: NaiveDateTime, to: NaiveDateTime) -> Result<Vec<LogEntry>, DbError>;
+
+    /// Deletes the entry of the user with the ID, or returns [DbError::NotFound]
+    fn delete(&self, user_id: i32, id: i32) -> Result<(), DbError>;
+}
+
+/*
+=============================== Database ===============================
+ */
+
+/// Food log stored in Postgres or SQLite, see [crate::db::BackendConnection]
+pub struct DieselFoodLogRepository {
+    pool: DbPool,
+}
+
+impl DieselFoodLogRepository {
+    pub fn new(pool: DbPool) -> DieselFoodLogRepository {
+        DieselFoodLogRepository { pool }
+    }
+}
+
+impl FoodLogRepository for DieselFoodLogRepository {
+
+    fn insert(&self, entry: NewLogEntry) -> Result<LogEntry, DbError> {
+        let conn = &mut get_connection(&self.pool)?;
+        let entry = insert_into(food_log::table)
+            .values(entry)
+            .returning(LogEntry::as_returning())
+            .get_result(conn)?;
+        Ok(entry)
+    }
+
+    fn between(&self, user_id: i32, from: NaiveDateTime, to: NaiveDateTime) -> Result<Vec<LogEntry>, DbError> {
+        let conn = &mut get_connection(&self.pool)?;
+        let entries = food_log::table
+            .filter(food_log::user_id.eq(user_id))
+            .filter(food_log::eaten_at.ge(from))
+            .filter(food_log::eaten_at.lt(to))
+            .order((food_log::eaten_at, food_log::id))
+            .select(LogEntry::as_select())
+            .load(conn)?;
+        Ok(entries)
+    }
+
+    fn delete(&self, user_id: i32, id: i32) -> Result<(), DbError> {
+        let conn = &mut get_connection(&self.pool)?;
+        let deleted = delete(food_log::table.filter(food_log::user_id.eq(user_id)).filter(food_log::id.eq(id))).execute(conn)?;
+        match deleted {
+            0 => Err(DbError::NotFound),
+            _ => Ok(()),
+        }
+    }
+}
+
+/*
+=============================== In memory ===============================
+ */
+
+/// The entries of the [InMemoryFoodLogRepository] with the ID of their user, and the last ID handed out
+#[derive(Default)]
+struct Entries {
+    entries: Vec<(i32, LogEntry)>,
+    last_id: i32,
+}
+
+/// Food log kept in memory, with the same behavior as [DieselFoodLogRepository]
+#[derive(Default)]
+pub struct InMemoryFoodLogRepository {
+    entries: Mutex<Entries>,
+}
+
+impl InMemoryFoodLogRepository {
+
+    pub fn new() -> InMemoryFoodLogRepository {
+        InMemoryFoodLogRepository::default()
+    }
+
+    /// Locks the entries, a panic of another thread holding the lock leaves them usable
+    fn entries(&self) -> std::sync::MutexGuard<'_, Entries> {
+        self.entries.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl FoodLogRepository for InMemoryFoodLogRepository {
+
+    fn insert(&self, entry: NewLogEntry) -> Result<LogEntry, DbError> {
+        let mut entries = self.entries();
+        entries.last_id += 1;
+        let stored = LogEntry {
+            ID: entries.last_id,
+            meal: entry.meal_id,
+            dish: entry.dish_id,
+            name: entry.name,
+            portion: entry.portion,
+            eaten_at: entry.eaten_at,
+            cal: entry.cal,
+            sodium: entry.sodium,
+            sugar: entry.sugar,
+        };
+        entries.entries.push((entry.user_id, stored.clone()));
+        Ok(stored)
+    }
+
+    fn between(&self, user_id: i32, from: NaiveDateTime, to: NaiveDateTime) -> Result<Vec<LogEntry>, DbError> {
+        let mut found: Vec<LogEntry> = self.entries().entries.iter()
+            .filter(|(owner, entry)| *owner == user_id && entry.eaten_at >= from && entry.eaten_at < to)
+            .map(|(_, entry)| entry.clone())
+            .collect();
+        found.sort_by_key(|entry| (entry.eaten_at, entry.ID));
+        Ok(found)
+    }
+
+    fn delete(&self, user_id: i32, id: i32) -> Result<(), DbError> {
+        let mut entries = self.entries();
+        let before = entries.entries.len();
+        entries.entries.retain(|(owner, entry)| !(*owner == user_id && entry.ID == id));
+        match entries.entries.len() < before {
+            true => Ok(()),
+            false => Err(DbError::NotFound),
+        }
+    }
+}
+
+#[cfg(all(test, feature = "sqlite"))]
+mod tests {
+    use super::*;
+    use crate::db::sqlite_test_pool;
+    use crate::meals::{DieselMealsRepository, DishRepository, NewDish};
+    use crate::users::{ActivityLevel, DieselUserRepository, Profile, Sex, UserRepository};
+
+    #[test]
+    fn entries_keep_their_nutrients_when_the_dish_is_deleted() {
+        let pool = sqlite_test_pool();
+        let profile = Profile { age: 30, sex: Sex::Male, weight_kg: 80.0, height_cm: 180.0, activity_level: ActivityLevel::Light };
+        let user = DieselUserRepository::new(pool.clone()).insert("a", "alice", profile).unwrap();
+        let dishes = DieselMealsRepository::new(pool.clone());
+        let soup = DishRepository::insert(&dishes, "a", NewDish { name: "soup".to_string(), cal: 100.0, sodium: 10.0, sugar: 1.0, size: 250.0 }).unwrap();
+
+        let repository = DieselFoodLogRepository::new(pool);
+        let at = |time: &str| format!("2026-10-18T{}", time).parse::<NaiveDateTime>().unwrap();
+        let entry = |eaten_at| NewLogEntry {
+            user_id: user.ID, meal_id: None, dish_id: Some(soup.ID), name: "soup".to_string(), portion: 2.0, eaten_at, cal: 200.0, sodium: 20.0, sugar: 2.0,
+        };
+        let late = repository.insert(entry(at("20:00:00"))).unwrap();
+        let early = repository.insert(entry(at("08:00:00"))).unwrap();
+        repository.insert(entry(at("23:00:00"))).unwrap();
+
+        let found = repository.between(user.ID, at("00:00:00"), at("21:00:00")).unwrap();
+        assert_eq!(found.iter().map(|entry| entry.ID).collect::<Vec<_>>(), [early.ID, late.ID]);
+
+        /// The entry only loses the reference to the deleted dish
+        DishRepository::delete(&dishes, "a", soup.ID).unwrap();
+        let found = repository.between(user.ID, at("00:00:00"), at("21:00:00")).unwrap();
+        assert_eq!((found[0].dish, found[0].cal), (None, 200.0));
+
+        repository.delete(user.ID, early.ID).unwrap();
+        assert!(matches!(repository.delete(user.ID, early.ID), Err(DbError::NotFound)));
+        assert!(matches!(repository.delete(user.ID + 1, late.ID), Err(DbError::NotFound)));
+    }
+}
@@ -0,0 +1,138 @@
+#![allow(unused_doc_comments)]
+//! This file contains the day views and summaries of the food log
+//!
+//! Entries belong to the UTC day they were eaten on. The limits of a diet are daily limits,
+//! so a week is compared against the limits times the number of its days within the summary
+
+/// Misc imports
+use chrono::{Datelike, NaiveDate};
+
+/// Module imports
+use super::models::{DayLog, DaySummary, LogEntry, LogSummary, WeekSummary};
+
+/// Crate imports
+use crate::meals::Nutrients;
+
+/// The nutrients above their limit, nothing is exceeded without limits
+fn exceeded(totals: &Nutrients, limits: Option<&Nutrients>) -> Vec<String> {
+    limits.map(|limits| totals.exceeded(limits)).unwrap_or_default()
+}
+
+/// # Builds the view of one day
+/// ## Arguments
+/// * `date` - The day
+/// * `entries` - The entries of the day
+/// * `limits` - The daily limits of the active diets, if there are any
+pub fn day_log(date: NaiveDate, entries: Vec<LogEntry>, limits: Option<Nutrients>) -> DayLog {
+    let totals: Nutrients = entries.iter().map(LogEntry::nutrients).sum();
+    DayLog { date, exceeded: exceeded(&totals, limits.as_ref()), entries, totals, limits }
+}
+
+/// # Sums up the entries of a date range per day and per ISO week
+/// ## Arguments
+/// * `from` - The first day, not after `to`
+/// * `to` - The last day
+/// * `entries` - The entries of the range
+/// * `limits` - The daily limits of the active diets, if there are any
+/// ## Returns
+/// * A [LogSummary] with every day of the range, also the days without entries
+pub fn summarize(from: NaiveDate, to: NaiveDate, entries: &[LogEntry], limits: Option<Nutrients>) -> LogSummary {
+    let daily_totals = from.iter_days().take_while(|date| *date <= to).map(|date| {
+        let totals: Nutrients = entries.iter()
+            .filter(|entry| entry.eaten_at.date() == date)
+            .map(LogEntry::nutrients)
+            .sum();
+        (date, totals)
+    });
+    let (days, weeks) = summarize_days(daily_totals, limits);
+    let totals = days.iter().map(|day| day.totals).sum();
+    LogSummary { from, to, limits, days, weeks, totals }
+}
+
+/// # Compares the totals of consecutive days and of their ISO weeks against daily limits
+/// Also used for meal plans, see [crate::plans]
+/// ## Arguments
+/// * `daily_totals` - The totals of each day, ordered by date
+/// * `limits` - The daily limits, nothing is exceeded without them
+/// ## Returns
+/// * A [DaySummary] per day and a [WeekSummary] per ISO week, whose limits are the daily limits times its number of days
+pub fn summarize_days(daily_totals: impl IntoIterator<Item = (NaiveDate, Nutrients)>, limits: Option<Nutrients>) -> (Vec<DaySummary>, Vec<WeekSummary>) {
+    let mut days: Vec<DaySummary> = Vec::new();
+    let mut weeks: Vec<WeekSummary> = Vec::new();
+
+    for (date, totals) in daily_totals {
+        days.push(DaySummary { date, totals, exceeded: exceeded(&totals, limits.as_ref()) });
+
+        /// The days are in order, so a day belongs to the last week or starts a new one
+        let iso_week = date.iso_week();
+        let week = format!("{}-W{:02}", iso_week.year(), iso_week.week());
+        match weeks.last_mut() {
+            Some(last) if last.week == week => {
+                last.days += 1;
+                last.totals = last.totals + totals;
+            }
+            _ => weeks.push(WeekSummary { week, days: 1, totals, limits: None, exceeded: Vec::new() }),
+        }
+    }
+
+    for week in &mut weeks {
+        week.limits = limits.map(|limits| limits * week.days as f64);
+        week.exceeded = exceeded(&week.totals, week.limits.as_ref());
+    }
+    (days, weeks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(id: i32, eaten_at: &str, cal: f64) -> LogEntry {
+        LogEntry {
+            ID: id,
+            meal: Some(1),
+            dish: None,
+            name: "dinner".to_string(),
+            portion: 1.0,
+            eaten_at: eaten_at.parse().unwrap(),
+            cal,
+            sodium: 100.0,
+            sugar: 10.0,
+        }
+    }
+
+    fn date(date: &str) -> NaiveDate {
+        date.parse().unwrap()
+    }
+
+    #[test]
+    fn days_are_compared_against_the_limits() {
+        let limits = Nutrients { cal: 2000.0, sodium: 150.0, sugar: 50.0 };
+        let day = day_log(date("2026-10-18"), vec![entry(1, "2026-10-18T08:00:00", 900.0), entry(2, "2026-10-18T19:00:00", 900.0)], Some(limits));
+        assert_eq!(day.totals, Nutrients { cal: 1800.0, sodium: 200.0, sugar: 20.0 });
+        assert_eq!(day.exceeded, ["sodium"]);
+
+        let day = day_log(date("2026-10-18"), Vec::new(), None);
+        assert_eq!((day.totals, day.exceeded.len()), (Nutrients::default(), 0));
+    }
+
+    #[test]
+    fn ranges_are_summed_per_day_and_iso_week() {
+        /// 2026-10-18 is the Sunday of week 42, so the range covers one day of it and two of week 43
+        let entries = [
+            entry(1, "2026-10-18T12:00:00", 2500.0),
+            entry(2, "2026-10-19T12:00:00", 1500.0),
+            entry(3, "2026-10-19T23:59:59", 1000.0),
+        ];
+        let limits = Nutrients { cal: 2000.0, sodium: 1000.0, sugar: 100.0 };
+        let summary = summarize(date("2026-10-18"), date("2026-10-20"), &entries, Some(limits));
+
+        let days: Vec<(NaiveDate, f64, usize)> = summary.days.iter().map(|day| (day.date, day.totals.cal, day.exceeded.len())).collect();
+        assert_eq!(days, [(date("2026-10-18"), 2500.0, 1), (date("2026-10-19"), 2500.0, 1), (date("2026-10-20"), 0.0, 0)]);
+
+        let weeks: Vec<(&str, u32, f64, Option<f64>, usize)> = summary.weeks.iter()
+            .map(|week| (week.week.as_str(), week.days, week.totals.cal, week.limits.map(|limits| limits.cal), week.exceeded.len()))
+            .collect();
+        assert_eq!(weeks, [("2026-W42", 1, 2500.0, Some(2000.0), 1), ("2026-W43", 2, 2500.0, Some(4000.0), 0)]);
+        assert_eq!(summary.totals.cal, 5000.0);
+    }
+}
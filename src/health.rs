@@ -0,0 +1,181 @@
+#![allow(unused_doc_comments)]
+//! This file contains the liveness and readiness checks used by both Services
+//!
+//! * `GET /healthz` answers as long as the process serves requests, see [healthz]
+//! * `GET /readyz` checks the dependencies of a Service and reports the status of each of them.
+//!   Each Service declares its own `readyz` route from the checks in this file and answers with [readiness_response]
+
+/// Actix imports
+use actix_web::{get, web, HttpResponse, Responder};
+
+/// Diesel imports
+use diesel::{sql_query, RunQueryDsl};
+use diesel_migrations::MigrationHarness;
+
+/// Misc imports
+use serde::Serialize;
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+use utoipa::ToSchema;
+
+/// Crate imports
+use crate::db::{DbPool, MIGRATIONS};
+
+/// How long a single dependency check may take before the dependency is reported as down
+pub const CHECK_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Status of a single dependency
+#[derive(Serialize, ToSchema)]
+pub struct Check {
+    /// `up` or `down`
+    pub status: &'static str,
+    /// How long the check took
+    pub latency_ms: u128,
+    /// Why the dependency is down, or additional information about it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+impl Check {
+
+    fn up(started: Instant, detail: Option<String>) -> Check {
+        Check { status: "up", latency_ms: started.elapsed().as_millis(), detail }
+    }
+
+    fn down(started: Instant, detail: String) -> Check {
+        Check { status: "down", latency_ms: started.elapsed().as_millis(), detail: Some(detail) }
+    }
+
+    /// Whether the dependency is usable
+    pub fn is_up(&self) -> bool {
+        self.status == "up"
+    }
+}
+
+/// Response body of `GET /readyz`
+#[derive(Serialize, ToSchema)]
+pub struct Readiness {
+    /// `ready` if every dependency is up, `not_ready` otherwise
+    pub status: &'static str,
+    /// The status of every dependency by name
+    pub checks: BTreeMap<&'static str, Check>,
+}
+
+/*
+=============================== GET /healthz ===============================
+ */
+///
+/// # Creates the liveness route in "/healthz"
+/// Does not touch any dependency, so it only fails if the process does not serve requests anymore
+/// ## Returns
+/// * [HttpResponse::Ok] with a JSON body
+#[utoipa::path(
+    get,
+    path = "/healthz",
+    tag = "health",
+    responses(
+        (status = 200, description = "The process is alive"),
+    ),
+)]
+#[get("/healthz")]
+pub async fn healthz() -> impl Responder {
+    HttpResponse::Ok().json(json!({
+        "status": "ok"
+    }))
+}
+
+/// # Checks the database
+/// Takes a connection from the pool, runs a trivial query and looks for pending migrations
+/// ## Arguments
+/// * `pool` - The [DbPool] of the Service
+/// ## Returns
+/// * The [Check]s of the database connectivity and of the migrations, in this order
+pub async fn check_database(pool: DbPool) -> (Check, Check) {
+    let started = Instant::now();
+    let result = web::block(move || {
+        let conn = &mut pool.get_timeout(CHECK_TIMEOUT).map_err(|e| (true, e.to_string()))?;
+        sql_query("SELECT 1").execute(conn).map_err(|e| (true, e.to_string()))?;
+        let database_latency = started.elapsed();
+        let pending = conn.pending_migrations(MIGRATIONS).map_err(|e| (false, e.to_string()))?;
+        Ok::<_, (bool, String)>((database_latency, pending.len()))
+    }).await;
+
+    match result {
+        Ok(Ok((database_latency, pending))) => {
+            let database = Check { status: "up", latency_ms: database_latency.as_millis(), detail: None };
+            let migrations = match pending {
+                0 => Check::up(started, None),
+                n => Check::down(started, format!("{} pending migrations", n)),
+            };
+            (database, migrations)
+        }
+        /// The database itself failed, so the migrations could not be checked either
+        Ok(Err((true, e))) => (Check::down(started, e), Check::down(started, "Database unavailable".to_string())),
+        Ok(Err((false, e))) => (Check::up(started, None), Check::down(started, e)),
+        Err(e) => (Check::down(started, e.to_string()), Check::down(started, e.to_string())),
+    }
+}
+
+/// # Checks if an HTTP dependency is reachable
+/// Any answer below 500 counts as reachable, so a 404 of a service without the path or
+/// a 400 of an API rejecting the empty probe request still count as up
+/// ## Arguments
+/// * `url` - The URL to send a GET request to
+/// ## Returns
+/// * The [Check] of the dependency
+pub async fn check_http(url: &str) -> Check {
+    let started = Instant::now();
+    let client = match reqwest::Client::builder().timeout(CHECK_TIMEOUT).build() {
+        Ok(client) => client,
+        Err(e) => return Check::down(started, e.to_string()),
+    };
+    match client.get(url).send().await {
+        Ok(response) if response.status().is_server_error() => Check::down(started, format!("Answered with {}", response.status())),
+        Ok(response) => Check::up(started, Some(format!("Answered with {}", response.status()))),
+        Err(e) if e.is_timeout() => Check::down(started, format!("No answer within {:?}", CHECK_TIMEOUT)),
+        Err(e) => Check::down(started, e.to_string()),
+    }
+}
+
+/// # Creates the response of a readiness route
+/// ## Arguments
+/// * `checks` - The [Check] of every dependency by name
+/// ## Returns
+/// * [HttpResponse::Ok] if every dependency is up, [HttpResponse::ServiceUnavailable] otherwise, both with a [Readiness] body
+pub fn readiness_response(checks: BTreeMap<&'static str, Check>) -> HttpResponse {
+    if checks.values().all(Check::is_up) {
+        HttpResponse::Ok().json(Readiness { status: "ready", checks })
+    } else {
+        HttpResponse::ServiceUnavailable().json(Readiness { status: "not_ready", checks })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::body::to_bytes;
+
+    fn check(status: &'static str) -> Check {
+        Check { status, latency_ms: 1, detail: None }
+    }
+
+    #[actix_web::test]
+    async fn ready_only_if_every_check_is_up() {
+        let ready = readiness_response(BTreeMap::from([("database", check("up")), ("migrations", check("up"))]));
+        assert_eq!(ready.status(), 200);
+
+        let not_ready = readiness_response(BTreeMap::from([("database", check("up")), ("diets_service", check("down"))]));
+        assert_eq!(not_ready.status(), 503);
+        let body: serde_json::Value = serde_json::from_slice(&to_bytes(not_ready.into_body()).await.unwrap()).unwrap();
+        assert_eq!(body["status"], "not_ready");
+        assert_eq!(body["checks"]["diets_service"]["status"], "down");
+    }
+
+    #[actix_web::test]
+    async fn unreachable_http_dependency_is_down() {
+        let port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let check = check_http(&format!("http://127.0.0.1:{}/healthz", port)).await;
+        assert!(!check.is_up());
+    }
+}
@@ -0,0 +1,24 @@
+//! Library of the Meals and Diets Services
+//!
+//! The route handlers, models and database access of both Services live here and are shared by the
+//! `meals_api`, `diets_api` and `data_transfer` binaries.
+//! Other Rust services talking to the APIs can use the typed [client] instead of building requests by hand.
+
+pub mod auth;
+pub mod client;
+pub mod config;
+pub mod csv_export;
+pub mod db;
+pub mod diets;
+pub mod food_log;
+pub mod health;
+pub mod meals;
+pub mod metrics;
+pub mod negotiation;
+pub mod openapi;
+pub mod plans;
+pub mod schema;
+pub mod stub;
+pub mod telemetry;
+pub mod transfer;
+pub mod users;
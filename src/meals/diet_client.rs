@@ -1,39 +1,406 @@
 #![allow(unused_doc_comments)]
 
-/// Module that handles communication with the Diets Microservice
-/// Actix imports
-use actix_web::http::StatusCode;
-use dotenv::dotenv;
+//! Module that handles communication with the Diets Microservice
+//!
+//! The [DietClient] is created once at startup and shared between the workers with `app_data`,
+//! so the connections to the Diets Microservice are reused and the diets are cached for a short time
+//!
+//! Diets are looked up in the tenant of the incoming request, which is forwarded as `X-Tenant-Id` header.
+//! The API key of the Meals Service must therefore not be bound to a tenant
+//!
+//! Without `diets_service.url` the Meals Service uses a [DietClient::disabled] client, which answers every lookup with 503
 
 /// Misc imports
-use reqwest::get;
-
-
-
-/*
-======================= GET /diet/{name} =======================
- */
-/// # Get Diet by Name
-/// Function that gets a diet by name from the Diets Microservice
-/// ## Arguments
-/// * diet_name - The name of the diet to get
-/// ## Returns
-/// * A [Result] containing a [Diet] if the diet was found, or an [reqwest::Error] if the request failed
-pub async fn get_diet_by_name(diet_name: &str) -> Result<String, reqwest::StatusCode> {
-    dotenv().ok();
-    let diets_url = std::env::var("DIET_SERVICE_URL").expect("DIET_SERVICE_URL must be set");
-    /// Send a GET request to the Diets Microservice
-    let res = get(format!("{}/diets/{}", diets_url, diet_name)).await;
-    match res {
-        Ok(res) => {
-            if res.status().is_success() {
-                let data = res.text().await.unwrap();
-                Ok(data)
-            } else {
-                Err(res.status())
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use actix_web::rt::time::sleep;
+use actix_web::HttpResponse;
+use serde_json::json;
+use tracing::{debug_span, error, warn, Instrument};
 
+/// Crate imports
+use crate::client::{ClientError, DietsClient};
+use crate::diets::NewDiet;
+use crate::meals::Nutrients;
+use crate::metrics::metrics;
+use crate::telemetry::RequestId;
+
+/// Error code of an unavailable or too slow Diets Microservice
+const INTERNAL_SERVER_ERROR: &str = "-8";
+
+/// Defaults used if the corresponding setting is not configured, see [crate::config]
+const DEFAULT_TIMEOUT_MS: u64 = 2000;
+const DEFAULT_RETRIES: u32 = 2;
+const DEFAULT_RETRY_BACKOFF_MS: u64 = 100;
+const DEFAULT_CACHE_TTL_SECS: u64 = 30;
+
+/// Configuration of the [DietClient]
+#[derive(Clone, Debug)]
+pub struct DietClientConfig {
+    /// Base URL of the Diets Microservice, e.g. `http://diets_api:8002`
+    pub base_url: String,
+    /// Timeout of a single request, including connecting
+    pub timeout: Duration,
+    /// How often a request is retried after a connection error, a timeout or a 5xx response
+    pub retries: u32,
+    /// Delay before the first retry, doubled for every further retry
+    pub retry_backoff: Duration,
+    /// How long a fetched diet is served from the cache, zero disables the cache
+    pub cache_ttl: Duration,
+    /// API key the Meals Service authenticates with, needed unless the Diets Microservice runs in dev mode
+    pub api_key: Option<String>,
+}
+
+impl DietClientConfig {
+
+    /// Creates a configuration with the default timeout, retries and cache TTL
+    pub fn new(base_url: &str) -> DietClientConfig {
+        DietClientConfig {
+            base_url: base_url.to_string(),
+            timeout: Duration::from_millis(DEFAULT_TIMEOUT_MS),
+            retries: DEFAULT_RETRIES,
+            retry_backoff: Duration::from_millis(DEFAULT_RETRY_BACKOFF_MS),
+            cache_ttl: Duration::from_secs(DEFAULT_CACHE_TTL_SECS),
+            api_key: None,
+        }
+    }
+}
+
+/// Error returned by the [DietClient]
+#[derive(Debug)]
+pub enum DietClientError {
+    /// The Diets Microservice does not know the diet
+    NotFound,
+    /// The Diets Microservice did not answer in time, even after retrying
+    Timeout,
+    /// The Diets Microservice is not reachable or answered with an unexpected response, even after retrying
+    Unavailable(String),
+    /// No URL of the Diets Microservice is configured
+    NotConfigured,
+}
+
+impl fmt::Display for DietClientError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DietClientError::NotFound => write!(f, "Diet not found"),
+            DietClientError::Timeout => write!(f, "Diets service timed out"),
+            DietClientError::Unavailable(e) => write!(f, "Diets service unavailable: {}", e),
+            DietClientError::NotConfigured => write!(f, "Diets service not configured"),
+        }
+    }
+}
+
+impl std::error::Error for DietClientError {}
+
+impl DietClientError {
+
+    /// Short name of the error, used as metric label
+    pub fn kind(&self) -> &'static str {
+        match self {
+            DietClientError::NotFound => "not_found",
+            DietClientError::Timeout => "timeout",
+            DietClientError::Unavailable(_) => "unavailable",
+            DietClientError::NotConfigured => "not_configured",
+        }
+    }
+
+    /// # Turns the error into the response of a route that looked up the diet `diet_name`
+    /// ## Returns
+    /// * [HttpResponse::NotFound] if the diet does not exist, [HttpResponse::GatewayTimeout] or
+    ///   [HttpResponse::ServiceUnavailable] with a JSON body containing the error and the error code -8 otherwise
+    pub fn response(&self, diet_name: &str) -> HttpResponse {
+        match self {
+            DietClientError::NotFound => HttpResponse::NotFound().body("Diet {} not found".replace("{}", diet_name)),
+            DietClientError::Timeout => {
+                error!("{}", self);
+                HttpResponse::GatewayTimeout().json(json!({
+                    "message": self.to_string(),
+                    "error_code": INTERNAL_SERVER_ERROR,
+                }))
+            }
+            DietClientError::Unavailable(_) | DietClientError::NotConfigured => {
+                error!("{}", self);
+                HttpResponse::ServiceUnavailable().json(json!({
+                    "message": self.to_string(),
+                    "error_code": INTERNAL_SERVER_ERROR,
+                }))
+            }
+        }
+    }
+}
+
+/// Whether a request that failed with this error should be retried
+fn is_transient(e: &ClientError) -> bool {
+    match e {
+        ClientError::Http(_) => true,
+        ClientError::Api { status, .. } => status.is_server_error(),
+        ClientError::Decode(_) => false,
+    }
+}
+
+impl From<ClientError> for DietClientError {
+    fn from(e: ClientError) -> Self {
+        match e {
+            ClientError::Http(e) if e.is_timeout() => DietClientError::Timeout,
+            e if e.is_not_found() => DietClientError::NotFound,
+            e => DietClientError::Unavailable(e.to_string()),
+        }
+    }
+}
+
+/// Client for the Diets Microservice with connection reuse, timeouts, retries and a cache of the fetched diets
+pub struct DietClient {
+    /// None if the client is [DietClient::disabled]
+    client: Option<DietsClient>,
+    config: DietClientConfig,
+    /// The fetched diets by tenant and name
+    cache: Mutex<HashMap<(String, String), (Instant, NewDiet)>>,
+}
+
+impl DietClient {
+
+    /// Creates a [DietClient] from its [DietClientConfig]
+    pub fn new(config: DietClientConfig) -> Result<DietClient, ClientError> {
+        let http = reqwest::Client::builder()
+            .timeout(config.timeout)
+            .connect_timeout(config.timeout)
+            .build()?;
+        let mut client = DietsClient::with_http_client(http, &config.base_url)?;
+        if let Some(api_key) = &config.api_key {
+            client = client.with_api_key(api_key);
+        }
+        Ok(DietClient {
+            client: Some(client),
+            config,
+            cache: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Creates a [DietClient] for a Meals Service without a Diets Microservice, every lookup fails with [DietClientError::NotConfigured]
+    pub fn disabled() -> DietClient {
+        DietClient {
+            client: None,
+            config: DietClientConfig::new(""),
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Returns the cached diet if it is younger than the TTL
+    fn cached(&self, tenant: &str, diet_name: &str) -> Option<NewDiet> {
+        let cache = self.cache.lock().unwrap_or_else(|e| e.into_inner());
+        match cache.get(&(tenant.to_string(), diet_name.to_string())) {
+            Some((fetched_at, diet)) if fetched_at.elapsed() < self.config.cache_ttl => Some(diet.clone()),
+            _ => None,
+        }
+    }
+
+    /*
+    ======================= GET /diet/{name} =======================
+     */
+    /// # Get Diet by Name
+    /// Gets a diet by name from the cache or the Diets Microservice
+    /// Connection errors, timeouts and 5xx responses are retried with an exponential backoff
+    /// ## Arguments
+    /// * tenant - The tenant of the incoming request, whose diets are searched
+    /// * diet_name - The name of the diet to get
+    /// * request_id - The [RequestId] of the incoming request, forwarded to the Diets Microservice
+    /// ## Returns
+    /// * A [Result] containing the [NewDiet] if the diet was found, or a [DietClientError] if not
+    pub async fn get_diet_by_name(&self, tenant: &str, diet_name: &str, request_id: &RequestId) -> Result<NewDiet, DietClientError> {
+        let Some(client) = &self.client else {
+            metrics().diet_client_errors.with_label_values(&[DietClientError::NotConfigured.kind()]).inc();
+            return Err(DietClientError::NotConfigured)
+        };
+        if let Some(diet) = self.cached(tenant, diet_name) {
+            metrics().diet_client_cache_hits.inc();
+            return Ok(diet)
+        }
+
+        let client = client.with_request_id(request_id.as_str()).with_tenant(tenant);
+        let mut backoff = self.config.retry_backoff;
+        let mut attempt = 0;
+        let diet = loop {
+            /// Send a GET request to the Diets Microservice
+            let span = debug_span!("diets_service.get_diet", diet = diet_name, attempt);
+            match client.get_diet_by_name(diet_name).instrument(span).await {
+                Ok(diet) => break diet,
+                Err(e) if is_transient(&e) && attempt < self.config.retries => {
+                    warn!(error = %e, retry_in_ms = backoff.as_millis() as u64, "Diets service request failed, retrying");
+                    attempt += 1;
+                    sleep(backoff).await;
+                    backoff *= 2;
+                }
+                Err(e) => {
+                    let e = DietClientError::from(e);
+                    metrics().diet_client_errors.with_label_values(&[e.kind()]).inc();
+                    return Err(e)
+                }
             }
+        };
+
+        /// Cache the diet for the next requests
+        if !self.config.cache_ttl.is_zero() {
+            let mut cache = self.cache.lock().unwrap_or_else(|e| e.into_inner());
+            cache.insert((tenant.to_string(), diet_name.to_string()), (Instant::now(), diet.clone()));
         }
-        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+        Ok(diet)
+    }
+
+    /// # Gets the limits of several diets, the tightest limit of each nutrient applies
+    /// ## Arguments
+    /// * tenant - The tenant of the incoming request, whose diets are searched
+    /// * diet_names - The names of the diets, at least one
+    /// * request_id - The [RequestId] of the incoming request, forwarded to the Diets Microservice
+    /// ## Returns
+    /// * The combined limits, or a [LimitsError] for the first diet that could not be fetched
+    pub async fn get_limits(&self, tenant: &str, diet_names: &[String], request_id: &RequestId) -> Result<Nutrients, LimitsError> {
+        let mut limits = Nutrients { cal: f64::INFINITY, sodium: f64::INFINITY, sugar: f64::INFINITY };
+        for diet_name in diet_names {
+            let diet = self.get_diet_by_name(tenant, diet_name, request_id).await
+                .map_err(|error| LimitsError { diet: diet_name.clone(), error })?;
+            limits = limits.min(&Nutrients::from(&diet));
+        }
+        Ok(limits)
+    }
+}
+
+/// A diet whose limits could not be fetched, see [DietClient::get_limits]
+#[derive(Debug)]
+pub struct LimitsError {
+    pub diet: String,
+    pub error: DietClientError,
+}
+
+impl LimitsError {
+
+    /// Turns the error into the response of a route, see [DietClientError::response]
+    pub fn response(&self) -> HttpResponse {
+        self.error.response(&self.diet)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::{DEFAULT_TENANT, TENANT_HEADER};
+    use actix_web::{get, web, App, HttpResponse, HttpServer};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    /// Stub of the Diets Microservice that knows a single diet and counts its requests
+    #[get("/diets/{name}")]
+    async fn stub_diet(name: web::Path<String>, calls: web::Data<Arc<AtomicUsize>>, req: actix_web::HttpRequest) -> HttpResponse {
+        calls.fetch_add(1, Ordering::SeqCst);
+        match name.as_str() {
+            "echo_request_id" => {
+                let request_id = req.headers().get(crate::telemetry::REQUEST_ID_HEADER).and_then(|v| v.to_str().ok()).unwrap_or_default();
+                HttpResponse::Ok().json(NewDiet { name: request_id.to_string(), cal: 0.0, sodium: 0.0, sugar: 0.0 })
+            }
+            "tenant_diet" => {
+                let tenant = req.headers().get(TENANT_HEADER).and_then(|v| v.to_str().ok()).unwrap_or_default();
+                HttpResponse::Ok().json(NewDiet { name: tenant.to_string(), cal: 0.0, sodium: 0.0, sugar: 0.0 })
+            }
+            "low_sugar" => HttpResponse::Ok().json(NewDiet { name: "low_sugar".to_string(), cal: 500.0, sodium: 100.0, sugar: 5.0 }),
+            "flaky" => HttpResponse::ServiceUnavailable().finish(),
+            "slow" => {
+                sleep(Duration::from_millis(500)).await;
+                HttpResponse::NotFound().finish()
+            }
+            _ => HttpResponse::NotFound().body("Diet not found"),
+        }
+    }
+
+    /// Starts the stub on a random local port, returns its base URL and request counter
+    fn start_stub() -> (String, Arc<AtomicUsize>) {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let data = web::Data::new(calls.clone());
+        let server = HttpServer::new(move || App::new().app_data(data.clone()).service(stub_diet))
+            .workers(1)
+            .bind(("127.0.0.1", 0))
+            .unwrap();
+        let url = format!("http://{}", server.addrs()[0]);
+        actix_web::rt::spawn(server.run());
+        (url, calls)
+    }
+
+    fn config(base_url: &str) -> DietClientConfig {
+        DietClientConfig { retry_backoff: Duration::from_millis(1), ..DietClientConfig::new(base_url) }
+    }
+
+    #[actix_web::test]
+    async fn found_diets_are_cached() {
+        let (url, calls) = start_stub();
+        let client = DietClient::new(config(&url)).unwrap();
+
+        assert_eq!(client.get_diet_by_name(DEFAULT_TENANT, "low_sugar", &RequestId::generate()).await.unwrap().sugar, 5.0);
+        assert_eq!(client.get_diet_by_name(DEFAULT_TENANT, "low_sugar", &RequestId::generate()).await.unwrap().sugar, 5.0);
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[actix_web::test]
+    async fn request_id_is_forwarded() {
+        let (url, _) = start_stub();
+        let client = DietClient::new(config(&url)).unwrap();
+        let request_id = RequestId::parse("trace-me-42").unwrap();
+
+        assert_eq!(client.get_diet_by_name(DEFAULT_TENANT, "echo_request_id", &request_id).await.unwrap().name, "trace-me-42");
+    }
+
+    #[actix_web::test]
+    async fn diets_are_fetched_and_cached_per_tenant() {
+        let (url, calls) = start_stub();
+        let client = DietClient::new(config(&url)).unwrap();
+
+        assert_eq!(client.get_diet_by_name("team-a", "tenant_diet", &RequestId::generate()).await.unwrap().name, "team-a");
+        assert_eq!(client.get_diet_by_name("team-b", "tenant_diet", &RequestId::generate()).await.unwrap().name, "team-b");
+        assert_eq!(client.get_diet_by_name("team-a", "tenant_diet", &RequestId::generate()).await.unwrap().name, "team-a");
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[actix_web::test]
+    async fn missing_diets_are_not_retried() {
+        let (url, calls) = start_stub();
+        let client = DietClient::new(config(&url)).unwrap();
+
+        assert!(matches!(client.get_diet_by_name(DEFAULT_TENANT, "keto", &RequestId::generate()).await, Err(DietClientError::NotFound)));
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[actix_web::test]
+    async fn server_errors_are_retried_then_unavailable() {
+        let (url, calls) = start_stub();
+        let client = DietClient::new(config(&url)).unwrap();
+
+        assert!(matches!(client.get_diet_by_name(DEFAULT_TENANT, "flaky", &RequestId::generate()).await, Err(DietClientError::Unavailable(_))));
+        assert_eq!(calls.load(Ordering::SeqCst), 1 + DEFAULT_RETRIES as usize);
+    }
+
+    #[actix_web::test]
+    async fn slow_service_times_out() {
+        let (url, _) = start_stub();
+        let config = DietClientConfig { timeout: Duration::from_millis(50), retries: 0, ..config(&url) };
+        let client = DietClient::new(config).unwrap();
+
+        assert!(matches!(client.get_diet_by_name(DEFAULT_TENANT, "slow", &RequestId::generate()).await, Err(DietClientError::Timeout)));
+    }
+
+    #[actix_web::test]
+    async fn unreachable_service_is_unavailable() {
+        /// Bind and drop a listener to get a port nobody listens on
+        let port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let client = DietClient::new(config(&format!("http://127.0.0.1:{}", port))).unwrap();
+
+        assert!(matches!(client.get_diet_by_name(DEFAULT_TENANT, "low_sugar", &RequestId::generate()).await, Err(DietClientError::Unavailable(_))));
+    }
+
+    #[actix_web::test]
+    async fn disabled_client_answers_with_503() {
+        let client = DietClient::disabled();
+
+        let e = client.get_diet_by_name(DEFAULT_TENANT, "low_sugar", &RequestId::generate()).await.err().unwrap();
+        assert!(matches!(e, DietClientError::NotConfigured));
+        assert_eq!(e.response("low_sugar").status(), actix_web::http::StatusCode::SERVICE_UNAVAILABLE);
     }
-}
\ No newline at end of file
+}
@@ -2,29 +2,26 @@
 
 /// Actix imports
 use actix_web::{get, post, delete, HttpResponse, Responder, HttpRequest, web};
-use actix_web::web::{Data};
-
-/// Diesel imports
-use diesel;
-use diesel::prelude::*;
-use diesel::{insert_into, QueryDsl, RunQueryDsl};
-use diesel::r2d2::{ConnectionManager, PooledConnection};
-use diesel::PgConnection;
-use futures::{StreamExt};
+use actix_web::web::{Data, Query};
 
 /// Misc imports
-use serde_json::json;
+use futures::{StreamExt};
 use std::collections::BTreeMap;
+use tracing::{error, warn};
 
 /// Module imports
 use super::models::{Dish, NewDish, ReqDish};
-use super::ninjas_api::get_nutrition_info;
+use super::ninjas_api::NutritionClient;
 use super::ninjas_api::NutritionInfo;
+use super::recipes::resolve_all;
+use super::repository::DishRepository;
 
 /// Crate imports
-use crate::db::DbPool;
-use crate::schema::dishes::dsl::dishes;
-use crate::schema::dishes::{id, name};
+use crate::auth::{Editor, Reader, Require};
+use crate::csv_export::{csv_response, wants_csv, FormatQuery};
+use crate::db::{run_blocking, DbError};
+use crate::negotiation::{decode_body, negotiate, request_format};
+use crate::telemetry::RequestId;
 
 /// Error codes as defined in the Assignment
 const NOT_JSON: &str = "0";
@@ -41,26 +38,50 @@ const DISH_NOT_FOUND: &str = "-5";
 ///
 /// # Creates the route for getting all dishes in "/dishes"
 /// ## Arguments
-/// * `db_pool` - A [web::Data<DbPool>] containing the connection pool to the database
+/// * `auth` - Requires the [Reader] role, only the data of its tenant is used
+/// * `dishes` - The [DishRepository] storing the dishes
+/// * `req` - A [HttpRequest] containing the request, used to check if CSV is requested
+/// * `query` - A [web::Query<FormatQuery>] containing the optional `format` parameter
 /// ## Returns
-/// * [HttpResponse::Ok] with a JSON body
+/// * [HttpResponse::Ok] with a JSON body, or a CSV body if requested
+#[utoipa::path(
+    get,
+    path = "/dishes",
+    tag = "dishes",
+    security(("api_key" = ["reader"]), ("bearer" = ["reader"])),
+    params(FormatQuery),
+    responses(
+        (status = 200, description = "All dishes indexed by their ID", content(
+            (BTreeMap<i32, Dish> = "application/json"),
+            ("text/csv"),
+        )),
+        (status = 503, description = "Database unavailable, error code -8"),
+    ),
+)]
 #[get("/dishes")]
-pub async fn  get_all_dishes(db_pool: Data<DbPool>) -> impl Responder {
-    let conn: &mut PooledConnection<ConnectionManager<PgConnection>> = &mut db_pool.get().unwrap();
+pub async fn get_all_dishes(auth: Require<Reader>, dishes: Data<dyn DishRepository>, req: HttpRequest, query: Query<FormatQuery>) -> impl Responder {
+    let tenant = auth.principal.tenant;
+
     /// Load all dishes from the database
-    let all_dishes = dishes.load::<Dish>(conn);
+    let all_dishes = run_blocking(move || dishes.all(&tenant)).await;
     /// Check if the query was successful
     let all_dishes = match all_dishes {
         Ok(all_dishes) => all_dishes,
-        Err(e) => panic!("Error: {}", e),
+        Err(e) => return e.response(),
     };
+
+    /// If CSV was requested, return one row per dish
+    if wants_csv(&req, query.format.as_deref()) {
+        return csv_response("dishes.csv", all_dishes)
+    }
+
     /// Convert Vec<Dish> to JSON object indexed by ID
-    let mut all_dishes: BTreeMap<i32, Dish> = all_dishes.into_iter().map(|dish| (dish.ID, dish)).collect();
+    let all_dishes: BTreeMap<i32, Dish> = all_dishes.into_iter().map(|dish| (dish.ID, dish)).collect();
 
 
 
     /// Return a JSON response with all the dishes
-    HttpResponse::Ok().json(all_dishes)
+    negotiate(&req, HttpResponse::Ok(), &all_dishes)
 }
 
 /*
@@ -70,40 +91,56 @@ pub async fn  get_all_dishes(db_pool: Data<DbPool>) -> impl Responder {
 ///
 /// # Creates the route for creating a dish in "/dishes"
 /// ## Arguments
-/// * `db_pool` - A [web::Data<DbPool>] containing the connection pool to the database
+/// * `auth` - Requires the [Editor] role, only the data of its tenant is used
+/// * `dishes` - The [DishRepository] storing the dishes
+/// * `nutrition_client` - A [web::Data<NutritionClient>] used to look up the nutrition values in the Ninjas API
+/// * `request_id` - The [RequestId] of the request, forwarded to the Ninjas API
 /// * `request` - A [HttpRequest] containing the request
 /// * `req_dish` - A [web::Json<ReqDish>] containing the JSON body of the requested dish
+/// With ingredients, every ingredient is looked up instead of the name, see [super::recipes]
 /// ## Returns
 /// * [HttpResponse::Created] with a JSON body containing the ID of the new dish
+#[utoipa::path(
+    post,
+    path = "/dishes",
+    tag = "dishes",
+    security(("api_key" = ["editor"]), ("bearer" = ["editor"])),
+    request_body = ReqDish,
+    responses(
+        (status = 201, description = "ID of the new dish", body = i32),
+        (status = 415, description = "Content type not supported, error code 0", body = String),
+        (status = 422, description = "Body not parsable (0), name or a value of an ingredient missing (-1), dish already exists (-2), or the dish or an ingredient not recognized by the nutrition API (-3)", body = String),
+        (status = 503, description = "Database unavailable, error code -8"),
+        (status = 504, description = "Nutrition API not reachable, error code -4", body = String),
+    ),
+)]
 #[post("/dishes")]
-pub async fn create_dish(db_pool: web::Data<DbPool>, request: HttpRequest, mut payload: web::Payload) -> impl Responder {
+pub async fn create_dish(auth: Require<Editor>, dishes: Data<dyn DishRepository>, nutrition_client: web::Data<NutritionClient>, request_id: RequestId, request: HttpRequest, mut payload: web::Payload) -> impl Responder {
+    let tenant = auth.principal.tenant;
 
-    /// Check if the Content-Type is application/json
+    /// Check if the Content-Type is JSON, MessagePack or CBOR
     ///
     /// If it is not, return a [HttpResponse::UnsupportedMediaType] with a Error Code 0
-    match request.headers().get("Content-Type") {
-        Some(content_type) => {
-            if content_type != "application/json" {
-                return HttpResponse::UnsupportedMediaType().body(NOT_JSON)
-            }
-        },
-        None => {
-            return HttpResponse::UnsupportedMediaType().body(NOT_JSON)
-        }
-    }
+    let body_format = match request_format(&request) {
+        Some(body_format) => body_format,
+        None => return HttpResponse::UnsupportedMediaType().body(NOT_JSON)
+    };
 
-    /// Deserialize the JSON body ( This step is technically not needed, but the stupid test fails otherwise.
+    /// Deserialize the body ( This step is technically not needed, but the stupid test fails otherwise.
     /// Alternatively I could just automatically deserialize the body in the function call using web::Json<ReqDish> )
-    /// If the JSON body is not valid, return a [HttpResponse::UnprocessableEntity] with a Error Code 0
+    /// If the body is not valid, return a [HttpResponse::UnprocessableEntity] with a Error Code 0
     let mut payload_bytes = web::BytesMut::new();
     while let Some(item) = payload.next().await {
-        payload_bytes.extend_from_slice(&item.unwrap());
+        match item {
+            Ok(bytes) => payload_bytes.extend_from_slice(&bytes),
+            Err(e) => return HttpResponse::from_error(e),
+        }
     }
 
-    let req_dish: ReqDish = match serde_json::from_slice(&payload_bytes) {
+    let req_dish: ReqDish = match decode_body(body_format, &payload_bytes) {
         Ok(req_dish) => req_dish,
         Err(e) => {
-            println!("Error: {}", e);
+            warn!("{}", e);
             return HttpResponse::UnprocessableEntity().body(NOT_JSON)
         }
     };
@@ -116,11 +153,24 @@ pub async fn create_dish(db_pool: web::Data<DbPool>, request: HttpRequest, mut p
         None => return HttpResponse::UnprocessableEntity().body(PARAM_NOT_FOUND)
     };
 
+    /// If ingredients are given, resolve each of them and store the dish as a recipe
+    if let Some(lines) = req_dish.ingredients {
+        let ingredients = match resolve_all(&nutrition_client, lines, &request_id).await {
+            Ok(ingredients) => ingredients,
+            Err(e) => return e.response(),
+        };
+        return match run_blocking(move || dishes.insert_recipe(&tenant, dish_name, ingredients)).await {
+            Ok(dish) => HttpResponse::Created().body(dish.ID.to_string()),
+            Err(DbError::AlreadyExists) => HttpResponse::UnprocessableEntity().body(DISH_ALREADY_EXISTS),
+            Err(e) => e.response(),
+        }
+    }
+
     /// Get the nutrition information from the Ninjas API
     ///
     /// If the API is not responding, return a [HttpResponse::GatewayTimeout] with a Error Code -4
     /// If the dish is not found, return a [HttpResponse::UnprocessableEntity] with a Error Code -3
-    let nut_info = get_nutrition_info(&*dish_name).await;
+    let nut_info = nutrition_client.get_nutrition_info(&dish_name, &request_id).await;
     let nut_info = match nut_info {
         Ok(nut_info) => {
             if nut_info.is_empty() {
@@ -147,17 +197,14 @@ pub async fn create_dish(db_pool: web::Data<DbPool>, request: HttpRequest, mut p
             }
         },
         Err(e) => {
-            eprintln!("Error: {}", e);
+            error!("{}", e);
             return HttpResponse::GatewayTimeout().body(NINJAS_UNAVAILABLE)
         }
     };
 
-    /// Get a connection to the database
-    let conn: &mut PooledConnection<ConnectionManager<PgConnection>> = &mut db_pool.get().unwrap();
-
     /// Create a new dish struct with the nutrition information
     let new_dish = NewDish {
-        name: (&*dish_name).parse().unwrap(),
+        name: dish_name,
         cal: nut_info.calories,
         sodium: nut_info.sodium_mg,
         sugar: nut_info.sugar_g,
@@ -165,18 +212,17 @@ pub async fn create_dish(db_pool: web::Data<DbPool>, request: HttpRequest, mut p
     };
 
     /// Insert the new dish into the database
-    let dish = &mut insert_into(dishes).values(new_dish).get_result::<Dish>(conn);
-
+    let dish = run_blocking(move || dishes.insert(&tenant, new_dish)).await;
 
     /// Check if the insertion was successful
     ///
-    /// If it was not, return a [HttpResponse::UnprocessableEntity] with a Error Code -2
+    /// If the dish already exists, return a [HttpResponse::UnprocessableEntity] with a Error Code -2
     let dish = match dish {
         Ok(dish) => dish,
-        Err(e) => {
-            eprintln!("Error: {}", e);
+        Err(DbError::AlreadyExists) => {
             return HttpResponse::UnprocessableEntity().body(DISH_ALREADY_EXISTS)
         }
+        Err(e) => return e.response(),
     };
 
     let new_dish_id = dish.ID;
@@ -206,10 +252,21 @@ pub async fn create_dish(db_pool: web::Data<DbPool>, request: HttpRequest, mut p
 ///
 /// # Creates the route for deleting a dish in "/dishes"
 ///
+/// ## Arguments
+/// * `_auth` - Requires the [Editor] role
 /// ## Returns
 /// * [HttpResponse::MethodNotAllowed] with a JSON body containing an error message and the error code -7
+#[utoipa::path(
+    delete,
+    path = "/dishes",
+    tag = "dishes",
+    security(("api_key" = ["editor"]), ("bearer" = ["editor"])),
+    responses(
+        (status = 405, description = "The dishes collection can not be deleted"),
+    ),
+)]
 #[delete("/dishes")]
-pub async fn collection_deletion() -> impl Responder {
+pub async fn collection_deletion(_auth: Require<Editor>) -> impl Responder {
 
     /// Return a [HttpResponse::MethodNotAllowed] with a JSON body containing an error message and the error code -7
     HttpResponse::MethodNotAllowed()
@@ -221,32 +278,43 @@ pub async fn collection_deletion() -> impl Responder {
 
 /// # Creates the route for getting a dish by id in "/dishes/{id}"
 /// ## Arguments
-/// * `db_pool` - The database connection pool
+/// * `auth` - Requires the [Reader] role, only the data of its tenant is used
+/// * `dishes` - The [DishRepository] storing the dishes
+/// * `req` - The [HttpRequest], used to pick the response format
 /// * `req_id` - The ID of the dish to be retrieved
 /// ## Returns
 /// * [HttpResponse::Ok] with a JSON body containing the dish
+#[utoipa::path(
+    get,
+    path = "/dishes/{id}",
+    tag = "dishes",
+    security(("api_key" = ["reader"]), ("bearer" = ["reader"])),
+    params(("id" = i32, Path, description = "ID of the dish")),
+    responses(
+        (status = 200, description = "The dish", body = Dish),
+        (status = 404, description = "Dish not found, error code -5", body = String),
+        (status = 503, description = "Database unavailable, error code -8"),
+    ),
+)]
 #[get("/dishes/{id:\\d+}")]
-pub async fn get_dish(db_pool: web::Data<DbPool>, req_id: web::Path<i32>) -> impl Responder {
-
-    /// Get a connection to the database
-    let conn: &mut PooledConnection<ConnectionManager<PgConnection>> = &mut db_pool.get().unwrap();
+pub async fn get_dish(auth: Require<Reader>, dishes: Data<dyn DishRepository>, req: HttpRequest, req_id: web::Path<i32>) -> impl Responder {
+    let tenant = auth.principal.tenant;
 
     /// Get the dish from the database
-    let dish = dishes.find(&*req_id).first::<Dish>(conn);
+    let dish_id = *req_id;
+    let dish = run_blocking(move || dishes.find(&tenant, dish_id)).await;
 
     /// Check if the dish was found in the database
     ///
     /// If it was not, return a [HttpResponse::NotFound] with a JSON body containing an error message and the error code -5
     let dish = match dish {
         Ok(dish) => dish,
-        Err(e) => {
-            eprintln!("Error: {}", e);
-            return HttpResponse::NotFound().body(DISH_NOT_FOUND)
-        }
+        Err(DbError::NotFound) => return HttpResponse::NotFound().body(DISH_NOT_FOUND),
+        Err(e) => return e.response(),
     };
 
     /// Return a [HttpResponse::Ok] with a JSON body containing the dish
-    HttpResponse::Ok().json(dish)
+    negotiate(&req, HttpResponse::Ok(), &dish)
 }
 
 /*
@@ -255,42 +323,39 @@ pub async fn get_dish(db_pool: web::Data<DbPool>, req_id: web::Path<i32>) -> imp
 
 /// # Creates the route for deleting a dish by id in "/dishes/{id}"
 /// ## Arguments
-/// * `db_pool` - The database connection pool
+/// * `auth` - Requires the [Editor] role, only the data of its tenant is used
+/// * `dishes` - The [DishRepository] storing the dishes
 /// * `req_id` - The name of the dish to be deleted
 /// ## Returns
 /// * [HttpResponse::Ok] with a JSON body containing the dish
+#[utoipa::path(
+    delete,
+    path = "/dishes/{id}",
+    tag = "dishes",
+    security(("api_key" = ["editor"]), ("bearer" = ["editor"])),
+    params(("id" = i32, Path, description = "ID of the dish")),
+    responses(
+        (status = 200, description = "ID of the deleted dish", body = i32),
+        (status = 404, description = "Dish not found, error code -5", body = String),
+        (status = 503, description = "Database unavailable, error code -8"),
+    ),
+)]
 #[delete("/dishes/{id:\\d+}")]
-pub async fn delete_dish(db_pool: Data<DbPool>, req_id: web::Path<i32>) -> impl Responder {
-    /// Get a connection to the database
-    let conn: &mut PooledConnection<ConnectionManager<PgConnection>> = &mut db_pool.get().unwrap();
+pub async fn delete_dish(auth: Require<Editor>, dishes: Data<dyn DishRepository>, req_id: web::Path<i32>) -> impl Responder {
+    let tenant = auth.principal.tenant;
 
-    /// Get the dish from the database
-    let dish = dishes.find(&*req_id).first::<Dish>(conn);
+    /// Check if the dish exists and delete it from the database
+    let dish_id = *req_id;
+    let delete_dish = run_blocking(move || dishes.delete(&tenant, dish_id)).await;
 
-    /// Check if the dish was found in the database
-    /// If it was not, return a [HttpResponse::NotFound] with a JSON body containing an error message and the error code -5
-    match dish {
-        Ok(dish) => dish,
-        Err(e) => {
-            eprintln!("Error: {}", e);
-            return HttpResponse::NotFound().body(DISH_NOT_FOUND)
-        }
-    };
-
-    /// Delete the dish from the database
-    let delete_dish = diesel::delete(dishes.find(&*req_id)).execute(conn);
-
-    /// Check if the dish was deleted from the database
+    /// Check if the dish was found and deleted
     ///
-    /// If it was not, return a [HttpResponse::InternalServerError] with a JSON body containing an error message and the error code -8
+    /// If it was not found, return a [HttpResponse::NotFound] with the error code -5
+    /// If the deletion failed, return a [HttpResponse::InternalServerError] with a JSON body containing an error message and the error code -8
     match delete_dish {
         Ok(delete_dish) => delete_dish,
-        Err(e) => {
-            eprintln!("Error: {}", e);
-            return HttpResponse::InternalServerError().json(json!({
-                "message": "Error deleting dish",
-            }))
-        }
+        Err(DbError::NotFound) => return HttpResponse::NotFound().body(DISH_NOT_FOUND),
+        Err(e) => return e.response(),
     };
 
     /// Return a [HttpResponse::Ok] with a JSON body containing a success message and the id of the deleted dish
@@ -303,32 +368,43 @@ pub async fn delete_dish(db_pool: Data<DbPool>, req_id: web::Path<i32>) -> impl
 
 /// # Creates the route for getting a dish by name in "/dishes/{name}"
 /// ## Arguments
-/// * `db_pool` - [web::Data<DbPool>] The database connection pool
+/// * `auth` - Requires the [Reader] role, only the data of its tenant is used
+/// * `dishes` - The [DishRepository] storing the dishes
+/// * `req` - [HttpRequest] The request, used to pick the response format
 /// * `dish_name` - [web::Path<String>] The name of the dish to be retrieved
 /// ## Returns
 /// * [HttpResponse::Ok] with a JSON body containing the dish
+#[utoipa::path(
+    get,
+    path = "/dishes/{name}",
+    tag = "dishes",
+    security(("api_key" = ["reader"]), ("bearer" = ["reader"])),
+    params(("name" = String, Path, description = "Name of the dish, names made of digits only are taken as IDs")),
+    responses(
+        (status = 200, description = "The dish", body = Dish),
+        (status = 404, description = "Dish not found, error code -5", body = String),
+        (status = 503, description = "Database unavailable, error code -8"),
+    ),
+)]
 #[get("/dishes/{name:.*}")]
-pub async fn get_dish_by_name(db_pool: web::Data<DbPool>, dish_name: web::Path<String>) -> impl Responder {
-
-    /// Get a connection to the database
-    let conn: &mut PooledConnection<ConnectionManager<PgConnection>> = &mut db_pool.get().unwrap();
+pub async fn get_dish_by_name(auth: Require<Reader>, dishes: Data<dyn DishRepository>, req: HttpRequest, dish_name: web::Path<String>) -> impl Responder {
+    let tenant = auth.principal.tenant;
 
     /// Get the dish from the database
-    let dish = dishes.filter(name.eq(&*dish_name)).first::<Dish>(conn);
+    let dish_name = dish_name.into_inner();
+    let dish = run_blocking(move || dishes.find_by_name(&tenant, &dish_name)).await;
 
     /// Check if the dish was found in the database
     ///
     /// If it was not, return a [HttpResponse::NotFound] with a JSON body containing an error message and the error code -5
     let dish = match dish {
         Ok(dish) => dish,
-        Err(e) => {
-            eprintln!("Error: {}", e);
-            return HttpResponse::NotFound().body(DISH_NOT_FOUND)
-        }
+        Err(DbError::NotFound) => return HttpResponse::NotFound().body(DISH_NOT_FOUND),
+        Err(e) => return e.response(),
     };
 
     /// Return a [HttpResponse::Ok] with a JSON body containing the dish
-    HttpResponse::Ok().json(dish)
+    negotiate(&req, HttpResponse::Ok(), &dish)
 }
 
 /*
@@ -337,53 +413,74 @@ pub async fn get_dish_by_name(db_pool: web::Data<DbPool>, dish_name: web::Path<S
 
 /// # Creates the route for deleting a dish by name in "/dishes/{name}"
 /// ## Arguments
-/// * `db_pool` - [web::Data<DbPool>] The database connection pool
+/// * `auth` - Requires the [Editor] role, only the data of its tenant is used
+/// * `dishes` - The [DishRepository] storing the dishes
 /// * `dish_name` - [web::Path<String>] The name of the dish to be deleted
 /// ## Returns
 /// * [HttpResponse::Ok] with a JSON body containing the dish
+#[utoipa::path(
+    delete,
+    path = "/dishes/{name}",
+    tag = "dishes",
+    security(("api_key" = ["editor"]), ("bearer" = ["editor"])),
+    params(("name" = String, Path, description = "Name of the dish, names made of digits only are taken as IDs")),
+    responses(
+        (status = 200, description = "ID of the deleted dish", body = i32),
+        (status = 404, description = "Dish not found, error code -5", body = String),
+        (status = 503, description = "Database unavailable, error code -8"),
+    ),
+)]
 #[delete("/dishes/{name:.*}")]
-pub async fn delete_dish_by_name(db_pool: web::Data<DbPool>, dish_name: web::Path<String>) -> impl Responder {
-
-    /// Get a connection to the database
-    let conn: &mut PooledConnection<ConnectionManager<PgConnection>> = &mut db_pool.get().unwrap();
+pub async fn delete_dish_by_name(auth: Require<Editor>, dishes: Data<dyn DishRepository>, dish_name: web::Path<String>) -> impl Responder {
+    let tenant = auth.principal.tenant;
 
-    /// Check if the dish exists in the database and get its ID
-    let dish_id = dishes.filter(name.eq(&*dish_name)).select(id).first::<i32>(conn);
+    /// Get the ID of the dish and delete it from the database
+    let dish_name = dish_name.into_inner();
+    let deletion_result = run_blocking(move || dishes.delete_by_name(&tenant, &dish_name)).await;
 
-    /// Check if the ID could be retrieved
+    /// Check if the dish was found and deleted
     ///
-    /// If it was not, return a [HttpResponse::NotFound] with a JSON body containing an error message and the error code -5
-    let dish_id = match dish_id {
-        Ok(new_dish_id) => new_dish_id,
-        Err(e) => {
-            eprintln!("Error: {}", e);
-            return HttpResponse::NotFound().body(DISH_NOT_FOUND)
-        }
-    };
-
-    /// Delete the dish from the database
-    let deletion_result = diesel::delete(dishes.filter(name.eq(&*dish_name))).execute(conn);
-
-    /// Check if the dish was deleted from the database
-    ///
-    /// If it was not, return a [HttpResponse::InternalServerError] with a JSON body containing an error message and the error code -8
-    match deletion_result {
-        Ok(deletion_result) => deletion_result,
-        Err(e) => {
-            eprintln!("Error: {}", e);
-            return HttpResponse::InternalServerError().json(json!({
-                "message": "Error deleting dish",
-            }))
-        }
+    /// If it was not found, return a [HttpResponse::NotFound] with the error code -5
+    /// If the deletion failed, return a [HttpResponse::InternalServerError] with a JSON body containing an error message and the error code -8
+    let dish_id = match deletion_result {
+        Ok(dish_id) => dish_id,
+        Err(DbError::NotFound) => return HttpResponse::NotFound().body(DISH_NOT_FOUND),
+        Err(e) => return e.response(),
     };
 
     /// Return a [HttpResponse::Ok] with a JSON body containing a success message and the id of the deleted dish
     HttpResponse::Ok().body(dish_id.to_string())
 }
 
-
-
-
-
-
-
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{test, App};
+    use actix_web::middleware::from_fn;
+    use crate::auth::{authenticate, AuthConfig};
+    use crate::db::unreachable_pool;
+    use crate::meals::repository::DieselMealsRepository;
+    use std::sync::Arc;
+
+    #[actix_web::test]
+    async fn database_outage_returns_503() {
+        let app = test::init_service(App::new()
+            .app_data(Data::new(AuthConfig::dev()))
+            .wrap(from_fn(authenticate))
+            .app_data(Data::from(Arc::new(DieselMealsRepository::new(unreachable_pool())) as Arc<dyn DishRepository>))
+            .service(get_all_dishes)
+            .service(get_dish)
+            .service(delete_dish_by_name)).await;
+
+        for req in [
+            test::TestRequest::get().uri("/dishes"),
+            test::TestRequest::get().uri("/dishes/1"),
+            test::TestRequest::delete().uri("/dishes/rice"),
+        ] {
+            let res = test::call_service(&app, req.to_request()).await;
+            assert_eq!(res.status(), 503);
+            let body: serde_json::Value = test::read_body_json(res).await;
+            assert_eq!(body["error_code"], "-8");
+        }
+    }
+}
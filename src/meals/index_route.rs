@@ -0,0 +1,94 @@
+#![allow(unused_doc_comments)]
+
+/// Actix imports
+use actix_web::{get, web, HttpResponse, Responder};
+
+/// Misc imports
+use serde_json::json;
+use std::collections::BTreeMap;
+
+/// Crate imports
+use crate::config::Config;
+use crate::db::DbPool;
+use crate::health::{check_database, check_http, readiness_response, Check, Readiness};
+
+///
+/// # Creates the default route for the API in "/"
+///
+/// ## Returns
+/// * [HttpResponse::Ok] with a JSON body
+#[utoipa::path(
+    get,
+    path = "/",
+    responses(
+        (status = 200, description = "Welcome message"),
+    ),
+)]
+#[get("/")]
+pub async fn index() -> impl Responder {
+    /// Return a JSON response with a message
+    HttpResponse::Ok().json(json!({
+        "message": "Welcome to the MEALS API"
+    }))
+}
+
+/*
+=============================== GET /readyz ===============================
+ */
+///
+/// # Creates the readiness route for the Meals Service in "/readyz"
+/// Checks the database, the migrations, the Diets Service and the Ninjas API concurrently
+/// The Diets Service is left out if it is not configured, the Meals Service is usable without it
+/// ## Arguments
+/// * `db_pool` - A [web::Data<DbPool>] containing the connection pool to the database
+/// * `config` - A [web::Data<Config>] containing the URLs of the Diets Service and the Ninjas API
+/// ## Returns
+/// * [HttpResponse::Ok] if every dependency is up, [HttpResponse::ServiceUnavailable] otherwise, with the status of each dependency
+#[utoipa::path(
+    get,
+    path = "/readyz",
+    tag = "health",
+    responses(
+        (status = 200, description = "Every dependency is up", body = Readiness),
+        (status = 503, description = "At least one dependency is down", body = Readiness),
+    ),
+)]
+#[get("/readyz")]
+pub async fn readyz(db_pool: web::Data<DbPool>, config: web::Data<Config>) -> impl Responder {
+
+    /// Probe the liveness route of the Diets Service
+    let diets_service = async {
+        match config.diets_service.as_ref() {
+            Some(diets_service) => Some(check_http(&format!("{}/healthz", diets_service.base_url.trim_end_matches('/'))).await),
+            None => None,
+        }
+    };
+    /// Probe the Ninjas API with an empty query, any answer but a server error shows that it is reachable
+    /// The API key is not sent, the Ninjas API refuses the probe without counting it against the paid quota
+    let nutrition_provider = async {
+        match config.ninjas.as_ref() {
+            Some(ninjas) => check_http(&ninjas.base_url).await,
+            None => not_configured(),
+        }
+    };
+
+    let ((database, migrations), diets_service, nutrition_provider) = futures::join!(
+        check_database(db_pool.get_ref().clone()),
+        diets_service,
+        nutrition_provider,
+    );
+    let mut checks = BTreeMap::from([
+        ("database", database),
+        ("migrations", migrations),
+        ("nutrition_provider", nutrition_provider),
+    ]);
+    if let Some(diets_service) = diets_service {
+        checks.insert("diets_service", diets_service);
+    }
+    readiness_response(checks)
+}
+
+/// Status of a dependency without configuration, which can only happen if the [Config] was not loaded for the Meals Service
+fn not_configured() -> Check {
+    Check { status: "down", latency_ms: 0, detail: Some("Not configured".to_string()) }
+}
@@ -1,28 +1,28 @@
 #![allow(unused_doc_comments)]
 
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashMap};
 /// Actix Imports
 use actix_web::{get, post, delete, put, HttpResponse, Responder, HttpRequest, web};
 use actix_web::web::{Data, Query};
 
-/// Diesel Imports
-use diesel;
-use diesel::prelude::*;
-use diesel::{delete, insert_into, QueryDsl, RunQueryDsl};
-use diesel::r2d2::{ConnectionManager, PooledConnection};
-
 /// Misc Imports
 use std::string::ToString;
 use futures::StreamExt;
-use serde_json::{from_str, json};
+use serde_json::json;
+use tracing::{error, warn};
 
 /// Module Imports
-use super::models::{Meal, NewMeal, ReqMeal, Diet, ReqDiet};
-use super::diet_client::get_diet_by_name;
+use super::models::{Meal, MealCsvRow, NewMeal, ReqMeal, ReqDiet};
+use super::diet_client::DietClient;
+use super::repository::{DishRepository, MealRepository};
 
 /// Crate Imports
-use crate::schema::meals::dsl::*;
-use crate::db::DbPool;
+use crate::auth::{Editor, Reader, Require};
+use crate::csv_export::{csv_response, wants_csv};
+use crate::db::{run_blocking, DbError};
+use crate::negotiation::{decode_body, negotiate, request_format};
+use crate::telemetry::RequestId;
+use crate::users::UserRepository;
 
 /// Error codes as defined in the Assigment
 const NOT_JSON: &str = "0";
@@ -30,13 +30,25 @@ const PARAM_NOT_FOUND: &str = "-1";
 const MEAL_ALREADY_EXISTS: &str = "-2";
 const MEAL_NOT_FOUND: &str = "-5";
 const DISH_ID_NOT_FOUND: &str = "-6";
-const DIET_NOT_FOUND: &str = "-7";
-const INTERNAL_SERVER_ERROR: &str = "-8";
+
+/// The only user the meals can be filtered by, the caller
+const USER_ME: &str = "me";
 
 /// Disallow DELETE requests to the /meals route
 /// Returns a [HttpResponse::MethodNotAllowed] with a JSON body containing an error message and the error code -7
+/// ## Arguments
+/// * `_auth` - Requires the [Editor] role
+#[utoipa::path(
+    delete,
+    path = "/meals",
+    tag = "meals",
+    security(("api_key" = ["editor"]), ("bearer" = ["editor"])),
+    responses(
+        (status = 405, description = "The meals collection can not be deleted"),
+    ),
+)]
 #[delete("/meals")]
-pub async fn meals_collection_deletion() -> impl Responder {
+pub async fn meals_collection_deletion(_auth: Require<Editor>) -> impl Responder {
     /// Return a [HttpResponse::MethodNotAllowed] with a JSON body containing an error message and the error code -7
     HttpResponse::MethodNotAllowed().json(json!({
         "message": "Method not allowed",
@@ -47,76 +59,130 @@ pub async fn meals_collection_deletion() -> impl Responder {
 =============================== GET /meals ===============================
  */
 /// # Creates the route for getting all meals in "/meals"
+/// With `diet` or `user=me` only the meals within the limits of the diet and of all active diets of the caller are returned
 /// ## Arguments
-/// * `db_pool` - A [web::Data<DbPool>] containing the connection pool to the database
-/// * `query` - A optional [web::Query<String>] containing the diet to filter the meals by
+/// * `auth` - Requires the [Reader] role, only the data of its tenant is used
+/// * `meals` - The [MealRepository] storing the meals
+/// * `dishes` - The [DishRepository], used to look up the names of the dishes for CSV exports
+/// * `users` - The [UserRepository], used to look up the active diets of the caller
+/// * `diet_client` - A [web::Data<DietClient>] used to look up the diets in the Diets Microservice
+/// * `request_id` - The [RequestId] of the request, forwarded to the Diets Microservice
+/// * `req` - A [HttpRequest] containing the request, used to check if CSV is requested
+/// * `query` - A [web::Query<ReqDiet>] containing the optional diet and user to filter the meals by and the optional `format`
 /// ## Returns
-/// * [HttpResponse] with a status of 200 and a JSON body containing all meals
+/// * [HttpResponse] with a status of 200 and a JSON body containing all meals, or a CSV body if requested
+#[utoipa::path(
+    get,
+    path = "/meals",
+    tag = "meals",
+    security(("api_key" = ["reader"]), ("bearer" = ["reader"])),
+    params(ReqDiet),
+    responses(
+        (status = 200, description = "All meals indexed by their ID, or a list of the meals within the limits of the diets", content(
+            (BTreeMap<i32, Meal> = "application/json"),
+            ("text/csv"),
+        )),
+        (status = 400, description = "A user other than `me` was requested", body = String),
+        (status = 404, description = "Diet not found, caller not registered or without an active diet", body = String),
+        (status = 503, description = "The Diets Service or the database is unavailable"),
+        (status = 504, description = "The Diets Service did not answer in time"),
+    ),
+)]
 #[get("/meals")]
-pub async fn get_all_meals(db_pool: Data<DbPool>, query: Query<ReqDiet>) -> impl Responder {
-
-    /// Check if the diet query parameter is present and is not empty
-    if let Some(diet_name) = &query.diet {
-        if diet_name.is_empty() {
-            /// Establish a connection to the database
-            let conn: &mut PooledConnection<ConnectionManager<PgConnection>> = &mut db_pool.get().unwrap();
-            /// Get all meals from the database
-            let results = meals.load::<Meal>(conn).expect("Error loading meals");
-            /// Return a 200 response with the meals in the body
-            HttpResponse::Ok().json(results)
-        } else {
-
-            /// Send GET request to "/diet/{diet}"
-            let diet = get_diet_by_name(diet_name).await;
-            /// If the diet is found parse the response body into a Diet struct
-            /// If the diet is not found, return a 404 response with a JSON body containing an error message and the error code -7
-            let diet: Diet = match diet {
-                Ok(diet) => {
-                    match from_str(&diet) {
-                        Ok(diet) => diet,
-                        Err(e) => {
-                            return HttpResponse::InternalServerError().body(e.to_string());
-                        }
-                    }
-                },
-                Err(e) => {
-                    println!("{}", e);
-                    return HttpResponse::NotFound().body("Diet {} not found".replace("{}", diet_name));
-
-                }
+#[allow(clippy::too_many_arguments)]
+pub async fn get_all_meals(auth: Require<Reader>, meals: Data<dyn MealRepository>, dishes: Data<dyn DishRepository>, users: Data<dyn UserRepository>, diet_client: Data<DietClient>, request_id: RequestId, req: HttpRequest, query: Query<ReqDiet>) -> impl Responder {
+    let principal = auth.principal;
+    let tenant = principal.tenant.clone();
+
+    /// Collect the names of the diets to filter by, the diet query parameter is ignored if it is empty
+    let mut diet_names: Vec<String> = query.diet.iter().filter(|diet_name| !diet_name.is_empty()).cloned().collect();
+
+    /// With `user=me` the active diets of the caller are added
+    match query.user.as_deref() {
+        None => {}
+        Some(USER_ME) => {
+            let subject = principal.subject.clone();
+            let user_diets = run_blocking(move || users.diets(&principal.tenant, &principal.subject)).await;
+            let user_diets = match user_diets {
+                Ok(user_diets) => user_diets,
+                Err(DbError::NotFound) => return HttpResponse::NotFound().body("User {} not found".replace("{}", &subject)),
+                Err(e) => return e.response(),
             };
+            let active: Vec<String> = user_diets.into_iter().filter(|diet| diet.active).map(|diet| diet.diet).collect();
+            if active.is_empty() {
+                return HttpResponse::NotFound().body("User {} has no active diet".replace("{}", &subject));
+            }
+            diet_names.extend(active);
+        }
+        Some(_) => return HttpResponse::BadRequest().body("Only user=me is supported"),
+    }
 
-            /// Establish a connection to the database
-            let conn: &mut PooledConnection<ConnectionManager<PgConnection>> = &mut db_pool.get().unwrap();
-            /// Get all meals from the database that have <= the calories, sodium, and sugar of the diet
-            let results = meals
-                .filter(cal.le(&diet.cal))
-                .filter(sodium.le(&diet.sodium))
-                .filter(sugar.le(&diet.sugar))
-                .load::<Meal>(conn);
-            /// If there is an error loading the meals, return a 500 response with a JSON body containing an error message and the error code -8
-            return match results {
-                Ok(results) => HttpResponse::Ok().json(results),
-                Err(e) => {
-                    HttpResponse::InternalServerError().json(json!({
-                    "message": "Internal Server Error",
-                    "error_code": INTERNAL_SERVER_ERROR,
-                    "error": e.to_string()
-                }))
-                }
-            };
+    let results = if diet_names.is_empty() {
+        /// Get all meals from the database
+        let tenant = tenant.clone();
+        match run_blocking(move || meals.all(&tenant)).await {
+            Ok(results) => results,
+            Err(e) => return e.response(),
         }
     } else {
-        /// Establish a connection to the database
-        let conn: &mut PooledConnection<ConnectionManager<PgConnection>> = &mut db_pool.get().unwrap();
-        /// Get all meals from the database
-        let results = meals.load::<Meal>(conn).expect("Error loading meals");
-        /// Convert the meals to JSON indexed by ID
-        let mut all_meals: BTreeMap<i32, Meal> = results.into_iter().map(|meal| (meal.ID, meal)).collect();
-        /// Return a 200 response with the meals in the body
-        HttpResponse::Ok().json(all_meals)
+        /// Send GET request to "/diet/{diet}" for every diet, the tightest limit of each nutrient applies
+        /// If a diet is not found return a 404 response, if the Diets Service is down or too slow a 503 or 504 response
+        let limits = match diet_client.get_limits(&tenant, &diet_names, &request_id).await {
+            Ok(limits) => limits,
+            Err(e) => return e.response(),
+        };
+
+        /// Get all meals from the database that have <= the calories, sodium, and sugar of the diets
+        let tenant = tenant.clone();
+        let results = run_blocking(move || meals.within(&tenant, limits.cal, limits.sodium, limits.sugar)).await;
+        /// If there is an error loading the meals, return a 500 or 503 response with a JSON body containing an error message and the error code -8
+        match results {
+            Ok(results) => results,
+            Err(e) => return e.response(),
+        }
+    };
+
+    /// If CSV was requested, return one row per meal including the names of its dishes
+    if wants_csv(&req, query.format.as_deref()) {
+        return match run_blocking(move || meal_csv_rows(&**dishes, &tenant, results)).await {
+            Ok(rows) => csv_response("meals.csv", rows),
+            Err(e) => e.response(),
+        }
+    }
+
+    /// Without a diet or user, the meals are returned as JSON indexed by ID, otherwise as a list
+    if query.diet.is_none() && query.user.is_none() {
+        let all_meals: BTreeMap<i32, Meal> = results.into_iter().map(|meal| (meal.ID, meal)).collect();
+        negotiate(&req, HttpResponse::Ok(), &all_meals)
+    } else {
+        negotiate(&req, HttpResponse::Ok(), &results)
     }
+}
 
+/// Converts meals into [MealCsvRow]s by looking up the names of the referenced dishes
+fn meal_csv_rows(dishes: &dyn DishRepository, tenant: &str, results: Vec<Meal>) -> Result<Vec<MealCsvRow>, DbError> {
+
+    /// Load the names of all referenced dishes at once
+    let dish_ids: Vec<i32> = results.iter()
+        .flat_map(|meal| [meal.appetizer, meal.main, meal.dessert])
+        .flatten()
+        .collect();
+    let dish_names: HashMap<i32, String> = dishes.names(tenant, &dish_ids)?;
+    let dish_name = |dish_id: Option<i32>| dish_id.and_then(|dish_id| dish_names.get(&dish_id).cloned());
+
+    Ok(results.into_iter().map(|meal| MealCsvRow {
+        ID: meal.ID,
+        appetizer_name: dish_name(meal.appetizer),
+        main_name: dish_name(meal.main),
+        dessert_name: dish_name(meal.dessert),
+        name: meal.name,
+        appetizer: meal.appetizer,
+        main: meal.main,
+        dessert: meal.dessert,
+        cal: meal.cal,
+        sodium: meal.sodium,
+        sugar: meal.sugar,
+    }).collect())
 }
 
 /*
@@ -125,40 +191,52 @@ pub async fn get_all_meals(db_pool: Data<DbPool>, query: Query<ReqDiet>) -> impl
 /// # Creates the route for creating a meal in "/meals"
 /// Creates a new meal in the database, based on the JSON body of the request
 /// ## Arguments
-/// * `db_pool` - A [web::Data<DbPool>] containing the connection pool to the database
+/// * `auth` - Requires the [Editor] role, only the data of its tenant is used
+/// * `meals` - The [MealRepository] storing the meals
 /// * `req` - A [HttpRequest] containing the request
 /// * `req_meal` - A [web::Json<ReqMeal>] containing the JSON body of the request with the Requested Meal
 /// ## Returns
 /// * [HttpResponse] with a status of 201 and a JSON body containing the new meal
+#[utoipa::path(
+    post,
+    path = "/meals",
+    tag = "meals",
+    security(("api_key" = ["editor"]), ("bearer" = ["editor"])),
+    request_body = ReqMeal,
+    responses(
+        (status = 201, description = "ID of the new meal", body = i32),
+        (status = 415, description = "Content type not supported, error code 0", body = String),
+        (status = 422, description = "Body not parsable (0), field missing (-1), meal already exists (-2) or a dish does not exist (-6)", body = String),
+        (status = 503, description = "Database unavailable, error code -8"),
+    ),
+)]
 #[post("/meals")]
-pub async fn create_meal(db_pool: web::Data<DbPool>, req: HttpRequest, mut payload: web::Payload) -> impl Responder {
+pub async fn create_meal(auth: Require<Editor>, meals: Data<dyn MealRepository>, req: HttpRequest, mut payload: web::Payload) -> impl Responder {
+    let tenant = auth.principal.tenant;
 
-    /// Check if the Content-Type is application/json
+    /// Check if the Content-Type is JSON, MessagePack or CBOR
     ///
     /// If it is not, return a [HttpResponse::UnsupportedMediaType] with a Error Code 0
-    match req.headers().get("Content-Type") {
-        Some(content_type) => {
-            if content_type != "application/json" {
-                return HttpResponse::UnsupportedMediaType().body(NOT_JSON)
-            }
-        },
-        None => {
-            return HttpResponse::UnsupportedMediaType().body(NOT_JSON)
-        }
-    }
+    let body_format = match request_format(&req) {
+        Some(body_format) => body_format,
+        None => return HttpResponse::UnsupportedMediaType().body(NOT_JSON)
+    };
 
-    /// Deserialize the JSON body ( This step is technically not needed, but the stupid test fails otherwise.
+    /// Deserialize the body ( This step is technically not needed, but the stupid test fails otherwise.
     /// Alternatively I could just automatically deserialize the body in the function call using web::Json<ReqMeal> )
-    /// If the JSON body is not valid, return a [HttpResponse::UnprocessableEntity] with a Error Code 0
+    /// If the body is not valid, return a [HttpResponse::UnprocessableEntity] with a Error Code 0
     let mut payload_bytes = web::BytesMut::new();
     while let Some(item) = payload.next().await {
-        payload_bytes.extend_from_slice(&item.unwrap());
+        match item {
+            Ok(bytes) => payload_bytes.extend_from_slice(&bytes),
+            Err(e) => return HttpResponse::from_error(e),
+        }
     }
 
-    let req_meal:ReqMeal = match serde_json::from_slice(&payload_bytes) {
+    let req_meal:ReqMeal = match decode_body(body_format, &payload_bytes) {
         Ok(req_meal) => req_meal,
         Err(e) => {
-            println!("{}", e);
+            warn!("{}", e);
             return HttpResponse::UnprocessableEntity().body(NOT_JSON);
         }
     };
@@ -173,32 +251,27 @@ pub async fn create_meal(db_pool: web::Data<DbPool>, req: HttpRequest, mut paylo
     /// Create a new NewMeal struct with the values from the request
     let new_meal:NewMeal = NewMeal {
         name: req_meal.name.clone().unwrap(),
-        appetizer: req_meal.appetizer.clone().unwrap(),
-        main: req_meal.main.clone().unwrap(),
-        dessert: req_meal.dessert.clone().unwrap(),
+        appetizer: req_meal.appetizer.unwrap(),
+        main: req_meal.main.unwrap(),
+        dessert: req_meal.dessert.unwrap(),
     };
 
-    /// Create a connection to the database
-    let conn: &mut PooledConnection<ConnectionManager<PgConnection>> = &mut db_pool.get().unwrap();
 
-    let meal = &mut insert_into(meals).values(new_meal).get_result::<Meal>(conn);
+    let meal = run_blocking(move || meals.insert(&tenant, new_meal)).await;
 
     /// Check if the insertion was successful
     ///
-    /// If it was not, return a [HttpResponse::UnprocessableEntity] with a Error Code -2
+    /// If the meal already exists, return a [HttpResponse::UnprocessableEntity] with a Error Code -2
+    /// If one of the dishes does not exist, return a [HttpResponse::UnprocessableEntity] with a Error Code -6
     let meal = match meal {
         Ok(meal) => meal,
-        Err(e) => {
-            /// TODO: Make this nicer with a match statement and Error types
-            if e.to_string().contains("duplicate key value violates unique constraint") {
-                return HttpResponse::UnprocessableEntity().body(MEAL_ALREADY_EXISTS)
-            }
-            if e.to_string().contains("violates foreign key constraint") {
-                return HttpResponse::UnprocessableEntity().body(DISH_ID_NOT_FOUND)
-            }
-            return HttpResponse::UnprocessableEntity().body(e.to_string())
-
+        Err(DbError::AlreadyExists) => {
+            return HttpResponse::UnprocessableEntity().body(MEAL_ALREADY_EXISTS)
         }
+        Err(DbError::MissingReference) => {
+            return HttpResponse::UnprocessableEntity().body(DISH_ID_NOT_FOUND)
+        }
+        Err(e) => return e.response(),
     };
 
     let new_meal_id = meal.ID;
@@ -214,65 +287,88 @@ pub async fn create_meal(db_pool: web::Data<DbPool>, req: HttpRequest, mut paylo
 
 /// # Creates the route for getting a meal by ID in "/meals/{id}"
 /// # Arguments
-/// * `db_pool` - A [web::Data<DbPool>] containing the connection pool to the database
+/// * `auth` - Requires the [Reader] role, only the data of its tenant is used
+/// * `meals` - The [MealRepository] storing the meals
+/// * `req` - A [HttpRequest] containing the request, used to pick the response format
 /// * `req_id` - A [web::Path<i32>] containing the ID of the meal
 /// # Returns
 /// * [HttpResponse::Ok] with a JSON body containing the meal
+#[utoipa::path(
+    get,
+    path = "/meals/{id}",
+    tag = "meals",
+    security(("api_key" = ["reader"]), ("bearer" = ["reader"])),
+    params(("id" = i32, Path, description = "ID of the meal")),
+    responses(
+        (status = 200, description = "The meal", body = Meal),
+        (status = 404, description = "Meal not found, error code -5", body = String),
+        (status = 503, description = "Database unavailable, error code -8"),
+    ),
+)]
 #[get("/meals/{id:\\d+}")]
-pub async fn get_meal(db_pool: Data<DbPool>, req_id: web::Path<i32>) -> impl Responder {
-
-    /// Create a connection to the database
-    let conn: &mut PooledConnection<ConnectionManager<PgConnection>> = &mut db_pool.get().unwrap();
+pub async fn get_meal(auth: Require<Reader>, meals: Data<dyn MealRepository>, req: HttpRequest, req_id: web::Path<i32>) -> impl Responder {
+    let tenant = auth.principal.tenant;
 
     /// Get the meal with the specified ID
-    let meal = meals.find(&*req_id).first::<Meal>(conn);
+    let meal_id = *req_id;
+    let meal = run_blocking(move || meals.find(&tenant, meal_id)).await;
 
     /// Check if the meal exists
     ///
     /// If it does not, return a [HttpResponse::NotFound] with a Error Code -3
     let meal = match meal {
         Ok(meal) => meal,
-        Err(e) => {
-            eprintln!("Error: {}", e);
-            return HttpResponse::NotFound().body(MEAL_NOT_FOUND)
-        }
+        Err(DbError::NotFound) => return HttpResponse::NotFound().body(MEAL_NOT_FOUND),
+        Err(e) => return e.response(),
     };
 
     /// Return a [HttpResponse::Ok] with a JSON body containing the meal
-    HttpResponse::Ok().json(meal)
+    negotiate(&req, HttpResponse::Ok(), &meal)
 }
 
 /*
 =============================== GET /meals/{name} ===============================
  */
 /// # Creates the route for getting a meal by name in "/meals/{name}"
+/// A meal named `suggest` can only be read by its ID, since "/meals/suggest" is routed to [crate::meals::get_meal_suggestions] first
 /// ## Arguments
-/// * `db_pool` - A [web::Data<DbPool>] containing the connection pool to the database
+/// * `auth` - Requires the [Reader] role, only the data of its tenant is used
+/// * `meals` - The [MealRepository] storing the meals
+/// * `req` - A [HttpRequest] containing the request, used to pick the response format
 /// * `meal_name` - A [web::Path<String>] containing the name of the meal
 /// ## Returns
 /// * [HttpResponse::Ok] with a JSON body containing the meal
+#[utoipa::path(
+    get,
+    path = "/meals/{name}",
+    tag = "meals",
+    security(("api_key" = ["reader"]), ("bearer" = ["reader"])),
+    params(("name" = String, Path, description = "Name of the meal, names made of digits only are taken as IDs and `suggest` lists the suggestions")),
+    responses(
+        (status = 200, description = "The meal", body = Meal),
+        (status = 404, description = "Meal not found, error code -5", body = String),
+        (status = 503, description = "Database unavailable, error code -8"),
+    ),
+)]
 #[get("/meals/{name:.*}")]
-pub async fn get_meal_by_name(db_pool: Data<DbPool>, meal_name: web::Path<String>) -> impl Responder {
-
-    /// Create a connection to the database
-    let conn: &mut PooledConnection<ConnectionManager<PgConnection>> = &mut db_pool.get().unwrap();
+pub async fn get_meal_by_name(auth: Require<Reader>, meals: Data<dyn MealRepository>, req: HttpRequest, meal_name: web::Path<String>) -> impl Responder {
+    let tenant = auth.principal.tenant;
 
     /// Get the meal with the specified name
-    let meal = meals.filter(name.eq(&*meal_name)).first::<Meal>(conn);
+    let meal_name = meal_name.into_inner();
+    let meal = run_blocking(move || meals.find_by_name(&tenant, &meal_name)).await;
 
     /// Check if the meal exists
     ///
     /// If it does not, return a [HttpResponse::NotFound] with a Error Code -3
     let meal = match meal {
         Ok(meal) => meal,
-        Err(e) => {
-            eprintln!("Error: {}", e);
-            return HttpResponse::NotFound().body(MEAL_NOT_FOUND)
-        }
+        Err(DbError::NotFound) => return HttpResponse::NotFound().body(MEAL_NOT_FOUND),
+        Err(e) => return e.response(),
     };
 
     /// Return a [HttpResponse::Ok] with a JSON body containing the meal
-    HttpResponse::Ok().json(meal)
+    negotiate(&req, HttpResponse::Ok(), &meal)
 }
 
 /*
@@ -280,41 +376,38 @@ pub async fn get_meal_by_name(db_pool: Data<DbPool>, meal_name: web::Path<String
  */
 /// # Creates the route for deleting a meal by ID in "/meals/{id}"
 /// ## Arguments
-/// * `db_pool` - A [web::Data<DbPool>] containing the connection pool to the database
+/// * `auth` - Requires the [Editor] role, only the data of its tenant is used
+/// * `meals` - The [MealRepository] storing the meals
 /// * `req_id` - A [web::Path<i32>] containing the ID of the meal
 /// ## Returns
 /// * [HttpResponse::Ok] with a JSON body containing the ID of the deleted meal
+#[utoipa::path(
+    delete,
+    path = "/meals/{id}",
+    tag = "meals",
+    security(("api_key" = ["editor"]), ("bearer" = ["editor"])),
+    params(("id" = i32, Path, description = "ID of the meal")),
+    responses(
+        (status = 200, description = "ID of the deleted meal", body = i32),
+        (status = 404, description = "Meal not found, error code -5", body = String),
+        (status = 503, description = "Database unavailable, error code -8"),
+    ),
+)]
 #[delete("/meals/{id:\\d+}")]
-pub async fn delete_meal(db_pool: Data<DbPool>, req_id: web::Path<i32>) -> impl Responder {
-
-    /// Create a connection to the database
-    let conn: &mut PooledConnection<ConnectionManager<PgConnection>> = &mut db_pool.get().unwrap();
-
-    /// Check if the meal exists
-    /// If it does not, return a [HttpResponse::NotFound] with a Error Code -5
-    let meal_exists = meals.find(&*req_id).select(id).first::<i32>(conn);
-    match meal_exists {
-        Ok(meal_exists) => meal_exists,
-        Err(e) => {
-            eprintln!("Error: {}", e);
-            return HttpResponse::NotFound().body(MEAL_NOT_FOUND)
-        }
-    };
+pub async fn delete_meal(auth: Require<Editor>, meals: Data<dyn MealRepository>, req_id: web::Path<i32>) -> impl Responder {
+    let tenant = auth.principal.tenant;
 
-    /// Delete the meal with the specified ID
-    let meal = delete(meals.find(&*req_id)).execute(conn);
+    /// Check if the meal exists and delete it
+    let meal_id = *req_id;
+    let meal = run_blocking(move || meals.delete(&tenant, meal_id)).await;
 
-    /// Check if the meal exists
+    /// Check if the meal was found and deleted
     ///
-    /// If it does not, return a [HttpResponse::NotFound] with a Error Code -3
+    /// If it was not found, return a [HttpResponse::NotFound] with a Error Code -5
     match meal {
         Ok(meal) => meal,
-        Err(e) => {
-            eprintln!("Error: {}", e);
-            return HttpResponse::InternalServerError().json(json!({
-                "message": "Error deleting meal",
-            }))
-        }
+        Err(DbError::NotFound) => return HttpResponse::NotFound().body(MEAL_NOT_FOUND),
+        Err(e) => return e.response(),
     };
 
     /// Return a [HttpResponse::Ok] with a JSON body containing the ID of the deleted meal
@@ -327,42 +420,38 @@ pub async fn delete_meal(db_pool: Data<DbPool>, req_id: web::Path<i32>) -> impl
 
 /// # Creates the route for deleting a meal by name in "/meals/{name}"
 /// ## Arguments
-/// * `db_pool` - A [web::Data<DbPool>] containing the connection pool to the database
+/// * `auth` - Requires the [Editor] role, only the data of its tenant is used
+/// * `meals` - The [MealRepository] storing the meals
 /// * `meal_name` - A [web::Path<String>] containing the name of the meal
 /// ## Returns
 /// * [HttpResponse::Ok] with a JSON body containing the ID of the deleted meal
-
+#[utoipa::path(
+    delete,
+    path = "/meals/{name}",
+    tag = "meals",
+    security(("api_key" = ["editor"]), ("bearer" = ["editor"])),
+    params(("name" = String, Path, description = "Name of the meal, names made of digits only are taken as IDs")),
+    responses(
+        (status = 200, description = "ID of the deleted meal", body = i32),
+        (status = 404, description = "Meal not found, error code -5", body = String),
+        (status = 503, description = "Database unavailable, error code -8"),
+    ),
+)]
 #[delete("/meals/{name:.*}")]
-pub async fn delete_meal_by_name(db_pool: Data<DbPool>, meal_name: web::Path<String>) -> impl Responder {
+pub async fn delete_meal_by_name(auth: Require<Editor>, meals: Data<dyn MealRepository>, meal_name: web::Path<String>) -> impl Responder {
+    let tenant = auth.principal.tenant;
 
-    /// Create a connection to the database
-    let conn: &mut PooledConnection<ConnectionManager<PgConnection>> = &mut db_pool.get().unwrap();
-
-    /// Check if the meal exists
-    /// If it does, save the ID of the meal,
-    /// If it does not, return a [HttpResponse::NotFound] with a Error Code -5
-    let meal_exists = meals.filter(name.eq(&*meal_name)).select(id).first::<i32>(conn);
-    let deleted_id = match meal_exists {
-        Ok(meal_exists) => meal_exists,
-        Err(e) => {
-            eprintln!("Error: {}", e);
-            return HttpResponse::NotFound().body(MEAL_NOT_FOUND)
-        }
-    };
-
-    /// Delete the meal with the specified name
-    let meal = delete(meals.filter(name.eq(&*meal_name))).execute(conn);
+    /// Get the ID of the meal and delete it
+    let meal_name = meal_name.into_inner();
+    let meal = run_blocking(move || meals.delete_by_name(&tenant, &meal_name)).await;
 
     /// Check if deletion was successful
-    /// If it was not, return a [HttpResponse::InternalServerError] with a Error Code -8
-    match meal {
-        Ok(meal) => meal,
-        Err(e) => {
-            eprintln!("Error: {}", e);
-            return HttpResponse::InternalServerError().json(json!({
-                "message": "Error deleting meal",
-            }))
-        }
+    /// If the meal does not exist, return a [HttpResponse::NotFound] with a Error Code -5
+    /// If the deletion failed, return a [HttpResponse::InternalServerError] with a Error Code -8
+    let deleted_id = match meal {
+        Ok(meal_id) => meal_id,
+        Err(DbError::NotFound) => return HttpResponse::NotFound().body(MEAL_NOT_FOUND),
+        Err(e) => return e.response(),
     };
 
     /// Return a [HttpResponse::Ok] with a JSON body containing the ID of the deleted meal
@@ -374,32 +463,54 @@ pub async fn delete_meal_by_name(db_pool: Data<DbPool>, meal_name: web::Path<Str
  */
 /// # Creates the route for updating a meal by ID in "/meals/{id}"
 /// ## Arguments
-/// * `db_pool` - A [web::Data<DbPool>] containing the connection pool to the database
+/// * `auth` - Requires the [Editor] role, only the data of its tenant is used
+/// * `meals` - The [MealRepository] storing the meals
 /// * `req` - The [HttpRequest] object
 /// * `req_id` - A [web::Path<i32>] containing the ID of the meal
-/// * `req_meal` - A [web::Json<NewMeal>] containing the new meal data
+/// * `body` - A [web::Bytes] containing the new meal data as JSON, MessagePack or CBOR
 /// ## Returns
 /// * [HttpResponse::Ok] on success
-/// * [HttpResponse::UnsupportedMediaType] if the Content-Type is not application/json
+/// * [HttpResponse::UnsupportedMediaType] if the Content-Type is not JSON, MessagePack or CBOR
 /// * [HttpResponse::UnprocessableEntity] if the new meal data is missing required fields
-/// * [HttpResponse::InternalSexexrrverError] on failure
+/// * [HttpResponse::InternalServerError] or [HttpResponse::ServiceUnavailable] if the database access fails
 /// * [HttpResponse::NotFound] if the meal does not exist
+#[utoipa::path(
+    put,
+    path = "/meals/{id}",
+    tag = "meals",
+    security(("api_key" = ["editor"]), ("bearer" = ["editor"])),
+    params(("id" = i32, Path, description = "ID of the meal")),
+    request_body = ReqMeal,
+    responses(
+        (status = 200, description = "ID of the updated meal", body = i32),
+        (status = 400, description = "Body not parsable, error code 0", body = String),
+        (status = 404, description = "Meal not found, error code -5", body = String),
+        (status = 415, description = "Content type not supported, error code 0", body = String),
+        (status = 422, description = "Field missing (-1), name taken by another meal (-2) or a dish does not exist (-6)", body = String),
+        (status = 503, description = "Database unavailable, error code -8"),
+    ),
+)]
 #[put("/meals/{id:\\d+}")]
-pub async fn update_meal(db_pool: Data<DbPool>, req: HttpRequest, req_id: web::Path<i32>, req_meal: web::Json<ReqMeal>) -> impl Responder {
+pub async fn update_meal(auth: Require<Editor>, meals: Data<dyn MealRepository>, req: HttpRequest, req_id: web::Path<i32>, body: web::Bytes) -> impl Responder {
+    let tenant = auth.principal.tenant;
 
-    /// Check if the Content-Type is application/json
+    /// Check if the Content-Type is JSON, MessagePack or CBOR
     ///
     /// If it is not, return a [HttpResponse::UnsupportedMediaType] with a Error Code 0
-    match req.headers().get("Content-Type") {
-        Some(content_type) => {
-            if content_type != "application/json" {
-                return HttpResponse::UnsupportedMediaType().body(NOT_JSON)
-            }
-        },
-        None => {
-            return HttpResponse::UnsupportedMediaType().body(NOT_JSON)
+    let body_format = match request_format(&req) {
+        Some(body_format) => body_format,
+        None => return HttpResponse::UnsupportedMediaType().body(NOT_JSON)
+    };
+
+    /// Deserialize the body
+    /// If the body is not valid, return a [HttpResponse::BadRequest] with a Error Code 0
+    let req_meal: ReqMeal = match decode_body(body_format, &body) {
+        Ok(req_meal) => req_meal,
+        Err(e) => {
+            error!("{}", e);
+            return HttpResponse::BadRequest().body(NOT_JSON);
         }
-    }
+    };
 
     /// Check if req_meal is has all the required fields
     /// If it does not, return a [HttpResponse::UnprocessableEntity] with a Error Code -1
@@ -410,50 +521,120 @@ pub async fn update_meal(db_pool: Data<DbPool>, req: HttpRequest, req_id: web::P
     /// Create a [NewMeal] struct from the [ReqMeal] struct
     let new_meal:NewMeal = NewMeal {
         name: req_meal.name.clone().unwrap(),
-        appetizer: req_meal.appetizer.clone().unwrap(),
-        main: req_meal.main.clone().unwrap(),
-        dessert: req_meal.dessert.clone().unwrap(),
+        appetizer: req_meal.appetizer.unwrap(),
+        main: req_meal.main.unwrap(),
+        dessert: req_meal.dessert.unwrap(),
     };
 
-    /// Create a connection to the database
-    let conn: &mut PooledConnection<ConnectionManager<PgConnection>> = &mut db_pool.get().unwrap();
+    /// Check if the meal exists and update it
+    let meal_id = *req_id;
+    let meal = run_blocking(move || meals.update(&tenant, meal_id, new_meal)).await;
 
-    /// Check if the meal exists
-    /// If it does not, return a [HttpResponse::NotFound] with a Error Code -5
-    let meal_exists = meals.find(&*req_id).select(id).first::<i32>(conn);
-    match meal_exists {
-        Ok(meal_exists) => meal_exists,
-        Err(e) => {
-            // Can use this to create a new meal, but not required in assignment
-            eprintln!("Error: {}", e);
-            return HttpResponse::NotFound().body(MEAL_NOT_FOUND)
-        }
-    };
-
-    /// Update the meal with the specified ID
-    let meal = diesel::update(meals.find(&*req_id))
-        .set((
-            name.eq(&new_meal.name),
-            appetizer.eq(&new_meal.appetizer),
-            main.eq(&new_meal.main),
-            dessert.eq(&new_meal.dessert),
-
-        ))
-        .execute(conn);
-
-    /// Check if the meal exists
+    /// Check if the update was successful
     ///
-    /// If it does not, return a [HttpResponse::NotFound] with a Error Code -3
+    /// If the meal does not exist, return a [HttpResponse::NotFound] with a Error Code -5
+    /// If another meal has the new name, return a [HttpResponse::UnprocessableEntity] with a Error Code -2
+    /// If one of the dishes does not exist, return a [HttpResponse::UnprocessableEntity] with a Error Code -6
     match meal {
         Ok(meal) => meal,
-        Err(e) => {
-            eprintln!("Error: {}", e);
-            return HttpResponse::InternalServerError().json(json!({
-                "message": "Error updating meal",
-            }))
+        // Can use this to create a new meal, but not required in assignment
+        Err(DbError::NotFound) => return HttpResponse::NotFound().body(MEAL_NOT_FOUND),
+        Err(DbError::AlreadyExists) => {
+            return HttpResponse::UnprocessableEntity().body(MEAL_ALREADY_EXISTS)
+        }
+        Err(DbError::MissingReference) => {
+            return HttpResponse::UnprocessableEntity().body(DISH_ID_NOT_FOUND)
         }
+        Err(e) => return e.response(),
     };
 
     /// Return a [HttpResponse::Ok] with a JSON body containing the ID of the updated meal
     HttpResponse::Ok().body(req_id.to_string())
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{test, App};
+    use actix_web::middleware::from_fn;
+    use crate::auth::{authenticate, AuthConfig, DEFAULT_TENANT, TENANT_HEADER};
+    use std::sync::Arc;
+    use crate::meals::models::NewDish;
+    use crate::meals::InMemoryMealsRepository;
+
+    fn dish(name: &str, cal: f64, sodium: f64, sugar: f64) -> NewDish {
+        NewDish { name: name.to_string(), cal, sodium, sugar, size: 100.0 }
+    }
+
+    #[actix_web::test]
+    async fn meals_are_stored_in_the_repository() {
+        let repository = Arc::new(InMemoryMealsRepository::new());
+        for new_dish in [dish("soup", 100.0, 10.0, 1.0), dish("steak", 500.0, 50.0, 0.0), dish("cake", 300.0, 5.0, 30.0)] {
+            DishRepository::insert(&*repository, DEFAULT_TENANT, new_dish).unwrap();
+        }
+        let app = test::init_service(App::new()
+            .app_data(Data::new(AuthConfig::dev()))
+            .wrap(from_fn(authenticate))
+            .app_data(Data::from(repository.clone() as Arc<dyn MealRepository>))
+            .service(create_meal)
+            .service(get_meal)
+            .service(update_meal)
+            .service(delete_meal_by_name)).await;
+
+        let post = |body: &'static str| test::TestRequest::post().uri("/meals").insert_header(("Content-Type", "application/json")).set_payload(body).to_request();
+        let dinner = r#"{"name": "dinner", "appetizer": 1, "main": 2, "dessert": 3}"#;
+        assert_eq!(test::call_and_read_body(&app, post(dinner)).await, "1");
+        assert_eq!(test::call_and_read_body(&app, post(dinner)).await, MEAL_ALREADY_EXISTS);
+        assert_eq!(test::call_and_read_body(&app, post(r#"{"name": "lunch", "appetizer": 1, "main": 9, "dessert": 3}"#)).await, DISH_ID_NOT_FOUND);
+
+        let meal: Meal = test::call_and_read_body_json(&app, test::TestRequest::get().uri("/meals/1").to_request()).await;
+        assert_eq!((meal.cal, meal.sodium, meal.sugar), (Some(900.0), Some(65.0), Some(31.0)));
+
+        /// Deleting a dish unsets it in the meal and lowers its totals, like the foreign keys and the trigger do
+        DishRepository::delete(&*repository, DEFAULT_TENANT, 3).unwrap();
+        let meal: Meal = test::call_and_read_body_json(&app, test::TestRequest::get().uri("/meals/1").to_request()).await;
+        assert_eq!((meal.dessert, meal.cal, meal.sugar), (None, Some(600.0), Some(1.0)));
+
+        let put = test::TestRequest::put().uri("/meals/1").insert_header(("Content-Type", "application/json"))
+            .set_payload(r#"{"name": "dinner", "appetizer": 1, "main": 2, "dessert": 3}"#).to_request();
+        assert_eq!(test::call_and_read_body(&app, put).await, DISH_ID_NOT_FOUND);
+
+        assert_eq!(test::call_and_read_body(&app, test::TestRequest::delete().uri("/meals/dinner").to_request()).await, "1");
+        let res = test::call_service(&app, test::TestRequest::get().uri("/meals/1").to_request()).await;
+        assert_eq!(res.status(), 404);
+    }
+
+    #[actix_web::test]
+    async fn tenants_do_not_see_each_others_meals() {
+        let repository = Arc::new(InMemoryMealsRepository::new());
+        for tenant in ["team-a", "team-b"] {
+            for new_dish in [dish("soup", 100.0, 10.0, 1.0), dish("steak", 500.0, 50.0, 0.0), dish("cake", 300.0, 5.0, 30.0)] {
+                DishRepository::insert(&*repository, tenant, new_dish).unwrap();
+            }
+        }
+        let app = test::init_service(App::new()
+            .app_data(Data::new(AuthConfig::dev()))
+            .wrap(from_fn(authenticate))
+            .app_data(Data::from(repository.clone() as Arc<dyn MealRepository>))
+            .service(create_meal)
+            .service(get_meal)
+            .service(get_meal_by_name)
+            .service(delete_meal)).await;
+
+        /// Both tenants can use the same name, but only with their own dishes
+        let post = |tenant: &str, body: &'static str| test::TestRequest::post().uri("/meals")
+            .insert_header((TENANT_HEADER, tenant)).insert_header(("Content-Type", "application/json")).set_payload(body).to_request();
+        assert_eq!(test::call_and_read_body(&app, post("team-a", r#"{"name": "dinner", "appetizer": 1, "main": 2, "dessert": 3}"#)).await, "1");
+        assert_eq!(test::call_and_read_body(&app, post("team-b", r#"{"name": "dinner", "appetizer": 1, "main": 2, "dessert": 3}"#)).await, DISH_ID_NOT_FOUND);
+        assert_eq!(test::call_and_read_body(&app, post("team-b", r#"{"name": "dinner", "appetizer": 4, "main": 5, "dessert": 6}"#)).await, "2");
+
+        let get = |tenant: &str, uri: &str| test::TestRequest::get().uri(uri).insert_header((TENANT_HEADER, tenant)).to_request();
+        assert_eq!(test::call_service(&app, get("team-b", "/meals/1")).await.status(), 404);
+        let meal: Meal = test::call_and_read_body_json(&app, get("team-b", "/meals/dinner")).await;
+        assert_eq!(meal.ID, 2);
+        assert_eq!(test::call_service(&app, get(DEFAULT_TENANT, "/meals/dinner")).await.status(), 404);
+
+        let delete = test::TestRequest::delete().uri("/meals/1").insert_header((TENANT_HEADER, "team-b")).to_request();
+        assert_eq!(test::call_service(&app, delete).await.status(), 404);
+        assert_eq!(test::call_service(&app, get("team-a", "/meals/1")).await.status(), 200);
+    }
+}
@@ -1,9 +1,90 @@
 // Meals API module
+use actix_web::web::ServiceConfig;
+use utoipa::OpenApi;
+
+use crate::food_log::{delete_log_entry, get_day_log, get_log_summary, log_entry};
+use crate::health::healthz;
+use crate::metrics::metrics_route;
+use crate::openapi::swagger_ui;
+use crate::plans::{create_plan, delete_plan, generate_new_plan, get_all_plans, get_plan, remove_plan_slot, set_plan_slot, update_plan, validate_plan};
+use crate::users::{assign_diet, get_me, get_my_diets, register_user, remove_my_diet, update_me, update_my_diet};
+
 mod dishes_routes;
+mod index_route;
 mod meals_routes;
 mod models;
 mod ninjas_api;
 mod diet_client;
+mod openapi;
+mod recipe_routes;
+mod recipes;
+mod repository;
+mod suggest_routes;
+mod suggestions;
 
+pub use diet_client::{DietClient, DietClientConfig, DietClientError, LimitsError};
 pub use dishes_routes::*;
-pub use meals_routes::*;
\ No newline at end of file
+pub use index_route::{index, readyz};
+pub use meals_routes::*;
+pub use ninjas_api::{NinjasConfig, NutritionClient, NutritionInfo};
+pub use models::{Dish, Ingredient, Meal, NewDish, NewMeal, Nutrients, ReqDish, ReqIngredient, ReqMeal, ReqSuggest, ReqSuggestion, Suggestion, Unit};
+pub use openapi::MealsApiDoc;
+pub use recipe_routes::*;
+pub use recipes::{RecipeError, MAX_INGREDIENTS};
+pub use repository::{DishRepository, InMemoryMealsRepository, LinesError, MealRepository, DieselMealsRepository};
+pub use suggest_routes::*;
+pub use suggestions::suggest;
+
+/// Registers every route of the Meals Service, used by the `meals_api` binary and the integration tests
+/// The app needs the [crate::db::DbPool], the [DishRepository], the [MealRepository], the [crate::users::UserRepository],
+/// the [crate::food_log::FoodLogRepository], the [crate::plans::PlanRepository], the [DietClient],
+/// the [NutritionClient], the [crate::config::Config] and the [crate::auth::AuthConfig] as app data,
+/// and [crate::auth::authenticate] wrapped around it
+pub fn configure(cfg: &mut ServiceConfig) {
+    cfg.service(index)
+        .service(healthz)
+        .service(readyz)
+        .service(metrics_route)
+        .service(swagger_ui(MealsApiDoc::openapi()))
+        .service(collection_deletion)
+        .service(meals_collection_deletion)
+        .service(get_all_dishes)
+        .service(create_dish)
+        .service(get_dish)
+        .service(get_dish_ingredients)
+        .service(set_dish_ingredients)
+        .service(set_dish_ingredient)
+        .service(remove_dish_ingredient)
+        .service(get_dish_by_name)
+        .service(delete_dish)
+        .service(delete_dish_by_name)
+        .service(get_all_meals)
+        .service(create_meal)
+        .service(get_meal)
+        .service(get_meal_suggestions)
+        .service(save_meal_suggestion)
+        .service(get_meal_by_name)
+        .service(delete_meal)
+        .service(delete_meal_by_name)
+        .service(update_meal)
+        .service(register_user)
+        .service(get_me)
+        .service(update_me)
+        .service(get_my_diets)
+        .service(assign_diet)
+        .service(update_my_diet)
+        .service(remove_my_diet)
+        .service(log_entry)
+        .service(get_day_log)
+        .service(get_log_summary)
+        .service(delete_log_entry)
+        .service(get_all_plans)
+        .service(create_plan)
+        .service(generate_new_plan)
+        .service(get_plan)
+        .service(update_plan)
+        .service(delete_plan)
+        .service(set_plan_slot)
+        .service(remove_plan_slot)
+        .service(validate_plan);
+}
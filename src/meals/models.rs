@@ -1,14 +1,25 @@
 #![allow(unused_doc_comments)]
+#![allow(non_snake_case)]
 
+use crate::csv_export::CsvRecord;
+use crate::diets::NewDiet;
 use crate::schema::dishes;
 use crate::schema::meals;
 use diesel::prelude::*;
 use serde::{Serialize, Deserialize};
+use std::fmt;
+use std::iter::Sum;
+use std::ops::{Add, Mul};
+use std::str::FromStr;
+use utoipa::{IntoParams, ToSchema};
 
 /// Dish struct to represent a dish in the database
 /// ID should be snake case but the assignment wants UPPER CASE, sooooo...
-#[derive(Queryable, Serialize, Deserialize)]
+/// The tenant of the dish is not part of it, the repositories filter by it
+#[derive(Clone, Queryable, Selectable, Serialize, Deserialize, ToSchema)]
+#[diesel(table_name = dishes)]
 pub struct Dish {
+    #[diesel(column_name = id)]
     pub ID: i32,
     pub name: String,
     pub cal: f64,
@@ -17,8 +28,12 @@ pub struct Dish {
     pub size: f64,
 }
 
+impl CsvRecord for Dish {
+    const HEADER: &'static [&'static str] = &["ID", "name", "cal", "sodium", "sugar", "size"];
+}
+
 /// Struct to represent a new dish to be inserted into the database
-/// Translates to a Dish struct on insertion
+/// Translates to a Dish struct on insertion, together with the tenant of the request
 #[derive(Insertable, Deserialize)]
 #[diesel(table_name = dishes)]
 pub struct NewDish {
@@ -33,16 +48,101 @@ pub struct NewDish {
 /// Struct that represents a dish reqested by the user, either for creation or deletion
 /// The Values are optionals since a user might not include all of them and we need to be able to handle that
 /// Translates to a NewDish struct if all values are present
-#[derive(Deserialize)]
+/// With ingredients, the dish is a recipe and its nutrition is the sum of the ingredients instead of the result for its name
+#[derive(Serialize, Deserialize, ToSchema)]
 pub struct ReqDish {
     pub name: Option<String>,
+    pub ingredients: Option<Vec<ReqIngredient>>,
+}
+
+/// The units the quantity of an ingredient can be given in
+/// The volumes are converted like water, 1 ml weighs 1 g
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Unit {
+    G,
+    Kg,
+    Oz,
+    Lb,
+    Ml,
+    L,
+    /// The serving size the nutrition API answers with
+    Serving,
+}
+
+impl Unit {
+    pub const ALL: [Unit; 7] = [Unit::G, Unit::Kg, Unit::Oz, Unit::Lb, Unit::Ml, Unit::L, Unit::Serving];
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            Unit::G => "g",
+            Unit::Kg => "kg",
+            Unit::Oz => "oz",
+            Unit::Lb => "lb",
+            Unit::Ml => "ml",
+            Unit::L => "l",
+            Unit::Serving => "serving",
+        }
+    }
+
+    /// The grams of one unit, none for [Unit::Serving] whose weight depends on the ingredient
+    pub fn grams(&self) -> Option<f64> {
+        match self {
+            Unit::G | Unit::Ml => Some(1.0),
+            Unit::Kg | Unit::L => Some(1000.0),
+            Unit::Oz => Some(28.349523125),
+            Unit::Lb => Some(453.59237),
+            Unit::Serving => None,
+        }
+    }
+}
+
+impl FromStr for Unit {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Unit, String> {
+        Unit::ALL.into_iter().find(|unit| unit.as_str() == s).ok_or_else(|| format!("unknown unit `{}`", s))
+    }
+}
+
+impl fmt::Display for Unit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// An ingredient line of a recipe with the nutrition of its quantity
+/// The lines of a dish are numbered from 1 in the order they are listed
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct Ingredient {
+    /// The name the nutrition API is asked for
+    pub ingredient: String,
+    pub quantity: f64,
+    pub unit: Unit,
+    pub cal: f64,
+    pub sodium: f64,
+    pub sugar: f64,
+    /// The weight of the quantity in grams
+    pub size: f64,
+}
+
+/// Struct that represents an ingredient line requested by the user
+/// The Values are optionals since a user might not include all of them and we need to be able to handle that
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct ReqIngredient {
+    pub ingredient: Option<String>,
+    pub quantity: Option<f64>,
+    pub unit: Option<Unit>,
 }
 
 
 /// Meal struct to represent a meal in the database
 /// ID should be snake case but the assignment wants UPPER CASE, sooooo...
-#[derive(Queryable, Serialize, Deserialize)]
+/// The tenant of the meal is not part of it, the repositories filter by it
+#[derive(Clone, Queryable, Selectable, Serialize, Deserialize, ToSchema)]
+#[diesel(table_name = meals)]
 pub struct Meal {
+    #[diesel(column_name = id)]
     pub ID: i32,
     pub name: String,
     pub appetizer: Option<i32>,
@@ -53,10 +153,33 @@ pub struct Meal {
     pub sugar: Option<f64>,
 }
 
+/// Struct to represent a meal in a CSV export
+/// Contains the names of the dishes next to their IDs, so the export can be read without the dishes export
+#[derive(Serialize)]
+pub struct MealCsvRow {
+    pub ID: i32,
+    pub name: String,
+    pub appetizer: Option<i32>,
+    pub appetizer_name: Option<String>,
+    pub main: Option<i32>,
+    pub main_name: Option<String>,
+    pub dessert: Option<i32>,
+    pub dessert_name: Option<String>,
+    pub cal: Option<f64>,
+    pub sodium: Option<f64>,
+    pub sugar: Option<f64>,
+}
+
+impl CsvRecord for MealCsvRow {
+    const HEADER: &'static [&'static str] = &[
+        "ID", "name", "appetizer", "appetizer_name", "main", "main_name", "dessert", "dessert_name", "cal", "sodium", "sugar",
+    ];
+}
+
 /// Struct to represent a new meal to requested by the user
 /// The Values are optionals since a user might not include all of them and we need to be able to handle that
 /// Translates to a NewMeal struct if all values are present
-#[derive(Deserialize)]
+#[derive(Deserialize, ToSchema)]
 pub struct ReqMeal {
     pub name: Option<String>,
     pub appetizer: Option<i32>,
@@ -65,8 +188,8 @@ pub struct ReqMeal {
 }
 
 /// Struct to represent a new meal to be inserted into the database
-/// Translates to a Meal struct on insertion
-#[derive(Insertable, Deserialize)]
+/// Translates to a Meal struct on insertion, together with the tenant of the request
+#[derive(Insertable, Serialize, Deserialize)]
 #[diesel(table_name = meals)]
 pub struct NewMeal {
     pub name: String,
@@ -75,16 +198,119 @@ pub struct NewMeal {
     pub dessert: i32,
 }
 
-/// Struct that represents a meal reqested by the user
-#[derive(Deserialize)]
+/// Struct that represents the query parameters of a meals collection request
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct ReqDiet {
+    /// Only return the meals within the limits of the diet with this name
+    pub diet: Option<String>,
+    /// Set to `me` to only return the meals within the limits of all active diets of the caller
+    pub user: Option<String>,
+    /// Set to `csv` to get the meals as CSV
+    pub format: Option<String>,
+}
+
+/// Struct that represents the query parameters of a suggestion request
+#[derive(Serialize, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ReqSuggest {
+    /// Name of the diet the suggestions have to fit
     pub diet: Option<String>,
+    /// Number of suggestions, 10 if not given
+    pub limit: Option<usize>,
+}
+
+/// Struct that represents a suggestion to be stored as a meal
+/// `rank` is the position of the suggestion in `GET /meals/suggest?diet=`, starting at 1, the first if not given.
+/// The name defaults to the names of the dishes
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct ReqSuggestion {
+    pub diet: Option<String>,
+    pub rank: Option<usize>,
+    pub name: Option<String>,
 }
-/// Struct that represents a diet
-#[derive(Deserialize)]
-pub struct Diet {
+
+/// A combination of three dishes within the limits of a diet
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct Suggestion {
+    pub appetizer: i32,
+    pub main: i32,
+    pub dessert: i32,
+    /// The names of the dishes, e.g. `soup, steak and cake`
     pub name: String,
+    pub totals: Nutrients,
+    /// The unused shares of the limits summed up, from 0 if all limits are reached to 3
+    pub distance: f64,
+}
+
+/// Calories, sodium and sugar of something eaten, or the limits of a diet
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct Nutrients {
     pub cal: f64,
     pub sodium: f64,
     pub sugar: f64,
 }
+
+impl Nutrients {
+
+    /// The smaller value of each nutrient
+    pub fn min(&self, other: &Nutrients) -> Nutrients {
+        Nutrients { cal: self.cal.min(other.cal), sodium: self.sodium.min(other.sodium), sugar: self.sugar.min(other.sugar) }
+    }
+
+    /// The names of the nutrients above their limit, empty if all are within
+    pub fn exceeded(&self, limits: &Nutrients) -> Vec<String> {
+        [("cal", self.cal, limits.cal), ("sodium", self.sodium, limits.sodium), ("sugar", self.sugar, limits.sugar)]
+            .into_iter()
+            .filter(|(_, value, limit)| value > limit)
+            .map(|(name, _, _)| name.to_string())
+            .collect()
+    }
+}
+
+impl Add for Nutrients {
+    type Output = Nutrients;
+
+    fn add(self, other: Nutrients) -> Nutrients {
+        Nutrients { cal: self.cal + other.cal, sodium: self.sodium + other.sodium, sugar: self.sugar + other.sugar }
+    }
+}
+
+impl Mul<f64> for Nutrients {
+    type Output = Nutrients;
+
+    fn mul(self, factor: f64) -> Nutrients {
+        Nutrients { cal: self.cal * factor, sodium: self.sodium * factor, sugar: self.sugar * factor }
+    }
+}
+
+impl Sum for Nutrients {
+    fn sum<I: Iterator<Item = Nutrients>>(iter: I) -> Nutrients {
+        iter.fold(Nutrients::default(), Add::add)
+    }
+}
+
+impl From<&NewDiet> for Nutrients {
+    fn from(diet: &NewDiet) -> Nutrients {
+        Nutrients { cal: diet.cal, sodium: diet.sodium, sugar: diet.sugar }
+    }
+}
+
+impl From<&Ingredient> for Nutrients {
+    fn from(ingredient: &Ingredient) -> Nutrients {
+        Nutrients { cal: ingredient.cal, sodium: ingredient.sodium, sugar: ingredient.sugar }
+    }
+}
+
+impl From<&Dish> for Nutrients {
+    fn from(dish: &Dish) -> Nutrients {
+        Nutrients { cal: dish.cal, sodium: dish.sodium, sugar: dish.sugar }
+    }
+}
+
+/// The totals of a meal, a missing total counts as 0
+impl From<&Meal> for Nutrients {
+    fn from(meal: &Meal) -> Nutrients {
+        Nutrients { cal: meal.cal.unwrap_or(0.0), sodium: meal.sodium.unwrap_or(0.0), sugar: meal.sugar.unwrap_or(0.0) }
+    }
+}
@@ -1,19 +1,22 @@
 #![allow(unused_doc_comments)]
 
-use std::borrow::ToOwned;
-use std::env;
 use actix_web::http::StatusCode;
-use dotenv::dotenv;
-use lazy_static::lazy_static;
 use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tracing::{debug_span, error, Instrument};
+
+/// Crate imports
+use crate::metrics::metrics;
+use crate::telemetry::{RequestId, REQUEST_ID_HEADER};
 
 
 /// Struct to hold the API configuration
 ///
-/// The struct contains the base URL and the API key and is populated from the .env file
-struct APIConfig {
-    base_url: String,
-    api_key: String,
+/// The struct contains the base URL and the API key and is part of the [crate::config::Config]
+#[derive(Clone, Debug)]
+pub struct NinjasConfig {
+    pub base_url: String,
+    pub api_key: String,
 }
 
 /// Struct to hold the nutrition information returned by the API
@@ -73,63 +76,81 @@ impl Default for NutritionInfo {
     }
 }
 
-/// Create a static instance of the API configuration ard populate it from the .env file
-lazy_static! {
-    static ref API_CONFIG: APIConfig = {
-        dotenv().ok();
-        let base_url = env::var("NINJAS_API_BASE_URL").expect("NINJA_API_BASE_URL must be set").to_owned();
-        println!("{}", base_url);
-        let api_key = env::var("NINJAS_API_KEY").expect("NINJA_API_KEY must be set").to_owned();
-        APIConfig {
-            base_url,
-            api_key,
-        }
-    };
+/// How long a request to the Ninjas API may take before it is answered with a [StatusCode::GATEWAY_TIMEOUT]
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Client for the Ninjas API with connection reuse
+///
+/// The client is created once at startup and shared between the workers with `app_data`
+pub struct NutritionClient {
+    http: reqwest::Client,
+    config: NinjasConfig,
 }
 
-/// Function to get the nutrition information for a dish from the Ninjas API
-/// ## Arguments
-/// * `dish_name` - The name of the dish to get the nutrition information for
-/// * `serving_size` - The serving size of the dish
-/// ## Returns
-/// * `Result<Vec<NutritionInfo>, StatusCode>` - A vector of NutritionInfo structs or an error code
-pub async fn get_nutrition_info(dish_name: &str) -> Result<Vec<NutritionInfo>, StatusCode>{
-
-    /// Create the URL for the API call
-    let url = format!("{}{}", API_CONFIG.base_url, dish_name);
-    /// Create a new [reqwest::Client](https://docs.rs/reqwest/0.11.3/reqwest/struct.Client.html)
-    let client = reqwest::Client::new();
-    /// Send the request to the API
-    let response = client.get(&url)
-        .header("X-Api-Key", API_CONFIG.api_key.clone())
-        .send()
-        .await
-        .expect("Failed to send request");
-
-    /// Check if the response status is 200
-    ///
-    /// If not, return the error code
-    if response.status() != 200 {
-        return Err(response.status());
+impl NutritionClient {
+
+    /// Creates a [NutritionClient] from its [NinjasConfig]
+    pub fn new(config: NinjasConfig) -> Result<NutritionClient, reqwest::Error> {
+        Ok(NutritionClient {
+            http: reqwest::Client::builder().timeout(REQUEST_TIMEOUT).build()?,
+            config,
+        })
+    }
+
+    /// Function to get the nutrition information for a dish from the Ninjas API
+    /// ## Arguments
+    /// * `dish_name` - The name of the dish to get the nutrition information for
+    /// * `request_id` - The [RequestId] of the incoming request, forwarded to the Ninjas API
+    /// ## Returns
+    /// * `Result<Vec<NutritionInfo>, StatusCode>` - A vector of NutritionInfo structs or an error code
+    pub async fn get_nutrition_info(&self, dish_name: &str, request_id: &RequestId) -> Result<Vec<NutritionInfo>, StatusCode> {
+        let span = debug_span!("nutrition_provider.get_nutrition", dish = dish_name);
+        let result = self.fetch(dish_name, request_id).instrument(span).await;
+        let outcome = match &result {
+            Ok(nutrition_info) if nutrition_info.is_empty() => "empty",
+            Ok(_) => "ok",
+            Err(_) => "error",
+        };
+        metrics().nutrition_provider_calls.with_label_values(&[outcome]).inc();
+        result
     }
 
-    /// Check if the response is empty
-    ///
-    /// If so, return an empty vector
-    let body = match response.text().await {
-        Ok(body) => body,
-        Err(e) => panic!("Error: {}", e),
-    };
-
-    /// Deserialize the JSON response into a vector of NutritionInfo structs
-    ///
-    /// If there is an error, panic
-    let nutrition_info: Vec<NutritionInfo> = match serde_json::from_str(&body) {
-        Ok(nutrition_info) => nutrition_info,
-        Err(e) => panic!("Error: {}", e),
-    };
-
-    /// Return the vector of NutritionInfo structs
-    Ok(nutrition_info)
-
-}
\ No newline at end of file
+    /// Sends the request for a dish to the Ninjas API
+    async fn fetch(&self, dish_name: &str, request_id: &RequestId) -> Result<Vec<NutritionInfo>, StatusCode> {
+
+        /// Create the URL for the API call
+        let url = format!("{}{}", self.config.base_url, dish_name);
+        /// Send the request to the API
+        ///
+        /// If the API is not reachable or does not answer within the [REQUEST_TIMEOUT], return a [StatusCode::GATEWAY_TIMEOUT]
+        let response = match self.http.get(&url)
+            .header("X-Api-Key", &self.config.api_key)
+            .header(REQUEST_ID_HEADER, request_id.as_str())
+            .send()
+            .await {
+            Ok(response) => response,
+            Err(e) => {
+                error!(error = %e, "Ninjas API not reachable");
+                return Err(StatusCode::GATEWAY_TIMEOUT);
+            }
+        };
+
+        /// Check if the response status is 200
+        ///
+        /// If not, return the error code
+        if response.status() != 200 {
+            return Err(response.status());
+        }
+
+        /// Deserialize the JSON response into a vector of NutritionInfo structs
+        ///
+        /// If the body can not be read or parsed, return a [StatusCode::BAD_GATEWAY]
+        match response.json::<Vec<NutritionInfo>>().await {
+            Ok(nutrition_info) => Ok(nutrition_info),
+            Err(e) => {
+                error!(error = %e, "Unexpected answer of the Ninjas API");
+                Err(StatusCode::BAD_GATEWAY)
+            }
+        }
+    }
+}
@@ -0,0 +1,87 @@
+#![allow(unused_doc_comments)]
+
+/// Misc imports
+use utoipa::OpenApi;
+
+/// Crate imports
+use crate::health::{Check, Readiness};
+
+/// Module imports
+use super::dishes_routes::*;
+use super::meals_routes::*;
+use super::recipe_routes::*;
+use super::suggest_routes::*;
+use super::models::{Dish, Ingredient, Meal, Nutrients, ReqDish, ReqIngredient, ReqMeal, ReqSuggestion, Suggestion, Unit};
+use crate::food_log::*;
+use crate::plans::*;
+use crate::users::*;
+
+/// OpenAPI document of the Meals Service
+/// Every route handler of the Service has to be listed in `paths`, this is checked by the tests below
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "Meals API", description = "Dishes with their nutrition values and meals composed of them"),
+    paths(
+        super::index_route::index, super::index_route::readyz, crate::health::healthz, crate::metrics::metrics_route,
+        get_all_dishes, create_dish, collection_deletion, get_dish, delete_dish, get_dish_by_name, delete_dish_by_name,
+        get_dish_ingredients, set_dish_ingredients, set_dish_ingredient, remove_dish_ingredient,
+        get_all_meals, create_meal, meals_collection_deletion, get_meal, delete_meal, update_meal, get_meal_by_name, delete_meal_by_name,
+        get_meal_suggestions, save_meal_suggestion,
+        register_user, get_me, update_me, get_my_diets, assign_diet, update_my_diet, remove_my_diet,
+        log_entry, get_day_log, get_log_summary, delete_log_entry,
+        get_all_plans, create_plan, generate_new_plan, get_plan, update_plan, delete_plan, set_plan_slot, remove_plan_slot, validate_plan,
+    ),
+    components(schemas(
+        Dish, ReqDish, Ingredient, ReqIngredient, Unit, Meal, ReqMeal, Suggestion, ReqSuggestion, User, Profile, ReqProfile, Sex, ActivityLevel, UserDiet, ReqUserDiet,
+        Nutrients, LogEntry, ReqLogEntry, DayLog, DaySummary, WeekSummary, LogSummary,
+        Plan, PlanEntry, Slot, ReqPlan, ReqPlanSlot, ReqPlanGeneration, PlanView, PlanValidation, Readiness, Check,
+    )),
+    tags(
+        (name = "dishes", description = "Dishes and their nutrition values as returned by the nutrition API, or summed up from their ingredients"),
+        (name = "meals", description = "Meals composed of an appetizer, a main and a dessert"),
+        (name = "users", description = "Registered users, their profiles and the diets they follow"),
+        (name = "log", description = "What the users ate, per day and summed up over date ranges"),
+        (name = "plans", description = "Meals planned for the slots of consecutive days, with their totals"),
+        (name = "health", description = "Liveness and readiness of the Service and its dependencies"),
+    ),
+)]
+pub struct MealsApiDoc;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::openapi::{route_sources, undocumented_routes};
+
+    #[test]
+    fn every_route_is_documented() {
+        let sources = route_sources(&["meals", "users", "food_log", "plans"]);
+        let missing = undocumented_routes(&sources, &MealsApiDoc::openapi());
+        assert!(missing.is_empty(), "Routes without an OpenAPI description: {:?}", missing);
+    }
+
+    #[test]
+    fn json_bodies_are_offered_in_all_formats() {
+        let doc = crate::openapi::with_negotiated_formats(MealsApiDoc::openapi());
+        let dish = doc.paths.paths["/dishes/{id}"].get.as_ref().unwrap();
+        let ok = match &dish.responses.responses["200"] {
+            utoipa::openapi::RefOr::T(response) => response,
+            _ => panic!("Expected an inline response"),
+        };
+        for format in ["application/json", "application/msgpack", "application/cbor"] {
+            assert!(ok.content.contains_key(format), "Missing {}", format);
+        }
+    }
+
+    #[test]
+    fn protected_routes_declare_their_role() {
+        let doc = crate::openapi::with_security(MealsApiDoc::openapi());
+        let post_dishes = doc.paths.paths["/dishes"].post.as_ref().unwrap();
+        let security = serde_json::to_value(&post_dishes.security).unwrap();
+        assert_eq!(security, serde_json::json!([{"api_key": ["editor"]}, {"bearer": ["editor"]}]));
+        assert!(post_dishes.responses.responses.contains_key("401") && post_dishes.responses.responses.contains_key("403"));
+
+        /// The health routes stay open for the probes
+        let healthz = doc.paths.paths["/healthz"].get.as_ref().unwrap();
+        assert!(healthz.security.is_none() && !healthz.responses.responses.contains_key("401"));
+    }
+}
@@ -0,0 +1,332 @@
+#![allow(unused_doc_comments)]
+
+/// Actix Imports
+use actix_web::{get, put, delete, HttpResponse, Responder, HttpRequest, web};
+use actix_web::web::Data;
+
+/// Misc Imports
+use tracing::warn;
+
+/// Module Imports
+use super::models::{Dish, Ingredient, ReqIngredient};
+use super::ninjas_api::NutritionClient;
+use super::recipes::{check_lines, resolve, resolve_all, RecipeError};
+use super::repository::{DishRepository, LinesError};
+
+/// Crate Imports
+use crate::auth::{Editor, Reader, Require};
+use crate::db::{run_blocking, DbError};
+use crate::negotiation::{decode_body, negotiate, request_format};
+use crate::telemetry::RequestId;
+
+/// Error codes as defined in the Assigment
+const NOT_JSON: &str = "0";
+const NOT_FOUND: &str = "-5";
+
+/// Why the ingredient lines of a request were not stored
+enum IngredientsError {
+    /// The Content-Type is not JSON, MessagePack or CBOR
+    Unsupported,
+    /// The body can not be decoded
+    Invalid,
+    /// The dish or the line does not exist
+    NotFound,
+    Recipe(RecipeError),
+    Db(DbError),
+}
+
+impl IngredientsError {
+
+    /// Returns the [HttpResponse] with the error code 0 or -5, or the response of the recipe or database error
+    fn response(self) -> HttpResponse {
+        match self {
+            IngredientsError::Unsupported => HttpResponse::UnsupportedMediaType().body(NOT_JSON),
+            IngredientsError::Invalid => HttpResponse::UnprocessableEntity().body(NOT_JSON),
+            IngredientsError::NotFound | IngredientsError::Db(DbError::NotFound) => HttpResponse::NotFound().body(NOT_FOUND),
+            IngredientsError::Recipe(e) => e.response(),
+            IngredientsError::Db(e) => e.response(),
+        }
+    }
+}
+
+/// Decodes the body of a request as JSON, MessagePack or CBOR
+fn decode<T: serde::de::DeserializeOwned>(req: &HttpRequest, body: &web::Bytes) -> Result<T, IngredientsError> {
+    let body_format = request_format(req).ok_or(IngredientsError::Unsupported)?;
+    decode_body(body_format, body).map_err(|e| {
+        warn!("{}", e);
+        IngredientsError::Invalid
+    })
+}
+
+/// Returns the ingredient lines of a dish of the tenant
+async fn load(dishes: &Data<dyn DishRepository>, tenant: &str, id: i32) -> Result<Vec<Ingredient>, IngredientsError> {
+    let (dishes, tenant) = (dishes.clone(), tenant.to_string());
+    run_blocking(move || dishes.ingredients(&tenant, id)).await.map_err(IngredientsError::Db)
+}
+
+/// Stores the ingredient lines of a dish of the tenant and returns the dish with its new nutrition
+async fn store(dishes: Data<dyn DishRepository>, tenant: String, id: i32, ingredients: Vec<Ingredient>) -> Result<Dish, IngredientsError> {
+    run_blocking(move || dishes.set_ingredients(&tenant, id, ingredients)).await.map_err(IngredientsError::Db)
+}
+
+/// Edits the ingredient lines of a dish of the tenant in one transaction and returns the dish with its new nutrition
+async fn edit<F>(dishes: Data<dyn DishRepository>, tenant: String, id: i32, mut edit: F) -> Result<Dish, IngredientsError>
+where
+    F: FnMut(&mut Vec<Ingredient>) -> Result<(), LinesError> + Send + 'static,
+{
+    let result = run_blocking(move || Ok(dishes.edit_ingredients(&tenant, id, &mut edit))).await.map_err(IngredientsError::Db)?;
+    result.map_err(|e| match e {
+        LinesError::Recipe(e) => IngredientsError::Recipe(e),
+        LinesError::Db(e) => IngredientsError::Db(e),
+    })
+}
+
+/*
+=============================== GET /dishes/{id}/ingredients ===============================
+ */
+/// # Creates the route for getting the ingredient lines of a dish in "/dishes/{id}/ingredients"
+/// ## Arguments
+/// * `auth` - Requires the [Reader] role, only the data of its tenant is used
+/// * `dishes` - The [DishRepository] storing the dishes
+/// * `req` - The [HttpRequest], used to pick the response format
+/// * `path` - The ID of the dish
+/// ## Returns
+/// * [HttpResponse::Ok] with a JSON body containing the lines in order, empty if the dish is not a recipe
+#[utoipa::path(
+    get,
+    path = "/dishes/{id}/ingredients",
+    tag = "dishes",
+    security(("api_key" = ["reader"]), ("bearer" = ["reader"])),
+    params(("id" = i32, Path, description = "ID of the dish")),
+    responses(
+        (status = 200, description = "The ingredient lines with the nutrition of their quantity", body = Vec<Ingredient>),
+        (status = 404, description = "Dish not found, error code -5", body = String),
+        (status = 503, description = "Database unavailable, error code -8"),
+    ),
+)]
+#[get("/dishes/{id:\\d+}/ingredients")]
+pub async fn get_dish_ingredients(auth: Require<Reader>, dishes: Data<dyn DishRepository>, req: HttpRequest, path: web::Path<i32>) -> impl Responder {
+    let tenant = auth.principal.tenant;
+    match load(&dishes, &tenant, path.into_inner()).await {
+        Ok(ingredients) => negotiate(&req, HttpResponse::Ok(), &ingredients),
+        Err(e) => e.response(),
+    }
+}
+
+/*
+=============================== PUT /dishes/{id}/ingredients ===============================
+ */
+/// # Creates the route for replacing the ingredient lines of a dish in "/dishes/{id}/ingredients"
+/// Every line is resolved through the nutrition API, the dish and the meals containing it are recomputed.
+/// A dish created from its name becomes a recipe
+/// ## Arguments
+/// * `auth` - Requires the [Editor] role, only the data of its tenant is used
+/// * `dishes` - The [DishRepository] storing the dishes
+/// * `nutrition_client` - A [web::Data<NutritionClient>] used to look up the ingredients in the Ninjas API
+/// * `request_id` - The [RequestId] of the request, forwarded to the Ninjas API
+/// * `req` - A [HttpRequest] containing the request
+/// * `path` - The ID of the dish
+/// * `body` - A [web::Bytes] containing a list of [ReqIngredient] as JSON, MessagePack or CBOR
+/// ## Returns
+/// * [HttpResponse::Ok] with a JSON body containing the dish with its new nutrition
+#[utoipa::path(
+    put,
+    path = "/dishes/{id}/ingredients",
+    tag = "dishes",
+    security(("api_key" = ["editor"]), ("bearer" = ["editor"])),
+    params(("id" = i32, Path, description = "ID of the dish")),
+    request_body = Vec<ReqIngredient>,
+    responses(
+        (status = 200, description = "The dish with its new nutrition", body = Dish),
+        (status = 404, description = "Dish not found, error code -5", body = String),
+        (status = 415, description = "Content type not supported, error code 0", body = String),
+        (status = 422, description = "Body not parsable (0), a value of a line missing (-1), or a quantity, the number of lines or an ingredient not recognized (-3)", body = String),
+        (status = 503, description = "Database unavailable, error code -8"),
+        (status = 504, description = "Nutrition API not reachable, error code -4", body = String),
+    ),
+)]
+#[put("/dishes/{id:\\d+}/ingredients")]
+#[allow(clippy::too_many_arguments)]
+pub async fn set_dish_ingredients(auth: Require<Editor>, dishes: Data<dyn DishRepository>, nutrition_client: Data<NutritionClient>, request_id: RequestId, req: HttpRequest, path: web::Path<i32>, body: web::Bytes) -> impl Responder {
+    let tenant = auth.principal.tenant;
+    let id = path.into_inner();
+    let result = async {
+        let lines: Vec<ReqIngredient> = decode(&req, &body)?;
+
+        /// Check that the dish exists before asking the nutrition API
+        load(&dishes, &tenant, id).await?;
+        let ingredients = resolve_all(&nutrition_client, lines, &request_id).await.map_err(IngredientsError::Recipe)?;
+        store(dishes, tenant, id, ingredients).await
+    }.await;
+    match result {
+        Ok(dish) => negotiate(&req, HttpResponse::Ok(), &dish),
+        Err(e) => e.response(),
+    }
+}
+
+/*
+=============================== PUT /dishes/{id}/ingredients/{line} ===============================
+ */
+/// # Creates the route for editing an ingredient line of a dish in "/dishes/{id}/ingredients/{line}"
+/// Replaces the line, or adds it if it is the one after the last. Only this line is resolved through the nutrition API,
+/// the dish and the meals containing it are recomputed
+/// ## Arguments
+/// * `auth` - Requires the [Editor] role, only the data of its tenant is used
+/// * `dishes` - The [DishRepository] storing the dishes
+/// * `nutrition_client` - A [web::Data<NutritionClient>] used to look up the ingredient in the Ninjas API
+/// * `request_id` - The [RequestId] of the request, forwarded to the Ninjas API
+/// * `req` - A [HttpRequest] containing the request
+/// * `path` - The ID of the dish and the number of the line, starting at 1
+/// * `body` - A [web::Bytes] containing the [ReqIngredient] as JSON, MessagePack or CBOR
+/// ## Returns
+/// * [HttpResponse::Ok] with a JSON body containing the dish with its new nutrition
+#[utoipa::path(
+    put,
+    path = "/dishes/{id}/ingredients/{line}",
+    tag = "dishes",
+    security(("api_key" = ["editor"]), ("bearer" = ["editor"])),
+    params(
+        ("id" = i32, Path, description = "ID of the dish"),
+        ("line" = usize, Path, description = "Number of the line, starting at 1, or the one after the last to add a line"),
+    ),
+    request_body = ReqIngredient,
+    responses(
+        (status = 200, description = "The dish with its new nutrition", body = Dish),
+        (status = 404, description = "Dish or line not found, error code -5", body = String),
+        (status = 415, description = "Content type not supported, error code 0", body = String),
+        (status = 422, description = "Body not parsable (0), a value missing (-1), or the quantity, the number of lines or the ingredient not recognized (-3)", body = String),
+        (status = 503, description = "Database unavailable, error code -8"),
+        (status = 504, description = "Nutrition API not reachable, error code -4", body = String),
+    ),
+)]
+#[put("/dishes/{id:\\d+}/ingredients/{line:\\d+}")]
+#[allow(clippy::too_many_arguments)]
+pub async fn set_dish_ingredient(auth: Require<Editor>, dishes: Data<dyn DishRepository>, nutrition_client: Data<NutritionClient>, request_id: RequestId, req: HttpRequest, path: web::Path<(i32, usize)>, body: web::Bytes) -> impl Responder {
+    let tenant = auth.principal.tenant;
+    let (id, line) = path.into_inner();
+    let result = async {
+        let req_ingredient: ReqIngredient = decode(&req, &body)?;
+
+        /// Check that the dish and the line exist before asking the nutrition API
+        let lines = load(&dishes, &tenant, id).await?.len();
+        if line == 0 || line > lines + 1 {
+            return Err(IngredientsError::NotFound)
+        }
+        let ingredient = resolve(&nutrition_client, req_ingredient, &request_id).await.map_err(IngredientsError::Recipe)?;
+
+        /// The lines may have changed while the nutrition API answered, so the line is checked again in the transaction
+        edit(dishes, tenant, id, move |ingredients| {
+            match line {
+                line if line > 0 && line <= ingredients.len() => ingredients[line - 1] = ingredient.clone(),
+                line if line == ingredients.len() + 1 => ingredients.push(ingredient.clone()),
+                _ => return Err(LinesError::Db(DbError::NotFound)),
+            }
+            check_lines(ingredients.len()).map_err(LinesError::Recipe)
+        }).await
+    }.await;
+    match result {
+        Ok(dish) => negotiate(&req, HttpResponse::Ok(), &dish),
+        Err(e) => e.response(),
+    }
+}
+
+/*
+=============================== DELETE /dishes/{id}/ingredients/{line} ===============================
+ */
+/// # Creates the route for removing an ingredient line of a dish in "/dishes/{id}/ingredients/{line}"
+/// The lines after it move up, the dish and the meals containing it are recomputed. The last line can not be removed
+/// ## Arguments
+/// * `auth` - Requires the [Editor] role, only the data of its tenant is used
+/// * `dishes` - The [DishRepository] storing the dishes
+/// * `req` - The [HttpRequest], used to pick the response format
+/// * `path` - The ID of the dish and the number of the line, starting at 1
+/// ## Returns
+/// * [HttpResponse::Ok] with a JSON body containing the dish with its new nutrition
+#[utoipa::path(
+    delete,
+    path = "/dishes/{id}/ingredients/{line}",
+    tag = "dishes",
+    security(("api_key" = ["editor"]), ("bearer" = ["editor"])),
+    params(
+        ("id" = i32, Path, description = "ID of the dish"),
+        ("line" = usize, Path, description = "Number of the line, starting at 1"),
+    ),
+    responses(
+        (status = 200, description = "The dish with its new nutrition", body = Dish),
+        (status = 404, description = "Dish or line not found, error code -5", body = String),
+        (status = 422, description = "The line is the only one of the recipe, error code -3", body = String),
+        (status = 503, description = "Database unavailable, error code -8"),
+    ),
+)]
+#[delete("/dishes/{id:\\d+}/ingredients/{line:\\d+}")]
+pub async fn remove_dish_ingredient(auth: Require<Editor>, dishes: Data<dyn DishRepository>, req: HttpRequest, path: web::Path<(i32, usize)>) -> impl Responder {
+    let tenant = auth.principal.tenant;
+    let (id, line) = path.into_inner();
+    let result = async {
+        edit(dishes, tenant, id, move |ingredients| {
+            if line == 0 || line > ingredients.len() {
+                return Err(LinesError::Db(DbError::NotFound))
+            }
+            ingredients.remove(line - 1);
+            check_lines(ingredients.len()).map_err(LinesError::Recipe)
+        }).await
+    }.await;
+    match result {
+        Ok(dish) => negotiate(&req, HttpResponse::Ok(), &dish),
+        Err(e) => e.response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{test, App};
+    use actix_web::middleware::from_fn;
+    use crate::auth::{authenticate, AuthConfig, DEFAULT_TENANT};
+    use crate::meals::{InMemoryMealsRepository, NinjasConfig, Unit};
+    use std::sync::Arc;
+
+    #[actix_web::test]
+    async fn lines_are_checked_before_the_nutrition_api() {
+        let dishes = Arc::new(InMemoryMealsRepository::new());
+        let line = |ingredient: &str| Ingredient { ingredient: ingredient.to_string(), quantity: 1.0, unit: Unit::Serving, cal: 10.0, sodium: 1.0, sugar: 1.0, size: 100.0 };
+        let bowl = dishes.insert_recipe(DEFAULT_TENANT, "bowl".to_string(), vec![line("rice")]).unwrap();
+
+        /// Nothing here reaches the Ninjas API
+        let nutrition_client = NutritionClient::new(NinjasConfig { base_url: "http://127.0.0.1:9/".to_string(), api_key: String::new() }).unwrap();
+        let app = test::init_service(App::new()
+            .app_data(Data::new(AuthConfig::dev()))
+            .wrap(from_fn(authenticate))
+            .app_data(Data::from(dishes as Arc<dyn DishRepository>))
+            .app_data(Data::new(nutrition_client))
+            .service(get_dish_ingredients)
+            .service(set_dish_ingredients)
+            .service(set_dish_ingredient)
+            .service(remove_dish_ingredient)).await;
+
+        let ingredients: Vec<Ingredient> = test::call_and_read_body_json(&app, test::TestRequest::get().uri(&format!("/dishes/{}/ingredients", bowl.ID)).to_request()).await;
+        assert_eq!(ingredients, [line("rice")]);
+
+        let put = |uri: String, body: &'static str| test::TestRequest::put().uri(&uri).insert_header(("Content-Type", "application/json")).set_payload(body).to_request();
+        let uri = format!("/dishes/{}/ingredients", bowl.ID);
+        for (req, status, code) in [
+            (test::TestRequest::get().uri("/dishes/99/ingredients").to_request(), 404, NOT_FOUND),
+            (put(format!("{}/3", uri), r#"{"ingredient": "egg", "quantity": 1, "unit": "serving"}"#), 404, NOT_FOUND),
+            (put(format!("{}/1", uri), r#"{"ingredient": "egg", "unit": "serving"}"#), 422, "-1"),
+            (put(format!("{}/1", uri), r#"{"ingredient": "egg", "quantity": 1, "unit": "cup"}"#), 422, NOT_JSON),
+            (put(uri.clone(), "[]"), 422, "-3"),
+            (test::TestRequest::delete().uri(&format!("{}/2", uri)).to_request(), 404, NOT_FOUND),
+            (test::TestRequest::delete().uri(&format!("{}/1", uri)).to_request(), 422, "-3"),
+        ] {
+            let res = test::call_service(&app, req).await;
+            assert_eq!(res.status(), status);
+            let body = test::read_body(res).await;
+            let body = String::from_utf8_lossy(&body);
+            assert!(body == code || body.contains(&format!(r#""error_code":"{}""#, code)), "{}", body);
+        }
+
+        /// An unreachable nutrition API is reported like for `POST /dishes`
+        let res = test::call_service(&app, put(format!("{}/2", uri), r#"{"ingredient": "egg", "quantity": 0.5, "unit": "kg"}"#)).await;
+        assert_eq!(res.status(), 504);
+    }
+}
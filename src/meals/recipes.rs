@@ -0,0 +1,172 @@
+#![allow(unused_doc_comments)]
+//! This file contains the resolution of the ingredient lines of recipes through the nutrition API
+//!
+//! Every line is looked up by the name of its ingredient alone, and the answer is scaled from its serving size to
+//! the quantity of the line, e.g. `250 g` of an ingredient answered per 100 g counts 2.5 times. A quantity in
+//! servings counts the answer as often as given. The nutrition of a recipe dish is the sum of its lines
+
+/// Actix imports
+use actix_web::HttpResponse;
+
+/// Misc imports
+use serde_json::json;
+use tracing::error;
+
+/// Module imports
+use super::models::{Ingredient, NewDish, Nutrients, ReqIngredient, Unit};
+use super::ninjas_api::{NutritionClient, NutritionInfo};
+
+/// Crate imports
+use crate::telemetry::RequestId;
+
+/// Most ingredient lines of a recipe
+pub const MAX_INGREDIENTS: usize = 50;
+
+/// Error codes as defined in the Assignment
+const PARAM_NOT_FOUND: &str = "-1";
+const INVALID_INGREDIENT: &str = "-3";
+const NINJAS_UNAVAILABLE: &str = "-4";
+
+/// Why an ingredient line could not be resolved
+#[derive(Debug, PartialEq)]
+pub enum RecipeError {
+    /// The ingredient, the quantity or the unit is missing
+    Missing,
+    /// The quantity or the number of lines is not allowed, or the nutrition API does not know the ingredient
+    Invalid(String),
+    /// The nutrition API did not answer
+    Unavailable,
+}
+
+impl RecipeError {
+
+    /// Returns the [HttpResponse] with the error code -1, -3 with a message, or -4
+    pub fn response(&self) -> HttpResponse {
+        match self {
+            RecipeError::Missing => HttpResponse::UnprocessableEntity().body(PARAM_NOT_FOUND),
+            RecipeError::Invalid(message) => HttpResponse::UnprocessableEntity().json(json!({
+                "message": message,
+                "error_code": INVALID_INGREDIENT,
+            })),
+            RecipeError::Unavailable => HttpResponse::GatewayTimeout().body(NINJAS_UNAVAILABLE),
+        }
+    }
+}
+
+/// Checks the number of lines of a recipe
+pub fn check_lines(lines: usize) -> Result<(), RecipeError> {
+    match lines {
+        0 => Err(RecipeError::Invalid("a recipe needs at least one ingredient".to_string())),
+        lines if lines > MAX_INGREDIENTS => Err(RecipeError::Invalid(format!("a recipe has at most {} ingredients", MAX_INGREDIENTS))),
+        _ => Ok(()),
+    }
+}
+
+/// Scales the answer of the nutrition API for an ingredient to a quantity
+/// ## Returns
+/// * The [Ingredient] with the nutrition of the quantity
+/// * [RecipeError::Invalid] if the ingredient was not recognized, or has no serving size to scale a weight from
+pub fn scale(ingredient: String, quantity: f64, unit: Unit, nutrition_info: &[NutritionInfo]) -> Result<Ingredient, RecipeError> {
+    if nutrition_info.is_empty() {
+        return Err(RecipeError::Invalid(format!("the ingredient `{}` is not recognized", ingredient)))
+    }
+    let serving: f64 = nutrition_info.iter().map(|info| info.serving_size_g).sum();
+    let servings = match unit.grams() {
+        None => quantity,
+        Some(_) if serving <= 0.0 => return Err(RecipeError::Invalid(format!("the ingredient `{}` has no serving size to scale", ingredient))),
+        Some(grams) => quantity * grams / serving,
+    };
+    let per_serving = nutrition_info.iter()
+        .map(|info| Nutrients { cal: info.calories, sodium: info.sodium_mg, sugar: info.sugar_g })
+        .sum::<Nutrients>() * servings;
+    Ok(Ingredient {
+        ingredient,
+        quantity,
+        unit,
+        cal: per_serving.cal,
+        sodium: per_serving.sodium,
+        sugar: per_serving.sugar,
+        size: serving * servings,
+    })
+}
+
+/// # Resolves an ingredient line through the nutrition API
+/// ## Arguments
+/// * `nutrition_client` - The [NutritionClient] looking up the ingredient
+/// * `line` - The requested line
+/// * `request_id` - The [RequestId] of the request, forwarded to the nutrition API
+/// ## Returns
+/// * The [Ingredient] with the nutrition of its quantity, or the [RecipeError]
+pub async fn resolve(nutrition_client: &NutritionClient, line: ReqIngredient, request_id: &RequestId) -> Result<Ingredient, RecipeError> {
+    let (ingredient, quantity, unit) = match (line.ingredient, line.quantity, line.unit) {
+        (Some(ingredient), Some(quantity), Some(unit)) if !ingredient.trim().is_empty() => (ingredient, quantity, unit),
+        _ => return Err(RecipeError::Missing),
+    };
+    if !quantity.is_finite() || quantity <= 0.0 {
+        return Err(RecipeError::Invalid(format!("the quantity of `{}` must be positive", ingredient)))
+    }
+    let nutrition_info = nutrition_client.get_nutrition_info(&ingredient, request_id).await.map_err(|e| {
+        error!("{}", e);
+        RecipeError::Unavailable
+    })?;
+    scale(ingredient, quantity, unit, &nutrition_info)
+}
+
+/// Resolves all lines of a recipe, in order, and stops at the first one that fails
+pub async fn resolve_all(nutrition_client: &NutritionClient, lines: Vec<ReqIngredient>, request_id: &RequestId) -> Result<Vec<Ingredient>, RecipeError> {
+    check_lines(lines.len())?;
+    let mut ingredients = Vec::with_capacity(lines.len());
+    for line in lines {
+        ingredients.push(resolve(nutrition_client, line, request_id).await?);
+    }
+    Ok(ingredients)
+}
+
+/// The dish of a recipe, its nutrition and size are the sums of the lines
+pub fn recipe_dish(name: String, ingredients: &[Ingredient]) -> NewDish {
+    let totals: Nutrients = ingredients.iter().map(Nutrients::from).sum();
+    NewDish {
+        name,
+        cal: totals.cal,
+        sodium: totals.sodium,
+        sugar: totals.sugar,
+        size: ingredients.iter().map(|ingredient| ingredient.size).sum(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn info(name: &str, calories: f64, sodium_mg: f64, sugar_g: f64, serving_size_g: f64) -> NutritionInfo {
+        NutritionInfo { name: name.to_string(), calories, sodium_mg, sugar_g, serving_size_g, ..NutritionInfo::default() }
+    }
+
+    #[test]
+    fn lines_are_scaled_from_the_serving_size() {
+        let rice = [info("rice", 127.4, 1.0, 0.1, 100.0)];
+        let line = scale("rice".to_string(), 250.0, Unit::G, &rice).unwrap();
+        assert_eq!((line.cal, line.sodium, line.size), (318.5, 2.5, 250.0));
+        assert_eq!(scale("rice".to_string(), 0.5, Unit::Kg, &rice).unwrap().cal, 637.0);
+        assert_eq!(scale("rice".to_string(), 2.0, Unit::Serving, &rice).unwrap().size, 200.0);
+
+        /// An answer with several items counts all of them, per their combined serving
+        let rice_and_beans = [info("rice", 127.4, 1.0, 0.1, 100.0), info("beans", 91.1, 239.0, 0.3, 100.0)];
+        let line = scale("rice and beans".to_string(), 100.0, Unit::G, &rice_and_beans).unwrap();
+        assert_eq!((line.cal, line.sodium, line.size), (109.25, 120.0, 100.0));
+
+        assert!(matches!(scale("air".to_string(), 1.0, Unit::G, &[]), Err(RecipeError::Invalid(_))));
+        assert!(matches!(scale("rice".to_string(), 1.0, Unit::Oz, &[info("rice", 1.0, 1.0, 1.0, 0.0)]), Err(RecipeError::Invalid(_))));
+    }
+
+    #[test]
+    fn recipes_sum_up_their_lines() {
+        let lines = [
+            scale("rice".to_string(), 200.0, Unit::G, &[info("rice", 127.4, 1.0, 0.1, 100.0)]).unwrap(),
+            scale("orange".to_string(), 1.0, Unit::Serving, &[info("orange", 53.2, 1.0, 12.3, 100.0)]).unwrap(),
+        ];
+        let dish = recipe_dish("bowl".to_string(), &lines);
+        assert_eq!((dish.cal, dish.sodium, dish.sugar, dish.size), (308.0, 3.0, 12.5, 300.0));
+        assert!(check_lines(0).is_err() && check_lines(MAX_INGREDIENTS + 1).is_err() && check_lines(1).is_ok());
+    }
+}
@@ -0,0 +1,834 @@
+#![allow(unused_doc_comments)]
+//! This file contains the storage of the dishes and meals of the Meals Service
+//!
+//! The route handlers only know the [DishRepository] and [MealRepository] traits.
+//! [DieselMealsRepository] stores the dishes and meals in the database, [InMemoryMealsRepository] keeps them in memory,
+//! so the handlers can be tested without a database.
+//!
+//! The methods block, handlers call them with [crate::db::run_blocking].
+//! Both implementations report the same [DbError]s, e.g. [DbError::AlreadyExists] for a taken name
+//!
+//! A recipe dish also has ingredient lines, its nutrition is the sum of them, see [super::recipes].
+//! When the lines change, the nutrition of the meals containing the dish is recomputed like after a dish is deleted
+//!
+//! Every method works on the dishes and meals of one tenant, see [crate::auth::Principal]. The rows of the other tenants
+//! are treated as if they did not exist, so names are only unique within a tenant and a meal can only contain
+//! dishes of its own tenant
+
+/// Diesel imports
+use diesel::prelude::*;
+use diesel::result::DatabaseErrorKind;
+use diesel::{delete, insert_into, update};
+
+/// Misc imports
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+
+/// Module imports
+use super::models::{Dish, Ingredient, Meal, NewDish, NewMeal, Unit};
+use super::recipes::{recipe_dish, RecipeError};
+
+/// Crate imports
+use crate::db::{get_connection, BackendConnection, DbError, DbPool};
+use crate::schema::{dish_ingredients, dishes, meals};
+
+/// Storage of the dishes
+pub trait DishRepository: Send + Sync {
+
+    /// Returns all dishes ordered by their ID
+    fn all(&self, tenant: &str) -> Result<Vec<Dish>, DbError>;
+
+    /// Returns the dish with the ID, or [DbError::NotFound]
+    fn find(&self, tenant: &str, id: i32) -> Result<Dish, DbError>;
+
+    /// Returns the dish with the name, or [DbError::NotFound]
+    fn find_by_name(&self, tenant: &str, name: &str) -> Result<Dish, DbError>;
+
+    /// Returns the names of the dishes with the IDs, IDs without a dish are left out
+    fn names(&self, tenant: &str, ids: &[i32]) -> Result<HashMap<i32, String>, DbError>;
+
+    /// Stores a new dish, or returns [DbError::AlreadyExists] if its name is taken
+    fn insert(&self, tenant: &str, dish: NewDish) -> Result<Dish, DbError>;
+
+    /// Deletes the dish with the ID, or returns [DbError::NotFound]
+    /// The meals containing the dish lose it and their nutrition values are recomputed
+    fn delete(&self, tenant: &str, id: i32) -> Result<(), DbError>;
+
+    /// Deletes the dish with the name and returns its ID, or returns [DbError::NotFound]
+    fn delete_by_name(&self, tenant: &str, name: &str) -> Result<i32, DbError>;
+
+    /// Returns the ingredient lines of the dish in order, none if it is not a recipe, or [DbError::NotFound]
+    /// Returns [DbError::Query] if a stored line has a unit the service does not know
+    fn ingredients(&self, tenant: &str, id: i32) -> Result<Vec<Ingredient>, DbError>;
+
+    /// Stores a new recipe dish with its ingredient lines, or returns [DbError::AlreadyExists] if its name is taken
+    fn insert_recipe(&self, tenant: &str, name: String, ingredients: Vec<Ingredient>) -> Result<Dish, DbError>;
+
+    /// Replaces the ingredient lines of the dish with the ID and returns it with its new nutrition, or returns [DbError::NotFound]
+    /// The meals containing the dish are recomputed
+    fn set_ingredients(&self, tenant: &str, id: i32, ingredients: Vec<Ingredient>) -> Result<Dish, DbError>;
+
+    /// Edits the ingredient lines of the dish with the ID in one transaction and returns it with its new nutrition
+    /// Nothing is stored if `edit` fails, its error is returned as is. Returns [DbError::NotFound] if the dish does not exist
+    /// The meals containing the dish are recomputed
+    fn edit_ingredients(&self, tenant: &str, id: i32, edit: &mut dyn FnMut(&mut Vec<Ingredient>) -> Result<(), LinesError>) -> Result<Dish, LinesError>;
+}
+
+/// Why an edit of the ingredient lines of a dish was not stored
+#[derive(Debug)]
+pub enum LinesError {
+    /// The edit refused the lines, e.g. because the recipe would have too many
+    Recipe(RecipeError),
+    Db(DbError),
+}
+
+impl From<DbError> for LinesError {
+    fn from(e: DbError) -> Self {
+        LinesError::Db(e)
+    }
+}
+
+impl From<diesel::result::Error> for LinesError {
+    fn from(e: diesel::result::Error) -> Self {
+        LinesError::Db(e.into())
+    }
+}
+
+/// Storage of the meals
+/// The nutrition values of a meal are always the sum of the values of its dishes
+pub trait MealRepository: Send + Sync {
+
+    /// Returns all meals ordered by their ID
+    fn all(&self, tenant: &str) -> Result<Vec<Meal>, DbError>;
+
+    /// Returns the meals with at most the given calories, sodium and sugar, ordered by their ID
+    fn within(&self, tenant: &str, cal: f64, sodium: f64, sugar: f64) -> Result<Vec<Meal>, DbError>;
+
+    /// Returns the meal with the ID, or [DbError::NotFound]
+    fn find(&self, tenant: &str, id: i32) -> Result<Meal, DbError>;
+
+    /// Returns the meal with the name, or [DbError::NotFound]
+    fn find_by_name(&self, tenant: &str, name: &str) -> Result<Meal, DbError>;
+
+    /// Stores a new meal
+    /// Returns [DbError::AlreadyExists] if its name is taken, [DbError::MissingReference] if one of its dishes does not exist
+    fn insert(&self, tenant: &str, meal: NewMeal) -> Result<Meal, DbError>;
+
+    /// Replaces the meal with the ID, with the same errors as [MealRepository::insert] and [DbError::NotFound]
+    fn update(&self, tenant: &str, id: i32, meal: NewMeal) -> Result<(), DbError>;
+
+    /// Deletes the meal with the ID, or returns [DbError::NotFound]
+    fn delete(&self, tenant: &str, id: i32) -> Result<(), DbError>;
+
+    /// Deletes the meal with the name and returns its ID, or returns [DbError::NotFound]
+    fn delete_by_name(&self, tenant: &str, name: &str) -> Result<i32, DbError>;
+}
+
+/*
+=============================== Database ===============================
+ */
+
+/// Dishes and meals stored in Postgres or SQLite, see [crate::db::BackendConnection]
+/// The nutrition values of the meals are computed by the `update_meal_nutrition` triggers
+pub struct DieselMealsRepository {
+    pool: DbPool,
+}
+
+impl DieselMealsRepository {
+    pub fn new(pool: DbPool) -> DieselMealsRepository {
+        DieselMealsRepository { pool }
+    }
+}
+
+/// Checks that the dishes of a meal exist in its tenant
+/// The foreign keys only check that they exist at all, in any tenant
+fn check_dishes(conn: &mut BackendConnection, tenant: &str, meal: &NewMeal) -> Result<(), diesel::result::Error> {
+    let mut ids = vec![meal.appetizer, meal.main, meal.dessert];
+    ids.sort_unstable();
+    ids.dedup();
+    let found = dishes::table
+        .filter(dishes::tenant.eq(tenant))
+        .filter(dishes::id.eq_any(&ids))
+        .count()
+        .get_result::<i64>(conn)?;
+    match found as usize == ids.len() {
+        true => Ok(()),
+        false => Err(diesel::result::Error::DatabaseError(
+            DatabaseErrorKind::ForeignKeyViolation,
+            Box::new(format!("a dish of meal {} does not exist in tenant {}", meal.name, tenant)),
+        )),
+    }
+}
+
+/// A row of the `dish_ingredients` table without the dish and the line
+type IngredientRow = (String, f64, String, f64, f64, f64, f64);
+
+/// Replaces the ingredient lines of a dish, numbered from 1
+fn insert_ingredients(conn: &mut BackendConnection, id: i32, ingredients: &[Ingredient]) -> Result<(), diesel::result::Error> {
+    delete(dish_ingredients::table.filter(dish_ingredients::dish_id.eq(id))).execute(conn)?;
+    let rows: Vec<_> = ingredients.iter().enumerate()
+        .map(|(line, ingredient)| (
+            dish_ingredients::dish_id.eq(id),
+            dish_ingredients::line.eq(line as i32 + 1),
+            dish_ingredients::ingredient.eq(&ingredient.ingredient),
+            dish_ingredients::quantity.eq(ingredient.quantity),
+            dish_ingredients::unit.eq(ingredient.unit.to_string()),
+            dish_ingredients::cal.eq(ingredient.cal),
+            dish_ingredients::sodium.eq(ingredient.sodium),
+            dish_ingredients::sugar.eq(ingredient.sugar),
+            dish_ingredients::size.eq(ingredient.size),
+        ))
+        .collect();
+    if !rows.is_empty() {
+        insert_into(dish_ingredients::table).values(rows).execute(conn)?;
+    }
+    Ok(())
+}
+
+/// Returns the ingredient lines of a dish in order
+fn load_ingredients(conn: &mut BackendConnection, id: i32) -> Result<Vec<Ingredient>, DbError> {
+    let rows = dish_ingredients::table
+        .filter(dish_ingredients::dish_id.eq(id))
+        .order(dish_ingredients::line)
+        .select((
+            dish_ingredients::ingredient,
+            dish_ingredients::quantity,
+            dish_ingredients::unit,
+            dish_ingredients::cal,
+            dish_ingredients::sodium,
+            dish_ingredients::sugar,
+            dish_ingredients::size,
+        ))
+        .load::<IngredientRow>(conn)?;
+
+    /// A line with an unknown unit fails the whole read, since dropping it would change the totals of the recipe
+    rows.into_iter()
+        .map(|(ingredient, quantity, unit, cal, sodium, sugar, size)| {
+            let unit = unit.parse::<Unit>()
+                .map_err(|e| DbError::Query(diesel::result::Error::DeserializationError(e.into())))?;
+            Ok(Ingredient { ingredient, quantity, unit, cal, sodium, sugar, size })
+        })
+        .collect()
+}
+
+/// Replaces the ingredient lines of a dish of the tenant and sets its nutrition to their totals
+/// The `update_meals_of_dish` triggers recompute the meals once the nutrition of the dish changed
+fn store_ingredients(conn: &mut BackendConnection, tenant: &str, id: i32, ingredients: &[Ingredient]) -> Result<Dish, diesel::result::Error> {
+    let totals = recipe_dish(String::new(), ingredients);
+    let dish = update(dishes::table.find(id).filter(dishes::tenant.eq(tenant)))
+        .set((
+            dishes::cal.eq(totals.cal),
+            dishes::sodium.eq(totals.sodium),
+            dishes::sugar.eq(totals.sugar),
+            dishes::size.eq(totals.size),
+        ))
+        .returning(Dish::as_returning())
+        .get_result(conn)?;
+    insert_ingredients(conn, id, ingredients)?;
+    Ok(dish)
+}
+
+impl DishRepository for DieselMealsRepository {
+
+    fn all(&self, tenant: &str) -> Result<Vec<Dish>, DbError> {
+        let conn = &mut get_connection(&self.pool)?;
+        let results = dishes::table
+            .filter(dishes::tenant.eq(tenant))
+            .order(dishes::id)
+            .select(Dish::as_select())
+            .load(conn)?;
+        Ok(results)
+    }
+
+    fn find(&self, tenant: &str, id: i32) -> Result<Dish, DbError> {
+        let conn = &mut get_connection(&self.pool)?;
+        Ok(dishes::table.find(id).filter(dishes::tenant.eq(tenant)).select(Dish::as_select()).first(conn)?)
+    }
+
+    fn find_by_name(&self, tenant: &str, name: &str) -> Result<Dish, DbError> {
+        let conn = &mut get_connection(&self.pool)?;
+        let dish = dishes::table
+            .filter(dishes::tenant.eq(tenant))
+            .filter(dishes::name.eq(name))
+            .select(Dish::as_select())
+            .first(conn)?;
+        Ok(dish)
+    }
+
+    fn names(&self, tenant: &str, ids: &[i32]) -> Result<HashMap<i32, String>, DbError> {
+        let conn = &mut get_connection(&self.pool)?;
+        let names = dishes::table
+            .filter(dishes::tenant.eq(tenant))
+            .filter(dishes::id.eq_any(ids))
+            .select((dishes::id, dishes::name))
+            .load::<(i32, String)>(conn)?;
+        Ok(names.into_iter().collect())
+    }
+
+    fn insert(&self, tenant: &str, dish: NewDish) -> Result<Dish, DbError> {
+        let conn = &mut get_connection(&self.pool)?;
+        let dish = insert_into(dishes::table)
+            .values((&dish, dishes::tenant.eq(tenant)))
+            .returning(Dish::as_returning())
+            .get_result(conn)?;
+        Ok(dish)
+    }
+
+    fn delete(&self, tenant: &str, id: i32) -> Result<(), DbError> {
+        let conn = &mut get_connection(&self.pool)?;
+        match delete(dishes::table.find(id).filter(dishes::tenant.eq(tenant))).execute(conn)? {
+            0 => Err(DbError::NotFound),
+            _ => Ok(()),
+        }
+    }
+
+    fn delete_by_name(&self, tenant: &str, name: &str) -> Result<i32, DbError> {
+        let conn = &mut get_connection(&self.pool)?;
+        let deleted = delete(dishes::table.filter(dishes::tenant.eq(tenant)).filter(dishes::name.eq(name)))
+            .returning(dishes::id)
+            .get_result::<i32>(conn)?;
+        Ok(deleted)
+    }
+
+    fn ingredients(&self, tenant: &str, id: i32) -> Result<Vec<Ingredient>, DbError> {
+        let conn = &mut get_connection(&self.pool)?;
+        let dish = dishes::table.find(id).filter(dishes::tenant.eq(tenant)).select(dishes::id).first::<i32>(conn)?;
+        load_ingredients(conn, dish)
+    }
+
+    fn insert_recipe(&self, tenant: &str, name: String, ingredients: Vec<Ingredient>) -> Result<Dish, DbError> {
+        let conn = &mut get_connection(&self.pool)?;
+        let dish = recipe_dish(name, &ingredients);
+        let dish = conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            let dish = insert_into(dishes::table)
+                .values((&dish, dishes::tenant.eq(tenant)))
+                .returning(Dish::as_returning())
+                .get_result(conn)?;
+            insert_ingredients(conn, dish.ID, &ingredients)?;
+            Ok(dish)
+        })?;
+        Ok(dish)
+    }
+
+    fn set_ingredients(&self, tenant: &str, id: i32, ingredients: Vec<Ingredient>) -> Result<Dish, DbError> {
+        let conn = &mut get_connection(&self.pool)?;
+        let dish = conn.transaction(|conn| store_ingredients(conn, tenant, id, &ingredients))?;
+        Ok(dish)
+    }
+
+    fn edit_ingredients(&self, tenant: &str, id: i32, edit: &mut dyn FnMut(&mut Vec<Ingredient>) -> Result<(), LinesError>) -> Result<Dish, LinesError> {
+        let conn = &mut get_connection(&self.pool)?;
+        conn.transaction(|conn| {
+            /// Writing the dish first locks it, so concurrent edits of its lines wait for each other instead of losing one
+            update(dishes::table.find(id).filter(dishes::tenant.eq(tenant)))
+                .set(dishes::size.eq(dishes::size))
+                .returning(dishes::id)
+                .get_result::<i32>(conn)?;
+            let mut ingredients = load_ingredients(conn, id)?;
+            edit(&mut ingredients)?;
+            Ok(store_ingredients(conn, tenant, id, &ingredients)?)
+        })
+    }
+}
+
+impl MealRepository for DieselMealsRepository {
+
+    fn all(&self, tenant: &str) -> Result<Vec<Meal>, DbError> {
+        let conn = &mut get_connection(&self.pool)?;
+        let results = meals::table
+            .filter(meals::tenant.eq(tenant))
+            .order(meals::id)
+            .select(Meal::as_select())
+            .load(conn)?;
+        Ok(results)
+    }
+
+    fn within(&self, tenant: &str, cal: f64, sodium: f64, sugar: f64) -> Result<Vec<Meal>, DbError> {
+        let conn = &mut get_connection(&self.pool)?;
+        let results = meals::table
+            .filter(meals::tenant.eq(tenant))
+            .filter(meals::cal.le(cal))
+            .filter(meals::sodium.le(sodium))
+            .filter(meals::sugar.le(sugar))
+            .order(meals::id)
+            .select(Meal::as_select())
+            .load(conn)?;
+        Ok(results)
+    }
+
+    fn find(&self, tenant: &str, id: i32) -> Result<Meal, DbError> {
+        let conn = &mut get_connection(&self.pool)?;
+        Ok(meals::table.find(id).filter(meals::tenant.eq(tenant)).select(Meal::as_select()).first(conn)?)
+    }
+
+    fn find_by_name(&self, tenant: &str, name: &str) -> Result<Meal, DbError> {
+        let conn = &mut get_connection(&self.pool)?;
+        let meal = meals::table
+            .filter(meals::tenant.eq(tenant))
+            .filter(meals::name.eq(name))
+            .select(Meal::as_select())
+            .first(conn)?;
+        Ok(meal)
+    }
+
+    fn insert(&self, tenant: &str, meal: NewMeal) -> Result<Meal, DbError> {
+        let conn = &mut get_connection(&self.pool)?;
+
+        /// The meal is read again, because the SQLite triggers fill in its nutrition after the insert returned
+        let meal = conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            check_dishes(conn, tenant, &meal)?;
+            let id = insert_into(meals::table)
+                .values((&meal, meals::tenant.eq(tenant)))
+                .returning(meals::id)
+                .get_result::<i32>(conn)?;
+            meals::table.find(id).select(Meal::as_select()).first(conn)
+        })?;
+        Ok(meal)
+    }
+
+    fn update(&self, tenant: &str, id: i32, meal: NewMeal) -> Result<(), DbError> {
+        let conn = &mut get_connection(&self.pool)?;
+
+        /// The dishes are checked after the update, so a meal of another tenant is reported as not found
+        let updated = conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            let updated = update(meals::table.find(id).filter(meals::tenant.eq(tenant)))
+                .set((
+                    meals::name.eq(&meal.name),
+                    meals::appetizer.eq(meal.appetizer),
+                    meals::main.eq(meal.main),
+                    meals::dessert.eq(meal.dessert),
+                ))
+                .execute(conn)?;
+            if updated > 0 {
+                check_dishes(conn, tenant, &meal)?;
+            }
+            Ok(updated)
+        })?;
+        match updated {
+            0 => Err(DbError::NotFound),
+            _ => Ok(()),
+        }
+    }
+
+    fn delete(&self, tenant: &str, id: i32) -> Result<(), DbError> {
+        let conn = &mut get_connection(&self.pool)?;
+        match delete(meals::table.find(id).filter(meals::tenant.eq(tenant))).execute(conn)? {
+            0 => Err(DbError::NotFound),
+            _ => Ok(()),
+        }
+    }
+
+    fn delete_by_name(&self, tenant: &str, name: &str) -> Result<i32, DbError> {
+        let conn = &mut get_connection(&self.pool)?;
+        let deleted = delete(meals::table.filter(meals::tenant.eq(tenant)).filter(meals::name.eq(name)))
+            .returning(meals::id)
+            .get_result::<i32>(conn)?;
+        Ok(deleted)
+    }
+}
+
+/*
+=============================== In memory ===============================
+ */
+
+/// A row of the [InMemoryMealsRepository] and the tenant it belongs to
+#[derive(Clone)]
+struct Owned<T> {
+    tenant: String,
+    row: T,
+}
+
+/// The rows of the [InMemoryMealsRepository] and the last IDs handed out, like the `serial` columns
+/// The IDs are shared by all tenants, like in the database
+#[derive(Default)]
+struct Tables {
+    dishes: BTreeMap<i32, Owned<Dish>>,
+    ingredients: HashMap<i32, Vec<Ingredient>>,
+    meals: BTreeMap<i32, Owned<Meal>>,
+    last_dish_id: i32,
+    last_meal_id: i32,
+}
+
+impl Tables {
+
+    /// The dishes of a tenant, ordered by their ID
+    fn dishes<'a>(&'a self, tenant: &'a str) -> impl Iterator<Item = &'a Dish> + 'a {
+        self.dishes.values().filter(move |owned| owned.tenant == tenant).map(|owned| &owned.row)
+    }
+
+    /// The meals of a tenant, ordered by their ID
+    fn meals<'a>(&'a self, tenant: &'a str) -> impl Iterator<Item = &'a Meal> + 'a {
+        self.meals.values().filter(move |owned| owned.tenant == tenant).map(|owned| &owned.row)
+    }
+
+    /// The dish with the ID, if it belongs to the tenant
+    fn dish(&self, tenant: &str, id: i32) -> Option<&Dish> {
+        self.dishes.get(&id).filter(|owned| owned.tenant == tenant).map(|owned| &owned.row)
+    }
+
+    /// The meal with the ID, if it belongs to the tenant
+    fn meal(&self, tenant: &str, id: i32) -> Option<&Meal> {
+        self.meals.get(&id).filter(|owned| owned.tenant == tenant).map(|owned| &owned.row)
+    }
+
+    /// Sums the nutrition values of the dishes of a meal, like the `update_meal_nutrition` trigger
+    fn nutrition(&self, meal: &mut Meal) {
+        let courses = [meal.appetizer, meal.main, meal.dessert];
+        let dishes: Vec<&Dish> = courses.iter().flatten().filter_map(|id| self.dishes.get(id)).map(|owned| &owned.row).collect();
+        meal.cal = Some(dishes.iter().map(|dish| dish.cal).sum());
+        meal.sodium = Some(dishes.iter().map(|dish| dish.sodium).sum());
+        meal.sugar = Some(dishes.iter().map(|dish| dish.sugar).sum());
+    }
+
+    /// Checks the name and the dishes of a meal, `id` is the meal being replaced, if any
+    fn check_meal(&self, tenant: &str, meal: &NewMeal, id: Option<i32>) -> Result<(), DbError> {
+        if self.meals(tenant).any(|other| other.name == meal.name && Some(other.ID) != id) {
+            return Err(DbError::AlreadyExists)
+        }
+        if [meal.appetizer, meal.main, meal.dessert].iter().any(|dish| self.dish(tenant, *dish).is_none()) {
+            return Err(DbError::MissingReference)
+        }
+        Ok(())
+    }
+
+    /// Stores a new dish, unless its name is taken
+    fn insert_dish(&mut self, tenant: &str, dish: NewDish) -> Result<Dish, DbError> {
+        if self.dishes(tenant).any(|other| other.name == dish.name) {
+            return Err(DbError::AlreadyExists)
+        }
+        self.last_dish_id += 1;
+        let dish = Dish {
+            ID: self.last_dish_id,
+            name: dish.name,
+            cal: dish.cal,
+            sodium: dish.sodium,
+            sugar: dish.sugar,
+            size: dish.size,
+        };
+        self.dishes.insert(dish.ID, Owned { tenant: tenant.to_string(), row: dish.clone() });
+        Ok(dish)
+    }
+
+    /// Replaces the ingredient lines of a dish of the tenant, sets its nutrition to their totals and recomputes its meals
+    fn set_ingredients(&mut self, tenant: &str, id: i32, ingredients: Vec<Ingredient>) -> Result<Dish, DbError> {
+        let totals = recipe_dish(String::new(), &ingredients);
+        let dish = match self.dishes.get_mut(&id) {
+            Some(Owned { tenant: owner, row: dish }) if owner == tenant => dish,
+            _ => return Err(DbError::NotFound),
+        };
+        dish.cal = totals.cal;
+        dish.sodium = totals.sodium;
+        dish.sugar = totals.sugar;
+        dish.size = totals.size;
+        let dish = dish.clone();
+        self.ingredients.insert(id, ingredients);
+        self.update_meals_of_dish(id);
+        Ok(dish)
+    }
+
+    /// Recomputes the meals containing a dish, like the `update_meals_of_dish` trigger
+    fn update_meals_of_dish(&mut self, id: i32) {
+        let mut meals = std::mem::take(&mut self.meals);
+        for Owned { row: meal, .. } in meals.values_mut() {
+            if [meal.appetizer, meal.main, meal.dessert].contains(&Some(id)) {
+                self.nutrition(meal);
+            }
+        }
+        self.meals = meals;
+    }
+
+    /// Deletes a dish and removes it from the meals, like `ON DELETE SET NULL` followed by the trigger
+    fn delete_dish(&mut self, id: i32) {
+        self.dishes.remove(&id);
+        self.ingredients.remove(&id);
+        let mut meals = std::mem::take(&mut self.meals);
+        for Owned { row: meal, .. } in meals.values_mut() {
+            for course in [&mut meal.appetizer, &mut meal.main, &mut meal.dessert] {
+                if *course == Some(id) {
+                    *course = None;
+                }
+            }
+            self.nutrition(meal);
+        }
+        self.meals = meals;
+    }
+}
+
+/// Dishes and meals kept in memory, with the same behavior as [DieselMealsRepository]
+/// Implements both traits on the same tables, since deleting a dish changes the meals
+#[derive(Default)]
+pub struct InMemoryMealsRepository {
+    tables: Mutex<Tables>,
+}
+
+impl InMemoryMealsRepository {
+
+    pub fn new() -> InMemoryMealsRepository {
+        InMemoryMealsRepository::default()
+    }
+
+    /// Locks the tables, a panic of another thread holding the lock leaves them usable
+    fn tables(&self) -> std::sync::MutexGuard<'_, Tables> {
+        self.tables.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl DishRepository for InMemoryMealsRepository {
+
+    fn all(&self, tenant: &str) -> Result<Vec<Dish>, DbError> {
+        Ok(self.tables().dishes(tenant).cloned().collect())
+    }
+
+    fn find(&self, tenant: &str, id: i32) -> Result<Dish, DbError> {
+        self.tables().dish(tenant, id).cloned().ok_or(DbError::NotFound)
+    }
+
+    fn find_by_name(&self, tenant: &str, name: &str) -> Result<Dish, DbError> {
+        self.tables().dishes(tenant).find(|dish| dish.name == name).cloned().ok_or(DbError::NotFound)
+    }
+
+    fn names(&self, tenant: &str, ids: &[i32]) -> Result<HashMap<i32, String>, DbError> {
+        let tables = self.tables();
+        Ok(ids.iter().filter_map(|id| tables.dish(tenant, *id).map(|dish| (*id, dish.name.clone()))).collect())
+    }
+
+    fn insert(&self, tenant: &str, dish: NewDish) -> Result<Dish, DbError> {
+        self.tables().insert_dish(tenant, dish)
+    }
+
+    fn delete(&self, tenant: &str, id: i32) -> Result<(), DbError> {
+        let mut tables = self.tables();
+        if tables.dish(tenant, id).is_none() {
+            return Err(DbError::NotFound)
+        }
+        tables.delete_dish(id);
+        Ok(())
+    }
+
+    fn delete_by_name(&self, tenant: &str, name: &str) -> Result<i32, DbError> {
+        let mut tables = self.tables();
+        let id = tables.dishes(tenant).find(|dish| dish.name == name).map(|dish| dish.ID).ok_or(DbError::NotFound)?;
+        tables.delete_dish(id);
+        Ok(id)
+    }
+
+    fn ingredients(&self, tenant: &str, id: i32) -> Result<Vec<Ingredient>, DbError> {
+        let tables = self.tables();
+        tables.dish(tenant, id).ok_or(DbError::NotFound)?;
+        Ok(tables.ingredients.get(&id).cloned().unwrap_or_default())
+    }
+
+    fn insert_recipe(&self, tenant: &str, name: String, ingredients: Vec<Ingredient>) -> Result<Dish, DbError> {
+        let mut tables = self.tables();
+        let dish = tables.insert_dish(tenant, recipe_dish(name, &ingredients))?;
+        tables.ingredients.insert(dish.ID, ingredients);
+        Ok(dish)
+    }
+
+    fn set_ingredients(&self, tenant: &str, id: i32, ingredients: Vec<Ingredient>) -> Result<Dish, DbError> {
+        self.tables().set_ingredients(tenant, id, ingredients)
+    }
+
+    fn edit_ingredients(&self, tenant: &str, id: i32, edit: &mut dyn FnMut(&mut Vec<Ingredient>) -> Result<(), LinesError>) -> Result<Dish, LinesError> {
+        /// The lock is held from reading the lines to storing them, like the transaction of [DieselMealsRepository]
+        let mut tables = self.tables();
+        tables.dish(tenant, id).ok_or(DbError::NotFound)?;
+        let mut ingredients = tables.ingredients.get(&id).cloned().unwrap_or_default();
+        edit(&mut ingredients)?;
+        Ok(tables.set_ingredients(tenant, id, ingredients)?)
+    }
+}
+
+impl MealRepository for InMemoryMealsRepository {
+
+    fn all(&self, tenant: &str) -> Result<Vec<Meal>, DbError> {
+        Ok(self.tables().meals(tenant).cloned().collect())
+    }
+
+    fn within(&self, tenant: &str, cal: f64, sodium: f64, sugar: f64) -> Result<Vec<Meal>, DbError> {
+        /// Meals without nutrition values are left out, like rows with NULL values in SQL
+        let within = |value: Option<f64>, limit: f64| value.is_some_and(|value| value <= limit);
+        Ok(self.tables().meals(tenant)
+            .filter(|meal| within(meal.cal, cal) && within(meal.sodium, sodium) && within(meal.sugar, sugar))
+            .cloned()
+            .collect())
+    }
+
+    fn find(&self, tenant: &str, id: i32) -> Result<Meal, DbError> {
+        self.tables().meal(tenant, id).cloned().ok_or(DbError::NotFound)
+    }
+
+    fn find_by_name(&self, tenant: &str, name: &str) -> Result<Meal, DbError> {
+        self.tables().meals(tenant).find(|meal| meal.name == name).cloned().ok_or(DbError::NotFound)
+    }
+
+    fn insert(&self, tenant: &str, meal: NewMeal) -> Result<Meal, DbError> {
+        let mut tables = self.tables();
+        tables.check_meal(tenant, &meal, None)?;
+        tables.last_meal_id += 1;
+        let mut meal = Meal {
+            ID: tables.last_meal_id,
+            name: meal.name,
+            appetizer: Some(meal.appetizer),
+            main: Some(meal.main),
+            dessert: Some(meal.dessert),
+            cal: None,
+            sodium: None,
+            sugar: None,
+        };
+        tables.nutrition(&mut meal);
+        tables.meals.insert(meal.ID, Owned { tenant: tenant.to_string(), row: meal.clone() });
+        Ok(meal)
+    }
+
+    fn update(&self, tenant: &str, id: i32, meal: NewMeal) -> Result<(), DbError> {
+        let mut tables = self.tables();
+        if tables.meal(tenant, id).is_none() {
+            return Err(DbError::NotFound)
+        }
+        tables.check_meal(tenant, &meal, Some(id))?;
+        let mut meal = Meal {
+            ID: id,
+            name: meal.name,
+            appetizer: Some(meal.appetizer),
+            main: Some(meal.main),
+            dessert: Some(meal.dessert),
+            cal: None,
+            sodium: None,
+            sugar: None,
+        };
+        tables.nutrition(&mut meal);
+        tables.meals.insert(id, Owned { tenant: tenant.to_string(), row: meal });
+        Ok(())
+    }
+
+    fn delete(&self, tenant: &str, id: i32) -> Result<(), DbError> {
+        let mut tables = self.tables();
+        if tables.meal(tenant, id).is_none() {
+            return Err(DbError::NotFound)
+        }
+        tables.meals.remove(&id);
+        Ok(())
+    }
+
+    fn delete_by_name(&self, tenant: &str, name: &str) -> Result<i32, DbError> {
+        let mut tables = self.tables();
+        let id = tables.meals(tenant).find(|meal| meal.name == name).map(|meal| meal.ID).ok_or(DbError::NotFound)?;
+        tables.meals.remove(&id);
+        Ok(id)
+    }
+}
+
+#[cfg(all(test, feature = "sqlite"))]
+mod tests {
+    use super::*;
+    use crate::db::sqlite_test_pool;
+    use crate::meals::Unit;
+
+    const T: &str = "default";
+
+    fn dish(name: &str, cal: f64, sodium: f64, sugar: f64) -> NewDish {
+        NewDish { name: name.to_string(), cal, sodium, sugar, size: 100.0 }
+    }
+
+    #[test]
+    fn sqlite_triggers_keep_the_nutrition_of_meals() {
+        let repository = DieselMealsRepository::new(sqlite_test_pool());
+        let soup = DishRepository::insert(&repository, T, dish("soup", 100.0, 10.0, 1.0)).unwrap().ID;
+        let steak = DishRepository::insert(&repository, T, dish("steak", 500.0, 50.0, 0.0)).unwrap().ID;
+        let cake = DishRepository::insert(&repository, T, dish("cake", 300.0, 5.0, 30.0)).unwrap().ID;
+        assert!(matches!(DishRepository::insert(&repository, T, dish("cake", 1.0, 1.0, 1.0)), Err(DbError::AlreadyExists)));
+
+        let dinner = || NewMeal { name: "dinner".to_string(), appetizer: soup, main: steak, dessert: cake };
+        let meal = MealRepository::insert(&repository, T, dinner()).unwrap();
+        assert_eq!((meal.cal, meal.sodium, meal.sugar), (Some(900.0), Some(65.0), Some(31.0)));
+        assert!(matches!(MealRepository::insert(&repository, T, dinner()), Err(DbError::AlreadyExists)));
+        let lunch = NewMeal { name: "lunch".to_string(), appetizer: soup, main: 99, dessert: cake };
+        assert!(matches!(MealRepository::insert(&repository, T, lunch), Err(DbError::MissingReference)));
+
+        /// Updating the dishes of a meal recomputes its nutrition
+        MealRepository::update(&repository, T, meal.ID, NewMeal { name: "dinner".to_string(), appetizer: soup, main: soup, dessert: cake }).unwrap();
+        assert_eq!(MealRepository::find(&repository, T, meal.ID).unwrap().cal, Some(500.0));
+
+        /// Deleting a dish unsets it through the foreign keys and recomputes the meal
+        DishRepository::delete(&repository, T, cake).unwrap();
+        let meal = MealRepository::find(&repository, T, meal.ID).unwrap();
+        assert_eq!((meal.dessert, meal.cal, meal.sugar), (None, Some(200.0), Some(2.0)));
+        assert_eq!(repository.within(T, 200.0, 20.0, 2.0).unwrap().len(), 1);
+        assert_eq!(repository.within(T, 199.0, 20.0, 2.0).unwrap().len(), 0);
+    }
+
+    #[test]
+    fn sqlite_triggers_recompute_the_meals_of_a_recipe() {
+        let repository = DieselMealsRepository::new(sqlite_test_pool());
+        let line = |ingredient: &str, cal: f64| Ingredient {
+            ingredient: ingredient.to_string(), quantity: 100.0, unit: Unit::G, cal, sodium: 1.0, sugar: 0.5, size: 100.0,
+        };
+        let bowl = repository.insert_recipe(T, "bowl".to_string(), vec![line("rice", 130.0), line("beans", 90.0)]).unwrap();
+        assert_eq!((bowl.cal, bowl.sodium, bowl.size), (220.0, 2.0, 200.0));
+        assert!(matches!(repository.insert_recipe(T, "bowl".to_string(), vec![]), Err(DbError::AlreadyExists)));
+        let soup = DishRepository::insert(&repository, T, dish("soup", 100.0, 10.0, 1.0)).unwrap().ID;
+        let meal = MealRepository::insert(&repository, T, NewMeal { name: "lunch".to_string(), appetizer: soup, main: bowl.ID, dessert: bowl.ID }).unwrap();
+        assert_eq!(meal.cal, Some(540.0));
+
+        let bowl = repository.set_ingredients(T, bowl.ID, vec![line("rice", 130.0), line("tofu", 150.0), line("egg", 70.0)]).unwrap();
+        assert_eq!(bowl.cal, 350.0);
+        assert_eq!(repository.ingredients(T, bowl.ID).unwrap()[1], line("tofu", 150.0));
+        assert_eq!(MealRepository::find(&repository, T, meal.ID).unwrap().cal, Some(800.0));
+        assert!(repository.ingredients(T, soup).unwrap().is_empty());
+        assert!(matches!(repository.set_ingredients("b", bowl.ID, vec![]), Err(DbError::NotFound)));
+        assert!(matches!(repository.ingredients("b", bowl.ID), Err(DbError::NotFound)));
+
+        /// An edit stores nothing if it fails, and recomputes the dish and its meals otherwise
+        let refused = repository.edit_ingredients(T, bowl.ID, &mut |lines| {
+            lines.clear();
+            Err(LinesError::Recipe(RecipeError::Missing))
+        });
+        assert!(matches!(refused, Err(LinesError::Recipe(RecipeError::Missing))));
+        assert_eq!(repository.ingredients(T, bowl.ID).unwrap().len(), 3);
+        let bowl = repository.edit_ingredients(T, bowl.ID, &mut |lines| {
+            lines.remove(2);
+            Ok(())
+        }).unwrap();
+        assert_eq!(bowl.cal, 280.0);
+        assert_eq!(MealRepository::find(&repository, T, meal.ID).unwrap().cal, Some(660.0));
+        assert!(matches!(repository.edit_ingredients("b", bowl.ID, &mut |_| Ok(())), Err(LinesError::Db(DbError::NotFound))));
+
+        /// A line with a unit the service does not know fails the read instead of being left out
+        let conn = &mut get_connection(&repository.pool).unwrap();
+        update(dish_ingredients::table.filter(dish_ingredients::dish_id.eq(bowl.ID)).filter(dish_ingredients::line.eq(2)))
+            .set(dish_ingredients::unit.eq("cup"))
+            .execute(conn)
+            .unwrap();
+        assert!(matches!(repository.ingredients(T, bowl.ID), Err(DbError::Query(_))));
+
+        /// The lines go with the dish
+        DishRepository::delete(&repository, T, bowl.ID).unwrap();
+        assert!(matches!(repository.ingredients(T, bowl.ID), Err(DbError::NotFound)));
+    }
+
+    #[test]
+    fn tenants_do_not_see_each_other() {
+        let repository = DieselMealsRepository::new(sqlite_test_pool());
+        let soup_a = DishRepository::insert(&repository, "a", dish("soup", 100.0, 10.0, 1.0)).unwrap().ID;
+        let soup_b = DishRepository::insert(&repository, "b", dish("soup", 200.0, 20.0, 2.0)).unwrap().ID;
+        assert_eq!(DishRepository::find_by_name(&repository, "b", "soup").unwrap().ID, soup_b);
+        assert!(matches!(DishRepository::find(&repository, "b", soup_a), Err(DbError::NotFound)));
+        assert_eq!(DishRepository::all(&repository, "a").unwrap().len(), 1);
+        assert!(repository.names("b", &[soup_a]).unwrap().is_empty());
+
+        /// A meal can not contain the dishes of another tenant
+        let meal = |appetizer| NewMeal { name: "lunch".to_string(), appetizer, main: soup_a, dessert: soup_a };
+        assert!(matches!(MealRepository::insert(&repository, "a", meal(soup_b)), Err(DbError::MissingReference)));
+        let lunch = MealRepository::insert(&repository, "a", meal(soup_a)).unwrap();
+        assert!(matches!(MealRepository::update(&repository, "a", lunch.ID, meal(soup_b)), Err(DbError::MissingReference)));
+        assert!(matches!(MealRepository::update(&repository, "b", lunch.ID, meal(soup_a)), Err(DbError::NotFound)));
+        assert!(matches!(MealRepository::delete(&repository, "b", lunch.ID), Err(DbError::NotFound)));
+        assert!(MealRepository::all(&repository, "b").unwrap().is_empty());
+        assert!(repository.within("b", 1000.0, 1000.0, 1000.0).unwrap().is_empty());
+
+        assert!(matches!(DishRepository::delete_by_name(&repository, "c", "soup"), Err(DbError::NotFound)));
+        DishRepository::delete_by_name(&repository, "b", "soup").unwrap();
+        assert_eq!(MealRepository::find(&repository, "a", lunch.ID).unwrap().cal, Some(300.0));
+    }
+}
@@ -0,0 +1,238 @@
+#![allow(unused_doc_comments)]
+
+/// Actix Imports
+use actix_web::{get, post, HttpResponse, Responder, HttpRequest, web};
+use actix_web::web::{Data, Query};
+
+/// Misc Imports
+use tracing::warn;
+
+/// Module Imports
+use super::diet_client::{DietClient, LimitsError};
+use super::models::{Dish, NewMeal, Nutrients, ReqSuggest, ReqSuggestion, Suggestion};
+use super::repository::{DishRepository, MealRepository};
+use super::suggestions::suggest;
+
+/// Crate Imports
+use crate::auth::{Editor, Reader, Require};
+use crate::db::{run_blocking, DbError};
+use crate::negotiation::{decode_body, negotiate, request_format};
+use crate::telemetry::RequestId;
+
+/// Error codes as defined in the Assigment
+const NOT_JSON: &str = "0";
+const PARAM_NOT_FOUND: &str = "-1";
+const MEAL_ALREADY_EXISTS: &str = "-2";
+const SUGGESTION_NOT_FOUND: &str = "-5";
+const DISH_ID_NOT_FOUND: &str = "-6";
+
+/// Number of suggestions if no limit is given, and the most that can be asked for
+const DEFAULT_SUGGESTIONS: usize = 10;
+const MAX_SUGGESTIONS: usize = 100;
+
+/// Why the dishes or the limits of the diet could not be looked up
+enum LookupError {
+    Db(DbError),
+    Diet(LimitsError),
+}
+
+impl LookupError {
+
+    /// Returns the response of the database or Diets Service error
+    fn response(&self) -> HttpResponse {
+        match self {
+            LookupError::Db(e) => e.response(),
+            LookupError::Diet(e) => e.response(),
+        }
+    }
+}
+
+/// Returns the dishes of the tenant and the limits of the diet
+async fn dishes_and_limits(dishes: Data<dyn DishRepository>, diet_client: &DietClient, tenant: &str, diet: &str, request_id: &RequestId) -> Result<(Vec<Dish>, Nutrients), LookupError> {
+    let limits = diet_client.get_limits(tenant, &[diet.to_string()], request_id).await.map_err(LookupError::Diet)?;
+    let tenant = tenant.to_string();
+    let all = run_blocking(move || dishes.all(&tenant)).await.map_err(LookupError::Db)?;
+    Ok((all, limits))
+}
+
+/// Runs [suggest] on the blocking thread pool, since the number of combinations grows with the cube of the dishes
+async fn suggest_blocking(all: Vec<Dish>, limits: Nutrients, limit: usize) -> Result<Vec<Suggestion>, DbError> {
+    run_blocking(move || Ok(suggest(&all, limits, limit))).await
+}
+
+/*
+=============================== GET /meals/suggest ===============================
+ */
+/// # Creates the route for suggesting combinations of dishes for a diet in "/meals/suggest"
+/// Combines three different dishes of the tenant into meals within the limits of the diet, closest to the limits first
+/// ## Arguments
+/// * `auth` - Requires the [Reader] role, only the data of its tenant is used
+/// * `dishes` - The [DishRepository], the dishes to combine
+/// * `diet_client` - A [web::Data<DietClient>] used to look up the diet in the Diets Microservice
+/// * `request_id` - The [RequestId] of the request, forwarded to the Diets Microservice
+/// * `req` - A [HttpRequest] containing the request, used to pick the response format
+/// * `query` - A [web::Query<ReqSuggest>] containing the diet and the number of suggestions
+/// ## Returns
+/// * [HttpResponse::Ok] with a JSON body containing the suggestions, ranked
+#[utoipa::path(
+    get,
+    path = "/meals/suggest",
+    tag = "meals",
+    security(("api_key" = ["reader"]), ("bearer" = ["reader"])),
+    params(ReqSuggest),
+    responses(
+        (status = 200, description = "The combinations within the limits of the diet, the closest first", body = Vec<Suggestion>),
+        (status = 400, description = "Diet missing or limit not between 1 and 100", body = String),
+        (status = 404, description = "Diet not found", body = String),
+        (status = 503, description = "The Diets Service or the database is unavailable"),
+        (status = 504, description = "The Diets Service did not answer in time"),
+    ),
+)]
+#[get("/meals/suggest")]
+pub async fn get_meal_suggestions(auth: Require<Reader>, dishes: Data<dyn DishRepository>, diet_client: Data<DietClient>, request_id: RequestId, req: HttpRequest, query: Query<ReqSuggest>) -> impl Responder {
+    let tenant = auth.principal.tenant;
+
+    /// Check the diet and the number of suggestions
+    let diet = match query.diet.as_deref() {
+        Some(diet) if !diet.is_empty() => diet,
+        _ => return HttpResponse::BadRequest().body("diet is required"),
+    };
+    let limit = query.limit.unwrap_or(DEFAULT_SUGGESTIONS);
+    if !(1..=MAX_SUGGESTIONS).contains(&limit) {
+        return HttpResponse::BadRequest().body("limit must be between 1 and {}".replace("{}", &MAX_SUGGESTIONS.to_string()))
+    }
+
+    let (all, limits) = match dishes_and_limits(dishes, &diet_client, &tenant, diet, &request_id).await {
+        Ok(lookup) => lookup,
+        Err(e) => return e.response(),
+    };
+    match suggest_blocking(all, limits, limit).await {
+        Ok(suggestions) => negotiate(&req, HttpResponse::Ok(), &suggestions),
+        Err(e) => e.response(),
+    }
+}
+
+/*
+=============================== POST /meals/suggest ===============================
+ */
+/// # Creates the route for storing a suggestion as a meal in "/meals/suggest"
+/// The suggestions are computed again, so the rank refers to the current dishes
+/// ## Arguments
+/// * `auth` - Requires the [Editor] role, only the data of its tenant is used
+/// * `dishes` - The [DishRepository], the dishes to combine
+/// * `meals` - The [MealRepository] storing the meals
+/// * `diet_client` - A [web::Data<DietClient>] used to look up the diet in the Diets Microservice
+/// * `request_id` - The [RequestId] of the request, forwarded to the Diets Microservice
+/// * `req` - A [HttpRequest] containing the request
+/// * `body` - A [web::Bytes] containing the [ReqSuggestion] as JSON, MessagePack or CBOR
+/// ## Returns
+/// * [HttpResponse] with a status of 201 and a body containing the ID of the new meal
+#[utoipa::path(
+    post,
+    path = "/meals/suggest",
+    tag = "meals",
+    security(("api_key" = ["editor"]), ("bearer" = ["editor"])),
+    request_body = ReqSuggestion,
+    responses(
+        (status = 201, description = "ID of the new meal", body = i32),
+        (status = 404, description = "Diet not found, or no suggestion with the rank (-5)", body = String),
+        (status = 415, description = "Content type not supported, error code 0", body = String),
+        (status = 422, description = "Body not parsable (0), diet missing (-1), meal already exists (-2) or a dish was deleted meanwhile (-6)", body = String),
+        (status = 503, description = "The Diets Service or the database is unavailable"),
+        (status = 504, description = "The Diets Service did not answer in time"),
+    ),
+)]
+#[post("/meals/suggest")]
+#[allow(clippy::too_many_arguments)]
+pub async fn save_meal_suggestion(auth: Require<Editor>, dishes: Data<dyn DishRepository>, meals: Data<dyn MealRepository>, diet_client: Data<DietClient>, request_id: RequestId, req: HttpRequest, body: web::Bytes) -> impl Responder {
+    let tenant = auth.principal.tenant;
+
+    /// Check if the Content-Type is JSON, MessagePack or CBOR
+    ///
+    /// If it is not, return a [HttpResponse::UnsupportedMediaType] with a Error Code 0
+    let body_format = match request_format(&req) {
+        Some(body_format) => body_format,
+        None => return HttpResponse::UnsupportedMediaType().body(NOT_JSON)
+    };
+
+    /// Deserialize the body
+    /// If the body is not valid, return a [HttpResponse::UnprocessableEntity] with a Error Code 0
+    let req_suggestion: ReqSuggestion = match decode_body(body_format, &body) {
+        Ok(req_suggestion) => req_suggestion,
+        Err(e) => {
+            warn!("{}", e);
+            return HttpResponse::UnprocessableEntity().body(NOT_JSON);
+        }
+    };
+    let diet = match req_suggestion.diet {
+        Some(diet) if !diet.is_empty() => diet,
+        _ => return HttpResponse::UnprocessableEntity().body(PARAM_NOT_FOUND),
+    };
+    let rank = req_suggestion.rank.unwrap_or(1);
+    if !(1..=MAX_SUGGESTIONS).contains(&rank) {
+        return HttpResponse::NotFound().body(SUGGESTION_NOT_FOUND)
+    }
+
+    /// Find the suggestion with the rank
+    let (all, limits) = match dishes_and_limits(dishes, &diet_client, &tenant, &diet, &request_id).await {
+        Ok(lookup) => lookup,
+        Err(e) => return e.response(),
+    };
+    let suggestion = match suggest_blocking(all, limits, rank).await {
+        Ok(suggestions) => suggestions.into_iter().nth(rank - 1),
+        Err(e) => return e.response(),
+    };
+    let suggestion = match suggestion {
+        Some(suggestion) => suggestion,
+        None => return HttpResponse::NotFound().body(SUGGESTION_NOT_FOUND),
+    };
+
+    /// Store it like a meal created with `POST /meals`
+    let new_meal = NewMeal {
+        name: req_suggestion.name.unwrap_or(suggestion.name),
+        appetizer: suggestion.appetizer,
+        main: suggestion.main,
+        dessert: suggestion.dessert,
+    };
+    match run_blocking(move || meals.insert(&tenant, new_meal)).await {
+        Ok(meal) => HttpResponse::Created().body(meal.ID.to_string()),
+        Err(DbError::AlreadyExists) => HttpResponse::UnprocessableEntity().body(MEAL_ALREADY_EXISTS),
+        Err(DbError::MissingReference) => HttpResponse::UnprocessableEntity().body(DISH_ID_NOT_FOUND),
+        Err(e) => e.response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{test, App};
+    use actix_web::middleware::from_fn;
+    use crate::auth::{authenticate, AuthConfig, DEFAULT_TENANT};
+    use crate::meals::{DietClientConfig, InMemoryMealsRepository, NewDish};
+    use std::sync::Arc;
+
+    #[actix_web::test]
+    async fn suggestions_need_a_diet() {
+        let meals = Arc::new(InMemoryMealsRepository::new());
+        DishRepository::insert(&*meals, DEFAULT_TENANT, NewDish { name: "soup".to_string(), cal: 100.0, sodium: 10.0, sugar: 5.0, size: 100.0 }).unwrap();
+
+        /// Nothing here reaches the Diets Service
+        let app = test::init_service(App::new()
+            .app_data(Data::new(AuthConfig::dev()))
+            .wrap(from_fn(authenticate))
+            .app_data(Data::from(meals.clone() as Arc<dyn MealRepository>))
+            .app_data(Data::from(meals as Arc<dyn DishRepository>))
+            .app_data(Data::new(DietClient::new(DietClientConfig::new("http://127.0.0.1:9")).unwrap()))
+            .service(get_meal_suggestions)
+            .service(save_meal_suggestion)).await;
+
+        for uri in ["/meals/suggest", "/meals/suggest?diet=", "/meals/suggest?diet=keto&limit=0", "/meals/suggest?diet=keto&limit=101"] {
+            let res = test::call_service(&app, test::TestRequest::get().uri(uri).to_request()).await;
+            assert_eq!(res.status(), 400, "{}", uri);
+        }
+        let post = |body: &'static str| test::TestRequest::post().uri("/meals/suggest").insert_header(("Content-Type", "application/json")).set_payload(body).to_request();
+        assert_eq!(test::call_and_read_body(&app, post(r#"{"rank": 1}"#)).await, PARAM_NOT_FOUND);
+        assert_eq!(test::call_and_read_body(&app, post(r#"{"diet": "keto", "rank": 0}"#)).await, SUGGESTION_NOT_FOUND);
+        assert_eq!(test::call_and_read_body(&app, post("diet")).await, NOT_JSON);
+    }
+}
@@ -0,0 +1,206 @@
+#![allow(unused_doc_comments)]
+//! This file contains the search for the combinations of three dishes that fit a diet best
+//!
+//! A combination fits a diet if its totals are within the limits, like the meals returned by `GET /meals?diet=`.
+//! It is the closer to the diet the more of each limit it uses: the distance is the sum of the unused shares of the
+//! three limits, 0 if all of them are used up and 3 for nothing at all.
+//!
+//! The dishes are sorted by the share of the limits they use, and the combinations are searched with branch and bound:
+//! a branch is dropped once even the dishes with the largest shares left can not beat the worst of the best
+//! combinations found so far, or once the lightest dishes would exceed a limit
+
+/// Misc imports
+use std::cmp::Ordering;
+
+/// Module imports
+use super::models::{Dish, Nutrients, Suggestion};
+
+/// Number of dishes of a meal
+const COURSES: usize = 3;
+
+/// The share of the limits the nutrients use, summed up
+/// A limit of 0 counts as used up, a dish within it has none of the nutrient
+fn share(nutrients: &Nutrients, limits: &Nutrients) -> f64 {
+    [(nutrients.cal, limits.cal), (nutrients.sodium, limits.sodium), (nutrients.sugar, limits.sugar)]
+        .into_iter()
+        .map(|(value, limit)| if limit > 0.0 { value / limit } else { 1.0 })
+        .sum()
+}
+
+/// A combination found by the search, the dishes as their positions in the sorted list
+struct Found {
+    share: f64,
+    dishes: [usize; COURSES],
+}
+
+/// Ranks the combinations by the share they use, then by their dishes to keep the order stable
+fn rank(a: &Found, b: &Found) -> Ordering {
+    b.share.total_cmp(&a.share).then_with(|| a.dishes.cmp(&b.dishes))
+}
+
+/// The state of the search
+struct Search<'a> {
+    dishes: Vec<(&'a Dish, Nutrients, f64)>,
+    limits: Nutrients,
+    /// The smallest value of each nutrient among the dishes
+    lightest: Nutrients,
+    limit: usize,
+    /// The best combinations so far, ranked
+    best: Vec<Found>,
+}
+
+impl Search<'_> {
+
+    /// The share the worst of the best combinations uses, once there are enough of them
+    fn threshold(&self) -> Option<f64> {
+        match self.best.len() < self.limit {
+            true => None,
+            false => self.best.last().map(|found| found.share),
+        }
+    }
+
+    /// Adds the dishes from position `from` on to the chosen ones
+    fn branch(&mut self, chosen: &mut Vec<usize>, totals: Nutrients, share: f64, from: usize) {
+        if chosen.len() == COURSES {
+            let mut dishes = [0; COURSES];
+            dishes.copy_from_slice(chosen);
+            let found = Found { share, dishes };
+            let position = self.best.binary_search_by(|other| rank(other, &found)).unwrap_or_else(|position| position);
+            self.best.insert(position, found);
+            self.best.truncate(self.limit);
+            return
+        }
+
+        let remaining = COURSES - chosen.len();
+        for index in from..self.dishes.len() {
+            if self.dishes.len() - index < remaining {
+                return
+            }
+            let (_, nutrients, dish_share) = self.dishes[index];
+
+            /// The dishes are sorted by their share, so the next ones are the largest that are left
+            let bound = share + self.dishes[index..index + remaining].iter().map(|(_, _, share)| share).sum::<f64>();
+            if self.threshold().is_some_and(|threshold| bound <= threshold) {
+                return
+            }
+            let totals = totals + nutrients;
+            if !(totals + self.lightest * (remaining - 1) as f64).exceeded(&self.limits).is_empty() {
+                continue
+            }
+            chosen.push(index);
+            self.branch(chosen, totals, share + dish_share, index + 1);
+            chosen.pop();
+        }
+    }
+}
+
+/// # Suggests the combinations of three different dishes closest to the limits of a diet
+/// The dish with the most calories is suggested as the main, the sweetest of the other two as the dessert
+/// ## Arguments
+/// * `dishes` - The dishes to combine
+/// * `limits` - The limits of the diet
+/// * `limit` - The number of suggestions
+/// ## Returns
+/// * At most `limit` [Suggestion]s within the limits, the closest first
+pub fn suggest(dishes: &[Dish], limits: Nutrients, limit: usize) -> Vec<Suggestion> {
+
+    /// Dishes exceeding a limit on their own are never part of a suggestion
+    let mut candidates: Vec<(&Dish, Nutrients, f64)> = dishes.iter()
+        .map(|dish| (dish, Nutrients::from(dish)))
+        .filter(|(_, nutrients)| nutrients.exceeded(&limits).is_empty())
+        .map(|(dish, nutrients)| (dish, nutrients, share(&nutrients, &limits)))
+        .collect();
+    candidates.sort_by(|a, b| b.2.total_cmp(&a.2).then_with(|| a.0.ID.cmp(&b.0.ID)));
+    let lightest = candidates.iter().fold(
+        Nutrients { cal: f64::INFINITY, sodium: f64::INFINITY, sugar: f64::INFINITY },
+        |lightest, (_, nutrients, _)| lightest.min(nutrients),
+    );
+
+    let mut search = Search { dishes: candidates, limits, lightest, limit, best: Vec::new() };
+    if limit > 0 {
+        search.branch(&mut Vec::with_capacity(COURSES), Nutrients::default(), 0.0, 0);
+    }
+
+    search.best.iter().map(|found| {
+        let mut courses: Vec<&Dish> = found.dishes.iter().map(|index| search.dishes[*index].0).collect();
+        courses.sort_by(|a, b| a.cal.total_cmp(&b.cal).then_with(|| a.ID.cmp(&b.ID)));
+        let main = courses.pop().expect("three dishes");
+        courses.sort_by(|a, b| a.sugar.total_cmp(&b.sugar).then_with(|| a.ID.cmp(&b.ID)));
+        let (appetizer, dessert) = (courses[0], courses[1]);
+        Suggestion {
+            appetizer: appetizer.ID,
+            main: main.ID,
+            dessert: dessert.ID,
+            name: format!("{}, {} and {}", appetizer.name, main.name, dessert.name),
+            totals: [appetizer, main, dessert].into_iter().map(Nutrients::from).sum(),
+            distance: ((COURSES as f64) - found.share).max(0.0),
+        }
+    }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dish(id: i32, name: &str, cal: f64, sodium: f64, sugar: f64) -> Dish {
+        Dish { ID: id, name: name.to_string(), cal, sodium, sugar, size: 100.0 }
+    }
+
+    /// Every combination within the limits, ranked like the search, to compare against
+    fn brute_force(dishes: &[Dish], limits: Nutrients) -> Vec<[i32; 3]> {
+        let mut all = Vec::new();
+        for (i, a) in dishes.iter().enumerate() {
+            for (j, b) in dishes.iter().enumerate().skip(i + 1) {
+                for c in dishes.iter().skip(j + 1) {
+                    let totals: Nutrients = [a, b, c].into_iter().map(Nutrients::from).sum();
+                    if totals.exceeded(&limits).is_empty() {
+                        let mut ids = [a.ID, b.ID, c.ID];
+                        ids.sort_unstable();
+                        all.push((share(&totals, &limits), ids));
+                    }
+                }
+            }
+        }
+        all.sort_by(|a, b| b.0.total_cmp(&a.0));
+        all.into_iter().map(|(_, ids)| ids).collect()
+    }
+
+    #[test]
+    fn the_closest_combinations_come_first() {
+        let dishes = [
+            dish(1, "soup", 150.0, 600.0, 2.0),
+            dish(2, "steak", 700.0, 300.0, 0.0),
+            dish(3, "cake", 400.0, 200.0, 40.0),
+            dish(4, "salad", 80.0, 100.0, 3.0),
+            dish(5, "pasta", 650.0, 500.0, 5.0),
+            dish(6, "feast", 5000.0, 10.0, 10.0),
+            dish(7, "fruit", 60.0, 5.0, 15.0),
+        ];
+        let limits = Nutrients { cal: 1500.0, sodium: 1200.0, sugar: 50.0 };
+        let suggestions = suggest(&dishes, limits, 4);
+        let expected = brute_force(&dishes, limits);
+
+        assert_eq!(suggestions.len(), 4);
+        for (suggestion, expected) in suggestions.iter().zip(&expected) {
+            let mut ids = [suggestion.appetizer, suggestion.main, suggestion.dessert];
+            ids.sort_unstable();
+            assert_eq!(&ids, expected);
+            assert!(suggestion.totals.exceeded(&limits).is_empty());
+        }
+        assert!(suggestions.windows(2).all(|pair| pair[0].distance <= pair[1].distance));
+
+        /// The heaviest dish is the main, the sweeter of the others the dessert
+        let best = &suggestions[0];
+        assert_eq!((best.appetizer, best.main, best.dessert), (1, 2, 3));
+        assert_eq!(best.name, "soup, steak and cake");
+        assert_eq!(best.totals, Nutrients { cal: 1250.0, sodium: 1100.0, sugar: 42.0 });
+    }
+
+    #[test]
+    fn nothing_is_suggested_without_three_fitting_dishes() {
+        let dishes = [dish(1, "soup", 150.0, 600.0, 2.0), dish(2, "steak", 700.0, 300.0, 0.0), dish(3, "feast", 5000.0, 10.0, 10.0)];
+        let limits = Nutrients { cal: 1500.0, sodium: 1200.0, sugar: 50.0 };
+        assert!(suggest(&dishes, limits, 10).is_empty());
+        assert!(suggest(&dishes[..2], limits, 0).is_empty());
+    }
+}
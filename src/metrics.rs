@@ -0,0 +1,188 @@
+#![allow(unused_doc_comments)]
+//! This file contains the Prometheus metrics of both Services
+//!
+//! The metrics are kept in a process wide [Metrics] registry, see [metrics].
+//! [track_requests] is wrapped around the `App` of each Service and records every request,
+//! the clients of the external services record their calls, errors and cache hits,
+//! and the `/metrics` route renders everything in the Prometheus text format, see [metrics_route]
+
+/// Actix imports
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::middleware::Next;
+use actix_web::{get, web, Error, HttpResponse, Responder};
+
+/// Misc imports
+use prometheus::{Encoder, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGauge, IntGaugeVec, Opts, Registry, TextEncoder};
+use std::sync::LazyLock;
+use std::time::Instant;
+use tracing::{error};
+
+/// Crate imports
+use crate::db::DbPool;
+
+/// Route label of requests that did not match any route, so unknown paths do not create new series
+const UNMATCHED_ROUTE: &str = "unmatched";
+
+/// The metrics of a Service
+pub struct Metrics {
+    registry: Registry,
+    /// Handled requests by method, route pattern and status code
+    pub http_requests: IntCounterVec,
+    /// Request latency by method and route pattern
+    pub http_request_duration: HistogramVec,
+    /// Connections of the database pool by state, `idle` or `in_use`
+    pub db_pool_connections: IntGaugeVec,
+    /// Maximum size of the database pool
+    pub db_pool_max_connections: IntGauge,
+    /// Calls to the nutrition provider by outcome, `ok`, `empty` or `error`
+    pub nutrition_provider_calls: IntCounterVec,
+    /// Failed diet lookups by kind, `not_found`, `timeout`, `unavailable` or `not_configured`
+    pub diet_client_errors: IntCounterVec,
+    /// Diet lookups answered from the cache instead of the Diets Service
+    pub diet_client_cache_hits: IntCounter,
+}
+
+impl Metrics {
+
+    /// Creates and registers all metrics
+    fn new() -> Result<Metrics, prometheus::Error> {
+        let registry = Registry::new();
+
+        let http_requests = IntCounterVec::new(
+            Opts::new("http_requests_total", "Handled HTTP requests"),
+            &["method", "route", "status"],
+        )?;
+        let http_request_duration = HistogramVec::new(
+            HistogramOpts::new("http_request_duration_seconds", "Latency of the HTTP requests"),
+            &["method", "route"],
+        )?;
+        let db_pool_connections = IntGaugeVec::new(
+            Opts::new("db_pool_connections", "Connections of the database pool"),
+            &["state"],
+        )?;
+        let db_pool_max_connections = IntGauge::new("db_pool_max_connections", "Maximum size of the database pool")?;
+        let nutrition_provider_calls = IntCounterVec::new(
+            Opts::new("nutrition_provider_calls_total", "Calls to the nutrition provider"),
+            &["outcome"],
+        )?;
+        let diet_client_errors = IntCounterVec::new(
+            Opts::new("diet_client_errors_total", "Failed diet lookups in the Diets Service"),
+            &["kind"],
+        )?;
+        let diet_client_cache_hits = IntCounter::new("diet_client_cache_hits_total", "Diet lookups answered from the cache")?;
+
+        registry.register(Box::new(http_requests.clone()))?;
+        registry.register(Box::new(http_request_duration.clone()))?;
+        registry.register(Box::new(db_pool_connections.clone()))?;
+        registry.register(Box::new(db_pool_max_connections.clone()))?;
+        registry.register(Box::new(nutrition_provider_calls.clone()))?;
+        registry.register(Box::new(diet_client_errors.clone()))?;
+        registry.register(Box::new(diet_client_cache_hits.clone()))?;
+
+        Ok(Metrics {
+            registry,
+            http_requests,
+            http_request_duration,
+            db_pool_connections,
+            db_pool_max_connections,
+            nutrition_provider_calls,
+            diet_client_errors,
+            diet_client_cache_hits,
+        })
+    }
+
+    /// Updates the pool gauges from the current state of the pool
+    pub fn observe_pool(&self, pool: &DbPool) {
+        let state = pool.state();
+        self.db_pool_connections.with_label_values(&["idle"]).set(state.idle_connections as i64);
+        self.db_pool_connections.with_label_values(&["in_use"]).set((state.connections - state.idle_connections) as i64);
+        self.db_pool_max_connections.set(pool.max_size() as i64);
+    }
+
+    /// Renders all metrics in the Prometheus text format
+    pub fn render(&self) -> Result<String, prometheus::Error> {
+        let mut buffer = Vec::new();
+        TextEncoder::new().encode(&self.registry.gather(), &mut buffer)?;
+        Ok(String::from_utf8_lossy(&buffer).into_owned())
+    }
+}
+
+/// The process wide metrics
+static METRICS: LazyLock<Metrics> = LazyLock::new(|| Metrics::new().expect("The metric definitions are valid"));
+
+/// Returns the process wide [Metrics]
+pub fn metrics() -> &'static Metrics {
+    &METRICS
+}
+
+/// # Middleware recording the count and latency of every request
+/// Requests are labelled with their route pattern, e.g. `/dishes/{id}`, instead of the path,
+/// so the number of series does not grow with the IDs and names in the paths
+/// ## Usage
+/// `App::new().wrap(actix_web::middleware::from_fn(track_requests))`
+pub async fn track_requests(req: ServiceRequest, next: Next<impl MessageBody>) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let started = Instant::now();
+    let method = req.method().to_string();
+    let res = next.call(req).await?;
+
+    let route = res.request().match_pattern().unwrap_or_else(|| UNMATCHED_ROUTE.to_string());
+    let status = res.status().as_u16().to_string();
+    metrics().http_requests.with_label_values(&[&method, &route, &status]).inc();
+    metrics().http_request_duration.with_label_values(&[&method, &route]).observe(started.elapsed().as_secs_f64());
+    Ok(res)
+}
+
+/*
+=============================== GET /metrics ===============================
+ */
+///
+/// # Creates the route for the Prometheus metrics in "/metrics"
+/// ## Arguments
+/// * `db_pool` - A [web::Data<DbPool>] containing the connection pool, whose utilization is reported
+/// ## Returns
+/// * [HttpResponse::Ok] with the metrics in the Prometheus text format
+#[utoipa::path(
+    get,
+    path = "/metrics",
+    tag = "health",
+    responses(
+        (status = 200, description = "The metrics in the Prometheus text format", content_type = "text/plain"),
+    ),
+)]
+#[get("/metrics")]
+pub async fn metrics_route(db_pool: web::Data<DbPool>) -> impl Responder {
+    metrics().observe_pool(&db_pool);
+    match metrics().render() {
+        Ok(body) => HttpResponse::Ok().content_type(TextEncoder::new().format_type()).body(body),
+        Err(e) => {
+            error!("{}", e);
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::middleware::from_fn;
+    use actix_web::{test, App};
+
+    #[actix_web::test]
+    async fn requests_are_counted_by_route_pattern() {
+        let app = test::init_service(App::new()
+            .wrap(from_fn(track_requests))
+            .route("/things/{id}", web::get().to(HttpResponse::Ok))).await;
+        let before = metrics().http_requests.with_label_values(&["GET", "/things/{id}", "200"]).get();
+
+        for id in 1..=3 {
+            test::call_service(&app, test::TestRequest::get().uri(&format!("/things/{}", id)).to_request()).await;
+        }
+        test::call_service(&app, test::TestRequest::get().uri("/unknown").to_request()).await;
+
+        assert_eq!(metrics().http_requests.with_label_values(&["GET", "/things/{id}", "200"]).get(), before + 3);
+        assert!(metrics().http_requests.with_label_values(&["GET", UNMATCHED_ROUTE, "404"]).get() >= 1);
+        let rendered = metrics().render().unwrap();
+        assert!(rendered.contains("http_request_duration_seconds_bucket{method=\"GET\",route=\"/things/{id}\""));
+    }
+}
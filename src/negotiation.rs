@@ -0,0 +1,197 @@
+#![allow(unused_doc_comments)]
+//! This file contains the content negotiation used by both Services
+//!
+//! Request bodies and response bodies can be encoded as JSON, MessagePack or CBOR.
+//! The format of a request body is taken from its `Content-Type` header, see [request_format],
+//! the format of a response body from the `Accept` header of the request, see [response_format].
+//! JSON stays the default whenever the client does not state a preference.
+
+/// Actix imports
+use actix_web::{HttpMessage, HttpRequest, HttpResponse, HttpResponseBuilder};
+use actix_web::http::header::{self, Accept, Header};
+use actix_web::mime::{self, Mime};
+
+/// Misc imports
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use tracing::{error};
+
+/// The body formats supported by the Services
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BodyFormat {
+    Json,
+    MessagePack,
+    Cbor,
+}
+
+impl BodyFormat {
+
+    /// Maps a parsed media type onto a [BodyFormat], ignoring its parameters
+    /// Returns [None] if the media type is not supported
+    fn from_mime(media_type: &Mime) -> Option<BodyFormat> {
+        match (media_type.type_(), media_type.subtype().as_str(), media_type.suffix()) {
+            (mime::APPLICATION, "json", _) => Some(BodyFormat::Json),
+            (mime::APPLICATION, _, Some(mime::JSON)) => Some(BodyFormat::Json),
+            (mime::APPLICATION, "msgpack" | "x-msgpack" | "vnd.msgpack", _) => Some(BodyFormat::MessagePack),
+            (mime::APPLICATION, "cbor", _) => Some(BodyFormat::Cbor),
+            _ => None,
+        }
+    }
+
+    /// The `Content-Type` written on responses in this format
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            BodyFormat::Json => "application/json",
+            BodyFormat::MessagePack => "application/msgpack",
+            BodyFormat::Cbor => "application/cbor",
+        }
+    }
+}
+
+/// # Gets the format of the request body
+/// Parses the `Content-Type` header including its parameters, so `application/json; charset=utf-8` is accepted
+/// ## Arguments
+/// * `req` - The [HttpRequest]
+/// ## Returns
+/// * The [BodyFormat] of the body, or [None] if the header is missing, malformed, names an unsupported media type
+///   or a charset other than UTF-8
+pub fn request_format(req: &HttpRequest) -> Option<BodyFormat> {
+    let media_type = match req.mime_type() {
+        Ok(Some(media_type)) => media_type,
+        _ => return None,
+    };
+
+    /// Only UTF-8 is supported for JSON bodies, the binary formats do not have a charset
+    if let Some(charset) = media_type.get_param(mime::CHARSET) {
+        if charset != mime::UTF_8 {
+            return None
+        }
+    }
+
+    BodyFormat::from_mime(&media_type)
+}
+
+/// # Gets the format of the response body
+/// Picks the most preferred supported media type of the `Accept` header
+/// ## Arguments
+/// * `req` - The [HttpRequest]
+/// ## Returns
+/// * The preferred [BodyFormat], [BodyFormat::Json] if the header is missing or names no supported media type
+pub fn response_format(req: &HttpRequest) -> BodyFormat {
+    let accept = match Accept::parse(req) {
+        Ok(accept) => accept,
+        Err(_) => return BodyFormat::Json,
+    };
+
+    for media_type in accept.ranked() {
+        if media_type.type_() == mime::STAR || media_type.subtype() == mime::STAR {
+            return BodyFormat::Json
+        }
+        if let Some(format) = BodyFormat::from_mime(&media_type) {
+            return format
+        }
+    }
+
+    BodyFormat::Json
+}
+
+/// # Decodes a request body
+/// ## Arguments
+/// * `format` - The [BodyFormat] of the body as returned by [request_format]
+/// * `body` - The raw bytes of the body
+/// ## Returns
+/// * The decoded value or a message describing why decoding failed
+pub fn decode_body<T: DeserializeOwned>(format: BodyFormat, body: &[u8]) -> Result<T, String> {
+    match format {
+        BodyFormat::Json => serde_json::from_slice(body).map_err(|e| e.to_string()),
+        BodyFormat::MessagePack => rmp_serde::from_slice(body).map_err(|e| e.to_string()),
+        BodyFormat::Cbor => ciborium::from_reader(body).map_err(|e| e.to_string()),
+    }
+}
+
+/// Encodes a value in the given format
+fn encode_body<T: Serialize>(format: BodyFormat, value: &T) -> Result<Vec<u8>, String> {
+    match format {
+        BodyFormat::Json => serde_json::to_vec(value).map_err(|e| e.to_string()),
+        BodyFormat::MessagePack => rmp_serde::to_vec_named(value).map_err(|e| e.to_string()),
+        BodyFormat::Cbor => {
+            let mut body = Vec::new();
+            ciborium::into_writer(value, &mut body).map_err(|e| e.to_string())?;
+            Ok(body)
+        }
+    }
+}
+
+/// # Creates a response in the format the client prefers
+/// Replaces [HttpResponseBuilder::json] for responses that carry data
+/// ## Arguments
+/// * `req` - The [HttpRequest], whose `Accept` header selects the format
+/// * `builder` - The [HttpResponseBuilder] with the status of the response, e.g. [HttpResponse::Ok]
+/// * `value` - The value to serialize into the body
+/// ## Returns
+/// * The finished [HttpResponse], or a [HttpResponse::InternalServerError] if the value could not be serialized
+pub fn negotiate<T: Serialize>(req: &HttpRequest, mut builder: HttpResponseBuilder, value: &T) -> HttpResponse {
+    let format = response_format(req);
+    match encode_body(format, value) {
+        Ok(body) => builder
+            .content_type(format.content_type())
+            .insert_header((header::VARY, "Accept"))
+            .body(body),
+        Err(e) => {
+            error!("{}", e);
+            HttpResponse::InternalServerError().body(e)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::test::TestRequest;
+
+    fn content_type(value: &str) -> Option<BodyFormat> {
+        request_format(&TestRequest::default().insert_header((header::CONTENT_TYPE, value)).to_http_request())
+    }
+
+    fn accept(value: &str) -> BodyFormat {
+        response_format(&TestRequest::default().insert_header((header::ACCEPT, value)).to_http_request())
+    }
+
+    #[test]
+    fn request_formats_follow_the_content_type() {
+        assert_eq!(content_type("application/json"), Some(BodyFormat::Json));
+        assert_eq!(content_type("application/json; charset=utf-8"), Some(BodyFormat::Json));
+        assert_eq!(content_type("application/json;charset=UTF-8"), Some(BodyFormat::Json));
+        assert_eq!(content_type("application/problem+json"), Some(BodyFormat::Json));
+        assert_eq!(content_type("application/msgpack"), Some(BodyFormat::MessagePack));
+        assert_eq!(content_type("application/x-msgpack"), Some(BodyFormat::MessagePack));
+        assert_eq!(content_type("application/cbor"), Some(BodyFormat::Cbor));
+    }
+
+    #[test]
+    fn unsupported_request_formats_are_refused() {
+        assert_eq!(content_type("application/json; charset=iso-8859-1"), None);
+        assert_eq!(content_type("text/plain"), None);
+        assert_eq!(content_type("not a media type"), None);
+        assert_eq!(request_format(&TestRequest::default().to_http_request()), None);
+    }
+
+    #[test]
+    fn response_formats_follow_the_accept_header() {
+        assert_eq!(accept("application/cbor"), BodyFormat::Cbor);
+        assert_eq!(accept("application/msgpack;q=0.9, application/cbor;q=0.5"), BodyFormat::MessagePack);
+        assert_eq!(accept("text/html, application/cbor;q=0.1"), BodyFormat::Cbor);
+        assert_eq!(accept("*/*"), BodyFormat::Json);
+        assert_eq!(accept("text/html"), BodyFormat::Json);
+        assert_eq!(response_format(&TestRequest::default().to_http_request()), BodyFormat::Json);
+    }
+
+    #[test]
+    fn bodies_survive_every_format() {
+        for format in [BodyFormat::Json, BodyFormat::MessagePack, BodyFormat::Cbor] {
+            let body = encode_body(format, &vec![1, 2, 3]).unwrap();
+            assert_eq!(decode_body::<Vec<i32>>(format, &body).unwrap(), vec![1, 2, 3]);
+        }
+        assert!(decode_body::<Vec<i32>>(BodyFormat::Json, b"{").is_err());
+    }
+}
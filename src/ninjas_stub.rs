@@ -0,0 +1,98 @@
+#![allow(unused_doc_comments)]
+
+//! This file contains a stub of the Ninjas API, so the Meals Service can be run and tested without an API key
+//!
+//! The stub answers `GET /v1/nutrition?query=` from a fixtures file, see [meals_api::stub::Fixtures],
+//! and can be scripted to be slow, to fail with 429 or 500, or to return empty results.
+//!
+//! Usage:
+//! * `ninjas_stub [--fixtures FILE] [--host HOST] [--port PORT] [--latency-ms MS]`
+//!
+//! The Meals Service then uses it with `NINJAS_API_BASE_URL=http://localhost:8003/v1/nutrition?query=`
+
+use actix_web::{App, HttpServer};
+use actix_web::middleware::from_fn;
+use actix_web::web::Data;
+use std::process::exit;
+use meals_api::stub::{configure, Fixtures, NinjasStub};
+use meals_api::telemetry::{init_logging, trace_requests};
+use tracing::info;
+
+const USAGE: &str = "Usage:
+    ninjas_stub [--fixtures FILE] [--host HOST] [--port PORT] [--latency-ms MS]
+
+The fixtures default to fixtures/ninjas.json, or NINJAS_STUB_FIXTURES if it is set.
+--latency-ms overrides the latency added to every answer by the fixtures file.";
+
+/// Settings of the stub, see [Options::parse]
+struct Options {
+    fixtures: String,
+    host: String,
+    port: u16,
+    latency_ms: Option<u64>,
+}
+
+impl Options {
+
+    /// Parses the command line arguments into [Options]
+    fn parse(args: &[String]) -> Result<Options, String> {
+        let mut options = Options {
+            fixtures: std::env::var("NINJAS_STUB_FIXTURES").unwrap_or_else(|_| "fixtures/ninjas.json".to_string()),
+            host: "0.0.0.0".to_string(),
+            port: 8003,
+            latency_ms: None,
+        };
+
+        let mut args = args.iter();
+        while let Some(flag) = args.next() {
+            let value = args.next().ok_or_else(|| format!("{} expects a value", flag))?;
+            match flag.as_str() {
+                "--fixtures" => options.fixtures = value.clone(),
+                "--host" => options.host = value.clone(),
+                "--port" => options.port = value.parse().map_err(|_| format!("--port expects a port number, got `{}`", value))?,
+                "--latency-ms" => options.latency_ms = Some(value.parse().map_err(|_| format!("--latency-ms expects a number, got `{}`", value))?),
+                _ => return Err(format!("Unknown argument `{}`", flag)),
+            }
+        }
+        Ok(options)
+    }
+}
+
+/// Main function
+#[actix_web::main]
+async fn main() -> std::io::Result<()> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let options = match Options::parse(&args) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, USAGE);
+            exit(2);
+        }
+    };
+
+    /// Load the fixtures once, they are shared by all workers together with the progress of the scripts
+    let mut fixtures = match Fixtures::load(&options.fixtures) {
+        Ok(fixtures) => fixtures,
+        Err(e) => {
+            eprintln!("{}", e);
+            exit(1);
+        }
+    };
+    if let Some(latency_ms) = options.latency_ms {
+        fixtures.latency_ms = latency_ms;
+    }
+
+    init_logging();
+    info!(fixtures = %options.fixtures, dishes = fixtures.dishes.len(), "Serving the Ninjas API stub");
+
+    let stub = Data::new(NinjasStub::new(fixtures));
+    HttpServer::new(move || {
+        App::new()
+            .wrap(from_fn(trace_requests))
+            .app_data(stub.clone())
+            .configure(configure)
+    })
+        .bind((options.host.as_str(), options.port))?
+        .run()
+        .await
+}
@@ -0,0 +1,198 @@
+#![allow(unused_doc_comments)]
+//! This file contains the OpenAPI helpers used by both Services
+//!
+//! Each Service describes its routes with `#[utoipa::path]` attributes on the route handlers and collects them
+//! in an [utoipa::OpenApi] document. The document is served at `/openapi.json` together with a Swagger UI at `/docs/`,
+//! see [swagger_ui]
+
+/// Misc imports
+use utoipa::openapi::security::{ApiKey, ApiKeyValue, HttpAuthScheme, HttpBuilder, SecurityScheme};
+use utoipa::openapi::{OpenApi, ResponseBuilder};
+use utoipa_swagger_ui::SwaggerUi;
+
+/// Media types every JSON body can also be exchanged in, see [crate::negotiation]
+const BINARY_FORMATS: [&str; 2] = ["application/msgpack", "application/cbor"];
+
+/// # Adds the negotiated body formats to an OpenAPI document
+/// The `#[utoipa::path]` attributes only describe the JSON bodies, this copies the schema of every JSON
+/// request and response body to the MessagePack and CBOR media types
+/// ## Arguments
+/// * `doc` - The generated [OpenApi] document
+/// ## Returns
+/// * The [OpenApi] document with the additional media types
+pub fn with_negotiated_formats(mut doc: OpenApi) -> OpenApi {
+    for path_item in doc.paths.paths.values_mut() {
+        let operations = [&mut path_item.get, &mut path_item.post, &mut path_item.put, &mut path_item.delete];
+        for operation in operations.into_iter().flatten() {
+            if let Some(request_body) = operation.request_body.as_mut() {
+                if let Some(json) = request_body.content.get("application/json").cloned() {
+                    for format in BINARY_FORMATS {
+                        request_body.content.insert(format.to_string(), json.clone());
+                    }
+                }
+            }
+            for response in operation.responses.responses.values_mut() {
+                if let utoipa::openapi::RefOr::T(response) = response {
+                    if let Some(json) = response.content.get("application/json").cloned() {
+                        for format in BINARY_FORMATS {
+                            response.content.insert(format.to_string(), json.clone());
+                        }
+                    }
+                }
+            }
+        }
+    }
+    doc
+}
+
+/// # Adds the authentication to an OpenAPI document
+/// Declares the `api_key` and `bearer` security schemes of [crate::auth], which the `#[utoipa::path]` attributes
+/// of the protected routes refer to with the required role as scope, and adds the 401 and 403 responses to these routes
+/// ## Arguments
+/// * `doc` - The generated [OpenApi] document
+/// ## Returns
+/// * The [OpenApi] document with the security schemes
+pub fn with_security(mut doc: OpenApi) -> OpenApi {
+    let components = doc.components.get_or_insert_with(Default::default);
+    components.add_security_scheme("api_key", SecurityScheme::ApiKey(ApiKey::Header(ApiKeyValue::new("X-Api-Key"))));
+    components.add_security_scheme("bearer", SecurityScheme::Http(HttpBuilder::new().scheme(HttpAuthScheme::Bearer).bearer_format("JWT").build()));
+
+    for path_item in doc.paths.paths.values_mut() {
+        let operations = [&mut path_item.get, &mut path_item.post, &mut path_item.put, &mut path_item.delete];
+        for operation in operations.into_iter().flatten() {
+            if operation.security.as_ref().is_none_or(|security| security.is_empty()) {
+                continue;
+            }
+            let responses = &mut operation.responses.responses;
+            responses.insert("401".to_string(), ResponseBuilder::new().description("Credentials missing or invalid, error code -9").build().into());
+            responses.insert("403".to_string(), ResponseBuilder::new().description("The credentials lack the required role, error code -10").build().into());
+        }
+    }
+    doc
+}
+
+/// # Creates the documentation service
+/// ## Arguments
+/// * `doc` - The [OpenApi] document of the Service
+/// ## Returns
+/// * A [SwaggerUi] service serving the document at `/openapi.json` and the UI at `/docs/`
+pub fn swagger_ui(doc: OpenApi) -> SwaggerUi {
+    SwaggerUi::new("/docs/{_:.*}").url("/openapi.json", with_security(with_negotiated_formats(doc)))
+}
+
+/// Strips the regular expressions from an actix path template, so `/dishes/{id:\\d+}` becomes `/dishes/{id}`
+#[cfg(test)]
+fn strip_patterns(path: &str) -> String {
+    let mut stripped = String::with_capacity(path.len());
+    let mut depth = 0;
+    let mut in_pattern = false;
+    for c in path.chars() {
+        match c {
+            '{' => {
+                if depth == 0 {
+                    stripped.push('{');
+                }
+                depth += 1;
+            }
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    stripped.push('}');
+                    in_pattern = false;
+                }
+            }
+            ':' if depth == 1 => in_pattern = true,
+            _ if depth == 0 => stripped.push(c),
+            _ if !in_pattern => stripped.push(c),
+            _ => {}
+        }
+    }
+    stripped
+}
+
+/// Returns the operation of a path item for a lower case HTTP method
+#[cfg(test)]
+fn operation_of<'a>(path_item: &'a utoipa::openapi::PathItem, method: &str) -> Option<&'a utoipa::openapi::path::Operation> {
+    match method {
+        "get" => path_item.get.as_ref(),
+        "post" => path_item.post.as_ref(),
+        "put" => path_item.put.as_ref(),
+        "delete" => path_item.delete.as_ref(),
+        "patch" => path_item.patch.as_ref(),
+        _ => None,
+    }
+}
+
+/// # Reads the sources that may declare the routes of a Service
+/// ## Arguments
+/// * `modules` - The module directories below `src` belonging to the Service, the files directly in `src`
+///   such as the health and metrics routes are shared by both Services and always read
+/// ## Returns
+/// * The contents of every Rust file found
+#[cfg(test)]
+pub fn route_sources(modules: &[&str]) -> Vec<String> {
+    fn read_dir(dir: &std::path::Path, recursive: bool, sources: &mut Vec<String>) {
+        let mut entries: Vec<_> = std::fs::read_dir(dir).unwrap().map(|entry| entry.unwrap().path()).collect();
+        entries.sort();
+        for path in entries {
+            if path.is_dir() {
+                if recursive {
+                    read_dir(&path, true, sources);
+                }
+            } else if path.extension().is_some_and(|extension| extension == "rs") {
+                sources.push(std::fs::read_to_string(&path).unwrap());
+            }
+        }
+    }
+
+    let src = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("src");
+    let mut sources = Vec::new();
+    read_dir(&src, false, &mut sources);
+    for module in modules {
+        read_dir(&src.join(module), true, &mut sources);
+    }
+    sources
+}
+
+/// # Finds the routes that are missing from an OpenAPI document
+/// Scans the given Rust sources for actix route attributes such as `#[get("/dishes/{id:\\d+}")]` and the handler
+/// following them, and checks that the document describes the same method on the same path without its regular
+/// expressions, with the handler as operation id. Routes sharing a path template such as `/dishes/{id:\\d+}` and
+/// `/dishes/{name:.*}` therefore each need their own description
+/// ## Arguments
+/// * `sources` - The contents of the files declaring the routes, see [route_sources]
+/// * `doc` - The [OpenApi] document of the Service
+/// ## Returns
+/// * The routes without an operation in the document, formatted as `METHOD /path handler`
+#[cfg(test)]
+pub fn undocumented_routes(sources: &[String], doc: &OpenApi) -> Vec<String> {
+    let mut missing = Vec::new();
+    for source in sources {
+        let mut route: Option<(&str, String)> = None;
+        for line in source.lines() {
+            let line = line.trim();
+            for method in ["get", "post", "put", "delete", "patch"] {
+                let prefix = format!("#[{}(\"", method);
+                if let Some(path) = line.strip_prefix(&prefix).and_then(|rest| rest.split('"').next()) {
+                    route = Some((method, strip_patterns(path)));
+                }
+            }
+
+            let handler = match line.strip_prefix("pub async fn ").and_then(|rest| rest.split('(').next()) {
+                Some(handler) => handler,
+                None => continue,
+            };
+            let (method, path) = match route.take() {
+                Some(route) => route,
+                None => continue,
+            };
+            let is_documented = doc.paths.paths.get(&path)
+                .and_then(|path_item| operation_of(path_item, method))
+                .is_some_and(|operation| operation.operation_id.as_deref() == Some(handler));
+            if !is_documented {
+                missing.push(format!("{} {} {}", method.to_uppercase(), path, handler));
+            }
+        }
+    }
+    missing
+}
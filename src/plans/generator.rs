@@ -0,0 +1,272 @@
+#![allow(unused_doc_comments)]
+//! This file contains the search for a plan whose days stay within the limits of a diet
+//!
+//! Every slot of every day gets one of the existing meals. A day must not exceed the daily limits of the diet, so the
+//! weeks stay within the limits times their days as well. A meal is not repeated within the variety window,
+//! e.g. a window of 3 days allows a meal planned on Monday again on Thursday.
+//!
+//! The search fills the slots in order and backtracks when a day can not be completed. It prefers the meals
+//! that were planned least recently and gives up after [MAX_SEARCH_STEPS]. Before searching, the obvious reasons
+//! why no plan exists are looked for, so they can be reported instead of a failed search
+
+/// Misc imports
+use chrono::{Days, NaiveDate};
+use std::collections::HashMap;
+
+/// Module imports
+use super::models::{PlanEntry, Slot};
+
+/// Crate imports
+use crate::meals::{Meal, Nutrients};
+
+/// Number of meals tried before the search gives up
+pub const MAX_SEARCH_STEPS: usize = 100_000;
+
+/// What to generate a plan for
+pub struct Generation {
+    pub start: NaiveDate,
+    pub days: i32,
+    /// The slots to fill on every day, each at most once
+    pub slots: Vec<Slot>,
+    /// Number of consecutive days in which a meal is planned at most once, at least 1
+    pub variety_days: u32,
+}
+
+/// Why no plan could be generated
+#[derive(Debug, PartialEq)]
+pub struct Infeasible {
+    pub reasons: Vec<String>,
+}
+
+/// A candidate in the order it is tried in, with the day it was planned last
+type Candidate = (Option<usize>, i32, Nutrients);
+
+/// The state of the search, the slots are filled day by day
+struct Search<'a> {
+    generation: &'a Generation,
+    limits: Nutrients,
+    candidates: Vec<(i32, Nutrients)>,
+    /// The smallest value of each nutrient among the candidates, used to skip days that can not be completed
+    lightest: Nutrients,
+    /// The day each meal was planned last
+    last_planned: HashMap<i32, usize>,
+    /// The meal of each filled slot
+    planned: Vec<i32>,
+    steps: usize,
+}
+
+impl Search<'_> {
+
+    /// Whether the totals of a day stay within the limits even if its remaining slots get the lightest meals
+    fn completable(&self, totals: Nutrients, remaining: usize) -> bool {
+        (totals + self.lightest * remaining as f64).exceeded(&self.limits).is_empty()
+    }
+
+    /// The candidates allowed on a day by the variety window, the meals never planned first,
+    /// then the ones planned longest ago
+    fn day_order(&self, day: usize) -> Vec<Candidate> {
+        let window = self.generation.variety_days as usize;
+        let mut order: Vec<Candidate> = self.candidates.iter()
+            .map(|(id, nutrients)| (self.last_planned.get(id).copied(), *id, *nutrients))
+            .filter(|(last, _, _)| last.is_none_or(|last| day - last >= window))
+            .collect();
+        order.sort_by_key(|(last, id, _)| (*last, *id));
+        order
+    }
+
+    /// Fills the slots from the next one on, returns false if there is no way or the search gave up
+    /// ## Arguments
+    /// * `totals` - The totals of the slots filled so far on the current day
+    /// * `order` - The [Search::day_order] of the current day, computed anew on the first slot of every day
+    fn fill(&mut self, totals: Nutrients, order: &[Candidate]) -> bool {
+        let slots = self.generation.slots.len();
+        let cell = self.planned.len();
+        if cell == slots * self.generation.days as usize {
+            return true
+        }
+        let (day, slot) = (cell / slots, cell % slots);
+        let totals = if slot == 0 { Nutrients::default() } else { totals };
+
+        /// Only the first slot of a day sorts the candidates, the later slots skip the meals already planned on the day
+        let day_order;
+        let order = match slot {
+            0 => {
+                day_order = self.day_order(day);
+                &day_order
+            }
+            _ => order,
+        };
+
+        for &(last, id, nutrients) in order {
+            if self.last_planned.get(&id) == Some(&day) {
+                continue
+            }
+            if self.steps >= MAX_SEARCH_STEPS {
+                return false
+            }
+            self.steps += 1;
+            let day_totals = totals + nutrients;
+            if !self.completable(day_totals, slots - slot - 1) {
+                continue
+            }
+            self.planned.push(id);
+            self.last_planned.insert(id, day);
+            if self.fill(day_totals, order) {
+                return true
+            }
+            self.planned.pop();
+            match last {
+                Some(last) => self.last_planned.insert(id, last),
+                None => self.last_planned.remove(&id),
+            };
+        }
+        false
+    }
+}
+
+/// The sum of the `count` smallest values of a nutrient
+fn smallest_sum(candidates: &[(i32, Nutrients)], count: usize, value: fn(&Nutrients) -> f64) -> f64 {
+    let mut values: Vec<f64> = candidates.iter().map(|(_, nutrients)| value(nutrients)).collect();
+    values.sort_by(f64::total_cmp);
+    values.into_iter().take(count).sum()
+}
+
+/// The reasons that rule out a plan before searching
+fn obvious_reasons(generation: &Generation, limits: &Nutrients, meals: usize, candidates: &[(i32, Nutrients)]) -> Vec<String> {
+    let slots = generation.slots.len();
+    if meals == 0 {
+        return vec!["there are no meals to plan".to_string()]
+    }
+    if candidates.is_empty() {
+        return vec![format!("none of the {} meals is within the daily limits of the diet", meals)]
+    }
+
+    let mut reasons = Vec::new();
+    let window = (generation.variety_days as usize).min(generation.days as usize);
+    let needed = window * slots;
+    if candidates.len() < needed {
+        reasons.push(format!(
+            "{} different meals are needed to fill {} slots on {} days without repeating a meal, but only {} meals are within the daily limits of the diet",
+            needed, slots, window, candidates.len(),
+        ));
+    }
+    let count = slots.min(candidates.len());
+    let lightest = [
+        ("cal", smallest_sum(candidates, count, |n| n.cal), limits.cal),
+        ("sodium", smallest_sum(candidates, count, |n| n.sodium), limits.sodium),
+        ("sugar", smallest_sum(candidates, count, |n| n.sugar), limits.sugar),
+    ];
+    for (name, lightest, limit) in lightest {
+        if lightest > limit {
+            reasons.push(format!("the {} meals with the least {} already have {} together, above the daily limit of {}", slots, name, lightest, limit));
+        }
+    }
+    reasons
+}
+
+/// # Searches a plan whose days stay within the limits of a diet
+/// ## Arguments
+/// * `meals` - The meals to choose from
+/// * `limits` - The daily limits of the diet
+/// * `generation` - The days and slots to fill and the variety window
+/// ## Returns
+/// * The entries of the plan, one per slot of every day
+/// * [Infeasible] with the reasons if no plan was found
+pub fn generate_plan(meals: &[Meal], limits: Nutrients, generation: &Generation) -> Result<Vec<PlanEntry>, Infeasible> {
+
+    /// Only meals within the limits on their own can be part of a day
+    let candidates: Vec<(i32, Nutrients)> = meals.iter()
+        .map(|meal| (meal.ID, Nutrients::from(meal)))
+        .filter(|(_, nutrients)| nutrients.exceeded(&limits).is_empty())
+        .collect();
+    let reasons = obvious_reasons(generation, &limits, meals.len(), &candidates);
+    if !reasons.is_empty() {
+        return Err(Infeasible { reasons })
+    }
+
+    let lightest = candidates.iter().fold(
+        Nutrients { cal: f64::INFINITY, sodium: f64::INFINITY, sugar: f64::INFINITY },
+        |lightest, (_, nutrients)| lightest.min(nutrients),
+    );
+    let mut search = Search {
+        generation,
+        limits,
+        lightest,
+        last_planned: HashMap::new(),
+        planned: Vec::new(),
+        steps: 0,
+        candidates,
+    };
+    if !search.fill(Nutrients::default(), &[]) {
+        let reason = match search.steps >= MAX_SEARCH_STEPS {
+            true => format!("the search gave up after trying {} meals, fewer days, fewer slots or a shorter variety window may help", MAX_SEARCH_STEPS),
+            false => format!(
+                "no combination of the {} meals within the daily limits keeps every day within them without repeating a meal within {} days",
+                search.candidates.len(), generation.variety_days,
+            ),
+        };
+        return Err(Infeasible { reasons: vec![reason] })
+    }
+
+    let slots = generation.slots.len();
+    let entries = search.planned.iter().enumerate().map(|(cell, meal)| PlanEntry {
+        date: generation.start.checked_add_days(Days::new((cell / slots) as u64)).unwrap_or(generation.start),
+        slot: generation.slots[cell % slots],
+        meal: *meal,
+    }).collect();
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn meal(id: i32, cal: f64, sodium: f64) -> Meal {
+        Meal { ID: id, name: format!("meal {}", id), appetizer: None, main: None, dessert: None, cal: Some(cal), sodium: Some(sodium), sugar: Some(10.0) }
+    }
+
+    fn generation(days: i32, slots: &[Slot], variety_days: u32) -> Generation {
+        Generation { start: "2026-10-19".parse().unwrap(), days, slots: slots.to_vec(), variety_days }
+    }
+
+    const LIMITS: Nutrients = Nutrients { cal: 2000.0, sodium: 1500.0, sugar: 100.0 };
+
+    #[test]
+    fn days_stay_within_the_limits_without_repeats() {
+        /// Meal 1 and 2 together exceed the calories, so they must not share a day
+        let meals = [meal(1, 1200.0, 100.0), meal(2, 1000.0, 100.0), meal(3, 700.0, 100.0), meal(4, 600.0, 100.0), meal(5, 3000.0, 100.0)];
+        let generation = generation(4, &[Slot::Lunch, Slot::Dinner], 2);
+        let entries = generate_plan(&meals, LIMITS, &generation).unwrap();
+        assert_eq!(entries.len(), 8);
+
+        for day in generation.start.iter_days().take(4) {
+            let cal: f64 = entries.iter().filter(|entry| entry.date == day).map(|entry| Nutrients::from(&meals[entry.meal as usize - 1]).cal).sum();
+            assert!(cal <= LIMITS.cal, "{} has {} kcal", day, cal);
+        }
+        for (i, entry) in entries.iter().enumerate() {
+            assert_ne!(entry.meal, 5);
+            let repeated = entries[i + 1..].iter().any(|later| later.meal == entry.meal && (later.date - entry.date).num_days() < 2);
+            assert!(!repeated, "meal {} repeated within 2 days", entry.meal);
+        }
+        assert_eq!((entries[0].slot, entries[1].slot, entries[7].date.to_string()), (Slot::Lunch, Slot::Dinner, "2026-10-22".to_string()));
+    }
+
+    #[test]
+    fn infeasible_plans_are_explained() {
+        let meals = [meal(1, 1200.0, 100.0), meal(2, 1000.0, 100.0), meal(3, 900.0, 1450.0)];
+        let err = generate_plan(&[], LIMITS, &generation(1, &[Slot::Lunch], 1)).unwrap_err();
+        assert_eq!(err.reasons, ["there are no meals to plan"]);
+
+        /// Three slots need three meals a day, which exceed the calories in any combination
+        let err = generate_plan(&meals, LIMITS, &generation(1, &[Slot::Breakfast, Slot::Lunch, Slot::Dinner], 1)).unwrap_err();
+        assert!(err.reasons[0].starts_with("the 3 meals with the least cal"), "{:?}", err.reasons);
+
+        /// Two slots without repeats for three days need six meals
+        let err = generate_plan(&meals, LIMITS, &generation(3, &[Slot::Lunch, Slot::Dinner], 3)).unwrap_err();
+        assert!(err.reasons[0].starts_with("6 different meals are needed"), "{:?}", err.reasons);
+
+        /// Every pair exceeds a limit, which only the search finds out
+        let err = generate_plan(&meals, LIMITS, &generation(1, &[Slot::Lunch, Slot::Dinner], 1)).unwrap_err();
+        assert!(err.reasons[0].starts_with("no combination of the 3 meals"), "{:?}", err.reasons);
+    }
+}
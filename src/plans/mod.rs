@@ -0,0 +1,10 @@
+// Meal plans module, served by the Meals Service
+mod generator;
+mod models;
+mod plans_routes;
+mod repository;
+
+pub use generator::{generate_plan, Generation, Infeasible, MAX_SEARCH_STEPS};
+pub use models::{NewPlan, Plan, PlanEntry, PlanValidation, PlanView, ReqPlan, ReqPlanDiet, ReqPlanGeneration, ReqPlanSlot, Slot, MAX_PLAN_DAYS};
+pub use plans_routes::*;
+pub use repository::{DieselPlanRepository, InMemoryPlanRepository, PlanRepository};
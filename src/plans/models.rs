@@ -0,0 +1,243 @@
+#![allow(unused_doc_comments)]
+#![allow(non_snake_case)]
+
+use crate::food_log::{DaySummary, WeekSummary};
+use crate::meals::Nutrients;
+use chrono::{Days, NaiveDate};
+use serde::{Serialize, Deserialize};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::str::FromStr;
+use utoipa::{IntoParams, ToSchema};
+
+/// Longest plan in days
+pub const MAX_PLAN_DAYS: i32 = 28;
+
+/// The slots of a day a meal can be planned for
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Slot {
+    Breakfast,
+    Lunch,
+    Dinner,
+    Snack,
+}
+
+impl Slot {
+    pub const ALL: [Slot; 4] = [Slot::Breakfast, Slot::Lunch, Slot::Dinner, Slot::Snack];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Slot::Breakfast => "breakfast",
+            Slot::Lunch => "lunch",
+            Slot::Dinner => "dinner",
+            Slot::Snack => "snack",
+        }
+    }
+}
+
+impl FromStr for Slot {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Slot, String> {
+        Slot::ALL.into_iter().find(|slot| slot.as_str() == s).ok_or_else(|| format!("unknown slot `{}`", s))
+    }
+}
+
+impl fmt::Display for Slot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// The slots are stored as their names in a VARCHAR column, these conversions are used by Diesel
+impl TryFrom<String> for Slot {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Slot, String> {
+        s.parse()
+    }
+}
+
+impl From<Slot> for String {
+    fn from(slot: Slot) -> String {
+        slot.to_string()
+    }
+}
+
+/// A meal planned for a slot of a day
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct PlanEntry {
+    pub date: NaiveDate,
+    pub slot: Slot,
+    /// ID of the meal
+    pub meal: i32,
+}
+
+/// Plan struct to represent a meal plan of consecutive days
+/// The tenant of the plan is not part of it, the repositories filter by it
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct Plan {
+    pub ID: i32,
+    pub name: String,
+    /// The first day of the plan
+    pub start: NaiveDate,
+    /// Number of days of the plan
+    pub days: i32,
+    /// The planned meals, ordered by day and slot
+    pub entries: Vec<PlanEntry>,
+}
+
+impl Plan {
+
+    /// The days of the plan, in order
+    pub fn dates(&self) -> impl Iterator<Item = NaiveDate> {
+        self.start.iter_days().take(self.days as usize)
+    }
+
+    /// The totals of every day of the plan, meals without nutrients count as 0
+    /// ## Arguments
+    /// * `nutrients` - The nutrients of the meals by their ID
+    pub fn daily_totals(&self, nutrients: &HashMap<i32, Nutrients>) -> Vec<(NaiveDate, Nutrients)> {
+        self.dates().map(|date| {
+            let totals = self.entries.iter()
+                .filter(|entry| entry.date == date)
+                .filter_map(|entry| nutrients.get(&entry.meal).copied())
+                .sum();
+            (date, totals)
+        }).collect()
+    }
+}
+
+/// Struct to represent a new plan, or the new content of a plan when it is updated
+pub struct NewPlan {
+    pub name: String,
+    pub start: NaiveDate,
+    pub days: i32,
+    pub entries: Vec<PlanEntry>,
+}
+
+impl NewPlan {
+
+    /// Checks the length of the plan and that every entry is on one of its days with each slot planned once,
+    /// returns the problem otherwise
+    pub fn validate(&self) -> Result<(), String> {
+        if !(1..=MAX_PLAN_DAYS).contains(&self.days) {
+            return Err(format!("days must be between 1 and {}, not {}", MAX_PLAN_DAYS, self.days))
+        }
+        let end = self.start.checked_add_days(Days::new(self.days as u64)).ok_or("the plan ends after the last supported date")?;
+        let mut planned = HashSet::new();
+        for entry in &self.entries {
+            if entry.date < self.start || entry.date >= end {
+                return Err(format!("{} is not a day of the plan", entry.date))
+            }
+            if !planned.insert((entry.date, entry.slot)) {
+                return Err(format!("the {} of {} is planned more than once", entry.slot, entry.date))
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Struct that represents a plan requested by the user, for creation or an update
+/// The Values are optionals since a user might not include all of them and we need to be able to handle that
+/// Translates to a [NewPlan] if name, start and days are present, the entries default to none
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct ReqPlan {
+    pub name: Option<String>,
+    pub start: Option<NaiveDate>,
+    pub days: Option<i32>,
+    pub entries: Option<Vec<PlanEntry>>,
+}
+
+impl ReqPlan {
+
+    /// The [NewPlan], if all required values are present
+    pub fn into_plan(self) -> Option<NewPlan> {
+        Some(NewPlan {
+            name: self.name?,
+            start: self.start?,
+            days: self.days?,
+            entries: self.entries.unwrap_or_default(),
+        })
+    }
+}
+
+/// Struct that represents the meal requested for a slot of a plan
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct ReqPlanSlot {
+    pub meal: Option<i32>,
+}
+
+/// Struct that represents a plan to be generated for a diet
+/// The slots default to breakfast, lunch and dinner, the variety window to 1 day, i.e. no meal twice a day
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct ReqPlanGeneration {
+    /// Name the generated plan is stored with
+    pub name: Option<String>,
+    /// Name of the diet in the Diets Service whose daily limits the days have to stay within
+    pub diet: Option<String>,
+    pub start: Option<NaiveDate>,
+    pub days: Option<i32>,
+    pub slots: Option<Vec<Slot>>,
+    /// Number of consecutive days in which a meal is planned at most once
+    pub variety_days: Option<u32>,
+}
+
+/// Query parameters of a plan validation
+#[derive(Serialize, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ReqPlanDiet {
+    /// Name of the diet in the Diets Service the plan is validated against
+    pub diet: Option<String>,
+}
+
+/// A plan with the totals of its days and ISO weeks, computed from the current nutrients of its meals
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct PlanView {
+    #[serde(flatten)]
+    pub plan: Plan,
+    pub daily: Vec<DaySummary>,
+    pub weekly: Vec<WeekSummary>,
+    pub totals: Nutrients,
+}
+
+/// The totals of a plan compared against the limits of a diet
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct PlanValidation {
+    /// ID of the plan
+    pub plan: i32,
+    /// Name of the diet in the Diets Service
+    pub diet: String,
+    /// The daily limits of the diet
+    pub limits: Nutrients,
+    /// True if no day and no week exceeds the limits
+    pub valid: bool,
+    pub daily: Vec<DaySummary>,
+    pub weekly: Vec<WeekSummary>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn plan(days: i32, entries: &[(&str, Slot)]) -> NewPlan {
+        NewPlan {
+            name: "week".to_string(),
+            start: "2026-10-19".parse().unwrap(),
+            days,
+            entries: entries.iter().map(|(date, slot)| PlanEntry { date: date.parse().unwrap(), slot: *slot, meal: 1 }).collect(),
+        }
+    }
+
+    #[test]
+    fn entries_are_on_the_days_of_the_plan() {
+        assert!(plan(7, &[("2026-10-19", Slot::Lunch), ("2026-10-19", Slot::Dinner), ("2026-10-25", Slot::Snack)]).validate().is_ok());
+        assert!(plan(0, &[]).validate().is_err());
+        assert!(plan(MAX_PLAN_DAYS + 1, &[]).validate().is_err());
+        assert!(plan(7, &[("2026-10-26", Slot::Lunch)]).validate().is_err());
+        assert!(plan(7, &[("2026-10-18", Slot::Lunch)]).validate().is_err());
+        assert!(plan(7, &[("2026-10-20", Slot::Lunch), ("2026-10-20", Slot::Lunch)]).validate().is_err());
+        assert_eq!("snack".parse::<Slot>(), Ok(Slot::Snack));
+    }
+}
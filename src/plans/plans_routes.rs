@@ -0,0 +1,661 @@
+#![allow(unused_doc_comments)]
+
+/// Actix Imports
+use actix_web::{get, post, delete, put, HttpResponse, Responder, HttpRequest, web};
+use actix_web::web::{Data, Query};
+
+/// Misc Imports
+use chrono::NaiveDate;
+use serde_json::json;
+use std::collections::{BTreeMap, HashMap};
+use tracing::warn;
+
+/// Module Imports
+use super::generator::{generate_plan, Generation, Infeasible};
+use super::models::{NewPlan, Plan, PlanEntry, PlanValidation, PlanView, ReqPlan, ReqPlanDiet, ReqPlanGeneration, ReqPlanSlot, Slot};
+use super::repository::PlanRepository;
+
+/// Crate Imports
+use crate::auth::{Editor, Reader, Require};
+use crate::db::{run_blocking, DbError};
+use crate::food_log::summarize_days;
+use crate::meals::{DietClient, MealRepository, Nutrients};
+use crate::negotiation::{decode_body, negotiate, request_format};
+use crate::telemetry::RequestId;
+
+/// Error codes as defined in the Assigment
+const NOT_JSON: &str = "0";
+const PARAM_NOT_FOUND: &str = "-1";
+const PLAN_ALREADY_EXISTS: &str = "-2";
+const INVALID_PLAN: &str = "-3";
+const PLAN_NOT_FOUND: &str = "-5";
+const MEAL_ID_NOT_FOUND: &str = "-6";
+
+/// Why the plan of a request was rejected
+enum PlanError {
+    /// The Content-Type is not JSON, MessagePack or CBOR
+    Unsupported,
+    /// The body can not be decoded
+    Invalid,
+    /// A required field is missing
+    Missing,
+    /// An entry is outside of the plan or a slot is planned twice, see [NewPlan::validate]
+    Inconsistent(String),
+    /// A planned meal does not exist in the tenant
+    MissingMeal,
+    /// No plan fits the diet, see [generate_plan]
+    Infeasible(Infeasible),
+    Db(DbError),
+}
+
+impl PlanError {
+
+    /// Returns the [HttpResponse] with the error code 0, -1, -3 or -6, or the response of the database error
+    /// An infeasible plan is answered with -3 and the reasons
+    fn response(self) -> HttpResponse {
+        match self {
+            PlanError::Unsupported => HttpResponse::UnsupportedMediaType().body(NOT_JSON),
+            PlanError::Invalid => HttpResponse::UnprocessableEntity().body(NOT_JSON),
+            PlanError::Missing => HttpResponse::UnprocessableEntity().body(PARAM_NOT_FOUND),
+            PlanError::Inconsistent(message) => HttpResponse::UnprocessableEntity().json(json!({
+                "message": message,
+                "error_code": INVALID_PLAN,
+            })),
+            PlanError::MissingMeal => HttpResponse::UnprocessableEntity().body(MEAL_ID_NOT_FOUND),
+            PlanError::Infeasible(infeasible) => HttpResponse::UnprocessableEntity().json(json!({
+                "message": "No plan fits the diet",
+                "error_code": INVALID_PLAN,
+                "reasons": infeasible.reasons,
+            })),
+            PlanError::Db(e) => e.response(),
+        }
+    }
+}
+
+/// Decodes the body of a request as JSON, MessagePack or CBOR
+fn decode<T: serde::de::DeserializeOwned>(req: &HttpRequest, body: &web::Bytes) -> Result<T, PlanError> {
+    let body_format = request_format(req).ok_or(PlanError::Unsupported)?;
+    decode_body(body_format, body).map_err(|e| {
+        warn!("{}", e);
+        PlanError::Invalid
+    })
+}
+
+/// Turns a requested plan into a [NewPlan], if all required fields are present and the entries fit the plan
+fn new_plan(req_plan: ReqPlan) -> Result<NewPlan, PlanError> {
+    let plan = req_plan.into_plan().ok_or(PlanError::Missing)?;
+    plan.validate().map_err(PlanError::Inconsistent)?;
+    Ok(plan)
+}
+
+/// Turns a requested generation into the [NewPlan] to fill and the [Generation], if all required fields are present and valid
+fn generation(req_generation: ReqPlanGeneration) -> Result<(NewPlan, String, Generation), PlanError> {
+    let (name, diet, start, days) = match (req_generation.name, req_generation.diet, req_generation.start, req_generation.days) {
+        (Some(name), Some(diet), Some(start), Some(days)) => (name, diet, start, days),
+        _ => return Err(PlanError::Missing),
+    };
+    let plan = NewPlan { name, start, days, entries: Vec::new() };
+    plan.validate().map_err(PlanError::Inconsistent)?;
+
+    let slots = req_generation.slots.unwrap_or_else(|| vec![Slot::Breakfast, Slot::Lunch, Slot::Dinner]);
+    if slots.is_empty() || slots.iter().enumerate().any(|(i, slot)| slots[..i].contains(slot)) {
+        return Err(PlanError::Inconsistent("slots must not be empty or contain a slot twice".to_string()))
+    }
+    let variety_days = req_generation.variety_days.unwrap_or(1);
+    if variety_days == 0 {
+        return Err(PlanError::Inconsistent("variety_days must be at least 1".to_string()))
+    }
+    Ok((plan, diet, Generation { start, days, slots, variety_days }))
+}
+
+/// Returns the nutrients of all meals of the tenant by their ID
+async fn meal_nutrients(meals: &Data<dyn MealRepository>, tenant: &str) -> Result<HashMap<i32, Nutrients>, DbError> {
+    let (meals, tenant) = (meals.clone(), tenant.to_string());
+    let all = run_blocking(move || meals.all(&tenant)).await?;
+    Ok(all.iter().map(|meal| (meal.ID, Nutrients::from(meal))).collect())
+}
+
+/// Checks that the planned meals exist in the tenant
+/// The foreign keys only check that they exist at all, in any tenant
+async fn check_meals(meals: &Data<dyn MealRepository>, tenant: &str, entries: &[PlanEntry]) -> Result<(), PlanError> {
+    let nutrients = meal_nutrients(meals, tenant).await.map_err(PlanError::Db)?;
+    match entries.iter().all(|entry| nutrients.contains_key(&entry.meal)) {
+        true => Ok(()),
+        false => Err(PlanError::MissingMeal),
+    }
+}
+
+/// Returns the response to an error of storing a plan
+///
+/// If the plan does not exist, a [HttpResponse::NotFound] with a Error Code -5
+/// If another plan has the name, a [HttpResponse::UnprocessableEntity] with a Error Code -2
+/// If a meal does not exist, a [HttpResponse::UnprocessableEntity] with a Error Code -6
+fn store_error(e: DbError) -> HttpResponse {
+    match e {
+        DbError::NotFound => HttpResponse::NotFound().body(PLAN_NOT_FOUND),
+        DbError::AlreadyExists => HttpResponse::UnprocessableEntity().body(PLAN_ALREADY_EXISTS),
+        DbError::MissingReference => HttpResponse::UnprocessableEntity().body(MEAL_ID_NOT_FOUND),
+        e => e.response(),
+    }
+}
+
+/*
+=============================== GET /plans ===============================
+ */
+/// # Creates the route for getting all plans in "/plans"
+/// ## Arguments
+/// * `auth` - Requires the [Reader] role, only the data of its tenant is used
+/// * `plans` - The [PlanRepository] storing the plans
+/// * `req` - A [HttpRequest] containing the request, used to pick the response format
+/// ## Returns
+/// * [HttpResponse::Ok] with a JSON body containing all plans indexed by their ID
+#[utoipa::path(
+    get,
+    path = "/plans",
+    tag = "plans",
+    security(("api_key" = ["reader"]), ("bearer" = ["reader"])),
+    responses(
+        (status = 200, description = "All plans indexed by their ID", body = BTreeMap<i32, Plan>),
+        (status = 503, description = "Database unavailable, error code -8"),
+    ),
+)]
+#[get("/plans")]
+pub async fn get_all_plans(auth: Require<Reader>, plans: Data<dyn PlanRepository>, req: HttpRequest) -> impl Responder {
+    let tenant = auth.principal.tenant;
+    match run_blocking(move || plans.all(&tenant)).await {
+        Ok(all) => {
+            let all_plans: BTreeMap<i32, Plan> = all.into_iter().map(|plan| (plan.ID, plan)).collect();
+            negotiate(&req, HttpResponse::Ok(), &all_plans)
+        }
+        Err(e) => e.response(),
+    }
+}
+
+/*
+=============================== POST /plans ===============================
+ */
+/// # Creates the route for creating a plan in "/plans"
+/// ## Arguments
+/// * `auth` - Requires the [Editor] role, only the data of its tenant is used
+/// * `plans` - The [PlanRepository] storing the plans
+/// * `meals` - The [MealRepository], used to check the planned meals
+/// * `req` - A [HttpRequest] containing the request
+/// * `body` - A [web::Bytes] containing the plan as JSON, MessagePack or CBOR
+/// ## Returns
+/// * [HttpResponse] with a status of 201 and a body containing the ID of the new plan
+#[utoipa::path(
+    post,
+    path = "/plans",
+    tag = "plans",
+    security(("api_key" = ["editor"]), ("bearer" = ["editor"])),
+    request_body = ReqPlan,
+    responses(
+        (status = 201, description = "ID of the new plan", body = i32),
+        (status = 415, description = "Content type not supported, error code 0", body = String),
+        (status = 422, description = "Body not parsable (0), field missing (-1), plan already exists (-2), entry outside of the plan or slot planned twice (-3) or a meal does not exist (-6)", body = String),
+        (status = 503, description = "Database unavailable, error code -8"),
+    ),
+)]
+#[post("/plans")]
+pub async fn create_plan(auth: Require<Editor>, plans: Data<dyn PlanRepository>, meals: Data<dyn MealRepository>, req: HttpRequest, body: web::Bytes) -> impl Responder {
+    let tenant = auth.principal.tenant;
+
+    /// Decode and check the plan and its meals
+    let plan = match decode(&req, &body).and_then(new_plan) {
+        Ok(plan) => plan,
+        Err(e) => return e.response(),
+    };
+    if let Err(e) = check_meals(&meals, &tenant, &plan.entries).await {
+        return e.response()
+    }
+
+    match run_blocking(move || plans.insert(&tenant, plan)).await {
+        Ok(plan) => HttpResponse::Created().body(plan.ID.to_string()),
+        Err(e) => store_error(e),
+    }
+}
+
+/*
+=============================== POST /plans/generate ===============================
+ */
+/// # Creates the route for generating a plan for a diet in "/plans/generate"
+/// Fills every slot of every day with one of the meals of the tenant, so that no day exceeds the daily limits of the diet
+/// and no meal is repeated within the variety window, and stores the plan
+/// ## Arguments
+/// * `auth` - Requires the [Editor] role, only the data of its tenant is used
+/// * `plans` - The [PlanRepository] storing the plans
+/// * `meals` - The [MealRepository], the meals to choose from
+/// * `diet_client` - A [web::Data<DietClient>] used to look up the diet in the Diets Microservice
+/// * `request_id` - The [RequestId] of the request, forwarded to the Diets Microservice
+/// * `req` - A [HttpRequest] containing the request
+/// * `body` - A [web::Bytes] containing the [ReqPlanGeneration] as JSON, MessagePack or CBOR
+/// ## Returns
+/// * [HttpResponse] with a status of 201 and a JSON body containing the new plan with its totals
+/// * [HttpResponse::UnprocessableEntity] with the reasons if no plan fits the diet
+#[utoipa::path(
+    post,
+    path = "/plans/generate",
+    tag = "plans",
+    security(("api_key" = ["editor"]), ("bearer" = ["editor"])),
+    request_body = ReqPlanGeneration,
+    responses(
+        (status = 201, description = "The new plan with its totals compared against the diet", body = PlanView),
+        (status = 404, description = "Diet not found", body = String),
+        (status = 415, description = "Content type not supported, error code 0", body = String),
+        (status = 422, description = "Body not parsable (0), field missing (-1), plan already exists (-2), or invalid days or slots or no plan fits the diet (-3, with the reasons)", body = String),
+        (status = 503, description = "The Diets Service or the database is unavailable"),
+        (status = 504, description = "The Diets Service did not answer in time"),
+    ),
+)]
+#[post("/plans/generate")]
+pub async fn generate_new_plan(auth: Require<Editor>, plans: Data<dyn PlanRepository>, meals: Data<dyn MealRepository>, diet_client: Data<DietClient>, request_id: RequestId, req: HttpRequest, body: web::Bytes) -> impl Responder {
+    let tenant = auth.principal.tenant;
+
+    /// Decode and check what to generate
+    let (mut plan, diet, generation) = match decode(&req, &body).and_then(generation) {
+        Ok(generation) => generation,
+        Err(e) => return e.response(),
+    };
+
+    let all_tenant = tenant.clone();
+    let all_meals = match run_blocking(move || meals.all(&all_tenant)).await {
+        Ok(all_meals) => all_meals,
+        Err(e) => return e.response(),
+    };
+    let limits = match diet_client.get_limits(&tenant, std::slice::from_ref(&diet), &request_id).await {
+        Ok(limits) => limits,
+        Err(e) => return e.response(),
+    };
+
+    /// Search the plan on the blocking thread pool, since the search can take up to [crate::plans::MAX_SEARCH_STEPS] steps,
+    /// and compute its totals from the same meals
+    let search = run_blocking(move || {
+        let entries = generate_plan(&all_meals, limits, &generation);
+        Ok((entries, all_meals))
+    }).await;
+    let (entries, all_meals) = match search {
+        Ok(search) => search,
+        Err(e) => return e.response(),
+    };
+    let entries = match entries {
+        Ok(entries) => entries,
+        Err(infeasible) => return PlanError::Infeasible(infeasible).response(),
+    };
+    plan.entries = entries;
+    let nutrients: HashMap<i32, Nutrients> = all_meals.iter().map(|meal| (meal.ID, Nutrients::from(meal))).collect();
+
+    match run_blocking(move || plans.insert(&tenant, plan)).await {
+        Ok(plan) => {
+            let (daily, weekly) = summarize_days(plan.daily_totals(&nutrients), Some(limits));
+            let totals = daily.iter().map(|day| day.totals).sum();
+            negotiate(&req, HttpResponse::Created(), &PlanView { plan, daily, weekly, totals })
+        }
+        Err(e) => store_error(e),
+    }
+}
+
+/*
+=============================== GET /plans/{id} ===============================
+ */
+/// # Creates the route for getting a plan with its totals in "/plans/{id}"
+/// The totals are computed from the current nutrients of the meals
+/// ## Arguments
+/// * `auth` - Requires the [Reader] role, only the data of its tenant is used
+/// * `plans` - The [PlanRepository] storing the plans
+/// * `meals` - The [MealRepository], used to look up the nutrients of the planned meals
+/// * `req` - A [HttpRequest] containing the request, used to pick the response format
+/// * `req_id` - A [web::Path<i32>] containing the ID of the plan
+/// ## Returns
+/// * [HttpResponse::Ok] with a JSON body containing the plan and the totals of its days and ISO weeks
+#[utoipa::path(
+    get,
+    path = "/plans/{id}",
+    tag = "plans",
+    security(("api_key" = ["reader"]), ("bearer" = ["reader"])),
+    params(("id" = i32, Path, description = "ID of the plan")),
+    responses(
+        (status = 200, description = "The plan with its daily and weekly totals", body = PlanView),
+        (status = 404, description = "Plan not found, error code -5", body = String),
+        (status = 503, description = "Database unavailable, error code -8"),
+    ),
+)]
+#[get("/plans/{id:\\d+}")]
+pub async fn get_plan(auth: Require<Reader>, plans: Data<dyn PlanRepository>, meals: Data<dyn MealRepository>, req: HttpRequest, req_id: web::Path<i32>) -> impl Responder {
+    let tenant = auth.principal.tenant;
+    let plan_id = *req_id;
+
+    let nutrients = match meal_nutrients(&meals, &tenant).await {
+        Ok(nutrients) => nutrients,
+        Err(e) => return e.response(),
+    };
+    let plan = match run_blocking(move || plans.find(&tenant, plan_id)).await {
+        Ok(plan) => plan,
+        Err(DbError::NotFound) => return HttpResponse::NotFound().body(PLAN_NOT_FOUND),
+        Err(e) => return e.response(),
+    };
+
+    let (daily, weekly) = summarize_days(plan.daily_totals(&nutrients), None);
+    let totals = daily.iter().map(|day| day.totals).sum();
+    negotiate(&req, HttpResponse::Ok(), &PlanView { plan, daily, weekly, totals })
+}
+
+/*
+=============================== PUT /plans/{id} ===============================
+ */
+/// # Creates the route for replacing a plan in "/plans/{id}"
+/// ## Arguments
+/// * `auth` - Requires the [Editor] role, only the data of its tenant is used
+/// * `plans` - The [PlanRepository] storing the plans
+/// * `meals` - The [MealRepository], used to check the planned meals
+/// * `req` - A [HttpRequest] containing the request
+/// * `req_id` - A [web::Path<i32>] containing the ID of the plan
+/// * `body` - A [web::Bytes] containing the new plan as JSON, MessagePack or CBOR
+/// ## Returns
+/// * [HttpResponse::Ok] with a body containing the ID of the updated plan
+#[utoipa::path(
+    put,
+    path = "/plans/{id}",
+    tag = "plans",
+    security(("api_key" = ["editor"]), ("bearer" = ["editor"])),
+    params(("id" = i32, Path, description = "ID of the plan")),
+    request_body = ReqPlan,
+    responses(
+        (status = 200, description = "ID of the updated plan", body = i32),
+        (status = 404, description = "Plan not found, error code -5", body = String),
+        (status = 415, description = "Content type not supported, error code 0", body = String),
+        (status = 422, description = "Body not parsable (0), field missing (-1), name taken by another plan (-2), entry outside of the plan or slot planned twice (-3) or a meal does not exist (-6)", body = String),
+        (status = 503, description = "Database unavailable, error code -8"),
+    ),
+)]
+#[put("/plans/{id:\\d+}")]
+pub async fn update_plan(auth: Require<Editor>, plans: Data<dyn PlanRepository>, meals: Data<dyn MealRepository>, req: HttpRequest, req_id: web::Path<i32>, body: web::Bytes) -> impl Responder {
+    let tenant = auth.principal.tenant;
+
+    /// Decode and check the plan and its meals
+    let plan = match decode(&req, &body).and_then(new_plan) {
+        Ok(plan) => plan,
+        Err(e) => return e.response(),
+    };
+    if let Err(e) = check_meals(&meals, &tenant, &plan.entries).await {
+        return e.response()
+    }
+
+    let plan_id = *req_id;
+    match run_blocking(move || plans.update(&tenant, plan_id, plan)).await {
+        Ok(()) => HttpResponse::Ok().body(plan_id.to_string()),
+        Err(e) => store_error(e),
+    }
+}
+
+/*
+=============================== DELETE /plans/{id} ===============================
+ */
+/// # Creates the route for deleting a plan in "/plans/{id}"
+/// ## Arguments
+/// * `auth` - Requires the [Editor] role, only the data of its tenant is used
+/// * `plans` - The [PlanRepository] storing the plans
+/// * `req_id` - A [web::Path<i32>] containing the ID of the plan
+/// ## Returns
+/// * [HttpResponse::Ok] with a body containing the ID of the deleted plan
+#[utoipa::path(
+    delete,
+    path = "/plans/{id}",
+    tag = "plans",
+    security(("api_key" = ["editor"]), ("bearer" = ["editor"])),
+    params(("id" = i32, Path, description = "ID of the plan")),
+    responses(
+        (status = 200, description = "ID of the deleted plan", body = i32),
+        (status = 404, description = "Plan not found, error code -5", body = String),
+        (status = 503, description = "Database unavailable, error code -8"),
+    ),
+)]
+#[delete("/plans/{id:\\d+}")]
+pub async fn delete_plan(auth: Require<Editor>, plans: Data<dyn PlanRepository>, req_id: web::Path<i32>) -> impl Responder {
+    let tenant = auth.principal.tenant;
+    let plan_id = *req_id;
+    match run_blocking(move || plans.delete(&tenant, plan_id)).await {
+        Ok(()) => HttpResponse::Ok().body(plan_id.to_string()),
+        Err(e) => store_error(e),
+    }
+}
+
+/*
+=============================== PUT /plans/{id}/days/{date}/{slot} ===============================
+ */
+/// # Creates the route for planning a meal for a slot of a day in "/plans/{id}/days/{date}/{slot}"
+/// The meal planned for the slot before is replaced
+/// ## Arguments
+/// * `auth` - Requires the [Editor] role, only the data of its tenant is used
+/// * `plans` - The [PlanRepository] storing the plans
+/// * `meals` - The [MealRepository], used to check the meal
+/// * `req` - A [HttpRequest] containing the request
+/// * `path` - A [web::Path] containing the ID of the plan, the day and the slot
+/// * `body` - A [web::Bytes] containing the meal as JSON, MessagePack or CBOR
+/// ## Returns
+/// * [HttpResponse::Ok] with a JSON body containing the new entry
+#[utoipa::path(
+    put,
+    path = "/plans/{id}/days/{date}/{slot}",
+    tag = "plans",
+    security(("api_key" = ["editor"]), ("bearer" = ["editor"])),
+    params(
+        ("id" = i32, Path, description = "ID of the plan"),
+        ("date" = NaiveDate, Path, description = "Day of the plan"),
+        ("slot" = Slot, Path, description = "Slot of the day"),
+    ),
+    request_body = ReqPlanSlot,
+    responses(
+        (status = 200, description = "The new entry", body = PlanEntry),
+        (status = 404, description = "Plan not found, error code -5", body = String),
+        (status = 415, description = "Content type not supported, error code 0", body = String),
+        (status = 422, description = "Body not parsable (0), meal missing (-1), day not in the plan (-3) or meal does not exist (-6)", body = String),
+        (status = 503, description = "Database unavailable, error code -8"),
+    ),
+)]
+#[put("/plans/{id:\\d+}/days/{date}/{slot}")]
+pub async fn set_plan_slot(auth: Require<Editor>, plans: Data<dyn PlanRepository>, meals: Data<dyn MealRepository>, req: HttpRequest, path: web::Path<(i32, NaiveDate, Slot)>, body: web::Bytes) -> impl Responder {
+    let tenant = auth.principal.tenant;
+    let (plan_id, date, slot) = path.into_inner();
+
+    let meal = match decode::<ReqPlanSlot>(&req, &body).and_then(|req_slot| req_slot.meal.ok_or(PlanError::Missing)) {
+        Ok(meal) => meal,
+        Err(e) => return e.response(),
+    };
+    let entry = PlanEntry { date, slot, meal };
+
+    /// The day has to be one of the plan
+    let (find_plans, find_tenant) = (plans.clone(), tenant.clone());
+    let plan = match run_blocking(move || find_plans.find(&find_tenant, plan_id)).await {
+        Ok(plan) => plan,
+        Err(DbError::NotFound) => return HttpResponse::NotFound().body(PLAN_NOT_FOUND),
+        Err(e) => return e.response(),
+    };
+    if !plan.dates().any(|day| day == date) {
+        return PlanError::Inconsistent(format!("{} is not a day of the plan", date)).response()
+    }
+    if let Err(e) = check_meals(&meals, &tenant, std::slice::from_ref(&entry)).await {
+        return e.response()
+    }
+
+    let stored_entry = entry.clone();
+    match run_blocking(move || plans.set_entry(&tenant, plan_id, stored_entry)).await {
+        Ok(()) => negotiate(&req, HttpResponse::Ok(), &entry),
+        Err(e) => store_error(e),
+    }
+}
+
+/*
+=============================== DELETE /plans/{id}/days/{date}/{slot} ===============================
+ */
+/// # Creates the route for removing the meal of a slot of a day in "/plans/{id}/days/{date}/{slot}"
+/// ## Arguments
+/// * `auth` - Requires the [Editor] role, only the data of its tenant is used
+/// * `plans` - The [PlanRepository] storing the plans
+/// * `path` - A [web::Path] containing the ID of the plan, the day and the slot
+/// ## Returns
+/// * [HttpResponse::Ok] with a body containing the ID of the plan
+#[utoipa::path(
+    delete,
+    path = "/plans/{id}/days/{date}/{slot}",
+    tag = "plans",
+    security(("api_key" = ["editor"]), ("bearer" = ["editor"])),
+    params(
+        ("id" = i32, Path, description = "ID of the plan"),
+        ("date" = NaiveDate, Path, description = "Day of the plan"),
+        ("slot" = Slot, Path, description = "Slot of the day"),
+    ),
+    responses(
+        (status = 200, description = "ID of the plan", body = i32),
+        (status = 404, description = "Plan not found or nothing planned for the slot, error code -5", body = String),
+        (status = 503, description = "Database unavailable, error code -8"),
+    ),
+)]
+#[delete("/plans/{id:\\d+}/days/{date}/{slot}")]
+pub async fn remove_plan_slot(auth: Require<Editor>, plans: Data<dyn PlanRepository>, path: web::Path<(i32, NaiveDate, Slot)>) -> impl Responder {
+    let tenant = auth.principal.tenant;
+    let (plan_id, date, slot) = path.into_inner();
+    match run_blocking(move || plans.remove_entry(&tenant, plan_id, date, slot)).await {
+        Ok(()) => HttpResponse::Ok().body(plan_id.to_string()),
+        Err(e) => store_error(e),
+    }
+}
+
+/*
+=============================== GET /plans/{id}/validation ===============================
+ */
+/// # Creates the route for validating a plan against a diet in "/plans/{id}/validation"
+/// Every day has to stay within the daily limits of the diet, and every ISO week within the limits times its days
+/// ## Arguments
+/// * `auth` - Requires the [Reader] role, only the data of its tenant is used
+/// * `plans` - The [PlanRepository] storing the plans
+/// * `meals` - The [MealRepository], used to look up the nutrients of the planned meals
+/// * `diet_client` - A [web::Data<DietClient>] used to look up the diet in the Diets Microservice
+/// * `request_id` - The [RequestId] of the request, forwarded to the Diets Microservice
+/// * `req` - A [HttpRequest] containing the request, used to pick the response format
+/// * `req_id` - A [web::Path<i32>] containing the ID of the plan
+/// * `query` - A [web::Query<ReqPlanDiet>] containing the name of the diet
+/// ## Returns
+/// * [HttpResponse::Ok] with a JSON body containing the daily and weekly totals and the limits they exceed
+#[utoipa::path(
+    get,
+    path = "/plans/{id}/validation",
+    tag = "plans",
+    security(("api_key" = ["reader"]), ("bearer" = ["reader"])),
+    params(("id" = i32, Path, description = "ID of the plan"), ReqPlanDiet),
+    responses(
+        (status = 200, description = "The totals of the plan compared against the diet", body = PlanValidation),
+        (status = 400, description = "Diet missing", body = String),
+        (status = 404, description = "Plan (-5) or diet not found", body = String),
+        (status = 503, description = "The Diets Service or the database is unavailable"),
+        (status = 504, description = "The Diets Service did not answer in time"),
+    ),
+)]
+#[get("/plans/{id:\\d+}/validation")]
+#[allow(clippy::too_many_arguments)]
+pub async fn validate_plan(auth: Require<Reader>, plans: Data<dyn PlanRepository>, meals: Data<dyn MealRepository>, diet_client: Data<DietClient>, request_id: RequestId, req: HttpRequest, req_id: web::Path<i32>, query: Query<ReqPlanDiet>) -> impl Responder {
+    let tenant = auth.principal.tenant;
+    let plan_id = *req_id;
+    let diet = match &query.diet {
+        Some(diet) => diet.clone(),
+        None => return HttpResponse::BadRequest().body("diet is required"),
+    };
+
+    let nutrients = match meal_nutrients(&meals, &tenant).await {
+        Ok(nutrients) => nutrients,
+        Err(e) => return e.response(),
+    };
+    let find_tenant = tenant.clone();
+    let plan = match run_blocking(move || plans.find(&find_tenant, plan_id)).await {
+        Ok(plan) => plan,
+        Err(DbError::NotFound) => return HttpResponse::NotFound().body(PLAN_NOT_FOUND),
+        Err(e) => return e.response(),
+    };
+    let limits = match diet_client.get_limits(&tenant, std::slice::from_ref(&diet), &request_id).await {
+        Ok(limits) => limits,
+        Err(e) => return e.response(),
+    };
+
+    let (daily, weekly) = summarize_days(plan.daily_totals(&nutrients), Some(limits));
+    let valid = daily.iter().all(|day| day.exceeded.is_empty()) && weekly.iter().all(|week| week.exceeded.is_empty());
+    negotiate(&req, HttpResponse::Ok(), &PlanValidation { plan: plan.ID, diet, limits, valid, daily, weekly })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{test, App};
+    use actix_web::middleware::from_fn;
+    use crate::auth::{authenticate, AuthConfig, DEFAULT_TENANT, TENANT_HEADER};
+    use crate::meals::{DishRepository, InMemoryMealsRepository, NewDish, NewMeal};
+    use crate::plans::InMemoryPlanRepository;
+    use std::sync::Arc;
+
+    #[actix_web::test]
+    async fn plans_are_managed_and_summed_up() {
+        let meals = Arc::new(InMemoryMealsRepository::new());
+        for (name, cal) in [("soup", 100.0), ("steak", 500.0), ("cake", 300.0)] {
+            DishRepository::insert(&*meals, DEFAULT_TENANT, NewDish { name: name.to_string(), cal, sodium: 10.0, sugar: 5.0, size: 100.0 }).unwrap();
+        }
+        MealRepository::insert(&*meals, DEFAULT_TENANT, NewMeal { name: "dinner".to_string(), appetizer: 1, main: 2, dessert: 3 }).unwrap();
+        MealRepository::insert(&*meals, DEFAULT_TENANT, NewMeal { name: "light".to_string(), appetizer: 1, main: 1, dessert: 1 }).unwrap();
+
+        let app = test::init_service(App::new()
+            .app_data(Data::new(AuthConfig::dev()))
+            .wrap(from_fn(authenticate))
+            .app_data(Data::from(meals as Arc<dyn MealRepository>))
+            .app_data(Data::from(Arc::new(InMemoryPlanRepository::new()) as Arc<dyn PlanRepository>))
+            .service(get_all_plans)
+            .service(create_plan)
+            .service(get_plan)
+            .service(update_plan)
+            .service(delete_plan)
+            .service(set_plan_slot)
+            .service(remove_plan_slot)).await;
+
+        let json = ("Content-Type", "application/json");
+        let post = |body: &'static str| test::TestRequest::post().uri("/plans").insert_header(json).set_payload(body).to_request();
+        let week = r#"{"name": "week", "start": "2026-10-18", "days": 3, "entries": [
+            {"date": "2026-10-18", "slot": "dinner", "meal": 1},
+            {"date": "2026-10-19", "slot": "lunch", "meal": 2},
+            {"date": "2026-10-19", "slot": "dinner", "meal": 1}
+        ]}"#;
+        assert_eq!(test::call_and_read_body(&app, post(week)).await, "1");
+        assert_eq!(test::call_and_read_body(&app, post(week)).await, PLAN_ALREADY_EXISTS);
+        assert_eq!(test::call_and_read_body(&app, post(r#"{"name": "x", "days": 3}"#)).await, PARAM_NOT_FOUND);
+        assert_eq!(test::call_and_read_body(&app, post(r#"{"name": "x", "start": "2026-10-18", "days": 3, "entries": [{"date": "2026-10-18", "slot": "lunch", "meal": 9}]}"#)).await, MEAL_ID_NOT_FOUND);
+        let res = test::call_service(&app, post(r#"{"name": "x", "start": "2026-10-18", "days": 3, "entries": [{"date": "2026-10-21", "slot": "lunch", "meal": 1}]}"#)).await;
+        assert_eq!(res.status(), 422);
+
+        /// 2026-10-18 is the Sunday of week 42, the other days are in week 43
+        let view: PlanView = test::call_and_read_body_json(&app, test::TestRequest::get().uri("/plans/1").to_request()).await;
+        let daily: Vec<f64> = view.daily.iter().map(|day| day.totals.cal).collect();
+        assert_eq!(daily, [900.0, 1200.0, 0.0]);
+        let weekly: Vec<(&str, f64)> = view.weekly.iter().map(|week| (week.week.as_str(), week.totals.cal)).collect();
+        assert_eq!(weekly, [("2026-W42", 900.0), ("2026-W43", 1200.0)]);
+        assert_eq!(view.totals.cal, 2100.0);
+
+        /// Slots are set and removed one by one
+        let slot = |uri: &'static str, body: &'static str| test::TestRequest::put().uri(uri).insert_header(json).set_payload(body).to_request();
+        let entry: PlanEntry = test::call_and_read_body_json(&app, slot("/plans/1/days/2026-10-20/snack", r#"{"meal": 2}"#)).await;
+        assert_eq!((entry.slot, entry.meal), (Slot::Snack, 2));
+        let res = test::call_service(&app, slot("/plans/1/days/2026-10-21/snack", r#"{"meal": 2}"#)).await;
+        assert_eq!(res.status(), 422);
+        let res = test::call_service(&app, slot("/plans/1/days/2026-10-20/brunch", r#"{"meal": 2}"#)).await;
+        assert_eq!(res.status(), 404);
+        assert_eq!(test::call_and_read_body(&app, test::TestRequest::delete().uri("/plans/1/days/2026-10-19/dinner").to_request()).await, "1");
+        assert_eq!(test::call_and_read_body(&app, test::TestRequest::delete().uri("/plans/1/days/2026-10-19/dinner").to_request()).await, PLAN_NOT_FOUND);
+        let plans: BTreeMap<i32, Plan> = test::call_and_read_body_json(&app, test::TestRequest::get().uri("/plans").to_request()).await;
+        assert_eq!(plans[&1].entries.len(), 3);
+
+        let update = test::TestRequest::put().uri("/plans/1").insert_header(json).set_payload(r#"{"name": "short", "start": "2026-10-18", "days": 1}"#).to_request();
+        assert_eq!(test::call_and_read_body(&app, update).await, "1");
+        let view: PlanView = test::call_and_read_body_json(&app, test::TestRequest::get().uri("/plans/1").to_request()).await;
+        assert_eq!((view.plan.name.as_str(), view.daily.len(), view.totals.cal), ("short", 1, 0.0));
+
+        /// Other tenants do not see the plan
+        let res = test::call_service(&app, test::TestRequest::get().uri("/plans/1").insert_header((TENANT_HEADER, "team-b")).to_request()).await;
+        assert_eq!(res.status(), 404);
+        assert_eq!(test::call_and_read_body(&app, test::TestRequest::delete().uri("/plans/1").to_request()).await, "1");
+        assert_eq!(test::call_and_read_body(&app, test::TestRequest::delete().uri("/plans/1").to_request()).await, PLAN_NOT_FOUND);
+    }
+}
@@ -0,0 +1,380 @@
+#![allow(unused_doc_comments)]
+//! This file contains the storage of the meal plans
+//!
+//! The route handlers only know the [PlanRepository] trait.
+//! [DieselPlanRepository] stores the plans in the database, [InMemoryPlanRepository] keeps them in memory,
+//! so the handlers can be tested without a database.
+//!
+//! The methods block, handlers call them with [crate::db::run_blocking].
+//! Every method works on the plans of one tenant, see [crate::auth::Principal]. The handlers check that the planned meals
+//! exist in the tenant, the foreign keys only remove the entries of a deleted meal
+
+/// Diesel imports
+use diesel::prelude::*;
+use diesel::{delete, insert_into, update};
+
+/// Misc imports
+use chrono::NaiveDate;
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+/// Module imports
+use super::models::{NewPlan, Plan, PlanEntry, Slot};
+
+/// Crate imports
+use crate::db::{get_connection, BackendConnection, DbError, DbPool};
+use crate::schema::{plan_entries, plans};
+
+/// Storage of the meal plans
+pub trait PlanRepository: Send + Sync {
+
+    /// Returns all plans of the tenant, ordered by ID
+    fn all(&self, tenant: &str) -> Result<Vec<Plan>, DbError>;
+
+    /// Returns the plan with the ID, or [DbError::NotFound]
+    fn find(&self, tenant: &str, id: i32) -> Result<Plan, DbError>;
+
+    /// Stores a new plan, or returns [DbError::AlreadyExists] if the name is taken
+    fn insert(&self, tenant: &str, plan: NewPlan) -> Result<Plan, DbError>;
+
+    /// Replaces the plan with the ID, including all of its entries
+    fn update(&self, tenant: &str, id: i32, plan: NewPlan) -> Result<(), DbError>;
+
+    /// Deletes the plan with the ID, or returns [DbError::NotFound]
+    fn delete(&self, tenant: &str, id: i32) -> Result<(), DbError>;
+
+    /// Plans the meal for a slot of a day of the plan, replacing the meal planned before
+    fn set_entry(&self, tenant: &str, id: i32, entry: PlanEntry) -> Result<(), DbError>;
+
+    /// Removes the meal planned for a slot of a day of the plan, or returns [DbError::NotFound] if there is none
+    fn remove_entry(&self, tenant: &str, id: i32, date: NaiveDate, slot: Slot) -> Result<(), DbError>;
+}
+
+/*
+=============================== Database ===============================
+ */
+
+/// Plans stored in Postgres or SQLite, see [crate::db::BackendConnection]
+pub struct DieselPlanRepository {
+    pool: DbPool,
+}
+
+impl DieselPlanRepository {
+    pub fn new(pool: DbPool) -> DieselPlanRepository {
+        DieselPlanRepository { pool }
+    }
+}
+
+/// A row of the `plans` table without its tenant
+type PlanRow = (i32, String, NaiveDate, i32);
+
+/// A row of the `plan_entries` table
+type EntryRow = (i32, NaiveDate, String, i32);
+
+/// Puts the entries to their plans, entries with an unknown slot are skipped
+fn assemble(rows: Vec<PlanRow>, entries: Vec<EntryRow>) -> Vec<Plan> {
+    let mut found: BTreeMap<i32, Plan> = rows.into_iter()
+        .map(|(id, name, start, days)| (id, Plan { ID: id, name, start, days, entries: Vec::new() }))
+        .collect();
+    for (plan_id, date, slot, meal) in entries {
+        if let (Some(plan), Ok(slot)) = (found.get_mut(&plan_id), slot.parse()) {
+            plan.entries.push(PlanEntry { date, slot, meal });
+        }
+    }
+    found.into_values().map(sorted).collect()
+}
+
+/// Orders the entries of a plan by day and slot
+fn sorted(mut plan: Plan) -> Plan {
+    plan.entries.sort_by_key(|entry| (entry.date, entry.slot));
+    plan
+}
+
+/// Loads the plans with the rows selected, together with their entries
+fn load(conn: &mut BackendConnection, rows: Vec<PlanRow>) -> Result<Vec<Plan>, diesel::result::Error> {
+    let ids: Vec<i32> = rows.iter().map(|row| row.0).collect();
+    let entries = plan_entries::table
+        .filter(plan_entries::plan_id.eq_any(&ids))
+        .select((plan_entries::plan_id, plan_entries::day, plan_entries::slot, plan_entries::meal_id))
+        .load::<EntryRow>(conn)?;
+    Ok(assemble(rows, entries))
+}
+
+/// Inserts the entries of a plan
+fn insert_entries(conn: &mut BackendConnection, id: i32, entries: &[PlanEntry]) -> Result<(), diesel::result::Error> {
+    let rows: Vec<_> = entries.iter()
+        .map(|entry| (
+            plan_entries::plan_id.eq(id),
+            plan_entries::day.eq(entry.date),
+            plan_entries::slot.eq(entry.slot.to_string()),
+            plan_entries::meal_id.eq(entry.meal),
+        ))
+        .collect();
+    if !rows.is_empty() {
+        insert_into(plan_entries::table).values(rows).execute(conn)?;
+    }
+    Ok(())
+}
+
+/// Returns whether the tenant has a plan with the ID
+fn owns(conn: &mut BackendConnection, tenant: &str, id: i32) -> Result<bool, diesel::result::Error> {
+    let found = plans::table.find(id).filter(plans::tenant.eq(tenant)).count().get_result::<i64>(conn)?;
+    Ok(found > 0)
+}
+
+impl PlanRepository for DieselPlanRepository {
+
+    fn all(&self, tenant: &str) -> Result<Vec<Plan>, DbError> {
+        let conn = &mut get_connection(&self.pool)?;
+        let rows = plans::table
+            .filter(plans::tenant.eq(tenant))
+            .select((plans::id, plans::name, plans::start_date, plans::days))
+            .load::<PlanRow>(conn)?;
+        Ok(load(conn, rows)?)
+    }
+
+    fn find(&self, tenant: &str, id: i32) -> Result<Plan, DbError> {
+        let conn = &mut get_connection(&self.pool)?;
+        let row = plans::table
+            .find(id)
+            .filter(plans::tenant.eq(tenant))
+            .select((plans::id, plans::name, plans::start_date, plans::days))
+            .first::<PlanRow>(conn)?;
+        load(conn, vec![row])?.pop().ok_or(DbError::NotFound)
+    }
+
+    fn insert(&self, tenant: &str, plan: NewPlan) -> Result<Plan, DbError> {
+        let conn = &mut get_connection(&self.pool)?;
+        let id = conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            let id = insert_into(plans::table)
+                .values((
+                    plans::tenant.eq(tenant),
+                    plans::name.eq(&plan.name),
+                    plans::start_date.eq(plan.start),
+                    plans::days.eq(plan.days),
+                ))
+                .returning(plans::id)
+                .get_result::<i32>(conn)?;
+            insert_entries(conn, id, &plan.entries)?;
+            Ok(id)
+        })?;
+        Ok(sorted(Plan { ID: id, name: plan.name, start: plan.start, days: plan.days, entries: plan.entries }))
+    }
+
+    fn update(&self, tenant: &str, id: i32, plan: NewPlan) -> Result<(), DbError> {
+        let conn = &mut get_connection(&self.pool)?;
+        let updated = conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            let updated = update(plans::table.find(id).filter(plans::tenant.eq(tenant)))
+                .set((plans::name.eq(&plan.name), plans::start_date.eq(plan.start), plans::days.eq(plan.days)))
+                .execute(conn)?;
+            if updated > 0 {
+                delete(plan_entries::table.filter(plan_entries::plan_id.eq(id))).execute(conn)?;
+                insert_entries(conn, id, &plan.entries)?;
+            }
+            Ok(updated)
+        })?;
+        match updated {
+            0 => Err(DbError::NotFound),
+            _ => Ok(()),
+        }
+    }
+
+    fn delete(&self, tenant: &str, id: i32) -> Result<(), DbError> {
+        let conn = &mut get_connection(&self.pool)?;
+        match delete(plans::table.find(id).filter(plans::tenant.eq(tenant))).execute(conn)? {
+            0 => Err(DbError::NotFound),
+            _ => Ok(()),
+        }
+    }
+
+    fn set_entry(&self, tenant: &str, id: i32, entry: PlanEntry) -> Result<(), DbError> {
+        let conn = &mut get_connection(&self.pool)?;
+
+        /// Delete and insert instead of an upsert, which is written differently in Postgres and SQLite
+        conn.transaction::<_, DbError, _>(|conn| {
+            if !owns(conn, tenant, id)? {
+                return Err(DbError::NotFound)
+            }
+            delete(plan_entries::table
+                .filter(plan_entries::plan_id.eq(id))
+                .filter(plan_entries::day.eq(entry.date))
+                .filter(plan_entries::slot.eq(entry.slot.to_string())))
+                .execute(conn)?;
+            insert_entries(conn, id, &[entry])?;
+            Ok(())
+        })
+    }
+
+    fn remove_entry(&self, tenant: &str, id: i32, date: NaiveDate, slot: Slot) -> Result<(), DbError> {
+        let conn = &mut get_connection(&self.pool)?;
+        conn.transaction::<_, DbError, _>(|conn| {
+            if !owns(conn, tenant, id)? {
+                return Err(DbError::NotFound)
+            }
+            let deleted = delete(plan_entries::table
+                .filter(plan_entries::plan_id.eq(id))
+                .filter(plan_entries::day.eq(date))
+                .filter(plan_entries::slot.eq(slot.to_string())))
+                .execute(conn)?;
+            match deleted {
+                0 => Err(DbError::NotFound),
+                _ => Ok(()),
+            }
+        })
+    }
+}
+
+/*
+=============================== In memory ===============================
+ */
+
+/// The plans of the [InMemoryPlanRepository] with their tenant, and the last ID handed out
+#[derive(Default)]
+struct Plans {
+    plans: BTreeMap<i32, (String, Plan)>,
+    last_id: i32,
+}
+
+impl Plans {
+
+    /// The plan with the ID, if it belongs to the tenant
+    fn plan(&mut self, tenant: &str, id: i32) -> Option<&mut Plan> {
+        self.plans.get_mut(&id).filter(|(owner, _)| owner == tenant).map(|(_, plan)| plan)
+    }
+
+    /// Whether another plan of the tenant has the name
+    fn name_taken(&self, tenant: &str, name: &str, id: Option<i32>) -> bool {
+        self.plans.values().any(|(owner, plan)| owner == tenant && plan.name == name && Some(plan.ID) != id)
+    }
+}
+
+/// Plans kept in memory, with the same behavior as [DieselPlanRepository]
+/// The entries of deleted meals are not removed, the handlers skip meals they do not find
+#[derive(Default)]
+pub struct InMemoryPlanRepository {
+    plans: Mutex<Plans>,
+}
+
+impl InMemoryPlanRepository {
+
+    pub fn new() -> InMemoryPlanRepository {
+        InMemoryPlanRepository::default()
+    }
+
+    /// Locks the plans, a panic of another thread holding the lock leaves them usable
+    fn plans(&self) -> std::sync::MutexGuard<'_, Plans> {
+        self.plans.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl PlanRepository for InMemoryPlanRepository {
+
+    fn all(&self, tenant: &str) -> Result<Vec<Plan>, DbError> {
+        Ok(self.plans().plans.values().filter(|(owner, _)| owner == tenant).map(|(_, plan)| plan.clone()).collect())
+    }
+
+    fn find(&self, tenant: &str, id: i32) -> Result<Plan, DbError> {
+        self.plans().plan(tenant, id).map(|plan| plan.clone()).ok_or(DbError::NotFound)
+    }
+
+    fn insert(&self, tenant: &str, plan: NewPlan) -> Result<Plan, DbError> {
+        let mut plans = self.plans();
+        if plans.name_taken(tenant, &plan.name, None) {
+            return Err(DbError::AlreadyExists)
+        }
+        plans.last_id += 1;
+        let stored = sorted(Plan { ID: plans.last_id, name: plan.name, start: plan.start, days: plan.days, entries: plan.entries });
+        plans.plans.insert(stored.ID, (tenant.to_string(), stored.clone()));
+        Ok(stored)
+    }
+
+    fn update(&self, tenant: &str, id: i32, plan: NewPlan) -> Result<(), DbError> {
+        let mut plans = self.plans();
+        if plans.plan(tenant, id).is_none() {
+            return Err(DbError::NotFound)
+        }
+        if plans.name_taken(tenant, &plan.name, Some(id)) {
+            return Err(DbError::AlreadyExists)
+        }
+        let updated = sorted(Plan { ID: id, name: plan.name, start: plan.start, days: plan.days, entries: plan.entries });
+        plans.plans.insert(id, (tenant.to_string(), updated));
+        Ok(())
+    }
+
+    fn delete(&self, tenant: &str, id: i32) -> Result<(), DbError> {
+        let mut plans = self.plans();
+        plans.plan(tenant, id).ok_or(DbError::NotFound)?;
+        plans.plans.remove(&id);
+        Ok(())
+    }
+
+    fn set_entry(&self, tenant: &str, id: i32, entry: PlanEntry) -> Result<(), DbError> {
+        let mut plans = self.plans();
+        let plan = plans.plan(tenant, id).ok_or(DbError::NotFound)?;
+        plan.entries.retain(|planned| (planned.date, planned.slot) != (entry.date, entry.slot));
+        plan.entries.push(entry);
+        plan.entries.sort_by_key(|entry| (entry.date, entry.slot));
+        Ok(())
+    }
+
+    fn remove_entry(&self, tenant: &str, id: i32, date: NaiveDate, slot: Slot) -> Result<(), DbError> {
+        let mut plans = self.plans();
+        let plan = plans.plan(tenant, id).ok_or(DbError::NotFound)?;
+        let before = plan.entries.len();
+        plan.entries.retain(|planned| (planned.date, planned.slot) != (date, slot));
+        match plan.entries.len() < before {
+            true => Ok(()),
+            false => Err(DbError::NotFound),
+        }
+    }
+}
+
+#[cfg(all(test, feature = "sqlite"))]
+mod tests {
+    use super::*;
+    use crate::db::sqlite_test_pool;
+    use crate::meals::{DieselMealsRepository, DishRepository, MealRepository, NewDish, NewMeal};
+
+    #[test]
+    fn plans_keep_their_entries_until_the_meal_is_deleted() {
+        let pool = sqlite_test_pool();
+        let meals = DieselMealsRepository::new(pool.clone());
+        let soup = DishRepository::insert(&meals, "a", NewDish { name: "soup".to_string(), cal: 100.0, sodium: 10.0, sugar: 1.0, size: 250.0 }).unwrap().ID;
+        let meal = |name: &str| MealRepository::insert(&meals, "a", NewMeal { name: name.to_string(), appetizer: soup, main: soup, dessert: soup }).unwrap().ID;
+        let (lunch, dinner) = (meal("lunch"), meal("dinner"));
+
+        let repository = DieselPlanRepository::new(pool);
+        let date = |date: &str| date.parse::<NaiveDate>().unwrap();
+        let week = || NewPlan {
+            name: "week".to_string(),
+            start: date("2026-10-19"),
+            days: 7,
+            entries: vec![
+                PlanEntry { date: date("2026-10-20"), slot: Slot::Dinner, meal: dinner },
+                PlanEntry { date: date("2026-10-20"), slot: Slot::Lunch, meal: lunch },
+            ],
+        };
+        let plan = repository.insert("a", week()).unwrap();
+        assert!(matches!(repository.insert("a", week()), Err(DbError::AlreadyExists)));
+        assert_eq!(repository.find("a", plan.ID).unwrap(), plan);
+        assert_eq!(plan.entries.iter().map(|entry| entry.slot).collect::<Vec<_>>(), [Slot::Lunch, Slot::Dinner]);
+
+        /// Setting a slot replaces its meal
+        repository.set_entry("a", plan.ID, PlanEntry { date: date("2026-10-20"), slot: Slot::Lunch, meal: dinner }).unwrap();
+        repository.remove_entry("a", plan.ID, date("2026-10-20"), Slot::Dinner).unwrap();
+        assert!(matches!(repository.remove_entry("a", plan.ID, date("2026-10-20"), Slot::Dinner), Err(DbError::NotFound)));
+        assert!(matches!(repository.set_entry("b", plan.ID, PlanEntry { date: date("2026-10-20"), slot: Slot::Lunch, meal: lunch }), Err(DbError::NotFound)));
+        assert_eq!(repository.find("a", plan.ID).unwrap().entries, [PlanEntry { date: date("2026-10-20"), slot: Slot::Lunch, meal: dinner }]);
+
+        /// Deleting a meal removes it from the plans
+        MealRepository::delete(&meals, "a", dinner).unwrap();
+        assert!(repository.find("a", plan.ID).unwrap().entries.is_empty());
+
+        repository.update("a", plan.ID, NewPlan { name: "next week".to_string(), ..week() }).unwrap_err();
+        repository.update("a", plan.ID, NewPlan { name: "next week".to_string(), entries: Vec::new(), ..week() }).unwrap();
+        assert!(matches!(repository.find("b", plan.ID), Err(DbError::NotFound)));
+        assert_eq!(repository.all("a").unwrap()[0].name, "next week");
+        repository.delete("a", plan.ID).unwrap();
+        assert!(repository.all("a").unwrap().is_empty());
+    }
+}
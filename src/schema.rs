@@ -7,6 +7,21 @@ diesel::table! {
         cal -> Float8,
         sodium -> Float8,
         sugar -> Float8,
+        tenant -> Varchar,
+    }
+}
+
+diesel::table! {
+    dish_ingredients (dish_id, line) {
+        dish_id -> Int4,
+        line -> Int4,
+        ingredient -> Varchar,
+        quantity -> Float8,
+        unit -> Varchar,
+        cal -> Float8,
+        sodium -> Float8,
+        sugar -> Float8,
+        size -> Float8,
     }
 }
 
@@ -18,6 +33,22 @@ diesel::table! {
         sodium -> Float8,
         sugar -> Float8,
         size -> Float8,
+        tenant -> Varchar,
+    }
+}
+
+diesel::table! {
+    food_log (id) {
+        id -> Int4,
+        user_id -> Int4,
+        meal_id -> Nullable<Int4>,
+        dish_id -> Nullable<Int4>,
+        name -> Varchar,
+        portion -> Float8,
+        eaten_at -> Timestamp,
+        cal -> Float8,
+        sodium -> Float8,
+        sugar -> Float8,
     }
 }
 
@@ -31,11 +62,66 @@ diesel::table! {
         cal -> Nullable<Float8>,
         sodium -> Nullable<Float8>,
         sugar -> Nullable<Float8>,
+        tenant -> Varchar,
     }
 }
 
+diesel::table! {
+    plan_entries (plan_id, day, slot) {
+        plan_id -> Int4,
+        day -> Date,
+        slot -> Varchar,
+        meal_id -> Int4,
+    }
+}
+
+diesel::table! {
+    plans (id) {
+        id -> Int4,
+        tenant -> Varchar,
+        name -> Varchar,
+        start_date -> Date,
+        days -> Int4,
+    }
+}
+
+diesel::table! {
+    user_diets (user_id, diet) {
+        user_id -> Int4,
+        diet -> Varchar,
+        active -> Bool,
+    }
+}
+
+diesel::table! {
+    users (id) {
+        id -> Int4,
+        tenant -> Varchar,
+        subject -> Varchar,
+        age -> Int4,
+        sex -> Varchar,
+        weight_kg -> Float8,
+        height_cm -> Float8,
+        activity_level -> Varchar,
+    }
+}
+
+diesel::joinable!(dish_ingredients -> dishes (dish_id));
+diesel::joinable!(food_log -> dishes (dish_id));
+diesel::joinable!(food_log -> meals (meal_id));
+diesel::joinable!(food_log -> users (user_id));
+diesel::joinable!(plan_entries -> meals (meal_id));
+diesel::joinable!(plan_entries -> plans (plan_id));
+diesel::joinable!(user_diets -> users (user_id));
+
 diesel::allow_tables_to_appear_in_same_query!(
     diets,
+    dish_ingredients,
     dishes,
+    food_log,
     meals,
+    plan_entries,
+    plans,
+    user_diets,
+    users,
 );
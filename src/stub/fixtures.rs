@@ -0,0 +1,166 @@
+#![allow(unused_doc_comments)]
+
+/// Misc imports
+use serde::{Deserialize, Deserializer};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// Crate imports
+use crate::meals::NutritionInfo;
+
+/// The fixtures file of the Ninjas stub
+///
+/// ```json
+/// {
+///     "latency_ms": 0,
+///     "dishes": {
+///         "orange": { "items": [{ "name": "orange", "calories": 53.2, "sodium_mg": 1.0, "sugar_g": 12.3 }] },
+///         "slow soup": { "latency_ms": 3000, "items": [{ "name": "soup", "calories": 80.0 }] },
+///         "rate limited": { "status": 429 },
+///         "flaky": { "script": [{ "status": 500 }], "items": [{ "name": "flaky", "calories": 10.0 }] }
+///     }
+/// }
+/// ```
+///
+/// Nutrition values left out of an item are 0. Dishes are looked up case-insensitively,
+/// dishes without a fixture get an empty list, like the Ninjas API answers for dishes it does not know
+#[derive(Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Fixtures {
+    /// Added to the latency of every answer
+    #[serde(default)]
+    pub latency_ms: u64,
+    #[serde(default)]
+    pub dishes: HashMap<String, Fixture>,
+}
+
+/// How the stub answers for one dish
+#[derive(Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Fixture {
+    #[serde(default = "ok")]
+    pub status: u16,
+    #[serde(default)]
+    pub latency_ms: u64,
+    #[serde(default, deserialize_with = "nutrition_items")]
+    pub items: Vec<NutritionInfo>,
+    /// Answers for the first requests of the dish, one per request, before the fixture itself is used
+    #[serde(default)]
+    pub script: Vec<Reply>,
+}
+
+/// One answer of the stub
+#[derive(Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Reply {
+    #[serde(default = "ok")]
+    pub status: u16,
+    #[serde(default)]
+    pub latency_ms: u64,
+    #[serde(default, deserialize_with = "nutrition_items")]
+    pub items: Vec<NutritionInfo>,
+}
+
+fn ok() -> u16 {
+    200
+}
+
+/// Deserializes nutrition items whose missing values are 0
+fn nutrition_items<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<NutritionInfo>, D::Error> {
+    let items = Vec::<Map<String, Value>>::deserialize(deserializer)?;
+    items.into_iter().map(|item| {
+        let mut full = match serde_json::to_value(NutritionInfo::default()) {
+            Ok(Value::Object(full)) => full,
+            _ => unreachable!("NutritionInfo is serialized as an object"),
+        };
+        full.extend(item);
+        serde_json::from_value(Value::Object(full)).map_err(serde::de::Error::custom)
+    }).collect()
+}
+
+impl Fixtures {
+
+    /// # Loads the fixtures from a JSON file
+    /// ## Returns
+    /// * The [Fixtures], or a message naming the file and the problem
+    pub fn load(path: &str) -> Result<Fixtures, String> {
+        let content = std::fs::read_to_string(path).map_err(|e| format!("Could not read {}: {}", path, e))?;
+        serde_json::from_str(&content).map_err(|e| format!("Invalid fixtures in {}: {}", path, e))
+    }
+}
+
+/// The state of the Ninjas stub, shared by its workers
+pub struct NinjasStub {
+    latency_ms: u64,
+    dishes: HashMap<String, Fixture>,
+    /// How often each dish was requested, to play its script
+    calls: Mutex<HashMap<String, usize>>,
+}
+
+impl NinjasStub {
+
+    pub fn new(fixtures: Fixtures) -> NinjasStub {
+        NinjasStub {
+            latency_ms: fixtures.latency_ms,
+            dishes: fixtures.dishes.into_iter().map(|(dish, fixture)| (dish.to_lowercase(), fixture)).collect(),
+            calls: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// # Picks the answer for a request of `query`
+    /// ## Returns
+    /// * The next step of the script of the dish, its fixture once the script is played,
+    ///   or an empty list if the dish has no fixture. The latency includes the latency of all answers
+    pub fn reply(&self, query: &str) -> Reply {
+        let dish = query.trim().to_lowercase();
+        let mut reply = match self.dishes.get(&dish) {
+            Some(fixture) => {
+                let mut calls = self.calls.lock().unwrap_or_else(|e| e.into_inner());
+                let call = calls.entry(dish).or_insert(0);
+                *call += 1;
+                match fixture.script.get(*call - 1) {
+                    Some(step) => step.clone(),
+                    None => Reply { status: fixture.status, latency_ms: fixture.latency_ms, items: fixture.items.clone() },
+                }
+            }
+            None => Reply { status: ok(), latency_ms: 0, items: Vec::new() },
+        };
+        reply.latency_ms += self.latency_ms;
+        reply
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scripts_are_played_before_the_fixture() {
+        let fixtures: Fixtures = serde_json::from_str(r#"{
+            "latency_ms": 5,
+            "dishes": {
+                "Flaky Soup": {
+                    "items": [{ "name": "soup", "calories": 80.0 }],
+                    "script": [{ "status": 500 }, { "status": 429, "latency_ms": 100 }]
+                }
+            }
+        }"#).unwrap();
+        let stub = NinjasStub::new(fixtures);
+
+        let replies: Vec<(u16, u64, usize)> = (0..3).map(|_| stub.reply("flaky soup ")).map(|r| (r.status, r.latency_ms, r.items.len())).collect();
+        assert_eq!(replies, [(500, 5, 0), (429, 105, 0), (200, 5, 1)]);
+        let soup = stub.reply("flaky soup").items.remove(0);
+        assert_eq!((soup.calories, soup.sodium_mg), (80.0, 0.0));
+
+        /// Unknown dishes are not recognized
+        let unknown = stub.reply("blah");
+        assert_eq!((unknown.status, unknown.items.len()), (200, 0));
+    }
+
+    #[test]
+    fn typos_in_fixtures_are_errors() {
+        assert!(serde_json::from_str::<Fixtures>(r#"{"dishes": {"soup": {"latency": 5}}}"#).is_err());
+        assert!(serde_json::from_str::<Fixtures>(r#"{"dishes": {"soup": {"items": [{"calories": "many"}]}}}"#).is_err());
+    }
+}
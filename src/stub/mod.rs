@@ -0,0 +1,9 @@
+// Ninjas API stub module
+//
+// Stands in for the nutrition route of the Ninjas API in local development and the tests,
+// see the `ninjas_stub` binary
+mod fixtures;
+mod routes;
+
+pub use fixtures::{Fixture, Fixtures, NinjasStub, Reply};
+pub use routes::{configure, nutrition};
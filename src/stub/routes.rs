@@ -0,0 +1,60 @@
+#![allow(unused_doc_comments)]
+
+/// Actix imports
+use actix_web::{get, web, HttpRequest, HttpResponse, Responder};
+use actix_web::http::StatusCode;
+use actix_web::web::{Data, Query};
+
+/// Misc imports
+use serde::Deserialize;
+use serde_json::json;
+use std::time::Duration;
+
+/// Module imports
+use super::fixtures::NinjasStub;
+
+/// Query of the nutrition route, the dish to look up
+#[derive(Deserialize)]
+pub struct NutritionQuery {
+    #[serde(default)]
+    query: String,
+}
+
+/*
+=============================== GET /v1/nutrition ===============================
+ */
+/// # Creates the route answering like the nutrition route of the Ninjas API in "/v1/nutrition?query="
+/// ## Arguments
+/// * `stub` - The [NinjasStub] with the fixtures
+/// * `req` - The [HttpRequest], which must carry an `X-Api-Key` header like for the Ninjas API
+/// * `query` - The dish to look up
+/// ## Returns
+/// * [HttpResponse::Ok] with the nutrition items of the dish, after the latency of its fixture
+/// * The status of the fixture with an error message, e.g. 429 or 500
+/// * [HttpResponse::BadRequest] if the API key is missing
+#[get("/v1/nutrition")]
+pub async fn nutrition(stub: Data<NinjasStub>, req: HttpRequest, query: Query<NutritionQuery>) -> impl Responder {
+
+    /// The Ninjas API rejects requests without an API key, any key is accepted
+    if !req.headers().contains_key("X-Api-Key") {
+        return HttpResponse::BadRequest().json(json!({"error": "Missing API Key."}))
+    }
+
+    let reply = stub.reply(&query.query);
+    if reply.latency_ms > 0 {
+        actix_web::rt::time::sleep(Duration::from_millis(reply.latency_ms)).await;
+    }
+
+    match StatusCode::from_u16(reply.status) {
+        Ok(StatusCode::OK) => HttpResponse::Ok().json(reply.items),
+        Ok(status) => HttpResponse::build(status).json(json!({
+            "error": status.canonical_reason().unwrap_or("Scripted error"),
+        })),
+        Err(_) => HttpResponse::InternalServerError().json(json!({"error": format!("Invalid scripted status {}", reply.status)})),
+    }
+}
+
+/// Registers the route of the stub
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(nutrition);
+}
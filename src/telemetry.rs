@@ -0,0 +1,139 @@
+#![allow(unused_doc_comments)]
+//! This file contains the structured logging and request tracing used by both Services
+//!
+//! * [init_logging] installs a `tracing` subscriber writing one JSON object per line to stdout,
+//!   filtered by `RUST_LOG` and defaulting to `info`
+//! * [trace_requests] is wrapped around the `App` of each Service. It takes the request ID from the
+//!   `X-Request-Id` header or generates one, runs the request inside a `request` span carrying the ID
+//!   and returns the ID in the `X-Request-Id` response header
+//! * Route handlers extract the [RequestId] and forward it on their calls to other services,
+//!   so a request can be followed across the Meals and Diets Services in the logs
+
+/// Actix imports
+use actix_web::body::MessageBody;
+use actix_web::dev::{Payload, ServiceRequest, ServiceResponse};
+use actix_web::http::header::{HeaderName, HeaderValue};
+use actix_web::middleware::Next;
+use actix_web::{Error, FromRequest, HttpMessage, HttpRequest};
+
+/// Misc imports
+use std::fmt;
+use std::future::{ready, Ready};
+use std::time::Instant;
+use tracing::{info, info_span, Instrument};
+use tracing_subscriber::EnvFilter;
+use uuid::Uuid;
+
+/// Header carrying the request ID between clients and services
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Longest request ID taken over from a client, longer IDs are replaced by a generated one
+const MAX_REQUEST_ID_LEN: usize = 128;
+
+/// # Installs the JSON logger
+/// The level is taken from `RUST_LOG`, e.g. `RUST_LOG=meals_api=debug`, and defaults to `info`.
+/// At `debug` every database query is logged in a `db.query` span
+pub fn init_logging() {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+    tracing_subscriber::fmt()
+        .json()
+        .with_env_filter(filter)
+        .with_current_span(false)
+        .with_span_list(true)
+        .init();
+}
+
+/// ID of the request being handled, see [trace_requests]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RequestId(String);
+
+impl RequestId {
+
+    /// Generates a new random request ID
+    pub fn generate() -> RequestId {
+        RequestId(Uuid::new_v4().to_string())
+    }
+
+    /// Takes over a request ID sent by a client if it is short and only contains URL safe characters,
+    /// so it can be logged and forwarded as is
+    pub fn parse(value: &str) -> Option<RequestId> {
+        let is_valid = !value.is_empty()
+            && value.len() <= MAX_REQUEST_ID_LEN
+            && value.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+        is_valid.then(|| RequestId(value.to_string()))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for RequestId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// Extracts the [RequestId] stored by [trace_requests], or generates one if the middleware is not installed
+impl FromRequest for RequestId {
+    type Error = Error;
+    type Future = Ready<Result<RequestId, Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let request_id = req.extensions().get::<RequestId>().cloned().unwrap_or_else(RequestId::generate);
+        ready(Ok(request_id))
+    }
+}
+
+/// # Middleware tracing every request
+/// Runs the request inside a `request` span with its ID, method and path,
+/// logs its status and latency, and returns the ID in the `X-Request-Id` response header
+/// ## Usage
+/// `App::new().wrap(actix_web::middleware::from_fn(trace_requests))`
+pub async fn trace_requests(req: ServiceRequest, next: Next<impl MessageBody>) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let started = Instant::now();
+    let request_id = req.headers().get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(RequestId::parse)
+        .unwrap_or_else(RequestId::generate);
+    req.extensions_mut().insert(request_id.clone());
+
+    let span = info_span!("request", request_id = %request_id, method = %req.method(), path = %req.path());
+    let mut res = next.call(req).instrument(span.clone()).await?;
+
+    span.in_scope(|| info!(status = res.status().as_u16(), latency_ms = started.elapsed().as_millis() as u64, "Request completed"));
+    if let Ok(value) = HeaderValue::from_str(request_id.as_str()) {
+        res.headers_mut().insert(HeaderName::from_static(REQUEST_ID_HEADER), value);
+    }
+    Ok(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::middleware::from_fn;
+    use actix_web::{test, web, App, HttpResponse};
+
+    /// Echoes the extracted request ID
+    async fn echo(request_id: RequestId) -> HttpResponse {
+        HttpResponse::Ok().body(request_id.to_string())
+    }
+
+    #[actix_web::test]
+    async fn request_ids_are_propagated_or_generated() {
+        let app = test::init_service(App::new()
+            .wrap(from_fn(trace_requests))
+            .route("/echo", web::get().to(echo))).await;
+
+        let req = test::TestRequest::get().uri("/echo").insert_header((REQUEST_ID_HEADER, "abc-123")).to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.headers().get(REQUEST_ID_HEADER).unwrap(), "abc-123");
+        assert_eq!(test::read_body(res).await, "abc-123");
+
+        let req = test::TestRequest::get().uri("/echo").insert_header((REQUEST_ID_HEADER, "bad id")).to_request();
+        let res = test::call_service(&app, req).await;
+        let generated = res.headers().get(REQUEST_ID_HEADER).unwrap().to_str().unwrap().to_string();
+        assert!(Uuid::parse_str(&generated).is_ok());
+        assert_eq!(test::read_body(res).await, generated);
+    }
+}
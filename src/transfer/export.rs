@@ -0,0 +1,46 @@
+#![allow(unused_doc_comments)]
+
+/// Diesel imports
+use diesel::prelude::*;
+
+/// Module imports
+use super::models::{Dataset, DietRecord, DishRecord, IngredientRecord, MealRecord, PlanEntryRecord, PlanRecord, FORMAT_VERSION};
+
+/// Crate imports
+use crate::db::BackendConnection;
+use crate::schema::{diets, dish_ingredients, dishes, meals, plan_entries, plans};
+
+/// # Export the database
+/// Loads all dishes with their ingredient lines, meals, diets and plans of all tenants into a [Dataset], ordered by their ID
+/// ## Arguments
+/// * `conn` - A connection to the database
+/// ## Returns
+/// * A [Dataset] containing every record, or the [diesel::result::Error] of the first failing query
+pub fn export_dataset(conn: &mut BackendConnection) -> Result<Dataset, diesel::result::Error> {
+
+    /// Run all queries in a single transaction so the document is a consistent snapshot
+    conn.transaction(|conn| {
+        let all_dishes = dishes::table.order(dishes::id).select(DishRecord::as_select()).load(conn)?;
+        let all_ingredients = dish_ingredients::table
+            .order((dish_ingredients::dish_id, dish_ingredients::line))
+            .select(IngredientRecord::as_select())
+            .load(conn)?;
+        let all_meals = meals::table.order(meals::id).select(MealRecord::as_select()).load(conn)?;
+        let all_diets = diets::table.order(diets::id).select(DietRecord::as_select()).load(conn)?;
+        let all_plans = plans::table.order(plans::id).select(PlanRecord::as_select()).load(conn)?;
+        let all_plan_entries = plan_entries::table
+            .order((plan_entries::plan_id, plan_entries::day, plan_entries::slot))
+            .select(PlanEntryRecord::as_select())
+            .load(conn)?;
+
+        Ok(Dataset {
+            version: FORMAT_VERSION,
+            dishes: all_dishes,
+            ingredients: all_ingredients,
+            meals: all_meals,
+            diets: all_diets,
+            plans: all_plans,
+            plan_entries: all_plan_entries,
+        })
+    })
+}
@@ -0,0 +1,229 @@
+#![allow(unused_doc_comments)]
+
+/// Diesel imports
+use diesel::prelude::*;
+use diesel::{delete, insert_into};
+
+/// Misc imports
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+
+/// Module imports
+use super::models::{Dataset, ImportDiet, ImportDish, ImportMeal, ImportMode, ImportPlan, ImportPlanEntry, ImportReport, ImportIngredient, FORMAT_VERSION};
+
+/// Crate imports
+use crate::db::BackendConnection;
+use crate::schema::{diets, dish_ingredients, dishes, food_log, meals, plan_entries, plans};
+
+/// # Import a dataset into the database
+/// Inserts all records of the [Dataset] in a single transaction.
+/// Dish and meal IDs are remapped to the IDs assigned by the target database and the ingredient line, meal and plan references are rewritten accordingly.
+/// Every record keeps its tenant, names are matched within the tenant.
+/// ## Arguments
+/// * `conn` - A connection to the database
+/// * `dataset` - The [Dataset] to import
+/// * `mode` - Whether to [ImportMode::Merge] into or [ImportMode::Replace] the existing data
+/// ## Returns
+/// * An [ImportReport] on success, nothing is written if any step fails
+pub fn import_dataset(conn: &mut BackendConnection, dataset: &Dataset, mode: ImportMode) -> Result<ImportReport, Box<dyn Error + Send + Sync + 'static>> {
+
+    /// Refuse documents written by a newer version of the exporter
+    if dataset.version > FORMAT_VERSION {
+        return Err(format!("Unsupported export version {}, this binary supports up to version {}", dataset.version, FORMAT_VERSION).into())
+    }
+
+    let report = conn.transaction::<_, diesel::result::Error, _>(|conn| {
+        let mut report = ImportReport::default();
+
+        /// In replace mode, clear the tables first. Plans are deleted before the meals they reference, meals before their dishes,
+        /// the ingredient lines go with their dishes.
+        /// The food log is not part of the document and stays, but its entries lose their references to the deleted meals and dishes
+        if mode == ImportMode::Replace {
+            report.log_entries_detached = food_log::table
+                .filter(food_log::meal_id.is_not_null().or(food_log::dish_id.is_not_null()))
+                .count()
+                .get_result::<i64>(conn)? as usize;
+            delete(plans::table).execute(conn)?;
+            delete(meals::table).execute(conn)?;
+            delete(dishes::table).execute(conn)?;
+            delete(diets::table).execute(conn)?;
+        }
+
+        /// Insert the dishes and remember which tenant and new ID every exported ID maps to
+        let mut dish_ids: HashMap<i32, (&str, i32)> = HashMap::new();
+        let mut inserted_dishes = HashSet::new();
+        for dish in &dataset.dishes {
+            let existing = dishes::table
+                .filter(dishes::tenant.eq(&dish.tenant))
+                .filter(dishes::name.eq(&dish.name))
+                .select(dishes::id)
+                .first::<i32>(conn)
+                .optional()?;
+
+            let new_id = match existing {
+                Some(existing_id) => {
+                    report.dishes_skipped += 1;
+                    existing_id
+                }
+                None => {
+                    report.dishes_inserted += 1;
+                    inserted_dishes.insert(dish.id);
+                    insert_into(dishes::table)
+                        .values(ImportDish {
+                            tenant: &dish.tenant,
+                            name: &dish.name,
+                            cal: dish.cal,
+                            sodium: dish.sodium,
+                            sugar: dish.sugar,
+                            size: dish.size,
+                        })
+                        .returning(dishes::id)
+                        .get_result::<i32>(conn)?
+                }
+            };
+            dish_ids.insert(dish.id, (&dish.tenant, new_id));
+        }
+
+        /// Insert the ingredient lines of the inserted dishes with their dish reference rewritten to the new ID
+        /// A skipped dish keeps the lines it has, the lines of dishes that are not part of the document are dropped
+        for line in &dataset.ingredients {
+            let dish_id = match dish_ids.get(&line.dish_id) {
+                Some((_, dish_id)) if inserted_dishes.contains(&line.dish_id) => *dish_id,
+                Some(_) => continue,
+                None => {
+                    report.dangling_references += 1;
+                    continue;
+                }
+            };
+            insert_into(dish_ingredients::table)
+                .values(ImportIngredient {
+                    dish_id,
+                    line: line.line,
+                    ingredient: &line.ingredient,
+                    quantity: line.quantity,
+                    unit: &line.unit,
+                    cal: line.cal,
+                    sodium: line.sodium,
+                    sugar: line.sugar,
+                    size: line.size,
+                })
+                .execute(conn)?;
+            report.ingredients_inserted += 1;
+        }
+
+        /// Insert the meals with their dish references rewritten to the new IDs and remember their new IDs
+        /// References to dishes of another tenant are dropped like references to missing dishes
+        /// The nutrition values are filled in by the update_meal_nutrition trigger
+        let mut meal_ids: HashMap<i32, (&str, i32)> = HashMap::new();
+        for meal in &dataset.meals {
+            let existing = meals::table
+                .filter(meals::tenant.eq(&meal.tenant))
+                .filter(meals::name.eq(&meal.name))
+                .select(meals::id)
+                .first::<i32>(conn)
+                .optional()?;
+            if let Some(existing_id) = existing {
+                report.meals_skipped += 1;
+                meal_ids.insert(meal.id, (&meal.tenant, existing_id));
+                continue;
+            }
+
+            let mut remap = |dish_id: Option<i32>| -> Option<i32> {
+                let dish_id = dish_id?;
+                let new_id = dish_ids.get(&dish_id)
+                    .filter(|(tenant, _)| *tenant == meal.tenant)
+                    .map(|(_, new_id)| *new_id);
+                if new_id.is_none() {
+                    report.dangling_references += 1;
+                }
+                new_id
+            };
+            let new_meal = ImportMeal {
+                tenant: &meal.tenant,
+                name: &meal.name,
+                appetizer: remap(meal.appetizer),
+                main: remap(meal.main),
+                dessert: remap(meal.dessert),
+            };
+
+            let new_id = insert_into(meals::table).values(new_meal).returning(meals::id).get_result::<i32>(conn)?;
+            meal_ids.insert(meal.id, (&meal.tenant, new_id));
+            report.meals_inserted += 1;
+        }
+
+        /// Insert the diets, diets with a name that already exists in their tenant are skipped
+        for diet in &dataset.diets {
+            let exists = diets::table
+                .filter(diets::tenant.eq(&diet.tenant))
+                .filter(diets::name.eq(&diet.name))
+                .select(diets::id)
+                .first::<i32>(conn)
+                .optional()?;
+            if exists.is_some() {
+                report.diets_skipped += 1;
+                continue;
+            }
+
+            insert_into(diets::table)
+                .values(ImportDiet {
+                    tenant: &diet.tenant,
+                    name: &diet.name,
+                    cal: diet.cal,
+                    sodium: diet.sodium,
+                    sugar: diet.sugar,
+                })
+                .execute(conn)?;
+            report.diets_inserted += 1;
+        }
+
+        /// Insert the plans, plans with a name that already exists in their tenant are skipped together with their entries
+        let mut plan_ids: HashMap<i32, (&str, i32)> = HashMap::new();
+        for plan in &dataset.plans {
+            let exists = plans::table
+                .filter(plans::tenant.eq(&plan.tenant))
+                .filter(plans::name.eq(&plan.name))
+                .select(plans::id)
+                .first::<i32>(conn)
+                .optional()?;
+            if exists.is_some() {
+                report.plans_skipped += 1;
+                continue;
+            }
+
+            let new_id = insert_into(plans::table)
+                .values(ImportPlan {
+                    tenant: &plan.tenant,
+                    name: &plan.name,
+                    start_date: plan.start_date,
+                    days: plan.days,
+                })
+                .returning(plans::id)
+                .get_result::<i32>(conn)?;
+            plan_ids.insert(plan.id, (&plan.tenant, new_id));
+            report.plans_inserted += 1;
+        }
+
+        /// Insert the entries of the inserted plans with their meal references rewritten to the new IDs
+        /// Entries of meals that are missing or belong to another tenant are dropped
+        for entry in &dataset.plan_entries {
+            let (tenant, plan_id) = match plan_ids.get(&entry.plan_id) {
+                Some(plan) => *plan,
+                None => continue,
+            };
+            let meal_id = match meal_ids.get(&entry.meal_id).filter(|(meal_tenant, _)| *meal_tenant == tenant) {
+                Some((_, meal_id)) => *meal_id,
+                None => {
+                    report.dangling_references += 1;
+                    continue;
+                }
+            };
+            insert_into(plan_entries::table)
+                .values(ImportPlanEntry { plan_id, day: entry.day, slot: &entry.slot, meal_id })
+                .execute(conn)?;
+        }
+
+        Ok(report)
+    })?;
+
+    Ok(report)
+}
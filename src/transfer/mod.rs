@@ -0,0 +1,8 @@
+// Data transfer module
+mod export;
+mod import;
+mod models;
+
+pub use export::export_dataset;
+pub use import::import_dataset;
+pub use models::{Dataset, DietRecord, DishRecord, ImportMode, IngredientRecord, ImportReport, MealRecord, PlanEntryRecord, PlanRecord, FORMAT_VERSION};
@@ -0,0 +1,229 @@
+#![allow(unused_doc_comments)]
+
+use crate::schema::{diets, dish_ingredients, dishes, meals, plan_entries, plans};
+use chrono::NaiveDate;
+use diesel::prelude::*;
+use serde::{Serialize, Deserialize};
+
+/// Version of the export document format
+/// Bump this whenever the layout of [Dataset] changes in a non backwards compatible way
+/// Version 2 added the tenant of every record, the records of version 1 documents belong to the default tenant.
+/// Version 3 added the plans, older documents have none
+/// Version 4 added the ingredient lines of recipe dishes, older documents have none
+pub const FORMAT_VERSION: u32 = 4;
+
+/// Tenant of the records of documents written before tenants were exported
+fn default_tenant() -> String {
+    crate::auth::DEFAULT_TENANT.to_string()
+}
+
+/// Struct that represents a full export of the database, with the data of all tenants
+/// The ids stored in the document are the ids of the source database and are remapped on import
+#[derive(Serialize, Deserialize)]
+pub struct Dataset {
+    pub version: u32,
+    pub dishes: Vec<DishRecord>,
+    #[serde(default)]
+    pub ingredients: Vec<IngredientRecord>,
+    pub meals: Vec<MealRecord>,
+    pub diets: Vec<DietRecord>,
+    #[serde(default)]
+    pub plans: Vec<PlanRecord>,
+    #[serde(default)]
+    pub plan_entries: Vec<PlanEntryRecord>,
+}
+
+/// Dish as it is stored in the export document
+#[derive(Queryable, Selectable, Serialize, Deserialize)]
+#[diesel(table_name = dishes)]
+pub struct DishRecord {
+    pub id: i32,
+    #[serde(default = "default_tenant")]
+    pub tenant: String,
+    pub name: String,
+    pub cal: f64,
+    pub sodium: f64,
+    pub sugar: f64,
+    pub size: f64,
+}
+
+/// Ingredient line of a recipe dish as it is stored in the export document
+/// The nutrition values are the ones resolved when the line was stored, the nutrition API is not asked again on import
+#[derive(Queryable, Selectable, Serialize, Deserialize)]
+#[diesel(table_name = dish_ingredients)]
+pub struct IngredientRecord {
+    pub dish_id: i32,
+    pub line: i32,
+    pub ingredient: String,
+    pub quantity: f64,
+    pub unit: String,
+    pub cal: f64,
+    pub sodium: f64,
+    pub sugar: f64,
+    pub size: f64,
+}
+
+/// Meal as it is stored in the export document
+/// The nutrition values are informational only, they are recomputed by the database on import
+#[derive(Queryable, Selectable, Serialize, Deserialize)]
+#[diesel(table_name = meals)]
+pub struct MealRecord {
+    pub id: i32,
+    #[serde(default = "default_tenant")]
+    pub tenant: String,
+    pub name: String,
+    pub appetizer: Option<i32>,
+    pub main: Option<i32>,
+    pub dessert: Option<i32>,
+    pub cal: Option<f64>,
+    pub sodium: Option<f64>,
+    pub sugar: Option<f64>,
+}
+
+/// Diet as it is stored in the export document
+#[derive(Queryable, Selectable, Serialize, Deserialize)]
+#[diesel(table_name = diets)]
+pub struct DietRecord {
+    pub id: i32,
+    #[serde(default = "default_tenant")]
+    pub tenant: String,
+    pub name: String,
+    pub cal: f64,
+    pub sodium: f64,
+    pub sugar: f64,
+}
+
+/// Plan as it is stored in the export document, its entries are stored separately
+#[derive(Queryable, Selectable, Serialize, Deserialize)]
+#[diesel(table_name = plans)]
+pub struct PlanRecord {
+    pub id: i32,
+    #[serde(default = "default_tenant")]
+    pub tenant: String,
+    pub name: String,
+    pub start_date: NaiveDate,
+    pub days: i32,
+}
+
+/// Meal planned for a slot of a day of a plan, as it is stored in the export document
+#[derive(Queryable, Selectable, Serialize, Deserialize)]
+#[diesel(table_name = plan_entries)]
+pub struct PlanEntryRecord {
+    pub plan_id: i32,
+    pub day: NaiveDate,
+    pub slot: String,
+    pub meal_id: i32,
+}
+
+/// Struct to represent an imported dish to be inserted into the database
+#[derive(Insertable)]
+#[diesel(table_name = dishes)]
+pub struct ImportDish<'a> {
+    pub tenant: &'a str,
+    pub name: &'a str,
+    pub cal: f64,
+    pub sodium: f64,
+    pub sugar: f64,
+    pub size: f64,
+}
+
+/// Struct to represent an imported ingredient line to be inserted into the database
+#[derive(Insertable)]
+#[diesel(table_name = dish_ingredients)]
+pub struct ImportIngredient<'a> {
+    pub dish_id: i32,
+    pub line: i32,
+    pub ingredient: &'a str,
+    pub quantity: f64,
+    pub unit: &'a str,
+    pub cal: f64,
+    pub sodium: f64,
+    pub sugar: f64,
+    pub size: f64,
+}
+
+/// Struct to represent an imported meal to be inserted into the database
+/// Unlike [crate::meals] NewMeal the dish references are nullable, since the source database might contain
+/// meals whose dishes were deleted
+#[derive(Insertable)]
+#[diesel(table_name = meals)]
+pub struct ImportMeal<'a> {
+    pub tenant: &'a str,
+    pub name: &'a str,
+    pub appetizer: Option<i32>,
+    pub main: Option<i32>,
+    pub dessert: Option<i32>,
+}
+
+/// Struct to represent an imported diet to be inserted into the database
+#[derive(Insertable)]
+#[diesel(table_name = diets)]
+pub struct ImportDiet<'a> {
+    pub tenant: &'a str,
+    pub name: &'a str,
+    pub cal: f64,
+    pub sodium: f64,
+    pub sugar: f64,
+}
+
+/// Struct to represent an imported plan to be inserted into the database
+#[derive(Insertable)]
+#[diesel(table_name = plans)]
+pub struct ImportPlan<'a> {
+    pub tenant: &'a str,
+    pub name: &'a str,
+    pub start_date: NaiveDate,
+    pub days: i32,
+}
+
+/// Struct to represent an imported plan entry to be inserted into the database
+#[derive(Insertable)]
+#[diesel(table_name = plan_entries)]
+pub struct ImportPlanEntry<'a> {
+    pub plan_id: i32,
+    pub day: NaiveDate,
+    pub slot: &'a str,
+    pub meal_id: i32,
+}
+
+/// How an import treats the data that is already in the database
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ImportMode {
+    /// Keep existing records, records with a name that already exists in their tenant are skipped and reused
+    Merge,
+    /// Delete all existing dishes, meals, diets and plans before importing
+    /// The food log of the users is kept, but its entries no longer reference the deleted meals and dishes
+    Replace,
+}
+
+impl std::str::FromStr for ImportMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "merge" => Ok(ImportMode::Merge),
+            "replace" => Ok(ImportMode::Replace),
+            other => Err(format!("Unknown import mode '{}', expected 'merge' or 'replace'", other)),
+        }
+    }
+}
+
+/// Summary of an import, printed by the data_transfer binary
+#[derive(Default, Serialize)]
+pub struct ImportReport {
+    pub dishes_inserted: usize,
+    pub dishes_skipped: usize,
+    /// Ingredient lines of the inserted dishes, the lines of skipped dishes are not imported
+    pub ingredients_inserted: usize,
+    pub meals_inserted: usize,
+    pub meals_skipped: usize,
+    pub diets_inserted: usize,
+    pub diets_skipped: usize,
+    pub plans_inserted: usize,
+    pub plans_skipped: usize,
+    /// Meal references to dishes that were not part of the document and were therefore set to null,
+    /// and ingredient lines and plan entries of dishes and meals that were not part of the document and were therefore dropped
+    pub dangling_references: usize,
+    /// Food log entries that referenced a meal or dish deleted by [ImportMode::Replace], they keep their nutrients
+    pub log_entries_detached: usize,
+}
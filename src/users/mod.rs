@@ -0,0 +1,8 @@
+// Users module, served by the Meals Service
+mod models;
+mod repository;
+mod users_routes;
+
+pub use models::{ActivityLevel, Profile, ReqProfile, ReqUserDiet, Sex, User, UserDiet};
+pub use repository::{DieselUserRepository, InMemoryUserRepository, UserRepository};
+pub use users_routes::*;
@@ -0,0 +1,206 @@
+#![allow(unused_doc_comments)]
+#![allow(non_snake_case)]
+
+use crate::schema::{user_diets, users};
+use diesel::prelude::*;
+use serde::{Serialize, Deserialize};
+use std::fmt;
+use std::str::FromStr;
+use utoipa::ToSchema;
+
+/// Sex of a user, as used by the energy expenditure formulas
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Sex {
+    Female,
+    Male,
+}
+
+/// How active a user is in everyday life
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ActivityLevel {
+    /// Little or no exercise
+    Sedentary,
+    /// Exercise 1 to 3 days a week
+    Light,
+    /// Exercise 3 to 5 days a week
+    Moderate,
+    /// Exercise 6 to 7 days a week
+    Active,
+    /// Hard exercise every day or a physical job
+    VeryActive,
+}
+
+impl Sex {
+    const ALL: [Sex; 2] = [Sex::Female, Sex::Male];
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            Sex::Female => "female",
+            Sex::Male => "male",
+        }
+    }
+}
+
+impl ActivityLevel {
+    const ALL: [ActivityLevel; 5] = [
+        ActivityLevel::Sedentary, ActivityLevel::Light, ActivityLevel::Moderate, ActivityLevel::Active, ActivityLevel::VeryActive,
+    ];
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            ActivityLevel::Sedentary => "sedentary",
+            ActivityLevel::Light => "light",
+            ActivityLevel::Moderate => "moderate",
+            ActivityLevel::Active => "active",
+            ActivityLevel::VeryActive => "very_active",
+        }
+    }
+}
+
+impl FromStr for Sex {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Sex, String> {
+        Sex::ALL.into_iter().find(|sex| sex.as_str() == s).ok_or_else(|| format!("unknown sex `{}`", s))
+    }
+}
+
+impl FromStr for ActivityLevel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<ActivityLevel, String> {
+        ActivityLevel::ALL.into_iter().find(|level| level.as_str() == s).ok_or_else(|| format!("unknown activity level `{}`", s))
+    }
+}
+
+impl fmt::Display for Sex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl fmt::Display for ActivityLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// The enums are stored as their names in VARCHAR columns, these conversions are used by Diesel
+impl TryFrom<String> for Sex {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Sex, String> {
+        s.parse()
+    }
+}
+
+impl From<Sex> for String {
+    fn from(sex: Sex) -> String {
+        sex.to_string()
+    }
+}
+
+impl TryFrom<String> for ActivityLevel {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<ActivityLevel, String> {
+        s.parse()
+    }
+}
+
+impl From<ActivityLevel> for String {
+    fn from(level: ActivityLevel) -> String {
+        level.to_string()
+    }
+}
+
+/// User struct to represent a registered user in the database
+/// The subject is the name of the API key or the `sub` claim the user authenticates with
+/// The tenant of the user is not part of it, the repositories filter by it
+#[derive(Clone, Debug, Queryable, Selectable, Serialize, Deserialize, ToSchema)]
+#[diesel(table_name = users)]
+pub struct User {
+    #[diesel(column_name = id)]
+    pub ID: i32,
+    pub subject: String,
+    #[diesel(embed)]
+    #[serde(flatten)]
+    pub profile: Profile,
+}
+
+/// The profile of a user, everything the user states about themselves
+#[derive(Clone, Debug, PartialEq, Queryable, Selectable, Insertable, AsChangeset, Serialize, Deserialize, ToSchema)]
+#[diesel(table_name = users)]
+pub struct Profile {
+    /// Age in years
+    pub age: i32,
+    #[diesel(deserialize_as = String, serialize_as = String)]
+    pub sex: Sex,
+    pub weight_kg: f64,
+    pub height_cm: f64,
+    #[diesel(deserialize_as = String, serialize_as = String)]
+    pub activity_level: ActivityLevel,
+}
+
+impl Profile {
+
+    /// Checks that the values are plausible for a human, returns the problem otherwise
+    pub fn validate(&self) -> Result<(), String> {
+        if !(1..=130).contains(&self.age) {
+            return Err(format!("age must be between 1 and 130, not {}", self.age))
+        }
+        if !(self.weight_kg > 0.0 && self.weight_kg <= 700.0) {
+            return Err(format!("weight_kg must be above 0 and at most 700, not {}", self.weight_kg))
+        }
+        if !(self.height_cm > 0.0 && self.height_cm <= 300.0) {
+            return Err(format!("height_cm must be above 0 and at most 300, not {}", self.height_cm))
+        }
+        Ok(())
+    }
+}
+
+/// Struct that represents a profile requested by the user, for registration or an update
+/// The Values are optionals since a user might not include all of them and we need to be able to handle that
+/// Translates to a [Profile] if all values are present
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct ReqProfile {
+    pub age: Option<i32>,
+    pub sex: Option<Sex>,
+    pub weight_kg: Option<f64>,
+    pub height_cm: Option<f64>,
+    pub activity_level: Option<ActivityLevel>,
+}
+
+impl ReqProfile {
+
+    /// The [Profile], if all values are present
+    pub fn into_profile(self) -> Option<Profile> {
+        Some(Profile {
+            age: self.age?,
+            sex: self.sex?,
+            weight_kg: self.weight_kg?,
+            height_cm: self.height_cm?,
+            activity_level: self.activity_level?,
+        })
+    }
+}
+
+/// A diet assigned to a user
+/// Only the active diets of a user filter the meals, see `GET /meals?user=me`
+#[derive(Clone, Debug, PartialEq, Queryable, Selectable, Serialize, Deserialize, ToSchema)]
+#[diesel(table_name = user_diets)]
+pub struct UserDiet {
+    /// Name of the diet in the Diets Service
+    pub diet: String,
+    pub active: bool,
+}
+
+/// Struct that represents a diet assignment requested by the user
+/// `active` defaults to true when a diet is assigned
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct ReqUserDiet {
+    pub diet: Option<String>,
+    pub active: Option<bool>,
+}
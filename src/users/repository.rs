@@ -0,0 +1,286 @@
+#![allow(unused_doc_comments)]
+//! This file contains the storage of the users and their diets
+//!
+//! The route handlers only know the [UserRepository] trait.
+//! [DieselUserRepository] stores the users in the database, [InMemoryUserRepository] keeps them in memory,
+//! so the handlers can be tested without a database.
+//!
+//! The methods block, handlers call them with [crate::db::run_blocking].
+//! A user is identified by its tenant and subject, see [crate::auth::Principal]
+
+/// Diesel imports
+use diesel::prelude::*;
+use diesel::{delete, insert_into, update};
+
+/// Misc imports
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+/// Module imports
+use super::models::{Profile, User, UserDiet};
+
+/// Crate imports
+use crate::db::{get_connection, BackendConnection, DbError, DbPool};
+use crate::schema::{user_diets, users};
+
+/// Storage of the users and the diets assigned to them
+pub trait UserRepository: Send + Sync {
+
+    /// Returns the user with the subject, or [DbError::NotFound]
+    fn find(&self, tenant: &str, subject: &str) -> Result<User, DbError>;
+
+    /// Registers a user, or returns [DbError::AlreadyExists] if the subject is already registered
+    fn insert(&self, tenant: &str, subject: &str, profile: Profile) -> Result<User, DbError>;
+
+    /// Replaces the profile of the user, or returns [DbError::NotFound]
+    fn update(&self, tenant: &str, subject: &str, profile: Profile) -> Result<User, DbError>;
+
+    /// Returns the diets assigned to the user ordered by their name, or [DbError::NotFound] if there is no such user
+    fn diets(&self, tenant: &str, subject: &str) -> Result<Vec<UserDiet>, DbError>;
+
+    /// Assigns a diet to the user
+    /// Returns [DbError::NotFound] if there is no such user, [DbError::AlreadyExists] if the diet is already assigned
+    fn assign_diet(&self, tenant: &str, subject: &str, diet: UserDiet) -> Result<(), DbError>;
+
+    /// Activates or deactivates a diet of the user, or returns [DbError::NotFound] if the diet is not assigned
+    fn set_diet_active(&self, tenant: &str, subject: &str, diet: &str, active: bool) -> Result<(), DbError>;
+
+    /// Removes a diet from the user, or returns [DbError::NotFound] if the diet is not assigned
+    fn remove_diet(&self, tenant: &str, subject: &str, diet: &str) -> Result<(), DbError>;
+}
+
+/*
+=============================== Database ===============================
+ */
+
+/// Users stored in Postgres or SQLite, see [crate::db::BackendConnection]
+pub struct DieselUserRepository {
+    pool: DbPool,
+}
+
+impl DieselUserRepository {
+    pub fn new(pool: DbPool) -> DieselUserRepository {
+        DieselUserRepository { pool }
+    }
+}
+
+/// Returns the ID of the user with the subject, or [diesel::result::Error::NotFound]
+fn user_id(conn: &mut BackendConnection, tenant: &str, subject: &str) -> Result<i32, diesel::result::Error> {
+    users::table
+        .filter(users::tenant.eq(tenant))
+        .filter(users::subject.eq(subject))
+        .select(users::id)
+        .first(conn)
+}
+
+impl UserRepository for DieselUserRepository {
+
+    fn find(&self, tenant: &str, subject: &str) -> Result<User, DbError> {
+        let conn = &mut get_connection(&self.pool)?;
+        let user = users::table
+            .filter(users::tenant.eq(tenant))
+            .filter(users::subject.eq(subject))
+            .select(User::as_select())
+            .first(conn)?;
+        Ok(user)
+    }
+
+    fn insert(&self, tenant: &str, subject: &str, profile: Profile) -> Result<User, DbError> {
+        let conn = &mut get_connection(&self.pool)?;
+        let user = insert_into(users::table)
+            .values((profile, users::tenant.eq(tenant), users::subject.eq(subject)))
+            .returning(User::as_returning())
+            .get_result(conn)?;
+        Ok(user)
+    }
+
+    fn update(&self, tenant: &str, subject: &str, profile: Profile) -> Result<User, DbError> {
+        let conn = &mut get_connection(&self.pool)?;
+        let user = update(users::table.filter(users::tenant.eq(tenant)).filter(users::subject.eq(subject)))
+            .set(profile)
+            .returning(User::as_returning())
+            .get_result(conn)?;
+        Ok(user)
+    }
+
+    fn diets(&self, tenant: &str, subject: &str) -> Result<Vec<UserDiet>, DbError> {
+        let conn = &mut get_connection(&self.pool)?;
+        let diets = conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            let user_id = user_id(conn, tenant, subject)?;
+            user_diets::table
+                .filter(user_diets::user_id.eq(user_id))
+                .order(user_diets::diet)
+                .select(UserDiet::as_select())
+                .load(conn)
+        })?;
+        Ok(diets)
+    }
+
+    fn assign_diet(&self, tenant: &str, subject: &str, diet: UserDiet) -> Result<(), DbError> {
+        let conn = &mut get_connection(&self.pool)?;
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            let user_id = user_id(conn, tenant, subject)?;
+            insert_into(user_diets::table)
+                .values((user_diets::user_id.eq(user_id), user_diets::diet.eq(&diet.diet), user_diets::active.eq(diet.active)))
+                .execute(conn)
+        })?;
+        Ok(())
+    }
+
+    fn set_diet_active(&self, tenant: &str, subject: &str, diet: &str, active: bool) -> Result<(), DbError> {
+        let conn = &mut get_connection(&self.pool)?;
+        let updated = conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            let user_id = user_id(conn, tenant, subject)?;
+            update(user_diets::table.find((user_id, diet)))
+                .set(user_diets::active.eq(active))
+                .execute(conn)
+        })?;
+        match updated {
+            0 => Err(DbError::NotFound),
+            _ => Ok(()),
+        }
+    }
+
+    fn remove_diet(&self, tenant: &str, subject: &str, diet: &str) -> Result<(), DbError> {
+        let conn = &mut get_connection(&self.pool)?;
+        let deleted = conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            let user_id = user_id(conn, tenant, subject)?;
+            delete(user_diets::table.find((user_id, diet))).execute(conn)
+        })?;
+        match deleted {
+            0 => Err(DbError::NotFound),
+            _ => Ok(()),
+        }
+    }
+}
+
+/*
+=============================== In memory ===============================
+ */
+
+/// A user of the [InMemoryUserRepository] with its diets
+struct StoredUser {
+    user: User,
+    diets: BTreeMap<String, bool>,
+}
+
+/// The users of the [InMemoryUserRepository] by tenant and subject, and the last ID handed out
+#[derive(Default)]
+struct Users {
+    users: BTreeMap<(String, String), StoredUser>,
+    last_id: i32,
+}
+
+/// Users kept in memory, with the same behavior as [DieselUserRepository]
+#[derive(Default)]
+pub struct InMemoryUserRepository {
+    users: Mutex<Users>,
+}
+
+impl InMemoryUserRepository {
+
+    pub fn new() -> InMemoryUserRepository {
+        InMemoryUserRepository::default()
+    }
+
+    /// Locks the users, a panic of another thread holding the lock leaves them usable
+    fn users(&self) -> std::sync::MutexGuard<'_, Users> {
+        self.users.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Runs `f` on the user with the subject, or returns [DbError::NotFound]
+    fn with_user<T>(&self, tenant: &str, subject: &str, f: impl FnOnce(&mut StoredUser) -> Result<T, DbError>) -> Result<T, DbError> {
+        let mut users = self.users();
+        let user = users.users.get_mut(&(tenant.to_string(), subject.to_string())).ok_or(DbError::NotFound)?;
+        f(user)
+    }
+}
+
+impl UserRepository for InMemoryUserRepository {
+
+    fn find(&self, tenant: &str, subject: &str) -> Result<User, DbError> {
+        self.with_user(tenant, subject, |stored| Ok(stored.user.clone()))
+    }
+
+    fn insert(&self, tenant: &str, subject: &str, profile: Profile) -> Result<User, DbError> {
+        let mut users = self.users();
+        let key = (tenant.to_string(), subject.to_string());
+        if users.users.contains_key(&key) {
+            return Err(DbError::AlreadyExists)
+        }
+        users.last_id += 1;
+        let user = User { ID: users.last_id, subject: subject.to_string(), profile };
+        users.users.insert(key, StoredUser { user: user.clone(), diets: BTreeMap::new() });
+        Ok(user)
+    }
+
+    fn update(&self, tenant: &str, subject: &str, profile: Profile) -> Result<User, DbError> {
+        self.with_user(tenant, subject, |stored| {
+            stored.user.profile = profile;
+            Ok(stored.user.clone())
+        })
+    }
+
+    fn diets(&self, tenant: &str, subject: &str) -> Result<Vec<UserDiet>, DbError> {
+        self.with_user(tenant, subject, |stored| {
+            Ok(stored.diets.iter().map(|(diet, active)| UserDiet { diet: diet.clone(), active: *active }).collect())
+        })
+    }
+
+    fn assign_diet(&self, tenant: &str, subject: &str, diet: UserDiet) -> Result<(), DbError> {
+        self.with_user(tenant, subject, |stored| {
+            if stored.diets.contains_key(&diet.diet) {
+                return Err(DbError::AlreadyExists)
+            }
+            stored.diets.insert(diet.diet, diet.active);
+            Ok(())
+        })
+    }
+
+    fn set_diet_active(&self, tenant: &str, subject: &str, diet: &str, active: bool) -> Result<(), DbError> {
+        self.with_user(tenant, subject, |stored| {
+            let assigned = stored.diets.get_mut(diet).ok_or(DbError::NotFound)?;
+            *assigned = active;
+            Ok(())
+        })
+    }
+
+    fn remove_diet(&self, tenant: &str, subject: &str, diet: &str) -> Result<(), DbError> {
+        self.with_user(tenant, subject, |stored| stored.diets.remove(diet).map(|_| ()).ok_or(DbError::NotFound))
+    }
+}
+
+#[cfg(all(test, feature = "sqlite"))]
+mod tests {
+    use super::*;
+    use crate::db::sqlite_test_pool;
+    use crate::users::models::{ActivityLevel, Sex};
+
+    fn profile(age: i32) -> Profile {
+        Profile { age, sex: Sex::Female, weight_kg: 60.0, height_cm: 165.0, activity_level: ActivityLevel::VeryActive }
+    }
+
+    #[test]
+    fn users_and_their_diets_are_stored() {
+        let repository = DieselUserRepository::new(sqlite_test_pool());
+        let alice = repository.insert("a", "alice", profile(30)).unwrap();
+        assert!(matches!(repository.insert("a", "alice", profile(31)), Err(DbError::AlreadyExists)));
+        assert_eq!(repository.insert("b", "alice", profile(40)).unwrap().profile.age, 40);
+
+        /// The enums are read back from their text columns
+        assert_eq!(repository.find("a", "alice").unwrap().profile, profile(30));
+        assert_eq!(repository.update("a", "alice", profile(31)).unwrap().ID, alice.ID);
+        assert!(matches!(repository.update("a", "bob", profile(31)), Err(DbError::NotFound)));
+
+        let diet = |name: &str, active| UserDiet { diet: name.to_string(), active };
+        repository.assign_diet("a", "alice", diet("keto", true)).unwrap();
+        repository.assign_diet("a", "alice", diet("atkins", false)).unwrap();
+        assert!(matches!(repository.assign_diet("a", "alice", diet("keto", false)), Err(DbError::AlreadyExists)));
+        assert!(matches!(repository.assign_diet("a", "bob", diet("keto", true)), Err(DbError::NotFound)));
+        repository.set_diet_active("a", "alice", "atkins", true).unwrap();
+        repository.remove_diet("a", "alice", "keto").unwrap();
+        assert!(matches!(repository.remove_diet("a", "alice", "keto"), Err(DbError::NotFound)));
+        assert_eq!(repository.diets("a", "alice").unwrap(), vec![diet("atkins", true)]);
+        assert!(repository.diets("b", "alice").unwrap().is_empty());
+    }
+}
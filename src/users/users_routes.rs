@@ -0,0 +1,444 @@
+#![allow(unused_doc_comments)]
+
+/// Actix Imports
+use actix_web::{get, post, delete, put, HttpResponse, Responder, HttpRequest, web};
+use actix_web::web::Data;
+
+/// Misc Imports
+use serde_json::json;
+use tracing::warn;
+
+/// Module Imports
+use super::models::{Profile, ReqProfile, ReqUserDiet, User, UserDiet};
+use super::repository::UserRepository;
+
+/// Crate Imports
+use crate::auth::{Reader, Require};
+use crate::db::{run_blocking, DbError};
+use crate::meals::DietClient;
+use crate::negotiation::{decode_body, negotiate, request_format};
+use crate::telemetry::RequestId;
+
+/// Error codes as defined in the Assigment
+const NOT_JSON: &str = "0";
+const PARAM_NOT_FOUND: &str = "-1";
+const ALREADY_EXISTS: &str = "-2";
+const INVALID_PROFILE: &str = "-11";
+const NOT_FOUND: &str = "-5";
+
+/// Why the body of a request was rejected
+enum BodyError {
+    /// The Content-Type is not JSON, MessagePack or CBOR
+    Unsupported,
+    /// The body can not be decoded
+    Invalid,
+    /// A required field is missing
+    Missing,
+    /// A value is implausible, see [Profile::validate]
+    Implausible(String),
+}
+
+impl BodyError {
+
+    /// Returns the [HttpResponse] with the error code 0, -1 or -11
+    fn response(self) -> HttpResponse {
+        match self {
+            BodyError::Unsupported => HttpResponse::UnsupportedMediaType().body(NOT_JSON),
+            BodyError::Invalid => HttpResponse::UnprocessableEntity().body(NOT_JSON),
+            BodyError::Missing => HttpResponse::UnprocessableEntity().body(PARAM_NOT_FOUND),
+            BodyError::Implausible(message) => HttpResponse::UnprocessableEntity().json(json!({
+                "message": message,
+                "error_code": INVALID_PROFILE,
+            })),
+        }
+    }
+}
+
+/// Decodes the body of a request as JSON, MessagePack or CBOR
+fn decode<T: serde::de::DeserializeOwned>(req: &HttpRequest, body: &web::Bytes) -> Result<T, BodyError> {
+    let body_format = request_format(req).ok_or(BodyError::Unsupported)?;
+    decode_body(body_format, body).map_err(|e| {
+        warn!("{}", e);
+        BodyError::Invalid
+    })
+}
+
+/// Turns a requested profile into a [Profile], if all fields are present and plausible
+fn profile(req_profile: ReqProfile) -> Result<Profile, BodyError> {
+    let profile = req_profile.into_profile().ok_or(BodyError::Missing)?;
+    profile.validate().map_err(BodyError::Implausible)?;
+    Ok(profile)
+}
+
+/*
+=============================== POST /users ===============================
+ */
+/// # Creates the route for registering the caller as a user in "/users"
+/// The user is identified by the subject and the tenant of its credentials, so everyone can only register themselves
+/// ## Arguments
+/// * `auth` - Requires the [Reader] role, the user belongs to its tenant
+/// * `users` - The [UserRepository] storing the users
+/// * `req` - A [HttpRequest] containing the request
+/// * `body` - A [web::Bytes] containing the profile as JSON, MessagePack or CBOR
+/// ## Returns
+/// * [HttpResponse] with a status of 201 and a body containing the ID of the new user
+#[utoipa::path(
+    post,
+    path = "/users",
+    tag = "users",
+    security(("api_key" = ["reader"]), ("bearer" = ["reader"])),
+    request_body = ReqProfile,
+    responses(
+        (status = 201, description = "ID of the new user", body = i32),
+        (status = 415, description = "Content type not supported, error code 0", body = String),
+        (status = 422, description = "Body not parsable (0), field missing (-1), already registered (-2) or implausible value (-11)", body = String),
+        (status = 503, description = "Database unavailable, error code -8"),
+    ),
+)]
+#[post("/users")]
+pub async fn register_user(auth: Require<Reader>, users: Data<dyn UserRepository>, req: HttpRequest, body: web::Bytes) -> impl Responder {
+    let principal = auth.principal;
+
+    /// Decode and check the profile
+    let profile = match decode(&req, &body).and_then(profile) {
+        Ok(profile) => profile,
+        Err(e) => return e.response(),
+    };
+
+    let user = run_blocking(move || users.insert(&principal.tenant, &principal.subject, profile)).await;
+
+    /// If the caller is already registered, return a [HttpResponse::UnprocessableEntity] with a Error Code -2
+    match user {
+        Ok(user) => HttpResponse::Created().body(user.ID.to_string()),
+        Err(DbError::AlreadyExists) => HttpResponse::UnprocessableEntity().body(ALREADY_EXISTS),
+        Err(e) => e.response(),
+    }
+}
+
+/*
+=============================== GET /users/me ===============================
+ */
+/// # Creates the route for getting the caller in "/users/me"
+/// ## Arguments
+/// * `auth` - Requires the [Reader] role, only the data of its tenant is used
+/// * `users` - The [UserRepository] storing the users
+/// * `req` - A [HttpRequest] containing the request, used to pick the response format
+/// ## Returns
+/// * [HttpResponse::Ok] with a JSON body containing the user and its profile
+#[utoipa::path(
+    get,
+    path = "/users/me",
+    tag = "users",
+    security(("api_key" = ["reader"]), ("bearer" = ["reader"])),
+    responses(
+        (status = 200, description = "The caller and its profile", body = User),
+        (status = 404, description = "Caller not registered, error code -5", body = String),
+        (status = 503, description = "Database unavailable, error code -8"),
+    ),
+)]
+#[get("/users/me")]
+pub async fn get_me(auth: Require<Reader>, users: Data<dyn UserRepository>, req: HttpRequest) -> impl Responder {
+    let principal = auth.principal;
+
+    let user = run_blocking(move || users.find(&principal.tenant, &principal.subject)).await;
+
+    /// If the caller is not registered, return a [HttpResponse::NotFound] with a Error Code -5
+    match user {
+        Ok(user) => negotiate(&req, HttpResponse::Ok(), &user),
+        Err(DbError::NotFound) => HttpResponse::NotFound().body(NOT_FOUND),
+        Err(e) => e.response(),
+    }
+}
+
+/*
+=============================== PUT /users/me ===============================
+ */
+/// # Creates the route for replacing the profile of the caller in "/users/me"
+/// ## Arguments
+/// * `auth` - Requires the [Reader] role, only the data of its tenant is used
+/// * `users` - The [UserRepository] storing the users
+/// * `req` - A [HttpRequest] containing the request
+/// * `body` - A [web::Bytes] containing the new profile as JSON, MessagePack or CBOR
+/// ## Returns
+/// * [HttpResponse::Ok] with a JSON body containing the updated user
+#[utoipa::path(
+    put,
+    path = "/users/me",
+    tag = "users",
+    security(("api_key" = ["reader"]), ("bearer" = ["reader"])),
+    request_body = ReqProfile,
+    responses(
+        (status = 200, description = "The updated user", body = User),
+        (status = 404, description = "Caller not registered, error code -5", body = String),
+        (status = 415, description = "Content type not supported, error code 0", body = String),
+        (status = 422, description = "Body not parsable (0), field missing (-1) or implausible value (-11)", body = String),
+        (status = 503, description = "Database unavailable, error code -8"),
+    ),
+)]
+#[put("/users/me")]
+pub async fn update_me(auth: Require<Reader>, users: Data<dyn UserRepository>, req: HttpRequest, body: web::Bytes) -> impl Responder {
+    let principal = auth.principal;
+
+    /// Decode and check the profile
+    let profile = match decode(&req, &body).and_then(profile) {
+        Ok(profile) => profile,
+        Err(e) => return e.response(),
+    };
+
+    let user = run_blocking(move || users.update(&principal.tenant, &principal.subject, profile)).await;
+
+    /// If the caller is not registered, return a [HttpResponse::NotFound] with a Error Code -5
+    match user {
+        Ok(user) => negotiate(&req, HttpResponse::Ok(), &user),
+        Err(DbError::NotFound) => HttpResponse::NotFound().body(NOT_FOUND),
+        Err(e) => e.response(),
+    }
+}
+
+/*
+=============================== GET /users/me/diets ===============================
+ */
+/// # Creates the route for getting the diets of the caller in "/users/me/diets"
+/// ## Arguments
+/// * `auth` - Requires the [Reader] role, only the data of its tenant is used
+/// * `users` - The [UserRepository] storing the users
+/// * `req` - A [HttpRequest] containing the request, used to pick the response format
+/// ## Returns
+/// * [HttpResponse::Ok] with a JSON body containing the diets ordered by name
+#[utoipa::path(
+    get,
+    path = "/users/me/diets",
+    tag = "users",
+    security(("api_key" = ["reader"]), ("bearer" = ["reader"])),
+    responses(
+        (status = 200, description = "The diets of the caller ordered by name", body = Vec<UserDiet>),
+        (status = 404, description = "Caller not registered, error code -5", body = String),
+        (status = 503, description = "Database unavailable, error code -8"),
+    ),
+)]
+#[get("/users/me/diets")]
+pub async fn get_my_diets(auth: Require<Reader>, users: Data<dyn UserRepository>, req: HttpRequest) -> impl Responder {
+    let principal = auth.principal;
+
+    let diets = run_blocking(move || users.diets(&principal.tenant, &principal.subject)).await;
+
+    /// If the caller is not registered, return a [HttpResponse::NotFound] with a Error Code -5
+    match diets {
+        Ok(diets) => negotiate(&req, HttpResponse::Ok(), &diets),
+        Err(DbError::NotFound) => HttpResponse::NotFound().body(NOT_FOUND),
+        Err(e) => e.response(),
+    }
+}
+
+/*
+=============================== POST /users/me/diets ===============================
+ */
+/// # Creates the route for assigning a diet to the caller in "/users/me/diets"
+/// The diet has to exist in the Diets Microservice, a new diet is active unless `active` is false
+/// ## Arguments
+/// * `auth` - Requires the [Reader] role, only the data of its tenant is used
+/// * `users` - The [UserRepository] storing the users
+/// * `diet_client` - A [web::Data<DietClient>] used to check that the diet exists in the Diets Microservice
+/// * `request_id` - The [RequestId] of the request, forwarded to the Diets Microservice
+/// * `req` - A [HttpRequest] containing the request
+/// * `body` - A [web::Bytes] containing the diet as JSON, MessagePack or CBOR
+/// ## Returns
+/// * [HttpResponse] with a status of 201 and a JSON body containing the assigned diet
+#[utoipa::path(
+    post,
+    path = "/users/me/diets",
+    tag = "users",
+    security(("api_key" = ["reader"]), ("bearer" = ["reader"])),
+    request_body = ReqUserDiet,
+    responses(
+        (status = 201, description = "The assigned diet", body = UserDiet),
+        (status = 404, description = "Caller not registered (-5) or diet not found", body = String),
+        (status = 415, description = "Content type not supported, error code 0", body = String),
+        (status = 422, description = "Body not parsable (0), diet missing (-1) or diet already assigned (-2)", body = String),
+        (status = 503, description = "The Diets Service or the database is unavailable"),
+        (status = 504, description = "The Diets Service did not answer in time"),
+    ),
+)]
+#[post("/users/me/diets")]
+pub async fn assign_diet(auth: Require<Reader>, users: Data<dyn UserRepository>, diet_client: Data<DietClient>, request_id: RequestId, req: HttpRequest, body: web::Bytes) -> impl Responder {
+    let principal = auth.principal;
+
+    /// Decode the diet, its name is required
+    let req_diet: ReqUserDiet = match decode(&req, &body) {
+        Ok(req_diet) => req_diet,
+        Err(e) => return e.response(),
+    };
+    let diet = match req_diet.diet {
+        Some(diet) if !diet.is_empty() => UserDiet { diet, active: req_diet.active.unwrap_or(true) },
+        _ => return HttpResponse::UnprocessableEntity().body(PARAM_NOT_FOUND),
+    };
+
+    /// Check that the diet exists in the Diets Service of the tenant
+    if let Err(e) = diet_client.get_diet_by_name(&principal.tenant, &diet.diet, &request_id).await {
+        return e.response(&diet.diet);
+    }
+
+    let assigned = diet.clone();
+    let result = run_blocking(move || users.assign_diet(&principal.tenant, &principal.subject, assigned)).await;
+
+    /// If the caller is not registered, return a [HttpResponse::NotFound] with a Error Code -5
+    /// If the diet is already assigned, return a [HttpResponse::UnprocessableEntity] with a Error Code -2
+    match result {
+        Ok(()) => negotiate(&req, HttpResponse::Created(), &diet),
+        Err(DbError::NotFound) => HttpResponse::NotFound().body(NOT_FOUND),
+        Err(DbError::AlreadyExists) => HttpResponse::UnprocessableEntity().body(ALREADY_EXISTS),
+        Err(e) => e.response(),
+    }
+}
+
+/*
+=============================== PUT /users/me/diets/{diet} ===============================
+ */
+/// # Creates the route for activating or deactivating a diet of the caller in "/users/me/diets/{diet}"
+/// ## Arguments
+/// * `auth` - Requires the [Reader] role, only the data of its tenant is used
+/// * `users` - The [UserRepository] storing the users
+/// * `req` - A [HttpRequest] containing the request
+/// * `diet_name` - A [web::Path<String>] containing the name of the diet
+/// * `body` - A [web::Bytes] containing `active` as JSON, MessagePack or CBOR
+/// ## Returns
+/// * [HttpResponse::Ok] with a JSON body containing the updated diet
+#[utoipa::path(
+    put,
+    path = "/users/me/diets/{diet}",
+    tag = "users",
+    security(("api_key" = ["reader"]), ("bearer" = ["reader"])),
+    params(("diet" = String, Path, description = "Name of the diet")),
+    request_body = ReqUserDiet,
+    responses(
+        (status = 200, description = "The updated diet", body = UserDiet),
+        (status = 404, description = "Caller not registered or diet not assigned, error code -5", body = String),
+        (status = 415, description = "Content type not supported, error code 0", body = String),
+        (status = 422, description = "Body not parsable (0) or active missing (-1)", body = String),
+        (status = 503, description = "Database unavailable, error code -8"),
+    ),
+)]
+#[put("/users/me/diets/{diet}")]
+pub async fn update_my_diet(auth: Require<Reader>, users: Data<dyn UserRepository>, req: HttpRequest, diet_name: web::Path<String>, body: web::Bytes) -> impl Responder {
+    let principal = auth.principal;
+
+    /// Decode the diet, `active` is required
+    let req_diet: ReqUserDiet = match decode(&req, &body) {
+        Ok(req_diet) => req_diet,
+        Err(e) => return e.response(),
+    };
+    let active = match req_diet.active {
+        Some(active) => active,
+        None => return HttpResponse::UnprocessableEntity().body(PARAM_NOT_FOUND),
+    };
+
+    let diet = UserDiet { diet: diet_name.into_inner(), active };
+    let diet_name = diet.diet.clone();
+    let result = run_blocking(move || users.set_diet_active(&principal.tenant, &principal.subject, &diet_name, active)).await;
+
+    /// If the caller is not registered or the diet not assigned, return a [HttpResponse::NotFound] with a Error Code -5
+    match result {
+        Ok(()) => negotiate(&req, HttpResponse::Ok(), &diet),
+        Err(DbError::NotFound) => HttpResponse::NotFound().body(NOT_FOUND),
+        Err(e) => e.response(),
+    }
+}
+
+/*
+=============================== DELETE /users/me/diets/{diet} ===============================
+ */
+/// # Creates the route for removing a diet from the caller in "/users/me/diets/{diet}"
+/// ## Arguments
+/// * `auth` - Requires the [Reader] role, only the data of its tenant is used
+/// * `users` - The [UserRepository] storing the users
+/// * `diet_name` - A [web::Path<String>] containing the name of the diet
+/// ## Returns
+/// * [HttpResponse::Ok] with a body containing the name of the removed diet
+#[utoipa::path(
+    delete,
+    path = "/users/me/diets/{diet}",
+    tag = "users",
+    security(("api_key" = ["reader"]), ("bearer" = ["reader"])),
+    params(("diet" = String, Path, description = "Name of the diet")),
+    responses(
+        (status = 200, description = "Name of the removed diet", body = String),
+        (status = 404, description = "Caller not registered or diet not assigned, error code -5", body = String),
+        (status = 503, description = "Database unavailable, error code -8"),
+    ),
+)]
+#[delete("/users/me/diets/{diet}")]
+pub async fn remove_my_diet(auth: Require<Reader>, users: Data<dyn UserRepository>, diet_name: web::Path<String>) -> impl Responder {
+    let principal = auth.principal;
+
+    let diet_name = diet_name.into_inner();
+    let removed = diet_name.clone();
+    let result = run_blocking(move || users.remove_diet(&principal.tenant, &principal.subject, &removed)).await;
+
+    /// If the caller is not registered or the diet not assigned, return a [HttpResponse::NotFound] with a Error Code -5
+    match result {
+        Ok(()) => HttpResponse::Ok().body(diet_name),
+        Err(DbError::NotFound) => HttpResponse::NotFound().body(NOT_FOUND),
+        Err(e) => e.response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{test, App};
+    use actix_web::middleware::from_fn;
+    use crate::auth::{authenticate, AuthConfig, DEFAULT_TENANT, TENANT_HEADER};
+    use crate::users::{ActivityLevel, InMemoryUserRepository};
+    use std::sync::Arc;
+
+    #[actix_web::test]
+    async fn users_register_and_manage_their_diets() {
+        let repository = Arc::new(InMemoryUserRepository::new());
+        let app = test::init_service(App::new()
+            .app_data(Data::new(AuthConfig::dev()))
+            .wrap(from_fn(authenticate))
+            .app_data(Data::from(repository.clone() as Arc<dyn UserRepository>))
+            .service(register_user)
+            .service(get_me)
+            .service(update_me)
+            .service(get_my_diets)
+            .service(update_my_diet)
+            .service(remove_my_diet)).await;
+
+        let json = |req: test::TestRequest, body: &'static str| req.insert_header(("Content-Type", "application/json")).set_payload(body).to_request();
+        let profile = r#"{"age": 34, "sex": "female", "weight_kg": 62.5, "height_cm": 170, "activity_level": "very_active"}"#;
+        assert_eq!(test::call_service(&app, test::TestRequest::get().uri("/users/me").to_request()).await.status(), 404);
+        assert_eq!(test::call_and_read_body(&app, json(test::TestRequest::post().uri("/users"), r#"{"age": 34}"#)).await, PARAM_NOT_FOUND);
+        assert_eq!(test::call_and_read_body(&app, json(test::TestRequest::post().uri("/users"), r#"{"age": 34, "sex": "x"}"#)).await, NOT_JSON);
+        assert_eq!(test::call_and_read_body(&app, json(test::TestRequest::post().uri("/users"), profile)).await, "1");
+        assert_eq!(test::call_and_read_body(&app, json(test::TestRequest::post().uri("/users"), profile)).await, ALREADY_EXISTS);
+
+        /// Implausible values are rejected with -11
+        let res = test::call_service(&app, json(test::TestRequest::put().uri("/users/me"),
+            r#"{"age": 34, "sex": "female", "weight_kg": -1, "height_cm": 170, "activity_level": "light"}"#)).await;
+        assert_eq!(res.status(), 422);
+        let body: serde_json::Value = test::read_body_json(res).await;
+        assert_eq!(body["error_code"], INVALID_PROFILE);
+
+        let user: User = test::call_and_read_body_json(&app, json(test::TestRequest::put().uri("/users/me"),
+            r#"{"age": 35, "sex": "female", "weight_kg": 61, "height_cm": 170, "activity_level": "light"}"#)).await;
+        assert_eq!((user.ID, user.profile.age, user.profile.activity_level), (1, 35, ActivityLevel::Light));
+        let user: User = test::call_and_read_body_json(&app, test::TestRequest::get().uri("/users/me").to_request()).await;
+        assert_eq!(user.profile.weight_kg, 61.0);
+
+        /// The diets are assigned through the repository, assigning them by route needs the Diets Service
+        let subject = user.subject;
+        repository.assign_diet(DEFAULT_TENANT, &subject, UserDiet { diet: "keto".to_string(), active: true }).unwrap();
+        repository.assign_diet(DEFAULT_TENANT, &subject, UserDiet { diet: "atkins".to_string(), active: true }).unwrap();
+        let diet: UserDiet = test::call_and_read_body_json(&app, json(test::TestRequest::put().uri("/users/me/diets/keto"), r#"{"active": false}"#)).await;
+        assert!(!diet.active);
+        assert_eq!(test::call_and_read_body(&app, json(test::TestRequest::put().uri("/users/me/diets/paleo"), r#"{"active": false}"#)).await, NOT_FOUND);
+        assert_eq!(test::call_and_read_body(&app, test::TestRequest::delete().uri("/users/me/diets/atkins").to_request()).await, "atkins");
+        let diets: Vec<UserDiet> = test::call_and_read_body_json(&app, test::TestRequest::get().uri("/users/me/diets").to_request()).await;
+        assert_eq!(diets, vec![UserDiet { diet: "keto".to_string(), active: false }]);
+
+        /// The same subject is a different user in another tenant
+        let res = test::call_service(&app, test::TestRequest::get().uri("/users/me").insert_header((TENANT_HEADER, "team-b")).to_request()).await;
+        assert_eq!(res.status(), 404);
+    }
+}
@@ -0,0 +1,252 @@
+#![allow(unused_doc_comments)]
+#![allow(dead_code)]
+//! Shared setup of the integration tests
+//!
+//! Every test gets its own database, see [TestDatabase], the stub of the Ninjas API, see [start_ninjas_stub],
+//! and a real Diets Service listening on a free port. The Meals and Diets apps are built with the same
+//! `configure` functions as the binaries, so the tests see exactly the routes that are deployed.
+//! [TestEnv::start] runs both Services in the dev mode of [meals_api::auth], [TestEnv::start_enforced] with an API key per role.
+//!
+//! With Postgres the tests need a server to create their databases on, given by `TEST_DATABASE_URL`,
+//! e.g. `postgres://postgres@localhost/postgres`. Without it they are skipped.
+//! With the `sqlite` feature every test uses a new file in the temporary directory instead
+
+/// Actix imports
+use actix_web::{App, HttpServer};
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceFactory, ServiceRequest, ServiceResponse};
+use actix_web::middleware::from_fn;
+use actix_web::web::Data;
+
+/// Misc imports
+use std::net::TcpListener;
+use std::sync::Arc;
+
+/// Crate imports
+use meals_api::auth::{authenticate, AuthConfig};
+use meals_api::config::{Config, Service};
+use meals_api::db::{create_pool, run_migrations, DbPool, PoolConfig};
+use meals_api::diets::{DieselDietRepository, DietRepository};
+use meals_api::food_log::{DieselFoodLogRepository, FoodLogRepository};
+use meals_api::meals::{DieselMealsRepository, DietClient, DishRepository, MealRepository, NutritionClient};
+use meals_api::plans::{DieselPlanRepository, PlanRepository};
+use meals_api::users::{DieselUserRepository, UserRepository};
+use meals_api::stub::{Fixtures, NinjasStub};
+use meals_api::telemetry::trace_requests;
+
+/// Dishes for which the fixtures of the Ninjas stub answer with a 500, a 429 and an empty list
+pub const NINJAS_FAILURE: &str = "server error";
+pub const NINJAS_RATE_LIMITED: &str = "rate limited";
+pub const NINJAS_EMPTY: &str = "air";
+
+/// API keys of [TestEnv::start_enforced], one per role, and the key the Meals Service calls the Diets Service with
+pub const READER_KEY: &str = "reader-key";
+pub const EDITOR_KEY: &str = "editor-key";
+pub const ADMIN_KEY: &str = "admin-key";
+/// Editor and admin keys bound to the [ACME] tenant
+pub const ACME_EDITOR_KEY: &str = "acme-editor-key";
+pub const ACME_ADMIN_KEY: &str = "acme-admin-key";
+pub const ACME: &str = "acme";
+const MEALS_SERVICE_KEY: &str = "meals-service-key";
+
+/// A database that only exists for one test
+/// With Postgres it is dropped when the test ends, with SQLite its file is deleted
+pub struct TestDatabase {
+    pub pool: DbPool,
+    pub url: String,
+    #[cfg(not(feature = "sqlite"))]
+    server_url: String,
+    #[cfg(not(feature = "sqlite"))]
+    name: String,
+}
+
+impl TestDatabase {
+
+    /// Creates and migrates a new database on the server of `TEST_DATABASE_URL`
+    /// Returns [None] if `TEST_DATABASE_URL` is not set
+    #[cfg(not(feature = "sqlite"))]
+    pub fn create() -> Option<TestDatabase> {
+        use diesel::{Connection, PgConnection, RunQueryDsl};
+
+        let server_url = match std::env::var("TEST_DATABASE_URL") {
+            Ok(server_url) => server_url,
+            Err(_) => {
+                eprintln!("TEST_DATABASE_URL is not set, skipping the test");
+                return None
+            }
+        };
+        let name = format!("meals_api_test_{}", uuid::Uuid::new_v4().simple());
+        let mut conn = PgConnection::establish(&server_url).expect("Could not connect to TEST_DATABASE_URL");
+        diesel::sql_query(format!("CREATE DATABASE {}", name)).execute(&mut conn).expect("Could not create the test database");
+
+        let mut url = reqwest::Url::parse(&server_url).expect("TEST_DATABASE_URL is not a URL");
+        url.set_path(&name);
+        Some(TestDatabase::migrate(url.to_string(), |pool, url| TestDatabase { pool, url, server_url, name }))
+    }
+
+    /// Creates and migrates a new SQLite file in the temporary directory
+    #[cfg(feature = "sqlite")]
+    pub fn create() -> Option<TestDatabase> {
+        let path = std::env::temp_dir().join(format!("meals-api-test-{}.db", uuid::Uuid::new_v4()));
+        Some(TestDatabase::migrate(path.display().to_string(), |pool, url| TestDatabase { pool, url }))
+    }
+
+    /// Creates the pool of the database at `url` and runs the migrations
+    fn migrate(url: String, database: impl FnOnce(DbPool, String) -> TestDatabase) -> TestDatabase {
+        /// A few connections are enough, the tests run in parallel on the same server
+        let config = PoolConfig { max_size: 4, ..PoolConfig::default() };
+        let pool = create_pool(&url, &config).expect("Could not connect to the test database");
+        run_migrations(pool.clone()).expect("Could not migrate the test database");
+        database(pool, url)
+    }
+}
+
+impl Drop for TestDatabase {
+
+    #[cfg(not(feature = "sqlite"))]
+    fn drop(&mut self) {
+        use diesel::{Connection, PgConnection, RunQueryDsl};
+
+        /// FORCE closes the connections the pool still holds
+        if let Ok(mut conn) = PgConnection::establish(&self.server_url) {
+            let _ = diesel::sql_query(format!("DROP DATABASE IF EXISTS {} WITH (FORCE)", self.name)).execute(&mut conn);
+        }
+    }
+
+    #[cfg(feature = "sqlite")]
+    fn drop(&mut self) {
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{}", self.url, suffix));
+        }
+    }
+}
+
+/// Binds a listener on a free local port and returns it with its base URL
+fn free_port() -> (TcpListener, String) {
+    let listener = TcpListener::bind("127.0.0.1:0").expect("No free port");
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    (listener, url)
+}
+
+/// Starts the stub of the Ninjas API with `fixtures/ninjas.json` and returns its base URL,
+/// the nutrition route is `/v1/nutrition?query=`
+pub fn start_ninjas_stub() -> String {
+    let fixtures = Fixtures::load(concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/ninjas.json")).unwrap();
+    let stub = Data::new(NinjasStub::new(fixtures));
+    let (listener, url) = free_port();
+    let server = HttpServer::new(move || App::new().app_data(stub.clone()).configure(meals_api::stub::configure))
+        .workers(1)
+        .disable_signals()
+        .listen(listener)
+        .expect("Could not start the Ninjas stub")
+        .run();
+    actix_web::rt::spawn(server);
+    url
+}
+
+/// Everything a test of the Services needs
+pub struct TestEnv {
+    pub database: TestDatabase,
+    /// Configuration of the Meals Service
+    pub config: Config,
+    /// Authentication of the Diets Service
+    pub diets_auth: AuthConfig,
+}
+
+impl TestEnv {
+
+    /// # Starts the Ninjas stub and the Diets Service on a new database, without authentication
+    /// ## Returns
+    /// * The [TestEnv], or [None] if there is no database to test against
+    pub async fn start() -> Option<TestEnv> {
+        TestEnv::start_with(&["--auth-mode=dev".to_string()]).await
+    }
+
+    /// # Starts the Ninjas stub and the Diets Service on a new database, with the API keys [READER_KEY],
+    /// [EDITOR_KEY] and [ADMIN_KEY], and [ACME_EDITOR_KEY] and [ACME_ADMIN_KEY] of the [ACME] tenant, required by both Services
+    /// ## Returns
+    /// * The [TestEnv], or [None] if there is no database to test against
+    pub async fn start_enforced() -> Option<TestEnv> {
+        let api_keys = format!(
+            "reader:reader:{},editor:editor:{},admin:admin:{},meals_api:reader:{},acme-editor@{acme}:editor:{},acme-admin@{acme}:admin:{}",
+            READER_KEY, EDITOR_KEY, ADMIN_KEY, MEALS_SERVICE_KEY, ACME_EDITOR_KEY, ACME_ADMIN_KEY, acme = ACME,
+        );
+        TestEnv::start_with(&["--auth-mode=enforced".to_string(), format!("--auth-api-keys={}", api_keys)]).await
+    }
+
+    /// Starts the environment with the given authentication flags for both Services
+    async fn start_with(auth_args: &[String]) -> Option<TestEnv> {
+        let database = TestDatabase::create()?;
+        let ninjas_url = start_ninjas_stub();
+
+        let mut args = vec![format!("--database-url={}", database.url)];
+        args.extend_from_slice(auth_args);
+        let diets_config = Config::from_sources(Service::Diets, &args, |_| None).expect("Invalid test configuration");
+        let diets_auth = diets_config.auth.clone().unwrap();
+
+        /// The Diets Service runs as a real server, since the Meals Service calls it over HTTP
+        let (listener, diets_url) = free_port();
+        let pool = database.pool.clone();
+        let auth = Data::new(diets_auth.clone());
+        let diets = Data::from(Arc::new(DieselDietRepository::new(pool.clone())) as Arc<dyn DietRepository>);
+        let server = HttpServer::new(move || App::new()
+                .wrap(from_fn(authenticate))
+                .app_data(auth.clone())
+                .app_data(Data::new(pool.clone()))
+                .app_data(diets.clone())
+                .configure(meals_api::diets::configure))
+            .workers(1)
+            .disable_signals()
+            .listen(listener)
+            .expect("Could not start the Diets Service")
+            .run();
+        actix_web::rt::spawn(server);
+
+        args.extend([
+            format!("--diet-service-url={}", diets_url),
+            format!("--diet-service-api-key={}", MEALS_SERVICE_KEY),
+            format!("--ninjas-api-base-url={}/v1/nutrition?query=", ninjas_url),
+            "--ninjas-api-key=test".to_string(),
+        ]);
+        let config = Config::from_sources(Service::Meals, &args, |_| None).expect("Invalid test configuration");
+        Some(TestEnv { database, config, diets_auth })
+    }
+
+    /// The base URL of the Diets Service
+    pub fn diets_url(&self) -> String {
+        self.config.diets_service.as_ref().unwrap().base_url.clone()
+    }
+
+    /// Builds the Meals Service app, to be started with `actix_web::test::init_service`
+    pub fn meals_app(&self) -> App<impl ServiceFactory<ServiceRequest, Config = (), Response = ServiceResponse<impl MessageBody>, Error = actix_web::Error, InitError = ()>> {
+        let repository = Arc::new(DieselMealsRepository::new(self.database.pool.clone()));
+        let diet_client = DietClient::new(self.config.diets_service.clone().unwrap()).unwrap();
+        let nutrition_client = NutritionClient::new(self.config.ninjas.clone().unwrap()).unwrap();
+        App::new()
+            .wrap(from_fn(authenticate))
+            .wrap(from_fn(trace_requests))
+            .app_data(Data::new(self.config.auth.clone().unwrap()))
+            .app_data(Data::new(self.database.pool.clone()))
+            .app_data(Data::new(self.config.clone()))
+            .app_data(Data::from(repository.clone() as Arc<dyn DishRepository>))
+            .app_data(Data::from(repository as Arc<dyn MealRepository>))
+            .app_data(Data::from(Arc::new(DieselUserRepository::new(self.database.pool.clone())) as Arc<dyn UserRepository>))
+            .app_data(Data::from(Arc::new(DieselFoodLogRepository::new(self.database.pool.clone())) as Arc<dyn FoodLogRepository>))
+            .app_data(Data::from(Arc::new(DieselPlanRepository::new(self.database.pool.clone())) as Arc<dyn PlanRepository>))
+            .app_data(Data::new(diet_client))
+            .app_data(Data::new(nutrition_client))
+            .configure(meals_api::meals::configure)
+    }
+
+    /// Builds the Diets Service app, to be started with `actix_web::test::init_service`
+    pub fn diets_app(&self) -> App<impl ServiceFactory<ServiceRequest, Config = (), Response = ServiceResponse<impl MessageBody>, Error = actix_web::Error, InitError = ()>> {
+        let pool = self.database.pool.clone();
+        App::new()
+            .wrap(from_fn(authenticate))
+            .wrap(from_fn(trace_requests))
+            .app_data(Data::new(self.diets_auth.clone()))
+            .app_data(Data::new(pool.clone()))
+            .app_data(Data::from(Arc::new(DieselDietRepository::new(pool)) as Arc<dyn DietRepository>))
+            .configure(meals_api::diets::configure)
+    }
+}
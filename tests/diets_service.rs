@@ -0,0 +1,53 @@
+#![allow(unused_doc_comments)]
+//! Integration tests of the Diets Service
+//!
+//! See [common] for the database they need
+
+mod common;
+
+use actix_web::test::{self, TestRequest};
+use serde_json::{json, Value};
+
+use common::TestEnv;
+
+#[actix_web::test]
+async fn diet_routes_and_error_codes() {
+    let env = match TestEnv::start().await {
+        Some(env) => env,
+        None => return,
+    };
+    let app = test::init_service(env.diets_app()).await;
+
+    let diet = json!({"name": "low sodium", "cal": 2000, "sodium": 1500, "sugar": 30});
+    let res = test::call_service(&app, TestRequest::post().uri("/diets").set_json(&diet).to_request()).await;
+    assert_eq!(res.status(), 201);
+    assert_eq!(test::read_body(res).await, "Diet low sodium was created successfully");
+
+    /// A diet with the same name, a missing field, a body that is not JSON and a wrong content type are rejected
+    assert_eq!(test::call_service(&app, TestRequest::post().uri("/diets").set_json(&diet).to_request()).await.status(), 422);
+    let missing_sugar = json!({"name": "keto", "cal": 1800, "sodium": 2000});
+    assert_eq!(test::call_service(&app, TestRequest::post().uri("/diets").set_json(missing_sugar).to_request()).await.status(), 422);
+    let req = TestRequest::post().uri("/diets").insert_header(("Content-Type", "application/json")).set_payload("{");
+    assert_eq!(test::call_service(&app, req.to_request()).await.status(), 400);
+    let req = TestRequest::post().uri("/diets").insert_header(("Content-Type", "text/plain")).set_payload("keto");
+    assert_eq!(test::call_service(&app, req.to_request()).await.status(), 415);
+
+    /// GET all, by ID and by name
+    let diets: Value = test::call_and_read_body_json(&app, TestRequest::get().uri("/diets").to_request()).await;
+    assert_eq!(diets, json!([{"name": "low sodium", "cal": 2000.0, "sodium": 1500.0, "sugar": 30.0}]));
+    let by_id: Value = test::call_and_read_body_json(&app, TestRequest::get().uri("/diets/1").to_request()).await;
+    let by_name: Value = test::call_and_read_body_json(&app, TestRequest::get().uri("/diets/low%20sodium").to_request()).await;
+    assert_eq!((&by_id, &by_name), (&diets[0], &diets[0]));
+
+    let res = test::call_service(&app, TestRequest::get().uri("/diets/keto").to_request()).await;
+    assert_eq!(res.status(), 404);
+    assert_eq!(test::read_body(res).await, "Diet keto not found");
+    assert_eq!(test::call_service(&app, TestRequest::get().uri("/diets/9").to_request()).await.status(), 404);
+
+    /// The collection can not be deleted
+    assert_eq!(test::call_service(&app, TestRequest::delete().uri("/diets").to_request()).await.status(), 405);
+
+    /// The Diets Service is ready once its database is migrated
+    let ready: Value = test::call_and_read_body_json(&app, TestRequest::get().uri("/readyz").to_request()).await;
+    assert_eq!(ready["status"], "ready", "{}", ready);
+}
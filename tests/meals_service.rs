@@ -0,0 +1,611 @@
+#![allow(unused_doc_comments)]
+//! Integration tests of the Meals Service, covering the Dishes and Meals APIs
+//!
+//! [assignment_dishes] and [assignment_meals] follow `tests/assn3_tests.py`,
+//! the other tests cover the remaining routes and error codes. See [common] for the database they need
+
+mod common;
+
+use actix_web::body::MessageBody;
+use actix_web::dev::ServiceResponse;
+use actix_web::test::{self, TestRequest};
+use serde_json::{json, Value};
+
+use meals_api::client::{DietsClient, ErrorCode};
+use meals_api::diets::{Goal, NewDiet, ReqGeneratedDiet};
+use meals_api::users::{ActivityLevel, ReqProfile, Sex};
+
+use common::{TestEnv, ACME, ACME_ADMIN_KEY, ACME_EDITOR_KEY, ADMIN_KEY, EDITOR_KEY, NINJAS_EMPTY, NINJAS_FAILURE, NINJAS_RATE_LIMITED, READER_KEY};
+
+/// Starts the [TestEnv], or skips the test if there is no database
+macro_rules! test_env {
+    () => {
+        match TestEnv::start().await {
+            Some(env) => env,
+            None => return,
+        }
+    };
+}
+
+/// Adds the API key header to a request
+fn with_key(req: TestRequest, key: &str) -> TestRequest {
+    req.insert_header(("X-Api-Key", key))
+}
+
+/// Returns the status and the body of a response as text
+async fn text<B: MessageBody>(res: ServiceResponse<B>) -> (u16, String) {
+    let status = res.status().as_u16();
+    let body = test::read_body(res).await;
+    (status, String::from_utf8(body.to_vec()).unwrap())
+}
+
+fn post(uri: &str, body: Value) -> TestRequest {
+    TestRequest::post().uri(uri).set_json(body)
+}
+
+fn put(uri: &str, body: Value) -> TestRequest {
+    TestRequest::put().uri(uri).set_json(body)
+}
+
+fn meal(name: &str, appetizer: i32, main: i32, dessert: i32) -> Value {
+    json!({"name": name, "appetizer": appetizer, "main": main, "dessert": dessert})
+}
+
+/// Tests 1 to 5 of the Python suite
+#[actix_web::test]
+async fn assignment_dishes() {
+    let env = test_env!();
+    let app = test::init_service(env.meals_app()).await;
+
+    /// Test 1: the dishes get distinct IDs
+    let mut ids = Vec::new();
+    for name in ["orange", "spaghetti", "apple pie"] {
+        let (status, id) = text(test::call_service(&app, post("/dishes", json!({"name": name})).to_request()).await).await;
+        assert_eq!(status, 201);
+        ids.push(id.parse::<i32>().unwrap());
+    }
+    assert!(ids[0] != ids[1] && ids[0] != ids[2] && ids[1] != ids[2]);
+
+    /// Test 2: the sodium of the orange comes from the nutrition API
+    let orange: Value = test::call_and_read_body_json(&app, TestRequest::get().uri(&format!("/dishes/{}", ids[0])).to_request()).await;
+    let sodium = orange["sodium"].as_f64().unwrap();
+    assert!((0.9..=1.1).contains(&sodium));
+
+    /// Test 3: all three dishes are listed
+    let dishes: Value = test::call_and_read_body_json(&app, TestRequest::get().uri("/dishes").to_request()).await;
+    assert_eq!(dishes.as_object().unwrap().len(), 3);
+
+    /// Test 4: a dish the nutrition API does not know is rejected with -3
+    let (status, body) = text(test::call_service(&app, post("/dishes", json!({"name": "blah"})).to_request()).await).await;
+    assert_eq!((status, body.as_str()), (422, "-3"));
+
+    /// Test 5: a dish that exists is rejected with -2
+    let (status, body) = text(test::call_service(&app, post("/dishes", json!({"name": "orange"})).to_request()).await).await;
+    assert_eq!((status, body.as_str()), (422, "-2"));
+}
+
+/// Tests 6 to 8 of the Python suite
+#[actix_web::test]
+async fn assignment_meals() {
+    let env = test_env!();
+    let app = test::init_service(env.meals_app()).await;
+    for name in ["orange", "spaghetti", "apple pie"] {
+        assert_eq!(test::call_service(&app, post("/dishes", json!({"name": name})).to_request()).await.status(), 201);
+    }
+
+    /// Test 6: a meal of the three dishes is created
+    let (status, id) = text(test::call_service(&app, post("/meals", meal("delicious", 1, 2, 3)).to_request()).await).await;
+    assert_eq!(status, 201);
+    assert!(id.parse::<i32>().unwrap() > 0);
+
+    /// Test 7: the meal is listed with the calories of its dishes
+    let meals: Value = test::call_and_read_body_json(&app, TestRequest::get().uri("/meals").to_request()).await;
+    let meals = meals.as_object().unwrap();
+    assert_eq!(meals.len(), 1);
+    for meal in meals.values() {
+        let cal = meal["cal"].as_f64().unwrap();
+        assert!((400.0..=500.0).contains(&cal));
+    }
+
+    /// Test 8: a meal with the same name is rejected with -2
+    let (status, body) = text(test::call_service(&app, post("/meals", meal("delicious", 1, 2, 3)).to_request()).await).await;
+    assert_eq!((status, body.as_str()), (422, "-2"));
+}
+
+#[actix_web::test]
+async fn dish_routes_and_error_codes() {
+    let env = test_env!();
+    let app = test::init_service(env.meals_app()).await;
+    assert_eq!(text(test::call_service(&app, post("/dishes", json!({"name": "orange"})).to_request()).await).await, (201, "1".to_string()));
+
+    /// 0: not JSON, MessagePack or CBOR, or not parsable
+    let req = TestRequest::post().uri("/dishes").insert_header(("Content-Type", "text/plain")).set_payload("orange");
+    assert_eq!(text(test::call_service(&app, req.to_request()).await).await, (415, "0".to_string()));
+    let req = TestRequest::post().uri("/dishes").insert_header(("Content-Type", "application/json")).set_payload("{");
+    assert_eq!(text(test::call_service(&app, req.to_request()).await).await, (422, "0".to_string()));
+
+    /// -1: the name is missing
+    assert_eq!(text(test::call_service(&app, post("/dishes", json!({"title": "orange"})).to_request()).await).await, (422, "-1".to_string()));
+
+    /// -3: the nutrition API returned no items
+    assert_eq!(text(test::call_service(&app, post("/dishes", json!({"name": NINJAS_EMPTY})).to_request()).await).await, (422, "-3".to_string()));
+
+    /// -4: the nutrition API failed or is rate limited
+    for name in [NINJAS_FAILURE, NINJAS_RATE_LIMITED] {
+        assert_eq!(text(test::call_service(&app, post("/dishes", json!({"name": name})).to_request()).await).await, (504, "-4".to_string()));
+    }
+
+    /// A query the nutrition API splits into several items is stored with their sums
+    assert_eq!(test::call_service(&app, post("/dishes", json!({"name": "rice and beans"})).to_request()).await.status(), 201);
+    let rice_and_beans: Value = test::call_and_read_body_json(&app, TestRequest::get().uri("/dishes/rice%20and%20beans").to_request()).await;
+    assert_eq!(rice_and_beans["sodium"].as_f64(), Some(240.0));
+
+    /// GET by ID and by name
+    let by_id: Value = test::call_and_read_body_json(&app, TestRequest::get().uri("/dishes/1").to_request()).await;
+    let by_name: Value = test::call_and_read_body_json(&app, TestRequest::get().uri("/dishes/orange").to_request()).await;
+    assert_eq!(by_id, by_name);
+    assert_eq!((by_id["name"].as_str(), by_id["cal"].as_f64()), (Some("orange"), Some(53.2)));
+
+    /// -5: the dish does not exist
+    for req in [TestRequest::get().uri("/dishes/9"), TestRequest::get().uri("/dishes/pizza"), TestRequest::delete().uri("/dishes/9"), TestRequest::delete().uri("/dishes/pizza")] {
+        assert_eq!(text(test::call_service(&app, req.to_request()).await).await, (404, "-5".to_string()));
+    }
+
+    /// DELETE by ID and by name return the ID of the deleted dish
+    assert_eq!(text(test::call_service(&app, TestRequest::delete().uri("/dishes/1").to_request()).await).await, (200, "1".to_string()));
+    assert_eq!(text(test::call_service(&app, post("/dishes", json!({"name": "spaghetti"})).to_request()).await).await, (201, "3".to_string()));
+    assert_eq!(text(test::call_service(&app, TestRequest::delete().uri("/dishes/spaghetti").to_request()).await).await, (200, "3".to_string()));
+
+    /// The collection can not be deleted
+    assert_eq!(test::call_service(&app, TestRequest::delete().uri("/dishes").to_request()).await.status(), 405);
+}
+
+#[actix_web::test]
+async fn recipes_are_recomputed_with_their_meals() {
+    let env = test_env!();
+    let app = test::init_service(env.meals_app()).await;
+    let close = |value: &Value, expected: f64| (value.as_f64().unwrap() - expected).abs() < 1e-9;
+
+    /// 150 g of apple and a serving of orange, both answered per 100 g
+    let lines = json!([
+        {"ingredient": "apple", "quantity": 150, "unit": "g"},
+        {"ingredient": "orange", "quantity": 1, "unit": "serving"},
+    ]);
+    let (status, salad) = text(test::call_service(&app, post("/dishes", json!({"name": "fruit salad", "ingredients": lines})).to_request()).await).await;
+    assert_eq!(status, 201);
+    let dish: Value = test::call_and_read_body_json(&app, TestRequest::get().uri(&format!("/dishes/{}", salad)).to_request()).await;
+    assert!(close(&dish["cal"], 132.7) && close(&dish["sugar"], 27.75) && close(&dish["size"], 250.0), "{}", dish);
+    assert_eq!(test::call_service(&app, post("/dishes", json!({"name": "spaghetti"})).to_request()).await.status(), 201);
+    let (_, lunch) = text(test::call_service(&app, post("/meals", meal("lunch", salad.parse().unwrap(), 2, salad.parse().unwrap())).to_request()).await).await;
+
+    /// Editing a line recomputes the dish and the meals containing it
+    let uri = format!("/dishes/{}/ingredients", salad);
+    let dish: Value = test::call_and_read_body_json(&app, put(&format!("{}/2", uri), json!({"ingredient": "orange", "quantity": 200, "unit": "g"})).to_request()).await;
+    assert!(close(&dish["cal"], 185.9), "{}", dish);
+    let meal: Value = test::call_and_read_body_json(&app, TestRequest::get().uri(&format!("/meals/{}", lunch)).to_request()).await;
+    assert!(close(&meal["cal"], 2.0 * 185.9 + 158.1), "{}", meal);
+
+    /// Removing a line moves the next ones up
+    let dish: Value = test::call_and_read_body_json(&app, TestRequest::delete().uri(&format!("{}/1", uri)).to_request()).await;
+    assert!(close(&dish["cal"], 106.4), "{}", dish);
+    let ingredients: Value = test::call_and_read_body_json(&app, TestRequest::get().uri(&uri).to_request()).await;
+    assert_eq!((ingredients.as_array().unwrap().len(), &ingredients[0]["ingredient"], &ingredients[0]["unit"]), (1, &json!("orange"), &json!("g")));
+    let meal: Value = test::call_and_read_body_json(&app, TestRequest::get().uri(&format!("/meals/{}", lunch)).to_request()).await;
+    assert!(close(&meal["sugar"], 2.0 * 24.6 + 0.6), "{}", meal);
+
+    /// -3 for an ingredient the nutrition API does not know, -4 if it fails, and the dish stays as it was
+    let (status, body) = text(test::call_service(&app, put(&format!("{}/2", uri), json!({"ingredient": NINJAS_EMPTY, "quantity": 1, "unit": "kg"})).to_request()).await).await;
+    assert_eq!(status, 422);
+    assert_eq!(serde_json::from_str::<Value>(&body).unwrap()["error_code"], "-3");
+    let res = test::call_service(&app, put(&uri, json!([{"ingredient": NINJAS_FAILURE, "quantity": 1, "unit": "serving"}])).to_request()).await;
+    assert_eq!(text(res).await, (504, "-4".to_string()));
+    let res = test::call_service(&app, post("/dishes", json!({"name": "pie", "ingredients": [{"ingredient": "apple", "unit": "g"}]})).to_request()).await;
+    assert_eq!(text(res).await, (422, "-1".to_string()));
+    let dish: Value = test::call_and_read_body_json(&app, TestRequest::get().uri(&format!("/dishes/{}", salad)).to_request()).await;
+    assert!(close(&dish["cal"], 106.4), "{}", dish);
+
+    /// A dish created from its name becomes a recipe once it gets lines
+    let dish: Value = test::call_and_read_body_json(&app, put("/dishes/2/ingredients", json!([{"ingredient": "spaghetti", "quantity": 0.25, "unit": "kg"}])).to_request()).await;
+    assert!(close(&dish["cal"], 395.25) && close(&dish["size"], 250.0), "{}", dish);
+    let meal: Value = test::call_and_read_body_json(&app, TestRequest::get().uri(&format!("/meals/{}", lunch)).to_request()).await;
+    assert!(close(&meal["cal"], 2.0 * 106.4 + 395.25), "{}", meal);
+}
+
+#[actix_web::test]
+async fn meal_routes_and_error_codes() {
+    let env = test_env!();
+    let app = test::init_service(env.meals_app()).await;
+    for name in ["orange", "spaghetti", "apple pie"] {
+        assert_eq!(test::call_service(&app, post("/dishes", json!({"name": name})).to_request()).await.status(), 201);
+    }
+    assert_eq!(text(test::call_service(&app, post("/meals", meal("dinner", 1, 2, 3)).to_request()).await).await, (201, "1".to_string()));
+
+    /// 0, -1 and -6 on POST
+    let req = TestRequest::post().uri("/meals").insert_header(("Content-Type", "text/plain")).set_payload("dinner");
+    assert_eq!(text(test::call_service(&app, req.to_request()).await).await, (415, "0".to_string()));
+    let missing_dessert = json!({"name": "lunch", "appetizer": 1, "main": 2});
+    assert_eq!(text(test::call_service(&app, post("/meals", missing_dessert).to_request()).await).await, (422, "-1".to_string()));
+    assert_eq!(text(test::call_service(&app, post("/meals", meal("lunch", 1, 2, 9)).to_request()).await).await, (422, "-6".to_string()));
+
+    /// GET by ID and by name
+    let by_id: Value = test::call_and_read_body_json(&app, TestRequest::get().uri("/meals/1").to_request()).await;
+    let by_name: Value = test::call_and_read_body_json(&app, TestRequest::get().uri("/meals/dinner").to_request()).await;
+    assert_eq!(by_id, by_name);
+    assert_eq!(by_id["sodium"].as_f64(), Some(167.0));
+
+    /// PUT replaces the dishes and recomputes the nutrition
+    assert_eq!(text(test::call_service(&app, put("/meals/1", meal("dinner", 1, 1, 1)).to_request()).await).await, (200, "1".to_string()));
+    let dinner: Value = test::call_and_read_body_json(&app, TestRequest::get().uri("/meals/1").to_request()).await;
+    assert_eq!(dinner["sodium"].as_f64(), Some(3.0));
+    assert_eq!(text(test::call_service(&app, put("/meals/9", meal("dinner", 1, 1, 1)).to_request()).await).await, (404, "-5".to_string()));
+    assert_eq!(text(test::call_service(&app, put("/meals/1", meal("dinner", 1, 1, 9)).to_request()).await).await, (422, "-6".to_string()));
+    assert_eq!(text(test::call_service(&app, put("/meals/1", json!({"name": "dinner"})).to_request()).await).await, (422, "-1".to_string()));
+    let malformed = TestRequest::put().uri("/meals/1").insert_header(("Content-Type", "application/json")).set_payload("{");
+    assert_eq!(text(test::call_service(&app, malformed.to_request()).await).await, (400, "0".to_string()));
+
+    /// -2 on PUT: the name belongs to another meal
+    let (status, lunch_id) = text(test::call_service(&app, post("/meals", meal("lunch", 2, 2, 2)).to_request()).await).await;
+    assert_eq!(status, 201);
+    assert_eq!(text(test::call_service(&app, put("/meals/1", meal("lunch", 1, 1, 1)).to_request()).await).await, (422, "-2".to_string()));
+
+    /// Deleting a dish removes it from the meals
+    assert_eq!(test::call_service(&app, TestRequest::delete().uri("/dishes/orange").to_request()).await.status(), 200);
+    let dinner: Value = test::call_and_read_body_json(&app, TestRequest::get().uri("/meals/1").to_request()).await;
+    assert_eq!((dinner["appetizer"].clone(), dinner["cal"].as_f64()), (Value::Null, Some(0.0)));
+
+    /// -5: the meal does not exist
+    for req in [TestRequest::get().uri("/meals/9"), TestRequest::get().uri("/meals/brunch"), TestRequest::delete().uri("/meals/9"), TestRequest::delete().uri("/meals/brunch")] {
+        assert_eq!(text(test::call_service(&app, req.to_request()).await).await, (404, "-5".to_string()));
+    }
+
+    /// DELETE by ID and by name return the ID of the deleted meal
+    assert_eq!(text(test::call_service(&app, TestRequest::delete().uri("/meals/1").to_request()).await).await, (200, "1".to_string()));
+    assert_eq!(text(test::call_service(&app, TestRequest::delete().uri("/meals/lunch").to_request()).await).await, (200, lunch_id));
+
+    /// The collection can not be deleted
+    assert_eq!(test::call_service(&app, TestRequest::delete().uri("/meals").to_request()).await.status(), 405);
+}
+
+#[actix_web::test]
+async fn meals_are_filtered_by_diet() {
+    let env = test_env!();
+    let app = test::init_service(env.meals_app()).await;
+    for name in ["orange", "spaghetti", "apple pie"] {
+        assert_eq!(test::call_service(&app, post("/dishes", json!({"name": name})).to_request()).await.status(), 201);
+    }
+    assert_eq!(test::call_service(&app, post("/meals", meal("light", 1, 1, 2)).to_request()).await.status(), 201);
+    assert_eq!(test::call_service(&app, post("/meals", meal("heavy", 3, 3, 3)).to_request()).await.status(), 201);
+
+    /// The diet is created in the Diets Service, which the Meals Service asks over HTTP
+    let diet = json!({"name": "low sodium", "cal": 500, "sodium": 10, "sugar": 50});
+    let res = reqwest::Client::new().post(format!("{}/diets", env.diets_url())).json(&diet).send().await.unwrap();
+    assert_eq!(res.status(), 201);
+
+    let meals: Value = test::call_and_read_body_json(&app, TestRequest::get().uri("/meals?diet=low%20sodium").to_request()).await;
+    let names: Vec<&str> = meals.as_array().unwrap().iter().map(|meal| meal["name"].as_str().unwrap()).collect();
+    assert_eq!(names, ["light"]);
+
+    let (status, body) = text(test::call_service(&app, TestRequest::get().uri("/meals?diet=keto").to_request()).await).await;
+    assert_eq!((status, body.as_str()), (404, "Diet keto not found"));
+}
+
+#[actix_web::test]
+async fn meals_are_filtered_by_the_active_diets_of_the_user() {
+    let env = test_env!();
+    let app = test::init_service(env.meals_app()).await;
+    for name in ["orange", "spaghetti", "apple pie"] {
+        assert_eq!(test::call_service(&app, post("/dishes", json!({"name": name})).to_request()).await.status(), 201);
+    }
+    for (name, appetizer, main, dessert) in [("light", 1, 1, 2), ("heavy", 3, 3, 3), ("fruit", 1, 1, 1)] {
+        assert_eq!(test::call_service(&app, post("/meals", meal(name, appetizer, main, dessert)).to_request()).await.status(), 201);
+    }
+    for diet in [json!({"name": "low sodium", "cal": 500, "sodium": 10, "sugar": 50}), json!({"name": "low sugar", "cal": 1000, "sodium": 1000, "sugar": 30})] {
+        let res = reqwest::Client::new().post(format!("{}/diets", env.diets_url())).json(&diet).send().await.unwrap();
+        assert_eq!(res.status(), 201);
+    }
+    let meal_names = |meals: Value| {
+        let mut names: Vec<String> = meals.as_array().unwrap().iter().map(|meal| meal["name"].as_str().unwrap().to_string()).collect();
+        names.sort();
+        names
+    };
+
+    /// The caller has to be registered and have an active diet
+    assert_eq!(test::call_service(&app, TestRequest::get().uri("/meals?user=me").to_request()).await.status(), 404);
+    let profile = json!({"age": 41, "sex": "male", "weight_kg": 80, "height_cm": 180, "activity_level": "moderate"});
+    assert_eq!(test::call_service(&app, post("/users", profile).to_request()).await.status(), 201);
+    let (status, body) = text(test::call_service(&app, TestRequest::get().uri("/meals?user=me").to_request()).await).await;
+    assert_eq!(status, 404, "{}", body);
+    assert!(body.ends_with("has no active diet"));
+
+    /// Only diets of the Diets Service can be assigned
+    let (status, body) = text(test::call_service(&app, post("/users/me/diets", json!({"diet": "keto"})).to_request()).await).await;
+    assert_eq!((status, body.as_str()), (404, "Diet keto not found"));
+    for diet in ["low sodium", "low sugar"] {
+        assert_eq!(test::call_service(&app, post("/users/me/diets", json!({"diet": diet})).to_request()).await.status(), 201);
+    }
+    assert_eq!(text(test::call_service(&app, post("/users/me/diets", json!({"diet": "low sugar"})).to_request()).await).await, (422, "-2".to_string()));
+
+    /// The meals have to be within the limits of every active diet
+    let meals: Value = test::call_and_read_body_json(&app, TestRequest::get().uri("/meals?user=me").to_request()).await;
+    assert_eq!(meal_names(meals), ["light"]);
+    assert_eq!(test::call_service(&app, put("/users/me/diets/low%20sugar", json!({"active": false})).to_request()).await.status(), 200);
+    let meals: Value = test::call_and_read_body_json(&app, TestRequest::get().uri("/meals?user=me").to_request()).await;
+    assert_eq!(meal_names(meals), ["fruit", "light"]);
+
+    /// An explicit diet narrows the active ones further
+    let meals: Value = test::call_and_read_body_json(&app, TestRequest::get().uri("/meals?user=me&diet=low%20sugar").to_request()).await;
+    assert_eq!(meal_names(meals), ["light"]);
+
+    assert_eq!(test::call_service(&app, TestRequest::get().uri("/meals?user=bob").to_request()).await.status(), 400);
+}
+
+#[actix_web::test]
+async fn eaten_meals_are_logged_and_compared_against_the_diets() {
+    let env = test_env!();
+    let app = test::init_service(env.meals_app()).await;
+    for name in ["orange", "spaghetti", "apple pie"] {
+        assert_eq!(test::call_service(&app, post("/dishes", json!({"name": name})).to_request()).await.status(), 201);
+    }
+    assert_eq!(test::call_service(&app, post("/meals", meal("heavy", 3, 3, 3)).to_request()).await.status(), 201);
+    let diet = json!({"name": "low sodium", "cal": 2000, "sodium": 1000, "sugar": 200});
+    let res = reqwest::Client::new().post(format!("{}/diets", env.diets_url())).json(&diet).send().await.unwrap();
+    assert_eq!(res.status(), 201);
+
+    /// Only registered users keep a log
+    assert_eq!(text(test::call_service(&app, post("/log", json!({"meal": 1})).to_request()).await).await, (404, "-5".to_string()));
+    let profile = json!({"age": 41, "sex": "male", "weight_kg": 80, "height_cm": 180, "activity_level": "moderate"});
+    assert_eq!(test::call_service(&app, post("/users", profile).to_request()).await.status(), 201);
+    assert_eq!(test::call_service(&app, post("/users/me/diets", json!({"diet": "low sodium"})).to_request()).await.status(), 201);
+
+    /// Two heavy meals on one day stay just below the sodium limit, the entries keep their nutrients when the meal is deleted
+    for eaten_at in ["2026-10-18T12:00:00Z", "2026-10-18T19:00:00Z"] {
+        let res = test::call_service(&app, post("/log", json!({"meal": 1, "eaten_at": eaten_at})).to_request()).await;
+        assert_eq!(res.status(), 201);
+    }
+    assert_eq!(test::call_service(&app, TestRequest::delete().uri("/meals/1").to_request()).await.status(), 200);
+    let day: Value = test::call_and_read_body_json(&app, TestRequest::get().uri("/log?date=2026-10-18").to_request()).await;
+    assert_eq!(day["entries"][0]["meal"], Value::Null);
+    assert_eq!((day["totals"]["sodium"].as_f64(), &day["exceeded"]), (Some(990.0), &json!([])));
+    /// A small portion of a dish on top exceeds it
+    let res = test::call_service(&app, post("/log", json!({"dish": 3, "portion": 0.1, "eaten_at": "2026-10-18T22:00:00Z"})).to_request()).await;
+    assert_eq!(res.status(), 201);
+    let day: Value = test::call_and_read_body_json(&app, TestRequest::get().uri("/log?date=2026-10-18").to_request()).await;
+    assert_eq!((day["limits"]["sodium"].as_f64(), &day["exceeded"]), (Some(1000.0), &json!(["sodium"])));
+
+    let summary: Value = test::call_and_read_body_json(&app, TestRequest::get().uri("/log/summary?from=2026-10-12&to=2026-10-25").to_request()).await;
+    assert_eq!((summary["days"].as_array().unwrap().len(), summary["weeks"].as_array().unwrap().len()), (14, 2));
+    assert_eq!((summary["weeks"][0]["exceeded"].clone(), summary["weeks"][1]["exceeded"].clone()), (json!([]), json!([])));
+}
+
+#[actix_web::test]
+async fn plans_are_validated_against_a_diet() {
+    let env = test_env!();
+    let app = test::init_service(env.meals_app()).await;
+    assert_eq!(test::call_service(&app, post("/dishes", json!({"name": "apple pie"})).to_request()).await.status(), 201);
+    assert_eq!(test::call_service(&app, post("/meals", meal("heavy", 1, 1, 1)).to_request()).await.status(), 201);
+    let diet = json!({"name": "low sodium", "cal": 2000, "sodium": 1000, "sugar": 200});
+    let res = reqwest::Client::new().post(format!("{}/diets", env.diets_url())).json(&diet).send().await.unwrap();
+    assert_eq!(res.status(), 201);
+
+    /// Two heavy meals fit the first day, three exceed the second one
+    let entries: Vec<Value> = [("2026-10-19", "lunch"), ("2026-10-19", "dinner"), ("2026-10-20", "breakfast"), ("2026-10-20", "lunch"), ("2026-10-20", "dinner")]
+        .into_iter()
+        .map(|(date, slot)| json!({"date": date, "slot": slot, "meal": 1}))
+        .collect();
+    let plan = json!({"name": "monday", "start": "2026-10-19", "days": 2, "entries": entries});
+    assert_eq!(text(test::call_service(&app, post("/plans", plan).to_request()).await).await, (201, "1".to_string()));
+    let res = test::call_service(&app, post("/plans", json!({"name": "other", "start": "2026-10-19", "days": 1, "entries": [{"date": "2026-10-19", "slot": "lunch", "meal": 7}]})).to_request()).await;
+    assert_eq!(text(res).await, (422, "-6".to_string()));
+
+    let validation: Value = test::call_and_read_body_json(&app, TestRequest::get().uri("/plans/1/validation?diet=low%20sodium").to_request()).await;
+    assert_eq!(validation["valid"], false);
+    assert_eq!((&validation["daily"][0]["exceeded"], &validation["daily"][1]["exceeded"]), (&json!([]), &json!(["cal", "sodium"])));
+    assert_eq!((validation["weekly"][0]["limits"]["sodium"].as_f64(), &validation["weekly"][0]["exceeded"]), (Some(2000.0), &json!(["sodium"])));
+    let res = test::call_service(&app, TestRequest::get().uri("/plans/1/validation?diet=unknown").to_request()).await;
+    assert_eq!(res.status(), 404);
+
+    /// Removing a meal of the second day makes the plan valid, deleting the meal empties it
+    assert_eq!(test::call_service(&app, TestRequest::delete().uri("/plans/1/days/2026-10-20/breakfast").to_request()).await.status(), 200);
+    let validation: Value = test::call_and_read_body_json(&app, TestRequest::get().uri("/plans/1/validation?diet=low%20sodium").to_request()).await;
+    assert_eq!(validation["valid"], true);
+    assert_eq!(test::call_service(&app, TestRequest::delete().uri("/meals/1").to_request()).await.status(), 200);
+    let view: Value = test::call_and_read_body_json(&app, TestRequest::get().uri("/plans/1").to_request()).await;
+    assert_eq!((&view["entries"], view["totals"]["cal"].as_f64()), (&json!([]), Some(0.0)));
+}
+
+#[actix_web::test]
+async fn plans_are_generated_within_a_diet() {
+    let env = test_env!();
+    let app = test::init_service(env.meals_app()).await;
+    for name in ["orange", "spaghetti", "apple pie", "rice and beans"] {
+        assert_eq!(test::call_service(&app, post("/dishes", json!({"name": name})).to_request()).await.status(), 201);
+    }
+    for (id, name) in [(1, "fruit"), (2, "pasta"), (3, "pie"), (4, "beans")] {
+        assert_eq!(test::call_service(&app, post("/meals", meal(name, id, id, id)).to_request()).await.status(), 201);
+    }
+    let diet = json!({"name": "balanced", "cal": 1500, "sodium": 1000, "sugar": 100});
+    let res = reqwest::Client::new().post(format!("{}/diets", env.diets_url())).json(&diet).send().await.unwrap();
+    assert_eq!(res.status(), 201);
+
+    /// Pie and beans together exceed the sodium, so they never share a day
+    let generation = json!({"name": "generated", "diet": "balanced", "start": "2026-10-19", "days": 3, "slots": ["lunch", "dinner"]});
+    let res = test::call_service(&app, post("/plans/generate", generation.clone()).to_request()).await;
+    assert_eq!(res.status(), 201);
+    let plan: Value = test::read_body_json(res).await;
+    assert_eq!(plan["entries"].as_array().unwrap().len(), 6);
+    assert!(plan["daily"].as_array().unwrap().iter().all(|day| day["exceeded"] == json!([])), "{}", plan);
+    let validation: Value = test::call_and_read_body_json(&app, TestRequest::get().uri(&format!("/plans/{}/validation?diet=balanced", plan["ID"])).to_request()).await;
+    assert_eq!(validation["valid"], true);
+    assert_eq!(text(test::call_service(&app, post("/plans/generate", generation).to_request()).await).await, (422, "-2".to_string()));
+
+    /// Without repeats for three days, two slots need six meals
+    let generation = json!({"name": "varied", "diet": "balanced", "start": "2026-10-19", "days": 3, "slots": ["lunch", "dinner"], "variety_days": 3});
+    let res = test::call_service(&app, post("/plans/generate", generation).to_request()).await;
+    assert_eq!(res.status(), 422);
+    let infeasible: Value = test::read_body_json(res).await;
+    assert_eq!(infeasible["error_code"], "-3");
+    assert!(infeasible["reasons"][0].as_str().unwrap().starts_with("6 different meals are needed"), "{}", infeasible);
+}
+
+#[actix_web::test]
+async fn dish_combinations_are_suggested_for_a_diet() {
+    let env = test_env!();
+    let app = test::init_service(env.meals_app()).await;
+    for name in ["orange", "spaghetti", "apple pie", "rice and beans"] {
+        assert_eq!(test::call_service(&app, post("/dishes", json!({"name": name})).to_request()).await.status(), 201);
+    }
+    let diet = json!({"name": "light", "cal": 700, "sodium": 500, "sugar": 50});
+    let res = reqwest::Client::new().post(format!("{}/diets", env.diets_url())).json(&diet).send().await.unwrap();
+    assert_eq!(res.status(), 201);
+
+    /// Every combination fits, the one using most of the limits comes first
+    let suggestions: Value = test::call_and_read_body_json(&app, TestRequest::get().uri("/meals/suggest?diet=light&limit=2").to_request()).await;
+    assert_eq!(suggestions.as_array().unwrap().len(), 2);
+    assert_eq!((&suggestions[0]["appetizer"], &suggestions[0]["main"], &suggestions[0]["dessert"]), (&json!(4), &json!(3), &json!(1)));
+    assert_eq!(suggestions[0]["name"], "rice and beans, apple pie and orange");
+    assert!(suggestions[0]["distance"].as_f64() < suggestions[1]["distance"].as_f64());
+    let res = test::call_service(&app, TestRequest::get().uri("/meals/suggest?diet=unknown").to_request()).await;
+    assert_eq!(res.status(), 404);
+
+    /// The second suggestion is stored as a meal
+    let res = test::call_service(&app, post("/meals/suggest", json!({"diet": "light", "rank": 2, "name": "chef special"})).to_request()).await;
+    assert_eq!(res.status(), 201);
+    let meal: Value = test::call_and_read_body_json(&app, TestRequest::get().uri("/meals/chef%20special").to_request()).await;
+    assert_eq!((&meal["appetizer"], &meal["main"], &meal["dessert"]), (&suggestions[1]["appetizer"], &suggestions[1]["main"], &suggestions[1]["dessert"]));
+    let res = test::call_service(&app, post("/meals/suggest", json!({"diet": "light", "rank": 2, "name": "chef special"})).to_request()).await;
+    assert_eq!(text(res).await, (422, "-2".to_string()));
+    let res = test::call_service(&app, post("/meals/suggest", json!({"diet": "light", "rank": 5})).to_request()).await;
+    assert_eq!(text(res).await, (404, "-5".to_string()));
+}
+
+#[actix_web::test]
+async fn meals_service_is_ready() {
+    let mut env = test_env!();
+    let app = test::init_service(env.meals_app()).await;
+
+    assert_eq!(test::call_service(&app, TestRequest::get().uri("/healthz").to_request()).await.status(), 200);
+    let ready: Value = test::call_and_read_body_json(&app, TestRequest::get().uri("/readyz").to_request()).await;
+    assert_eq!(ready["status"], "ready", "{}", ready);
+
+    /// The probe of the Ninjas API carries no API key, the stub refuses it like the real API without using any quota
+    assert_eq!(ready["checks"]["nutrition_provider"]["detail"], "Answered with 400 Bad Request", "{}", ready);
+
+    /// An unreachable Ninjas API makes the Service not ready, while the other dependencies stay up
+    let closed_port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+    env.config.ninjas.as_mut().unwrap().base_url = format!("http://127.0.0.1:{}/v1/nutrition?query=", closed_port);
+    let app = test::init_service(env.meals_app()).await;
+    let res = test::call_service(&app, TestRequest::get().uri("/readyz").to_request()).await;
+    assert_eq!(res.status(), 503);
+    let ready: Value = test::read_body_json(res).await;
+    assert_eq!(ready["status"], "not_ready", "{}", ready);
+    assert_eq!(ready["checks"]["nutrition_provider"]["status"], "down", "{}", ready);
+    assert_eq!(ready["checks"]["database"]["status"], "up", "{}", ready);
+    assert_eq!(ready["checks"]["diets_service"]["status"], "up", "{}", ready);
+}
+
+#[actix_web::test]
+async fn roles_are_enforced_across_the_services() {
+    let env = match TestEnv::start_enforced().await {
+        Some(env) => env,
+        None => return,
+    };
+    let app = test::init_service(env.meals_app()).await;
+
+    /// Without credentials only the health routes answer
+    assert_eq!(test::call_service(&app, TestRequest::get().uri("/healthz").to_request()).await.status(), 200);
+    let res = test::call_service(&app, TestRequest::get().uri("/dishes").to_request()).await;
+    assert_eq!(text(res).await, (401, "-9".to_string()));
+    let res = test::call_service(&app, with_key(TestRequest::get().uri("/dishes"), "guess").to_request()).await;
+    assert_eq!(res.status(), 401);
+
+    /// Readers read, editors write
+    let res = test::call_service(&app, with_key(post("/dishes", json!({"name": "orange"})), READER_KEY).to_request()).await;
+    assert_eq!(text(res).await, (403, "-10".to_string()));
+    let res = test::call_service(&app, with_key(post("/dishes", json!({"name": "orange"})), EDITOR_KEY).to_request()).await;
+    assert_eq!(res.status(), 201);
+    let res = test::call_service(&app, with_key(post("/meals", meal("snack", 1, 1, 1)), EDITOR_KEY).to_request()).await;
+    assert_eq!(res.status(), 201);
+    let res = test::call_service(&app, with_key(TestRequest::delete().uri("/meals/snack"), READER_KEY).to_request()).await;
+    assert_eq!(res.status(), 403);
+    let res = test::call_service(&app, with_key(TestRequest::get().uri("/dishes/orange"), READER_KEY).to_request()).await;
+    assert_eq!(res.status(), 200);
+
+    /// Only admins create diets, the typed client sends the API key
+    let diets = DietsClient::new(&env.diets_url()).unwrap();
+    let diet = NewDiet { name: "low sodium".to_string(), cal: 500.0, sodium: 10.0, sugar: 50.0 };
+    let e = diets.with_api_key(EDITOR_KEY).create_diet(&diet).await.unwrap_err();
+    assert_eq!(e.code(), Some(ErrorCode::Forbidden));
+    assert_eq!(diets.get_diets().await.err().and_then(|e| e.code()), Some(ErrorCode::Unauthenticated));
+    diets.with_api_key(ADMIN_KEY).create_diet(&diet).await.unwrap();
+
+    /// Generated diets are stored like created ones and come back with the energy they are based on
+    let profile = ReqProfile { age: Some(30), sex: Some(Sex::Male), weight_kg: Some(80.0), height_cm: Some(180.0), activity_level: Some(ActivityLevel::Light) };
+    let generated = ReqGeneratedDiet { name: Some("cut".to_string()), profile, goal: Some(Goal::Lose) };
+    let e = diets.with_api_key(EDITOR_KEY).generate_diet(&generated).await.err().unwrap();
+    assert_eq!(e.code(), Some(ErrorCode::Forbidden));
+    let generated = diets.with_api_key(ADMIN_KEY).generate_diet(&generated).await.unwrap();
+    assert_eq!((generated.diet.name.as_str(), generated.energy.bmr), ("cut", 1780.0));
+    assert_eq!(diets.with_api_key(ADMIN_KEY).get_diet_by_name("cut").await.unwrap().cal, generated.diet.cal);
+
+    /// The Meals Service asks the Diets Service with its own API key
+    let req = with_key(TestRequest::get().uri("/meals?diet=low%20sodium"), READER_KEY);
+    let meals: Value = test::call_and_read_body_json(&app, req.to_request()).await;
+    assert_eq!(meals.as_array().unwrap().len(), 1, "{}", meals);
+}
+
+#[actix_web::test]
+async fn tenants_do_not_see_each_others_data() {
+    let env = match TestEnv::start_enforced().await {
+        Some(env) => env,
+        None => return,
+    };
+    let app = test::init_service(env.meals_app()).await;
+
+    /// Both tenants create a dish and a meal with the same names
+    for key in [EDITOR_KEY, ACME_EDITOR_KEY] {
+        let res = test::call_service(&app, with_key(post("/dishes", json!({"name": "orange"})), key).to_request()).await;
+        assert_eq!(res.status(), 201);
+    }
+    let res = test::call_service(&app, with_key(post("/meals", meal("snack", 1, 1, 1)), EDITOR_KEY).to_request()).await;
+    assert_eq!(res.status(), 201);
+
+    /// A meal can not use the dishes of another tenant
+    let res = test::call_service(&app, with_key(post("/meals", meal("snack", 1, 1, 1)), ACME_EDITOR_KEY).to_request()).await;
+    assert_eq!(text(res).await, (422, "-6".to_string()));
+    let res = test::call_service(&app, with_key(post("/meals", meal("snack", 2, 2, 2)), ACME_EDITOR_KEY).to_request()).await;
+    assert_eq!(res.status(), 201);
+
+    /// Neither IDs nor names of the other tenant are visible
+    let res = test::call_service(&app, with_key(TestRequest::get().uri("/dishes/1"), ACME_EDITOR_KEY).to_request()).await;
+    assert_eq!(res.status(), 404);
+    let res = test::call_service(&app, with_key(TestRequest::delete().uri("/dishes/1"), ACME_EDITOR_KEY).to_request()).await;
+    assert_eq!(res.status(), 404);
+    let dishes: Value = test::call_and_read_body_json(&app, with_key(TestRequest::get().uri("/dishes"), ACME_EDITOR_KEY).to_request()).await;
+    assert_eq!(dishes.as_object().unwrap().keys().collect::<Vec<_>>(), vec!["2"]);
+    let meals: Value = test::call_and_read_body_json(&app, with_key(TestRequest::get().uri("/meals"), READER_KEY).to_request()).await;
+    assert_eq!(meals.as_object().unwrap().keys().collect::<Vec<_>>(), vec!["1"]);
+
+    /// A bound key can not switch tenants, an unbound one picks the tenant with the header
+    let req = with_key(TestRequest::get().uri("/meals"), ACME_EDITOR_KEY).insert_header(("X-Tenant-Id", "default"));
+    assert_eq!(text(test::call_service(&app, req.to_request()).await).await, (403, "-10".to_string()));
+    let req = with_key(TestRequest::get().uri("/meals/snack"), READER_KEY).insert_header(("X-Tenant-Id", ACME));
+    let snack: Value = test::call_and_read_body_json(&app, req.to_request()).await;
+    assert_eq!(snack["ID"], 2);
+
+    /// Diets with the same name are told apart by the tenant forwarded to the Diets Service
+    let diets = DietsClient::new(&env.diets_url()).unwrap();
+    let strict = NewDiet { name: "light".to_string(), cal: 1.0, sodium: 1.0, sugar: 1.0 };
+    let loose = NewDiet { name: "light".to_string(), cal: 10000.0, sodium: 10000.0, sugar: 10000.0 };
+    diets.with_api_key(ADMIN_KEY).create_diet(&strict).await.unwrap();
+    diets.with_api_key(ACME_ADMIN_KEY).create_diet(&loose).await.unwrap();
+    assert_eq!(diets.with_api_key(ACME_ADMIN_KEY).get_diets().await.unwrap().len(), 1);
+    let light = diets.with_api_key(ADMIN_KEY).with_tenant(ACME).get_diet_by_name("light").await.unwrap();
+    assert_eq!(light.cal, 10000.0);
+
+    let req = with_key(TestRequest::get().uri("/meals?diet=light"), READER_KEY);
+    let meals: Value = test::call_and_read_body_json(&app, req.to_request()).await;
+    assert!(meals.as_array().unwrap().is_empty(), "{}", meals);
+    let req = with_key(TestRequest::get().uri("/meals?diet=light"), ACME_EDITOR_KEY);
+    let meals: Value = test::call_and_read_body_json(&app, req.to_request()).await;
+    assert_eq!(meals.as_array().unwrap().len(), 1, "{}", meals);
+}
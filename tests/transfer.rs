@@ -0,0 +1,149 @@
+#![allow(unused_doc_comments)]
+//! Integration tests of the data transfer, exporting a database and importing the document into another one
+//!
+//! See [common] for the database they need
+
+mod common;
+
+use chrono::NaiveDate;
+
+use meals_api::diets::{DietRepository, DieselDietRepository, NewDiet};
+use meals_api::food_log::{DieselFoodLogRepository, FoodLogRepository, NewLogEntry};
+use meals_api::meals::{DieselMealsRepository, DishRepository, Ingredient, MealRepository, NewDish, NewMeal, Unit};
+use meals_api::plans::{DieselPlanRepository, NewPlan, PlanEntry, PlanRepository, Slot};
+use meals_api::transfer::{export_dataset, import_dataset, Dataset, ImportMode, FORMAT_VERSION};
+use meals_api::users::{ActivityLevel, DieselUserRepository, Profile, Sex, UserRepository};
+
+use common::TestDatabase;
+
+/// Creates a [TestDatabase], or skips the test if there is no database
+macro_rules! database {
+    () => {
+        match TestDatabase::create() {
+            Some(database) => database,
+            None => return,
+        }
+    };
+}
+
+fn dish(name: &str, cal: f64) -> NewDish {
+    NewDish { name: name.to_string(), cal, sodium: 10.0, sugar: 1.0, size: 100.0 }
+}
+
+fn line(ingredient: &str, cal: f64) -> Ingredient {
+    Ingredient { ingredient: ingredient.to_string(), quantity: 100.0, unit: Unit::G, cal, sodium: 1.0, sugar: 0.5, size: 100.0 }
+}
+
+fn date(day: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(2026, 10, day).unwrap()
+}
+
+/// A source database with a gap in the dish IDs, two tenants, a recipe, a diet and a plan
+fn source(database: &TestDatabase) -> Dataset {
+    let pool = &database.pool;
+    let meals = DieselMealsRepository::new(pool.clone());
+    let gone = DishRepository::insert(&meals, "a", dish("gone", 1.0)).unwrap().ID;
+    let soup = DishRepository::insert(&meals, "a", dish("soup", 100.0)).unwrap().ID;
+    let cake = DishRepository::insert(&meals, "a", dish("cake", 300.0)).unwrap().ID;
+    DishRepository::insert(&meals, "b", dish("soup", 200.0)).unwrap();
+    DishRepository::delete(&meals, "a", gone).unwrap();
+    meals.insert_recipe("a", "bowl".to_string(), vec![line("rice", 130.0), line("beans", 90.0)]).unwrap();
+    let lunch = MealRepository::insert(&meals, "a", NewMeal { name: "lunch".to_string(), appetizer: soup, main: soup, dessert: cake }).unwrap().ID;
+    DieselDietRepository::new(pool.clone()).insert("b", NewDiet { name: "light".to_string(), cal: 500.0, sodium: 100.0, sugar: 10.0 }).unwrap();
+    let entries = vec![PlanEntry { date: date(19), slot: Slot::Lunch, meal: lunch }];
+    DieselPlanRepository::new(pool.clone()).insert("a", NewPlan { name: "week".to_string(), start: date(19), days: 7, entries }).unwrap();
+    export_dataset(&mut pool.get().unwrap()).unwrap()
+}
+
+#[test]
+fn exports_are_imported_with_new_dish_ids() {
+    let dataset = source(&database!());
+    assert_eq!((dataset.version, dataset.dishes.len(), dataset.meals.len(), dataset.diets.len()), (FORMAT_VERSION, 4, 1, 1));
+    assert_eq!((dataset.ingredients.len(), dataset.plans.len(), dataset.plan_entries.len()), (2, 1, 1));
+
+    /// The target already has dishes and a meal, so the IDs of the imported ones differ from the source
+    let target = database!();
+    let pool = &target.pool;
+    let meals = DieselMealsRepository::new(pool.clone());
+    let filler = DishRepository::insert(&meals, "a", dish("filler", 1.0)).unwrap().ID;
+    DishRepository::insert(&meals, "a", dish("filler 2", 1.0)).unwrap();
+    MealRepository::insert(&meals, "a", NewMeal { name: "brunch".to_string(), appetizer: filler, main: filler, dessert: filler }).unwrap();
+    let report = import_dataset(&mut pool.get().unwrap(), &dataset, ImportMode::Merge).unwrap();
+    assert_eq!((report.dishes_inserted, report.meals_inserted, report.diets_inserted, report.dangling_references), (4, 1, 1, 0));
+    assert_eq!((report.ingredients_inserted, report.plans_inserted), (2, 1));
+
+    let lunch = MealRepository::find_by_name(&meals, "a", "lunch").unwrap();
+    let soup = DishRepository::find_by_name(&meals, "a", "soup").unwrap();
+    let cake = DishRepository::find_by_name(&meals, "a", "cake").unwrap();
+    assert_ne!(Some(soup.ID), dataset.meals[0].appetizer);
+    assert_eq!((lunch.appetizer, lunch.main, lunch.dessert, lunch.cal), (Some(soup.ID), Some(soup.ID), Some(cake.ID), Some(500.0)));
+    assert_eq!(DishRepository::find_by_name(&meals, "b", "soup").unwrap().cal, 200.0);
+    let bowl = DishRepository::find_by_name(&meals, "a", "bowl").unwrap();
+    assert_eq!((bowl.cal, meals.ingredients("a", bowl.ID).unwrap()), (220.0, vec![line("rice", 130.0), line("beans", 90.0)]));
+    assert!(DieselDietRepository::new(pool.clone()).find_by_name("b", "light").is_ok());
+    let plans = DieselPlanRepository::new(pool.clone());
+    let week = plans.all("a").unwrap().remove(0);
+    assert_ne!(lunch.ID, dataset.plan_entries[0].meal_id);
+    assert_eq!((week.name.as_str(), week.days, week.entries), ("week", 7, vec![PlanEntry { date: date(19), slot: Slot::Lunch, meal: lunch.ID }]));
+
+    /// Merging again skips everything, the skipped recipe keeps its lines
+    let report = import_dataset(&mut pool.get().unwrap(), &dataset, ImportMode::Merge).unwrap();
+    assert_eq!((report.dishes_skipped, report.meals_skipped, report.diets_skipped, report.plans_skipped, report.dishes_inserted), (4, 1, 1, 1, 0));
+    assert_eq!((report.ingredients_inserted, meals.ingredients("a", bowl.ID).unwrap().len()), (0, 2));
+
+    /// Replacing drops what the document does not contain, the food log only loses its references
+    let profile = Profile { age: 30, sex: Sex::Male, weight_kg: 80.0, height_cm: 180.0, activity_level: ActivityLevel::Light };
+    let user = DieselUserRepository::new(pool.clone()).insert("a", "alice", profile).unwrap();
+    let food_log = DieselFoodLogRepository::new(pool.clone());
+    let eaten_at = date(19).and_hms_opt(12, 0, 0).unwrap();
+    food_log.insert(NewLogEntry {
+        user_id: user.ID, meal_id: Some(lunch.ID), dish_id: None, name: "lunch".to_string(), portion: 1.0, eaten_at, cal: 500.0, sodium: 30.0, sugar: 3.0,
+    }).unwrap();
+    let report = import_dataset(&mut pool.get().unwrap(), &dataset, ImportMode::Replace).unwrap();
+    assert_eq!((report.dishes_inserted, report.meals_inserted, report.plans_inserted, report.log_entries_detached), (4, 1, 1, 1));
+    assert_eq!(report.ingredients_inserted, 2);
+    assert!(DishRepository::find_by_name(&meals, "a", "filler").is_err());
+    assert_eq!(MealRepository::find_by_name(&meals, "a", "lunch").unwrap().cal, Some(500.0));
+    assert_eq!(plans.all("a").unwrap()[0].entries.len(), 1);
+    let logged = food_log.between(user.ID, eaten_at, eaten_at + chrono::Duration::hours(1)).unwrap();
+    assert_eq!((logged[0].meal, logged[0].cal), (None, 500.0));
+}
+
+#[test]
+fn plan_entries_of_missing_meals_are_dropped() {
+    let mut dataset = source(&database!());
+    dataset.meals.clear();
+    let target = database!();
+    let pool = &target.pool;
+    let report = import_dataset(&mut pool.get().unwrap(), &dataset, ImportMode::Merge).unwrap();
+    assert_eq!((report.plans_inserted, report.dangling_references), (1, 1));
+    assert!(DieselPlanRepository::new(pool.clone()).all("a").unwrap()[0].entries.is_empty());
+}
+
+#[test]
+fn references_to_missing_dishes_are_dropped() {
+    let mut dataset = source(&database!());
+    dataset.dishes.retain(|dish| dish.name != "cake" && dish.name != "bowl");
+    let target = database!();
+    let pool = &target.pool;
+    let report = import_dataset(&mut pool.get().unwrap(), &dataset, ImportMode::Merge).unwrap();
+    assert_eq!((report.dangling_references, report.ingredients_inserted), (3, 0));
+    let lunch = MealRepository::find_by_name(&DieselMealsRepository::new(pool.clone()), "a", "lunch").unwrap();
+    assert_eq!((lunch.dessert, lunch.cal), (None, Some(200.0)));
+}
+
+#[test]
+fn newer_documents_are_refused_and_older_ones_use_the_default_tenant() {
+    let target = database!();
+    let pool = &target.pool;
+    let mut dataset = source(&database!());
+    dataset.version = FORMAT_VERSION + 1;
+    let e = import_dataset(&mut pool.get().unwrap(), &dataset, ImportMode::Replace).err().unwrap();
+    assert!(e.to_string().starts_with("Unsupported export version"), "{}", e);
+    assert!(DishRepository::all(&DieselMealsRepository::new(pool.clone()), "a").unwrap().is_empty());
+
+    /// Version 1 documents have no tenants
+    let dataset: Dataset = serde_json::from_str(r#"{"version": 1, "dishes": [{"id": 7, "name": "soup", "cal": 1, "sodium": 2, "sugar": 3, "size": 4}], "meals": [], "diets": []}"#).unwrap();
+    import_dataset(&mut pool.get().unwrap(), &dataset, ImportMode::Merge).unwrap();
+    assert!(DishRepository::find_by_name(&DieselMealsRepository::new(pool.clone()), meals_api::auth::DEFAULT_TENANT, "soup").is_ok());
+}